@@ -192,15 +192,15 @@ struct DummyClientAuth {
 }
 
 impl server::danger::ClientCertVerifier for DummyClientAuth {
-    fn offer_client_auth(&self) -> bool {
+    fn offer_client_auth(&self, _client_hello: &ClientHello<'_>) -> bool {
         true
     }
 
-    fn client_auth_mandatory(&self) -> bool {
+    fn client_auth_mandatory(&self, _client_hello: &ClientHello<'_>) -> bool {
         self.mandatory
     }
 
-    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+    fn root_hint_subjects(&self, _client_hello: &ClientHello<'_>) -> &[DistinguishedName] {
         &[]
     }
 
@@ -1,8 +1,9 @@
 use crate::builder::{ConfigBuilder, WantsVerifier};
 use crate::client::handy;
-use crate::client::{ClientConfig, ResolvesClientCert};
+use crate::client::{ClientConfig, ClientHelloPadding, ResolvesClientCert};
 use crate::crypto::{CryptoProvider, SupportedKxGroup};
 use crate::key_log::NoKeyLog;
+use crate::observer::NoHandshakeObserver;
 use crate::suites::SupportedCipherSuite;
 #[cfg(feature = "ring")]
 use crate::{error::Error, webpki};
@@ -151,8 +152,14 @@ impl ConfigBuilder<ClientConfig, WantsClientCert> {
             enable_sni: true,
             verifier: self.state.verifier,
             key_log: Arc::new(NoKeyLog {}),
+            handshake_observer: Arc::new(NoHandshakeObserver),
             enable_secret_extraction: false,
             enable_early_data: false,
+            ct_policy: None,
+            certificate_authorities: Vec::new(),
+            enable_delegated_credentials: false,
+            enable_grease: false,
+            client_hello_padding: ClientHelloPadding::Disabled,
         }
     }
 }
@@ -8,14 +8,16 @@ use crate::error::Error;
 #[cfg(feature = "logging")]
 use crate::log::trace;
 use crate::msgs::enums::NamedGroup;
-use crate::msgs::handshake::ClientExtension;
+use crate::msgs::handshake::{ClientExtension, DistinguishedName};
 use crate::msgs::persist;
+use crate::observer::HandshakeObserver;
 use crate::sign;
 use crate::suites::{ExtractedSecrets, SupportedCipherSuite};
 use crate::verify;
 use crate::versions;
 use crate::KeyLog;
 
+use super::ct::CertificateTransparencyPolicy;
 use super::handy::{ClientSessionMemoryCache, NoClientSessionStorage};
 use super::hs;
 
@@ -131,6 +133,7 @@ pub trait ResolvesClientCert: Send + Sync {
 ///    ids or tickets, with a max of eight tickets per server.
 /// * [`ClientConfig::alpn_protocols`]: the default is empty -- no ALPN protocol is negotiated.
 /// * [`ClientConfig::key_log`]: key material is not logged.
+/// * [`ClientConfig::handshake_observer`]: handshake events are not observed.
 ///
 /// [`RootCertStore`]: crate::RootCertStore
 pub struct ClientConfig {
@@ -187,6 +190,10 @@ pub struct ClientConfig {
     /// does nothing.
     pub key_log: Arc<dyn KeyLog>,
 
+    /// What to tell about handshake events, for example to export
+    /// metrics.  The default does nothing.
+    pub handshake_observer: Arc<dyn HandshakeObserver>,
+
     /// Allows traffic secrets to be extracted after the handshake,
     /// e.g. for kTLS setup.
     pub enable_secret_extraction: bool,
@@ -196,6 +203,105 @@ pub struct ClientConfig {
     ///
     /// The default is false.
     pub enable_early_data: bool,
+
+    /// Certificate Transparency requirements for the server's certificate.
+    ///
+    /// If set, the client requests Signed Certificate Timestamps from the
+    /// server and the handshake fails unless they satisfy the policy.
+    ///
+    /// The default is `None`: SCTs are neither requested nor checked.
+    pub ct_policy: Option<Arc<CertificateTransparencyPolicy>>,
+
+    /// Trust anchor subjects to advertise in the `certificate_authorities`
+    /// extension of TLS 1.3 client hellos.
+    ///
+    /// This lets a server holding several certificate chains pick one the
+    /// client can verify.  Typically this is populated from
+    /// [`RootCertStore::subjects()`].  If empty, the extension is not sent.
+    ///
+    /// The default is empty.
+    ///
+    /// [`RootCertStore::subjects()`]: crate::RootCertStore::subjects
+    pub certificate_authorities: Vec<DistinguishedName>,
+
+    /// Whether to accept delegated credentials (RFC 9345) from the server
+    /// in TLS 1.3 handshakes.
+    ///
+    /// These are verified by [`ServerCertVerifier::verify_delegated_credential`].
+    ///
+    /// The default is false.
+    ///
+    /// [`ServerCertVerifier::verify_delegated_credential`]: crate::client::danger::ServerCertVerifier::verify_delegated_credential
+    pub enable_delegated_credentials: bool,
+
+    /// Whether to send GREASE (RFC 8701) values in client hellos.
+    ///
+    /// These are random reserved values for cipher suites, key exchange
+    /// groups and key shares, signature schemes, protocol versions, ALPN
+    /// protocols and extensions, which compliant servers ignore.  Sending
+    /// them stops servers and middleboxes from depending on the exact
+    /// values rustls sends today.
+    ///
+    /// The default is false.
+    pub enable_grease: bool,
+
+    /// How to pad client hellos with the padding extension (RFC 7685).
+    ///
+    /// The default is [`ClientHelloPadding::Disabled`].
+    pub client_hello_padding: ClientHelloPadding,
+}
+
+/// How to pad client hellos, using the padding extension (RFC 7685).
+///
+/// Sizes here are those of the ClientHello handshake message, including
+/// its four byte header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClientHelloPadding {
+    /// Never pad client hellos.
+    Disabled,
+    /// Pad client hellos of between 256 and 511 bytes to 512 bytes.
+    ///
+    /// Some middleboxes (notably some F5 load balancers) fail to process
+    /// client hellos within this range.  This is the same workaround as
+    /// used by OpenSSL and BoringSSL.
+    AvoidProblematicSizes,
+    /// Pad every client hello to a multiple of the given number of bytes.
+    ///
+    /// This hides differences in the length of the server name and key
+    /// shares sent, at the cost of larger client hellos.  The range
+    /// avoided by [`ClientHelloPadding::AvoidProblematicSizes`] is
+    /// also avoided, by padding to the first multiple of at least 512
+    /// bytes instead.
+    Bucket(u16),
+}
+
+impl ClientHelloPadding {
+    /// How many bytes of padding extension payload to add to a client
+    /// hello of `unpadded_len` bytes, if any.
+    pub(super) fn padding_len(self, unpadded_len: usize) -> Option<usize> {
+        // The extension's type and length.
+        const HEADER_LEN: usize = 4;
+
+        let padded_len = match (self, unpadded_len) {
+            (Self::Disabled, _) => return None,
+            (Self::AvoidProblematicSizes, 256..=511) => Ord::max(512, unpadded_len + HEADER_LEN),
+            (Self::AvoidProblematicSizes, _) => return None,
+            (Self::Bucket(bucket), _) => {
+                let bucket = usize::from(bucket.max(1));
+                let round_up = |len: usize| (len + bucket - 1) / bucket * bucket;
+                match round_up(unpadded_len + HEADER_LEN) {
+                    256..=511 => round_up(512),
+                    padded_len => padded_len,
+                }
+            }
+        };
+
+        match padded_len > unpadded_len {
+            true => Some(padded_len - unpadded_len - HEADER_LEN),
+            false => None,
+        }
+    }
 }
 
 /// What mechanisms to support for resuming a TLS 1.2 session.
@@ -228,8 +334,14 @@ impl Clone for ClientConfig {
             enable_sni: self.enable_sni,
             verifier: Arc::clone(&self.verifier),
             key_log: Arc::clone(&self.key_log),
+            handshake_observer: Arc::clone(&self.handshake_observer),
             enable_secret_extraction: self.enable_secret_extraction,
             enable_early_data: self.enable_early_data,
+            ct_policy: self.ct_policy.clone(),
+            certificate_authorities: self.certificate_authorities.clone(),
+            enable_delegated_credentials: self.enable_delegated_credentials,
+            enable_grease: self.enable_grease,
+            client_hello_padding: self.client_hello_padding,
         }
     }
 }
@@ -242,6 +354,13 @@ impl fmt::Debug for ClientConfig {
             .field("max_fragment_size", &self.max_fragment_size)
             .field("enable_sni", &self.enable_sni)
             .field("enable_early_data", &self.enable_early_data)
+            .field("ct_policy", &self.ct_policy)
+            .field("certificate_authorities", &self.certificate_authorities)
+            .field(
+                "enable_delegated_credentials",
+                &self.enable_delegated_credentials,
+            )
+            .field("client_hello_padding", &self.client_hello_padding)
             .finish_non_exhaustive()
     }
 }
@@ -704,6 +823,20 @@ impl ConnectionCore<ClientConnectionData> {
         common_state.set_max_fragment_size(config.max_fragment_size)?;
         common_state.protocol = proto;
         common_state.enable_secret_extraction = config.enable_secret_extraction;
+        common_state.set_handshake_observer(&config.handshake_observer);
+        #[cfg(feature = "tracing")]
+        let _span = {
+            common_state.start_span();
+            match &name {
+                ServerName::DnsName(dns_name) => common_state
+                    .span
+                    .record("server_name", dns_name.as_ref()),
+                ServerName::IpAddress(ip) => common_state
+                    .span
+                    .record("server_name", tracing::field::display(ip)),
+            };
+            common_state.span.clone().entered()
+        };
         let mut data = ClientConnectionData::new();
 
         let mut cx = hs::ClientContext {
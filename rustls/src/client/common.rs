@@ -2,8 +2,8 @@ use super::ResolvesClientCert;
 #[cfg(feature = "logging")]
 use crate::log::{debug, trace};
 use crate::msgs::enums::ExtensionType;
-use crate::msgs::handshake::ServerExtension;
 use crate::msgs::handshake::{CertificatePayload, DistinguishedName};
+use crate::msgs::handshake::{Sct, ServerExtension};
 use crate::{sign, SignatureScheme};
 
 use alloc::boxed::Box;
@@ -14,15 +14,25 @@ use alloc::vec::Vec;
 pub(super) struct ServerCertDetails {
     pub(super) cert_chain: CertificatePayload,
     pub(super) ocsp_response: Vec<u8>,
+    pub(super) scts: Option<Vec<Sct>>,
 }
 
 impl ServerCertDetails {
-    pub(super) fn new(cert_chain: CertificatePayload, ocsp_response: Vec<u8>) -> Self {
+    pub(super) fn new(
+        cert_chain: CertificatePayload,
+        ocsp_response: Vec<u8>,
+        scts: Option<Vec<Sct>>,
+    ) -> Self {
         Self {
             cert_chain,
             ocsp_response,
+            scts,
         }
     }
+
+    pub(super) fn scts(&self) -> &[Sct] {
+        self.scts.as_deref().unwrap_or_default()
+    }
 }
 
 pub(super) struct ClientHelloDetails {
@@ -0,0 +1,721 @@
+use crate::crypto::hash::Hash;
+use crate::enums::SignatureScheme;
+use crate::error::{CertificateError, Error};
+#[cfg(feature = "logging")]
+use crate::log::{debug, trace};
+use crate::msgs::codec::Codec;
+use crate::msgs::enums::HashAlgorithm;
+use crate::msgs::handshake::Sct;
+use crate::webpki::{verify_signature_with_spki, WebPkiSupportedAlgorithms};
+use crate::x509::{self, Der, DER_EXTENSIONS_TAG, DER_OCTET_STRING_TAG, DER_SEQUENCE_TAG};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use pki_types::{CertificateDer, UnixTime};
+
+/// A Certificate Transparency log known to the client.
+#[derive(Clone, Debug)]
+pub struct CtLog {
+    /// Who operates this log.
+    ///
+    /// [`CertificateTransparencyPolicy`] can require that SCTs come from logs run
+    /// by a number of distinct operators; logs are considered to be run by the same
+    /// operator if this is equal.
+    pub operator: String,
+
+    /// The log's public key, as a DER-encoded `SubjectPublicKeyInfo`.
+    ///
+    /// The log's ID is derived from this, as described in RFC6962 section 3.2.
+    pub key: Vec<u8>,
+}
+
+/// Requirements for the Signed Certificate Timestamps (SCTs) a server must
+/// provide for its certificate, as described in RFC6962.
+///
+/// When this is set in [`ClientConfig::ct_policy`], the client requests SCTs
+/// from the server and collects them from:
+///
+/// - the `signed_certificate_timestamp` TLS extension,
+/// - the stapled OCSP response, and
+/// - the end-entity certificate's embedded SCT list extension.
+///
+/// The handshake fails with [`CertificateError::CertificateTransparencyPolicyNotMet`]
+/// unless enough SCTs from known logs verify correctly.  SCTs from unknown logs,
+/// SCTs with a timestamp in the future and SCTs that fail to verify are ignored.
+///
+/// Verifying embedded SCTs requires the certificate's issuer, so they only count
+/// when the server sends the issuer as the first intermediate certificate.
+///
+/// [`ClientConfig::ct_policy`]: crate::ClientConfig::ct_policy
+pub struct CertificateTransparencyPolicy {
+    logs: Vec<(LogId, CtLog)>,
+    min_scts: usize,
+    min_operators: usize,
+    sha256: &'static dyn Hash,
+    supported_algs: WebPkiSupportedAlgorithms,
+}
+
+impl CertificateTransparencyPolicy {
+    /// Make a policy that trusts `logs`, using the *ring* crypto provider.
+    ///
+    /// By default, two valid SCTs from logs run by two distinct operators are required.
+    #[cfg(feature = "ring")]
+    pub fn new(logs: Vec<CtLog>) -> Self {
+        Self::new_with_algorithms(
+            logs,
+            &crate::crypto::ring::hash::SHA256,
+            crate::webpki::SUPPORTED_SIG_ALGS,
+        )
+    }
+
+    /// Make a policy that trusts `logs`.
+    ///
+    /// `sha256` is used to compute log IDs and the issuer key hash of precertificate
+    /// entries; this panics if it is not SHA-256.  SCT signatures are verified using
+    /// `supported_algs`.
+    ///
+    /// By default, two valid SCTs from logs run by two distinct operators are required.
+    pub fn new_with_algorithms(
+        logs: Vec<CtLog>,
+        sha256: &'static dyn Hash,
+        supported_algs: WebPkiSupportedAlgorithms,
+    ) -> Self {
+        assert_eq!(
+            (sha256.algorithm(), sha256.output_len()),
+            (HashAlgorithm::SHA256, 32),
+            "CertificateTransparencyPolicy requires a SHA-256 implementation"
+        );
+
+        Self {
+            logs: logs
+                .into_iter()
+                .map(|log| (LogId::new(sha256.hash(&log.key).as_ref()), log))
+                .collect(),
+            min_scts: 2,
+            min_operators: 2,
+            sha256,
+            supported_algs,
+        }
+    }
+
+    /// Require at least `min_scts` valid SCTs, each from a different log.
+    pub fn with_min_scts(mut self, min_scts: usize) -> Self {
+        self.min_scts = min_scts;
+        self
+    }
+
+    /// Require valid SCTs from logs run by at least `min_operators` distinct operators.
+    pub fn with_min_distinct_operators(mut self, min_operators: usize) -> Self {
+        self.min_operators = min_operators;
+        self
+    }
+
+    /// Check the SCTs available for `end_entity` satisfy this policy.
+    ///
+    /// `stapled` are the SCTs the server sent in the TLS handshake, and
+    /// `ocsp_response` is the (possibly empty) stapled OCSP response.
+    pub(crate) fn verify(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        stapled: &[Sct],
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<(), Error> {
+        let x509_entry = Entry::X509(end_entity.as_ref());
+        let mut candidates = stapled
+            .iter()
+            .cloned()
+            .chain(ocsp_sct_list(ocsp_response).unwrap_or_default())
+            .map(|sct| (sct, x509_entry))
+            .collect::<Vec<_>>();
+
+        let precert = embedded_sct_list(end_entity.as_ref());
+        let issuer_key_hash = intermediates
+            .first()
+            .and_then(|issuer| x509::cert_spki(issuer.as_ref()))
+            .map(|spki| self.sha256.hash(spki));
+        if let (Some((list, tbs)), Some(issuer_key_hash)) = (&precert, &issuer_key_hash) {
+            let entry = Entry::Precert {
+                issuer_key_hash: issuer_key_hash.as_ref(),
+                tbs,
+            };
+            candidates.extend(
+                list.iter()
+                    .map(|sct| (sct.clone(), entry)),
+            );
+        }
+
+        let now_ms = now.as_secs().saturating_mul(1000);
+        let mut seen_logs = Vec::new();
+        let mut operators = Vec::new();
+
+        for (sct, entry) in &candidates {
+            let log = match self.verify_sct(sct.as_ref(), *entry, now_ms) {
+                Ok(log) => log,
+                Err(_err) => {
+                    debug!("Ignoring SCT: {}", _err);
+                    continue;
+                }
+            };
+
+            if seen_logs.contains(&log.0) {
+                continue;
+            }
+            seen_logs.push(log.0);
+
+            if !operators.contains(&&log.1.operator) {
+                operators.push(&log.1.operator);
+            }
+        }
+
+        trace!(
+            "Valid SCTs from {} logs and {} operators",
+            seen_logs.len(),
+            operators.len()
+        );
+        match seen_logs.len() >= self.min_scts && operators.len() >= self.min_operators {
+            true => Ok(()),
+            false => Err(CertificateError::CertificateTransparencyPolicyNotMet.into()),
+        }
+    }
+
+    fn verify_sct(
+        &self,
+        sct: &[u8],
+        entry: Entry<'_>,
+        now_ms: u64,
+    ) -> Result<&(LogId, CtLog), &'static str> {
+        let sct = ParsedSct::parse(sct).ok_or("malformed SCT")?;
+
+        let log = self
+            .logs
+            .iter()
+            .find(|(id, _)| *id == sct.log_id)
+            .ok_or("SCT from unknown log")?;
+
+        if sct.timestamp > now_ms {
+            return Err("SCT timestamp is in the future");
+        }
+
+        verify_signature_with_spki(
+            &log.1.key,
+            sct.scheme,
+            &sct.signed_data(entry),
+            sct.signature,
+            &self.supported_algs,
+        )
+        .map_err(|_| "SCT signature is invalid")?;
+
+        Ok(log)
+    }
+}
+
+impl fmt::Debug for CertificateTransparencyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertificateTransparencyPolicy")
+            .field(
+                "logs",
+                &self
+                    .logs
+                    .iter()
+                    .map(|(_, log)| log)
+                    .collect::<Vec<_>>(),
+            )
+            .field("min_scts", &self.min_scts)
+            .field("min_operators", &self.min_operators)
+            .field("supported_algs", &self.supported_algs)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LogId([u8; 32]);
+
+impl LogId {
+    /// `bytes` must be a SHA-256 hash.
+    fn new(bytes: &[u8]) -> Self {
+        let mut id = [0u8; 32];
+        id.copy_from_slice(bytes);
+        Self(id)
+    }
+}
+
+/// What a log signed: RFC6962 section 3.2's `signed_entry`.
+#[derive(Clone, Copy)]
+enum Entry<'a> {
+    X509(&'a [u8]),
+    Precert {
+        issuer_key_hash: &'a [u8],
+        tbs: &'a [u8],
+    },
+}
+
+struct ParsedSct<'a> {
+    log_id: LogId,
+    timestamp: u64,
+    extensions: &'a [u8],
+    scheme: SignatureScheme,
+    signature: &'a [u8],
+}
+
+impl<'a> ParsedSct<'a> {
+    fn parse(mut bytes: &'a [u8]) -> Option<Self> {
+        let r = &mut bytes;
+        if take(r, 1)? != [SCT_V1] {
+            return None;
+        }
+
+        let log_id = LogId::new(take(r, 32)?);
+        let timestamp = u64::read_bytes(take(r, 8)?).ok()?;
+        let ext_len = u16::read_bytes(take(r, 2)?).ok()? as usize;
+        let extensions = take(r, ext_len)?;
+        let scheme = SignatureScheme::read_bytes(take(r, 2)?).ok()?;
+        let sig_len = u16::read_bytes(take(r, 2)?).ok()? as usize;
+        let signature = take(r, sig_len)?;
+
+        match r.is_empty() {
+            true => Some(Self {
+                log_id,
+                timestamp,
+                extensions,
+                scheme,
+                signature,
+            }),
+            false => None,
+        }
+    }
+
+    /// The data covered by the SCT's signature: RFC6962 section 3.2's
+    /// `digitally-signed struct`.
+    fn signed_data(&self, entry: Entry<'_>) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.push(SCT_V1);
+        data.push(SIGNATURE_TYPE_CERTIFICATE_TIMESTAMP);
+        self.timestamp.encode(&mut data);
+
+        match entry {
+            Entry::X509(cert) => {
+                ENTRY_TYPE_X509.encode(&mut data);
+                encode_u24_prefixed(cert, &mut data);
+            }
+            Entry::Precert {
+                issuer_key_hash,
+                tbs,
+            } => {
+                ENTRY_TYPE_PRECERT.encode(&mut data);
+                data.extend_from_slice(issuer_key_hash);
+                encode_u24_prefixed(tbs, &mut data);
+            }
+        }
+
+        (self.extensions.len() as u16).encode(&mut data);
+        data.extend_from_slice(self.extensions);
+        data
+    }
+}
+
+/// Split `len` bytes off the front of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+
+    let (taken, rest) = input.split_at(len);
+    *input = rest;
+    Some(taken)
+}
+
+fn encode_u24_prefixed(bytes: &[u8], out: &mut Vec<u8>) {
+    let len = bytes.len() as u32;
+    out.extend_from_slice(&len.to_be_bytes()[1..]);
+    out.extend_from_slice(bytes);
+}
+
+/// Returns the SCT list embedded in the certificate `cert`, and the
+/// precertificate `TBSCertificate` which the SCTs in that list are over.
+///
+/// This is the certificate's `TBSCertificate` with the SCT list extension removed.
+fn embedded_sct_list(cert: &[u8]) -> Option<(Vec<Sct>, Vec<u8>)> {
+    let tbs = x509::tbs_certificate(cert)?;
+
+    let mut precert_tbs = Vec::new();
+    let mut sct_list = None;
+    for item in Der::items(tbs) {
+        let item = item?;
+        if item.tag != DER_EXTENSIONS_TAG {
+            precert_tbs.extend_from_slice(item.whole);
+            continue;
+        }
+
+        let mut extensions = Vec::new();
+        for ext in Der::items(Der::expect(item.value, DER_SEQUENCE_TAG)?) {
+            let ext = ext?;
+            match sct_list_extension(ext.value, OID_EMBEDDED_SCT_LIST) {
+                Some(value) => sct_list = Some(value),
+                None => extensions.extend_from_slice(ext.whole),
+            }
+        }
+
+        if !extensions.is_empty() {
+            x509::wrap_in_sequence(&mut extensions);
+            x509::wrap_in_asn1_len(&mut extensions);
+            extensions.insert(0, DER_EXTENSIONS_TAG);
+            precert_tbs.extend_from_slice(&extensions);
+        }
+    }
+
+    let sct_list = decode_sct_list(sct_list?)?;
+    x509::wrap_in_sequence(&mut precert_tbs);
+    Some((sct_list, precert_tbs))
+}
+
+/// Returns the SCTs in the `singleExtensions` of any `SingleResponse`
+/// in the DER-encoded `OCSPResponse` `ocsp_response`.
+///
+/// The response is not validated; SCTs are verified against the end-entity
+/// certificate regardless of what the response is for.
+fn ocsp_sct_list(ocsp_response: &[u8]) -> Option<Vec<Sct>> {
+    if ocsp_response.is_empty() {
+        return None;
+    }
+
+    let mut resp = Der::expect(ocsp_response, DER_SEQUENCE_TAG)?;
+    Der::read(&mut resp)?; // responseStatus
+    let response_bytes = Der::read(&mut resp)?;
+    let mut response_bytes = Der::expect(response_bytes.value, DER_SEQUENCE_TAG)?;
+    Der::read(&mut response_bytes)?; // responseType
+    let mut basic = Der::expect(Der::read(&mut response_bytes)?.value, DER_SEQUENCE_TAG)?;
+    let tbs_response_data = Der::read(&mut basic)?;
+
+    let mut scts = Vec::new();
+    let responses = Der::items(tbs_response_data.value)
+        .flatten()
+        .find(|item| item.tag == DER_SEQUENCE_TAG)?;
+    for single in Der::items(responses.value) {
+        let extensions = Der::items(single?.value)
+            .flatten()
+            .find(|item| item.tag == DER_SINGLE_EXTENSIONS);
+        let extensions = match extensions {
+            Some(extensions) => Der::expect(extensions.value, DER_SEQUENCE_TAG)?,
+            None => continue,
+        };
+
+        for ext in Der::items(extensions) {
+            if let Some(value) = sct_list_extension(ext?.value, OID_OCSP_SCT_LIST) {
+                scts.extend(decode_sct_list(value)?);
+            }
+        }
+    }
+
+    Some(scts)
+}
+
+/// If the `Extension` contents `ext` has the given `oid`, returns the
+/// contents of the OCTET STRING within its `extnValue`.
+fn sct_list_extension<'a>(ext: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+    Der::expect(x509::extension_value(ext, oid)?, DER_OCTET_STRING_TAG)
+}
+
+fn decode_sct_list(bytes: &[u8]) -> Option<Vec<Sct>> {
+    Vec::<Sct>::read_bytes(bytes).ok()
+}
+
+const SCT_V1: u8 = 0;
+const SIGNATURE_TYPE_CERTIFICATE_TIMESTAMP: u8 = 0;
+const ENTRY_TYPE_X509: u16 = 0;
+const ENTRY_TYPE_PRECERT: u16 = 1;
+
+const DER_SINGLE_EXTENSIONS: u8 = 0xa1;
+
+/// 1.3.6.1.4.1.11129.2.4.2
+const OID_EMBEDDED_SCT_LIST: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x02];
+/// 1.3.6.1.4.1.11129.2.4.5
+const OID_OCSP_SCT_LIST: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x05];
+
+#[cfg(all(test, feature = "ring"))]
+mod tests {
+    use super::*;
+    use crate::crypto::ring::hash::SHA256;
+    use crate::x509::test_util::{der, extension, with_extension};
+    use crate::x509::{DER_BIT_STRING_TAG, DER_OID_TAG};
+
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    struct TestLog {
+        key: EcdsaKeyPair,
+        log: CtLog,
+    }
+
+    impl TestLog {
+        fn new(operator: &str) -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+            let key =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+
+            let mut spki = P256_SPKI_PREFIX.to_vec();
+            spki.extend_from_slice(key.public_key().as_ref());
+            Self {
+                key,
+                log: CtLog {
+                    operator: operator.into(),
+                    key: spki,
+                },
+            }
+        }
+
+        fn sign(&self, entry: Entry<'_>, timestamp: u64) -> Sct {
+            let mut sct = ParsedSct {
+                log_id: LogId::new(SHA256.hash(&self.log.key).as_ref()),
+                timestamp,
+                extensions: &[],
+                scheme: SignatureScheme::ECDSA_NISTP256_SHA256,
+                signature: &[],
+            };
+            let signature = self
+                .key
+                .sign(&SystemRandom::new(), &sct.signed_data(entry))
+                .unwrap();
+            sct.signature = signature.as_ref();
+
+            let mut bytes = vec![SCT_V1];
+            bytes.extend_from_slice(&sct.log_id.0);
+            sct.timestamp.encode(&mut bytes);
+            0u16.encode(&mut bytes);
+            sct.scheme.encode(&mut bytes);
+            (sct.signature.len() as u16).encode(&mut bytes);
+            bytes.extend_from_slice(sct.signature);
+            Sct::from(bytes)
+        }
+    }
+
+    fn end_entity() -> CertificateDer<'static> {
+        load_cert(include_bytes!("../../../test-ca/ecdsa/end.cert"))
+    }
+
+    fn issuer() -> CertificateDer<'static> {
+        load_cert(include_bytes!("../../../test-ca/ecdsa/inter.cert"))
+    }
+
+    fn load_cert(pem: &[u8]) -> CertificateDer<'static> {
+        rustls_pemfile::certs(&mut &pem[..])
+            .next()
+            .unwrap()
+            .unwrap()
+    }
+
+    fn sct_extension(oid: &[u8], scts: &[Sct]) -> Vec<u8> {
+        extension(
+            oid,
+            &der(DER_OCTET_STRING_TAG, &scts.to_vec().get_encoding()),
+        )
+    }
+
+    /// Returns `cert` with the embedded SCT list extension containing `scts`
+    /// appended to its extensions.  The signature is left as-is.
+    fn with_embedded_scts(cert: &[u8], scts: &[Sct]) -> Vec<u8> {
+        with_extension(cert, &sct_extension(OID_EMBEDDED_SCT_LIST, scts))
+    }
+
+    fn ocsp_response(scts: &[Sct]) -> Vec<u8> {
+        let single_response = der(
+            DER_SEQUENCE_TAG,
+            &[
+                der(DER_SEQUENCE_TAG, &[]), // certID
+                der(0x80, &[]),             // good
+                der(0x18, b"20230101000000Z"),
+                der(
+                    DER_SINGLE_EXTENSIONS,
+                    &der(DER_SEQUENCE_TAG, &sct_extension(OID_OCSP_SCT_LIST, scts)),
+                ),
+            ]
+            .concat(),
+        );
+        let response_data = der(
+            DER_SEQUENCE_TAG,
+            &[
+                der(0xa2, &der(DER_OCTET_STRING_TAG, &[0; 20])),
+                der(0x18, b"20230101000000Z"),
+                der(DER_SEQUENCE_TAG, &single_response),
+            ]
+            .concat(),
+        );
+        let basic = der(
+            DER_SEQUENCE_TAG,
+            &[
+                response_data,
+                der(DER_SEQUENCE_TAG, &[]),
+                der(DER_BIT_STRING_TAG, &[0]),
+            ]
+            .concat(),
+        );
+        let response_bytes = der(
+            DER_SEQUENCE_TAG,
+            &[
+                der(
+                    DER_OID_TAG,
+                    &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01],
+                ),
+                der(DER_OCTET_STRING_TAG, &basic),
+            ]
+            .concat(),
+        );
+        der(
+            DER_SEQUENCE_TAG,
+            &[der(0x0a, &[0]), der(0xa0, &response_bytes)].concat(),
+        )
+    }
+
+    fn policy(logs: &[&TestLog]) -> CertificateTransparencyPolicy {
+        CertificateTransparencyPolicy::new(
+            logs.iter()
+                .map(|log| log.log.clone())
+                .collect(),
+        )
+    }
+
+    fn now() -> UnixTime {
+        UnixTime::since_unix_epoch(core::time::Duration::from_secs(1_700_000_000))
+    }
+
+    const PAST: u64 = 1_600_000_000_000;
+    const FUTURE: u64 = 1_800_000_000_000;
+
+    #[test]
+    #[should_panic(expected = "requires a SHA-256 implementation")]
+    fn test_rejects_other_hash_functions() {
+        CertificateTransparencyPolicy::new_with_algorithms(
+            vec![TestLog::new("operator").log],
+            &crate::crypto::ring::hash::SHA384,
+            crate::webpki::SUPPORTED_SIG_ALGS,
+        );
+    }
+
+    #[test]
+    fn stapled_scts_from_distinct_operators() {
+        let (a, b) = (TestLog::new("a"), TestLog::new("b"));
+        let ee = end_entity();
+        let scts = [
+            a.sign(Entry::X509(&ee), PAST),
+            b.sign(Entry::X509(&ee), PAST),
+        ];
+
+        assert_eq!(
+            policy(&[&a, &b]).verify(&ee, &[], &scts, &[], now()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn too_few_operators() {
+        let (a, b) = (TestLog::new("a"), TestLog::new("a"));
+        let ee = end_entity();
+        let scts = [
+            a.sign(Entry::X509(&ee), PAST),
+            b.sign(Entry::X509(&ee), PAST),
+        ];
+
+        assert_eq!(
+            policy(&[&a, &b]).verify(&ee, &[], &scts, &[], now()),
+            Err(CertificateError::CertificateTransparencyPolicyNotMet.into())
+        );
+        assert_eq!(
+            policy(&[&a, &b])
+                .with_min_distinct_operators(1)
+                .verify(&ee, &[], &scts, &[], now()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn scts_from_one_log_count_once() {
+        let a = TestLog::new("a");
+        let ee = end_entity();
+        let scts = [
+            a.sign(Entry::X509(&ee), PAST),
+            a.sign(Entry::X509(&ee), PAST + 1),
+        ];
+
+        assert!(policy(&[&a])
+            .with_min_distinct_operators(1)
+            .verify(&ee, &[], &scts, &[], now())
+            .is_err());
+    }
+
+    #[test]
+    fn ignores_unusable_scts() {
+        let (a, b, unknown) = (TestLog::new("a"), TestLog::new("b"), TestLog::new("c"));
+        let ee = end_entity();
+        let policy = policy(&[&a, &b]).with_min_scts(1);
+
+        for sct in [
+            unknown.sign(Entry::X509(&ee), PAST),
+            a.sign(Entry::X509(&ee), FUTURE),
+            a.sign(Entry::X509(&issuer()), PAST),
+            Sct::from(vec![1, 2, 3]),
+        ] {
+            assert!(policy
+                .verify(&ee, &[], &[sct], &[], now())
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn embedded_scts() {
+        let (a, b) = (TestLog::new("a"), TestLog::new("b"));
+        let (ee, issuer) = (end_entity(), issuer());
+        let tbs = Der::items(Der::expect(&ee, DER_SEQUENCE_TAG).unwrap())
+            .next()
+            .unwrap()
+            .unwrap()
+            .whole;
+        let issuer_key_hash = SHA256.hash(x509::cert_spki(&issuer).unwrap());
+        let entry = Entry::Precert {
+            issuer_key_hash: issuer_key_hash.as_ref(),
+            tbs,
+        };
+
+        let scts = [a.sign(entry, PAST), b.sign(entry, PAST)];
+        let ee = CertificateDer::from(with_embedded_scts(&ee, &scts));
+        assert_eq!(embedded_sct_list(&ee).unwrap().1, tbs);
+
+        let policy = policy(&[&a, &b]);
+        assert_eq!(policy.verify(&ee, &[issuer], &[], &[], now()), Ok(()));
+        // the issuer is needed to verify embedded SCTs
+        assert!(policy
+            .verify(&ee, &[], &[], &[], now())
+            .is_err());
+    }
+
+    #[test]
+    fn ocsp_scts() {
+        let (a, b) = (TestLog::new("a"), TestLog::new("b"));
+        let ee = end_entity();
+        let ocsp = ocsp_response(&[a.sign(Entry::X509(&ee), PAST)]);
+        let stapled = [b.sign(Entry::X509(&ee), PAST)];
+
+        let policy = policy(&[&a, &b]);
+        assert_eq!(policy.verify(&ee, &[], &stapled, &ocsp, now()), Ok(()));
+        assert!(policy
+            .verify(&ee, &[], &[], &ocsp, now())
+            .is_err());
+    }
+
+    #[test]
+    fn malformed_ocsp_response() {
+        assert!(ocsp_sct_list(&[0x30, 0x03, 0x0a, 0x01]).is_none());
+        assert!(ocsp_sct_list(&[]).is_none());
+    }
+
+    /// DER `SubjectPublicKeyInfo` prefix for an uncompressed P-256 public key.
+    const P256_SPKI_PREFIX: &[u8] = &[
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+    ];
+}
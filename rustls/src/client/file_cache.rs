@@ -0,0 +1,716 @@
+use crate::client::handy::MAX_TLS13_TICKETS_PER_SERVER;
+use crate::client::{ClientSessionStore, ServerName};
+use crate::dns_name::DnsName;
+use crate::error::{Error, InvalidMessage};
+#[cfg(feature = "logging")]
+use crate::log::warn;
+use crate::msgs::base::PayloadU8;
+use crate::msgs::codec::{Codec, Reader};
+use crate::msgs::persist;
+use crate::suites::SupportedCipherSuite;
+use crate::NamedGroup;
+
+use pki_types::UnixTime;
+
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::fs;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+/// An implementer of `ClientSessionStore` that stores everything in a
+/// file, so that sessions survive the process that made them.
+///
+/// Each operation reads and rewrites the whole file, so this is suited
+/// to programs that make few connections, like command-line tools.  The
+/// file may be shared between processes: they take turns using it, by
+/// creating a lock file next to it.  Expired sessions are pruned whenever
+/// the file is written.
+///
+/// The file contains the secrets of each session, so on Unix it is
+/// created readable only by its owner.  A file that cannot be read
+/// (because it is corrupt, or was written in another format) is
+/// treated as empty, and replaced on the next write.
+pub struct ClientSessionFileCache {
+    path: PathBuf,
+    lock_path: PathBuf,
+    max_servers: usize,
+    suites: Vec<SupportedCipherSuite>,
+    lock: Mutex<()>,
+}
+
+impl ClientSessionFileCache {
+    /// Make a new ClientSessionFileCache, which stores its sessions in
+    /// the file at `path`.  `size` is the maximum number of stored
+    /// sessions.
+    ///
+    /// Sessions are only resumed if their cipher suite is one of `suites`:
+    /// typically the cipher suites of the `ClientConfig` using this store.
+    pub fn new(path: impl AsRef<Path>, size: usize, suites: &[SupportedCipherSuite]) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let mut lock_path = path.clone().into_os_string();
+        lock_path.push(".lock");
+        Self {
+            path,
+            lock_path: lock_path.into(),
+            max_servers: size.saturating_add(MAX_TLS13_TICKETS_PER_SERVER - 1)
+                / MAX_TLS13_TICKETS_PER_SERVER,
+            suites: suites.to_vec(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Read the file, and call `f` on its contents.  If `f` returns
+    /// `true`, the (possibly modified) contents are written back.
+    fn with_servers<T>(&self, f: impl FnOnce(&mut Vec<ServerData>) -> (T, bool)) -> Option<T> {
+        let _guard = self.lock.lock().unwrap();
+        let _lock = match LockFile::acquire(&self.lock_path) {
+            Ok(lock) => lock,
+            Err(_err) => {
+                warn!("cannot lock {:?}: {}", self.lock_path, _err);
+                return None;
+            }
+        };
+
+        let mut servers = self.load();
+        let (ret, modified) = f(&mut servers);
+        if modified {
+            self.prune(&mut servers);
+            if let Err(_err) = self.save(&servers) {
+                warn!("cannot write {:?}: {}", self.path, _err);
+            }
+        }
+        Some(ret)
+    }
+
+    fn load(&self) -> Vec<ServerData> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Vec::new(),
+            Err(_err) => {
+                warn!("cannot read {:?}: {}", self.path, _err);
+                return Vec::new();
+            }
+        };
+
+        match read_file(&bytes) {
+            Ok(servers) => servers,
+            Err(_err) => {
+                warn!(
+                    "ignoring unreadable session file {:?}: {:?}",
+                    self.path, _err
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Remove sessions that have expired or cannot be resumed, and the
+    /// least recently changed servers beyond `max_servers`.
+    fn prune(&self, servers: &mut Vec<ServerData>) {
+        let now = UnixTime::now();
+        for server in servers.iter_mut() {
+            if let Some(tls12) = &server.tls12 {
+                if !self.is_resumable::<persist::Tls12ClientSessionValue>(tls12, now) {
+                    server.tls12 = None;
+                }
+            }
+            server
+                .tls13
+                .retain(|tls13| self.is_resumable::<persist::Tls13ClientSessionValue>(tls13, now));
+        }
+
+        let excess = servers
+            .len()
+            .saturating_sub(self.max_servers);
+        servers.drain(..excess);
+    }
+
+    fn is_resumable<V: ClientSessionValue>(&self, encoded: &[u8], now: UnixTime) -> bool {
+        V::read_bytes(encoded, &self.suites).map_or(false, |value| !value.has_expired(now))
+    }
+
+    fn save(&self, servers: &[ServerData]) -> io::Result<()> {
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+
+        let mut options = fs::OpenOptions::new();
+        options
+            .write(true)
+            .create(true)
+            .truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let mut file = options.open(&temp_path)?;
+        file.write_all(&write_file(servers))?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &self.path)
+    }
+
+    fn decode<V: ClientSessionValue>(&self, encoded: &[u8]) -> Option<V> {
+        V::read_bytes(encoded, &self.suites)
+            .map_err(|_err| warn!("cannot decode stored session: {:?}", _err))
+            .ok()
+    }
+}
+
+impl ClientSessionStore for ClientSessionFileCache {
+    fn set_kx_hint(&self, server_name: &ServerName, group: NamedGroup) {
+        self.with_servers(|servers| {
+            edit_server(servers, server_name).kx_hint = Some(group);
+            ((), true)
+        });
+    }
+
+    fn kx_hint(&self, server_name: &ServerName) -> Option<NamedGroup> {
+        self.with_servers(|servers| {
+            let kx_hint = find_server(servers, server_name).and_then(|server| server.kx_hint);
+            (kx_hint, false)
+        })?
+    }
+
+    fn set_tls12_session(&self, server_name: &ServerName, value: persist::Tls12ClientSessionValue) {
+        #[cfg(feature = "tls12")]
+        self.with_servers(|servers| {
+            edit_server(servers, server_name).tls12 = Some(value.get_encoding());
+            ((), true)
+        });
+
+        #[cfg(not(feature = "tls12"))]
+        let _ = (server_name, value);
+    }
+
+    fn tls12_session(&self, server_name: &ServerName) -> Option<persist::Tls12ClientSessionValue> {
+        let encoded = self.with_servers(|servers| {
+            let encoded = find_server(servers, server_name).and_then(|server| server.tls12.clone());
+            (encoded, false)
+        })??;
+        self.decode(&encoded)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName) {
+        self.with_servers(|servers| match find_server(servers, server_name) {
+            Some(ServerData {
+                tls12: tls12 @ Some(_),
+                ..
+            }) => {
+                *tls12 = None;
+                ((), true)
+            }
+            _ => ((), false),
+        });
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: &ServerName,
+        value: persist::Tls13ClientSessionValue,
+    ) {
+        self.with_servers(|servers| {
+            let server = edit_server(servers, server_name);
+            if server.tls13.len() == MAX_TLS13_TICKETS_PER_SERVER {
+                server.tls13.remove(0);
+            }
+            server.tls13.push(value.get_encoding());
+            ((), true)
+        });
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName,
+    ) -> Option<persist::Tls13ClientSessionValue> {
+        let encoded = self.with_servers(|servers| {
+            match find_server(servers, server_name).and_then(|server| server.tls13.pop()) {
+                Some(encoded) => (Some(encoded), true),
+                None => (None, false),
+            }
+        })??;
+        self.decode(&encoded)
+    }
+}
+
+/// The stored data for one server.  Sessions are kept in their
+/// `get_encoding()` form.
+struct ServerData {
+    name: ServerName,
+    kx_hint: Option<NamedGroup>,
+    tls12: Option<Vec<u8>>,
+    // Up to MAX_TLS13_TICKETS_PER_SERVER TLS1.3 tickets, oldest first.
+    tls13: Vec<Vec<u8>>,
+}
+
+fn find_server<'a>(
+    servers: &'a mut [ServerData],
+    server_name: &ServerName,
+) -> Option<&'a mut ServerData> {
+    servers
+        .iter_mut()
+        .find(|server| &server.name == server_name)
+}
+
+/// Returns the data for `server_name`, after moving it to the end of
+/// `servers` (adding it if needed), so that it is pruned last.
+fn edit_server<'a>(
+    servers: &'a mut Vec<ServerData>,
+    server_name: &ServerName,
+) -> &'a mut ServerData {
+    let server = match servers
+        .iter()
+        .position(|server| &server.name == server_name)
+    {
+        Some(index) => servers.remove(index),
+        None => ServerData {
+            name: server_name.clone(),
+            kx_hint: None,
+            tls12: None,
+            tls13: Vec::new(),
+        },
+    };
+    servers.push(server);
+    servers.last_mut().unwrap()
+}
+
+/// The start of a session file.
+const FILE_MAGIC: &[u8] = b"rustls-client-sessions";
+
+/// The version of the session file format.  Increment this whenever that
+/// format changes.
+const FILE_FORMAT_VERSION: u8 = 1;
+
+fn write_file(servers: &[ServerData]) -> Vec<u8> {
+    let mut bytes = FILE_MAGIC.to_vec();
+    FILE_FORMAT_VERSION.encode(&mut bytes);
+    (servers.len() as u32).encode(&mut bytes);
+    for server in servers {
+        match &server.name {
+            ServerName::DnsName(name) => {
+                0u8.encode(&mut bytes);
+                PayloadU8::new(name.as_ref().as_bytes().to_vec()).encode(&mut bytes);
+            }
+            ServerName::IpAddress(IpAddr::V4(addr)) => {
+                1u8.encode(&mut bytes);
+                PayloadU8::new(addr.octets().to_vec()).encode(&mut bytes);
+            }
+            ServerName::IpAddress(IpAddr::V6(addr)) => {
+                1u8.encode(&mut bytes);
+                PayloadU8::new(addr.octets().to_vec()).encode(&mut bytes);
+            }
+        }
+
+        match server.kx_hint {
+            Some(group) => {
+                1u8.encode(&mut bytes);
+                group.encode(&mut bytes);
+            }
+            None => 0u8.encode(&mut bytes),
+        }
+
+        match &server.tls12 {
+            Some(tls12) => {
+                1u8.encode(&mut bytes);
+                write_value(tls12, &mut bytes);
+            }
+            None => 0u8.encode(&mut bytes),
+        }
+
+        (server.tls13.len() as u8).encode(&mut bytes);
+        for tls13 in &server.tls13 {
+            write_value(tls13, &mut bytes);
+        }
+    }
+    bytes
+}
+
+fn read_file(bytes: &[u8]) -> Result<Vec<ServerData>, Error> {
+    let r = &mut Reader::init(bytes);
+    if r.take(FILE_MAGIC.len()) != Some(FILE_MAGIC) {
+        return Err(Error::General("not a session file".into()));
+    }
+    if u8::read(r)? != FILE_FORMAT_VERSION {
+        return Err(Error::General(
+            "session file has an unsupported format version".into(),
+        ));
+    }
+
+    let count = u32::read(r)?;
+    let mut servers = Vec::new();
+    for _ in 0..count {
+        let name = match (u8::read(r)?, PayloadU8::read(r)?.0) {
+            (0, name) => ServerName::DnsName(
+                DnsName::try_from_ascii(&name).map_err(|_| InvalidMessage::InvalidServerName)?,
+            ),
+            (1, addr) => match addr.len() {
+                4 => {
+                    let mut octets = [0u8; 4];
+                    octets.copy_from_slice(&addr);
+                    ServerName::IpAddress(Ipv4Addr::from(octets).into())
+                }
+                16 => {
+                    let mut octets = [0u8; 16];
+                    octets.copy_from_slice(&addr);
+                    ServerName::IpAddress(Ipv6Addr::from(octets).into())
+                }
+                _ => return Err(InvalidMessage::InvalidServerName.into()),
+            },
+            _ => return Err(InvalidMessage::InvalidServerName.into()),
+        };
+
+        let kx_hint = match u8::read(r)? {
+            0 => None,
+            _ => Some(NamedGroup::read(r)?),
+        };
+
+        let tls12 = match u8::read(r)? {
+            0 => None,
+            _ => Some(read_value(r)?),
+        };
+
+        let mut tls13 = Vec::new();
+        for _ in 0..u8::read(r)? {
+            tls13.push(read_value(r)?);
+        }
+
+        servers.push(ServerData {
+            name,
+            kx_hint,
+            tls12,
+            tls13,
+        });
+    }
+    r.expect_empty("ClientSessionFileCache")?;
+    Ok(servers)
+}
+
+/// Session values include certificate chains, so may not fit in a `PayloadU16`.
+fn write_value(value: &[u8], bytes: &mut Vec<u8>) {
+    (value.len() as u32).encode(bytes);
+    bytes.extend_from_slice(value);
+}
+
+fn read_value(r: &mut Reader) -> Result<Vec<u8>, InvalidMessage> {
+    let len = u32::read(r)? as usize;
+    r.take(len)
+        .map(<[u8]>::to_vec)
+        .ok_or(InvalidMessage::MessageTooShort)
+}
+
+/// The session types stored in the file.
+trait ClientSessionValue: Sized {
+    fn read_bytes(bytes: &[u8], suites: &[SupportedCipherSuite]) -> Result<Self, Error>;
+
+    fn has_expired(&self, now: UnixTime) -> bool;
+}
+
+impl ClientSessionValue for persist::Tls13ClientSessionValue {
+    fn read_bytes(bytes: &[u8], suites: &[SupportedCipherSuite]) -> Result<Self, Error> {
+        Self::read_bytes(bytes, suites)
+    }
+
+    fn has_expired(&self, now: UnixTime) -> bool {
+        self.common.has_expired(now)
+    }
+}
+
+impl ClientSessionValue for persist::Tls12ClientSessionValue {
+    #[cfg(feature = "tls12")]
+    fn read_bytes(bytes: &[u8], suites: &[SupportedCipherSuite]) -> Result<Self, Error> {
+        Self::read_bytes(bytes, suites)
+    }
+
+    #[cfg(not(feature = "tls12"))]
+    fn read_bytes(_bytes: &[u8], _suites: &[SupportedCipherSuite]) -> Result<Self, Error> {
+        Err(Error::General("TLS1.2 support is disabled".into()))
+    }
+
+    #[cfg(feature = "tls12")]
+    fn has_expired(&self, now: UnixTime) -> bool {
+        self.common.has_expired(now)
+    }
+
+    #[cfg(not(feature = "tls12"))]
+    fn has_expired(&self, _now: UnixTime) -> bool {
+        true
+    }
+}
+
+/// A file whose existence means another thread or process is using the
+/// session file.  It is removed when dropped.
+struct LockFile<'a>(&'a Path);
+
+impl<'a> LockFile<'a> {
+    fn acquire(path: &'a Path) -> io::Result<Self> {
+        let start = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+            {
+                Ok(_) => return Ok(Self(path)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err),
+            }
+
+            // A lock this old was left behind by a process that exited
+            // while holding it.
+            let is_stale = fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .map_or(false, |modified| {
+                    SystemTime::now()
+                        .duration_since(modified)
+                        .map_or(false, |age| age > STALE_LOCK_AGE)
+                });
+            if is_stale {
+                let _ = fs::remove_file(path);
+                continue;
+            }
+
+            if start.elapsed() > LOCK_TIMEOUT {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for lock",
+                ));
+            }
+            thread::sleep(LOCK_RETRY_INTERVAL);
+        }
+    }
+}
+
+impl Drop for LockFile<'_> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.0);
+    }
+}
+
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const STALE_LOCK_AGE: Duration = Duration::from_secs(30);
+
+#[cfg(all(test, feature = "ring"))]
+mod tests {
+    use super::*;
+    use crate::crypto::ring::ALL_CIPHER_SUITES;
+    use crate::msgs::enums::NamedGroup;
+    #[cfg(feature = "tls12")]
+    use crate::msgs::handshake::SessionId;
+    use crate::test_util::TempDir;
+
+    use core::convert::TryInto;
+
+    fn open_cache(dir: &TempDir) -> ClientSessionFileCache {
+        ClientSessionFileCache::new(dir.path().join("sessions"), 32, ALL_CIPHER_SUITES)
+    }
+
+    fn tls13_ticket(ticket: &[u8], lifetime_secs: u32) -> persist::Tls13ClientSessionValue {
+        #[cfg_attr(not(feature = "tls12"), allow(clippy::infallible_destructuring_match))]
+        let suite = match crate::cipher_suite::TLS13_AES_256_GCM_SHA384 {
+            SupportedCipherSuite::Tls13(inner) => inner,
+            #[cfg(feature = "tls12")]
+            _ => unreachable!(),
+        };
+        persist::Tls13ClientSessionValue::new(
+            suite,
+            ticket.to_vec(),
+            &[1, 2, 3],
+            Vec::new(),
+            UnixTime::now(),
+            lifetime_secs,
+            0x1234,
+            16384,
+        )
+    }
+
+    #[test]
+    fn test_tls13_tickets_survive_a_new_cache() {
+        let dir = TempDir::new();
+        let name = "example.com".try_into().unwrap();
+        let other = ServerName::IpAddress("::1".parse().unwrap());
+
+        let cache = open_cache(&dir);
+        cache.insert_tls13_ticket(&name, tls13_ticket(b"first", 3600));
+        cache.insert_tls13_ticket(&name, tls13_ticket(b"second", 3600));
+        cache.insert_tls13_ticket(&other, tls13_ticket(b"other", 3600));
+        drop(cache);
+
+        let cache = open_cache(&dir);
+        let ticket = cache.take_tls13_ticket(&name).unwrap();
+        assert_eq!(ticket.ticket(), b"second");
+        assert_eq!(ticket.secret(), &[1, 2, 3]);
+        assert_eq!(ticket.max_early_data_size(), 16384);
+
+        // taking a ticket removes it from the file
+        let cache = open_cache(&dir);
+        assert_eq!(
+            cache
+                .take_tls13_ticket(&name)
+                .unwrap()
+                .ticket(),
+            b"first"
+        );
+        assert!(cache.take_tls13_ticket(&name).is_none());
+        assert_eq!(
+            cache
+                .take_tls13_ticket(&other)
+                .unwrap()
+                .ticket(),
+            b"other"
+        );
+    }
+
+    #[test]
+    fn test_kx_hints_survive_a_new_cache() {
+        let dir = TempDir::new();
+        let name = "example.com".try_into().unwrap();
+
+        open_cache(&dir).set_kx_hint(&name, NamedGroup::X25519);
+        assert_eq!(open_cache(&dir).kx_hint(&name), Some(NamedGroup::X25519));
+        assert_eq!(
+            open_cache(&dir).kx_hint(&"other.com".try_into().unwrap()),
+            None
+        );
+    }
+
+    #[cfg(feature = "tls12")]
+    #[test]
+    fn test_tls12_sessions_survive_a_new_cache() {
+        let dir = TempDir::new();
+        let name = "example.com".try_into().unwrap();
+        let SupportedCipherSuite::Tls12(suite) =
+            crate::cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384
+        else {
+            unreachable!()
+        };
+
+        open_cache(&dir).set_tls12_session(
+            &name,
+            persist::Tls12ClientSessionValue::new(
+                suite,
+                SessionId::empty(),
+                b"ticket".to_vec(),
+                &[4, 5, 6],
+                Vec::new(),
+                UnixTime::now(),
+                3600,
+                true,
+            ),
+        );
+
+        let session = open_cache(&dir)
+            .tls12_session(&name)
+            .unwrap();
+        assert_eq!(session.ticket(), b"ticket");
+        assert_eq!(session.secret(), &[4, 5, 6]);
+        assert!(session.extended_ms());
+
+        open_cache(&dir).remove_tls12_session(&name);
+        assert!(open_cache(&dir)
+            .tls12_session(&name)
+            .is_none());
+    }
+
+    #[test]
+    fn test_expired_and_excess_sessions_are_pruned() {
+        let dir = TempDir::new();
+        let name = "example.com".try_into().unwrap();
+        let cache = open_cache(&dir);
+
+        let mut expired = tls13_ticket(b"expired", 60);
+        expired.rewind_epoch(120);
+        cache.insert_tls13_ticket(&name, expired);
+        for i in 0..MAX_TLS13_TICKETS_PER_SERVER {
+            cache.insert_tls13_ticket(&name, tls13_ticket(&[i as u8], 3600));
+        }
+        cache.insert_tls13_ticket(&name, tls13_ticket(b"newest", 3600));
+
+        let mut tickets = Vec::new();
+        while let Some(ticket) = cache.take_tls13_ticket(&name) {
+            tickets.push(ticket.ticket().to_vec());
+        }
+        assert_eq!(tickets.len(), MAX_TLS13_TICKETS_PER_SERVER);
+        assert_eq!(tickets[0], b"newest");
+        assert!(!tickets.contains(&b"expired".to_vec()));
+        assert!(!tickets.contains(&vec![0]));
+
+        // with room for one server, the least recently changed is pruned
+        let cache = ClientSessionFileCache::new(dir.path().join("small"), 1, ALL_CIPHER_SUITES);
+        let other = "other.com".try_into().unwrap();
+        cache.insert_tls13_ticket(&name, tls13_ticket(b"first", 3600));
+        cache.insert_tls13_ticket(&other, tls13_ticket(b"second", 3600));
+        assert!(cache.take_tls13_ticket(&name).is_none());
+        assert!(cache
+            .take_tls13_ticket(&other)
+            .is_some());
+    }
+
+    #[test]
+    fn test_sessions_with_unsupported_suites_are_ignored() {
+        let dir = TempDir::new();
+        let name = "example.com".try_into().unwrap();
+        open_cache(&dir).insert_tls13_ticket(&name, tls13_ticket(b"ticket", 3600));
+
+        let cache = ClientSessionFileCache::new(dir.path().join("sessions"), 32, &[]);
+        assert!(cache.take_tls13_ticket(&name).is_none());
+    }
+
+    #[test]
+    fn test_unreadable_files_are_replaced() {
+        let dir = TempDir::new();
+        let name = "example.com".try_into().unwrap();
+        let path = dir.path().join("sessions");
+
+        fs::write(&path, b"rustls-client-sessions\x02").unwrap();
+        assert!(open_cache(&dir)
+            .kx_hint(&name)
+            .is_none());
+
+        fs::write(&path, b"garbage").unwrap();
+        assert!(open_cache(&dir)
+            .kx_hint(&name)
+            .is_none());
+        open_cache(&dir).set_kx_hint(&name, NamedGroup::secp256r1);
+        assert_eq!(open_cache(&dir).kx_hint(&name), Some(NamedGroup::secp256r1));
+    }
+
+    #[test]
+    fn test_lock_file_is_removed_after_use() {
+        let dir = TempDir::new();
+        let name = "example.com".try_into().unwrap();
+        let cache = open_cache(&dir);
+
+        cache.set_kx_hint(&name, NamedGroup::X25519);
+        assert_eq!(cache.kx_hint(&name), Some(NamedGroup::X25519));
+        assert!(!cache.lock_path.exists());
+    }
+
+    #[test]
+    fn test_value_encodings_round_trip() {
+        #[cfg_attr(not(feature = "quic"), allow(unused_mut))]
+        let mut value = tls13_ticket(b"ticket", 3600);
+        #[cfg(feature = "quic")]
+        value.set_quic_params(b"params");
+        let encoded = value.get_encoding();
+        let decoded =
+            persist::Tls13ClientSessionValue::read_bytes(&encoded, ALL_CIPHER_SUITES).unwrap();
+        assert_eq!(decoded.get_encoding(), encoded);
+        #[cfg(feature = "quic")]
+        assert_eq!(decoded.quic_params(), b"params");
+
+        let mut bad_version = encoded.clone();
+        bad_version[0] = 2;
+        assert!(
+            persist::Tls13ClientSessionValue::read_bytes(&bad_version, ALL_CIPHER_SUITES).is_err()
+        );
+        assert!(
+            persist::Tls13ClientSessionValue::read_bytes(&encoded[..10], ALL_CIPHER_SUITES)
+                .is_err()
+        );
+    }
+}
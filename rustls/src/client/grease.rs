@@ -0,0 +1,140 @@
+use crate::crypto::CryptoProvider;
+use crate::enums::{CipherSuite, ProtocolVersion, SignatureScheme};
+use crate::msgs::base::Payload;
+use crate::msgs::enums::{ExtensionType, NamedGroup};
+use crate::msgs::handshake::{ClientExtension, KeyShareEntry, ProtocolName, UnknownExtension};
+use crate::rand::GetRandomFailed;
+
+use alloc::vec;
+
+/// The GREASE (RFC 8701) values sent by one client connection.
+///
+/// These are chosen at random once per connection: a ClientHello sent
+/// in response to a HelloRetryRequest must use the same values.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Grease {
+    cipher_suite: u16,
+    group: u16,
+    signature_scheme: u16,
+    version: u16,
+    alpn_protocol: u16,
+    first_extension: u16,
+    last_extension: u16,
+}
+
+impl Grease {
+    pub(super) fn new(provider: &dyn CryptoProvider) -> Result<Self, GetRandomFailed> {
+        let mut random = [0u8; 7];
+        provider.fill_random(&mut random)?;
+        let [cipher_suite, group, signature_scheme, version, alpn_protocol, first, last] =
+            random.map(grease_value);
+
+        Ok(Self {
+            cipher_suite,
+            group,
+            signature_scheme,
+            version,
+            alpn_protocol,
+            first_extension: first,
+            // The two extensions must differ, or the server will reject
+            // the ClientHello for having a duplicate extension.
+            last_extension: match last == first {
+                true => next_grease_value(first),
+                false => last,
+            },
+        })
+    }
+
+    pub(super) fn cipher_suite(&self) -> CipherSuite {
+        CipherSuite::from(self.cipher_suite)
+    }
+
+    pub(super) fn group(&self) -> NamedGroup {
+        NamedGroup::from(self.group)
+    }
+
+    /// A key share for [`Grease::group()`], containing a single zero byte.
+    pub(super) fn key_share(&self) -> KeyShareEntry {
+        KeyShareEntry::new(self.group(), &[0])
+    }
+
+    pub(super) fn signature_scheme(&self) -> SignatureScheme {
+        SignatureScheme::from(self.signature_scheme)
+    }
+
+    pub(super) fn version(&self) -> ProtocolVersion {
+        ProtocolVersion::from(self.version)
+    }
+
+    pub(super) fn alpn_protocol(&self) -> ProtocolName {
+        ProtocolName::from(
+            self.alpn_protocol
+                .to_be_bytes()
+                .to_vec(),
+        )
+    }
+
+    /// An empty extension, to be sent first.
+    pub(super) fn first_extension(&self) -> ClientExtension {
+        ClientExtension::Unknown(UnknownExtension {
+            typ: ExtensionType::from(self.first_extension),
+            payload: Payload::new(vec![]),
+        })
+    }
+
+    /// An extension containing a single zero byte, to be sent last
+    /// (but before any pre-shared key).
+    pub(super) fn last_extension(&self) -> ClientExtension {
+        ClientExtension::Unknown(UnknownExtension {
+            typ: ExtensionType::from(self.last_extension),
+            payload: Payload::new(vec![0]),
+        })
+    }
+}
+
+/// Whether `value` is one of the reserved GREASE code points.
+pub(super) fn is_grease(value: u16) -> bool {
+    let [high, low] = value.to_be_bytes();
+    high == low && low & 0x0f == 0x0a
+}
+
+/// Map a random byte onto one of the 16 GREASE code points:
+/// 0x0a0a, 0x1a1a, ..., 0xfafa.
+fn grease_value(random: u8) -> u16 {
+    let byte = (random & 0xf0) | 0x0a;
+    u16::from_be_bytes([byte, byte])
+}
+
+/// The GREASE code point after `value`, wrapping from 0xfafa to 0x0a0a.
+fn next_grease_value(value: u16) -> u16 {
+    grease_value((value as u8).wrapping_add(0x10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grease_values() {
+        for random in 0..=u8::MAX {
+            assert!(is_grease(grease_value(random)));
+        }
+        assert_eq!(grease_value(0x00), 0x0a0a);
+        assert_eq!(grease_value(0xff), 0xfafa);
+
+        assert!(!is_grease(0x0a1a));
+        assert!(!is_grease(0x0b0b));
+        assert!(!is_grease(NamedGroup::X25519.get_u16()));
+    }
+
+    #[test]
+    fn test_next_grease_value() {
+        assert_eq!(next_grease_value(0x0a0a), 0x1a1a);
+        assert_eq!(next_grease_value(0xfafa), 0x0a0a);
+        for random in 0..=u8::MAX {
+            let value = grease_value(random);
+            assert!(is_grease(next_grease_value(value)));
+            assert_ne!(next_grease_value(value), value);
+        }
+    }
+}
@@ -1,19 +1,20 @@
 use crate::client;
+#[cfg(feature = "ring")]
+use crate::crypto::ring;
 use crate::enums::SignatureScheme;
+use crate::error::{CertificateError, Error};
 use crate::limited_cache;
 use crate::msgs::persist;
 use crate::sign;
+use crate::x509;
 use crate::NamedGroup;
 use crate::ServerName;
-#[cfg(feature = "ring")]
-use crate::{crypto::ring, error::Error};
 
 #[cfg(feature = "ring")]
 use pki_types::{CertificateDer, PrivateKeyDer};
 
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
-#[cfg(feature = "ring")]
 use alloc::vec::Vec;
 use std::sync::Mutex;
 
@@ -42,7 +43,7 @@ impl client::ClientSessionStore for NoClientSessionStorage {
     }
 }
 
-const MAX_TLS13_TICKETS_PER_SERVER: usize = 8;
+pub(super) const MAX_TLS13_TICKETS_PER_SERVER: usize = 8;
 
 struct ServerData {
     kx_hint: Option<NamedGroup>,
@@ -207,18 +208,112 @@ impl client::ResolvesClientCert for AlwaysResolvesClientCert {
     }
 }
 
+/// Something that resolves to one of several client certificate chains,
+/// based on the certificate authorities the server asks for.
+///
+/// Each chain is chosen if any of its certificates was issued by an
+/// authority in the server's `CertificateRequest`, and its key supports
+/// one of the server's signature schemes.  Chains are tried in the order
+/// they were added.  If none is chosen, the default chain (if any) is used
+/// when its key supports one of the server's signature schemes.
+#[derive(Default)]
+pub struct ResolvesClientCertUsingCaHints {
+    identities: Vec<ClientIdentity>,
+    default: Option<Arc<sign::CertifiedKey>>,
+}
+
+struct ClientIdentity {
+    /// The DER-encoded issuer names of each certificate in the chain.
+    issuers: Vec<Vec<u8>>,
+    certified_key: Arc<sign::CertifiedKey>,
+}
+
+impl ResolvesClientCertUsingCaHints {
+    /// Create a new and empty (i.e., knows no certificates) resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a new `sign::CertifiedKey`, to be used when the server asks for
+    /// any of the authorities that issued its certificates.
+    ///
+    /// This function fails if the chain is empty, or if any of its
+    /// certificates cannot be parsed.
+    pub fn add(&mut self, ck: sign::CertifiedKey) -> Result<(), Error> {
+        ck.end_entity_cert()?;
+        let issuers = ck
+            .cert
+            .iter()
+            .map(|cert| x509::cert_issuer(cert).map(<[u8]>::to_vec))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::InvalidCertificate(CertificateError::BadEncoding))?;
+
+        self.identities.push(ClientIdentity {
+            issuers,
+            certified_key: Arc::new(ck),
+        });
+        Ok(())
+    }
+
+    /// Set the `sign::CertifiedKey` to use when no other is chosen,
+    /// or clear it with `None`.
+    pub fn set_default(&mut self, ck: Option<sign::CertifiedKey>) {
+        self.default = ck.map(Arc::new);
+    }
+}
+
+impl client::ResolvesClientCert for ResolvesClientCertUsingCaHints {
+    fn resolve(
+        &self,
+        root_hint_subjects: &[&[u8]],
+        sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<sign::CertifiedKey>> {
+        let supported = |ck: &sign::CertifiedKey| {
+            ck.key
+                .choose_scheme(sigschemes)
+                .is_some()
+        };
+
+        self.identities
+            .iter()
+            .find(|identity| {
+                identity
+                    .issuers
+                    .iter()
+                    .any(|issuer| root_hint_subjects.contains(&issuer.as_slice()))
+                    && supported(&identity.certified_key)
+            })
+            .map(|identity| &identity.certified_key)
+            .or_else(|| {
+                self.default
+                    .as_ref()
+                    .filter(|ck| supported(ck))
+            })
+            .map(Arc::clone)
+    }
+
+    fn has_certs(&self) -> bool {
+        !self.identities.is_empty() || self.default.is_some()
+    }
+}
+
 #[cfg(all(test, feature = "ring"))]
 mod tests {
-    use super::NoClientSessionStorage;
-    use crate::client::ClientSessionStore;
+    use super::{NoClientSessionStorage, ResolvesClientCertUsingCaHints};
+    use crate::client::{ClientSessionStore, ResolvesClientCert};
+    use crate::crypto::ring;
+    use crate::enums::SignatureScheme;
     use crate::msgs::enums::NamedGroup;
     #[cfg(feature = "tls12")]
     use crate::msgs::handshake::SessionId;
     use crate::msgs::persist::Tls13ClientSessionValue;
+    use crate::sign::CertifiedKey;
     use crate::suites::SupportedCipherSuite;
+    use crate::RootCertStore;
 
-    use pki_types::UnixTime;
+    use pki_types::{CertificateDer, UnixTime};
 
+    use alloc::sync::Arc;
     use core::convert::TryInto;
 
     #[test]
@@ -268,4 +363,150 @@ mod tests {
         );
         assert!(c.take_tls13_ticket(&name).is_none());
     }
+
+    fn client_identity(pem_chain: &[u8], pem_key: &[u8]) -> CertifiedKey {
+        let chain = rustls_pemfile::certs(&mut &pem_chain[..])
+            .map(|cert| cert.unwrap())
+            .collect();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut &pem_key[..])
+            .next()
+            .unwrap()
+            .unwrap()
+            .into();
+        CertifiedKey::new(chain, ring::sign::any_supported_type(&key).unwrap())
+    }
+
+    fn ca_hint(pem_ca: &[u8]) -> Vec<u8> {
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(
+                rustls_pemfile::certs(&mut &pem_ca[..])
+                    .next()
+                    .unwrap()
+                    .unwrap(),
+            )
+            .unwrap();
+        roots.subjects()[0].as_ref().to_vec()
+    }
+
+    fn resolved_end_entity(
+        resolver: &ResolvesClientCertUsingCaHints,
+        hints: &[&[u8]],
+        sigschemes: &[SignatureScheme],
+    ) -> Option<CertificateDer<'static>> {
+        resolver
+            .resolve(hints, sigschemes)
+            .map(|ck| ck.cert[0].clone())
+    }
+
+    #[test]
+    fn test_ca_hints_resolver_chooses_by_issuer() {
+        let rsa = client_identity(
+            include_bytes!("../../../test-ca/rsa/client.fullchain"),
+            include_bytes!("../../../test-ca/rsa/client.key"),
+        );
+        let ecdsa = client_identity(
+            include_bytes!("../../../test-ca/ecdsa/client.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/client.key"),
+        );
+        let (rsa_ee, ecdsa_ee) = (rsa.cert[0].clone(), ecdsa.cert[0].clone());
+        let rsa_hint = ca_hint(include_bytes!("../../../test-ca/rsa/ca.cert"));
+        let ecdsa_hint = ca_hint(include_bytes!("../../../test-ca/ecdsa/ca.cert"));
+        let all_schemes = &[
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+        ];
+
+        let mut resolver = ResolvesClientCertUsingCaHints::new();
+        assert!(!resolver.has_certs());
+        resolver.add(rsa).unwrap();
+        resolver.add(ecdsa).unwrap();
+        assert!(resolver.has_certs());
+
+        assert_eq!(
+            resolved_end_entity(&resolver, &[&ecdsa_hint], all_schemes),
+            Some(ecdsa_ee.clone())
+        );
+        assert_eq!(
+            resolved_end_entity(&resolver, &[&rsa_hint], all_schemes),
+            Some(rsa_ee.clone())
+        );
+        assert_eq!(
+            resolved_end_entity(
+                &resolver,
+                &[b"unknown", &ecdsa_hint, &rsa_hint],
+                all_schemes
+            ),
+            Some(rsa_ee.clone())
+        );
+        assert_eq!(
+            resolved_end_entity(&resolver, &[&rsa_hint, &ecdsa_hint], &all_schemes[1..]),
+            Some(ecdsa_ee)
+        );
+        assert_eq!(
+            resolved_end_entity(&resolver, &[&rsa_hint], &all_schemes[1..]),
+            None
+        );
+        assert_eq!(resolved_end_entity(&resolver, &[], all_schemes), None);
+    }
+
+    #[test]
+    fn test_ca_hints_resolver_falls_back_to_default() {
+        let rsa = client_identity(
+            include_bytes!("../../../test-ca/rsa/client.fullchain"),
+            include_bytes!("../../../test-ca/rsa/client.key"),
+        );
+        let rsa_ee = rsa.cert[0].clone();
+        let ecdsa_hint = ca_hint(include_bytes!("../../../test-ca/ecdsa/ca.cert"));
+
+        let mut resolver = ResolvesClientCertUsingCaHints::new();
+        resolver.set_default(Some(rsa));
+        assert!(resolver.has_certs());
+
+        assert_eq!(
+            resolved_end_entity(&resolver, &[], &[SignatureScheme::RSA_PSS_SHA256]),
+            Some(rsa_ee.clone())
+        );
+        assert_eq!(
+            resolved_end_entity(
+                &resolver,
+                &[&ecdsa_hint],
+                &[SignatureScheme::RSA_PSS_SHA256]
+            ),
+            Some(rsa_ee)
+        );
+        assert_eq!(
+            resolved_end_entity(&resolver, &[], &[SignatureScheme::ECDSA_NISTP256_SHA256]),
+            None
+        );
+
+        resolver.set_default(None);
+        assert!(!resolver.has_certs());
+        assert_eq!(
+            resolved_end_entity(&resolver, &[], &[SignatureScheme::RSA_PSS_SHA256]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ca_hints_resolver_rejects_bad_chains() {
+        let key = client_identity(
+            include_bytes!("../../../test-ca/rsa/client.fullchain"),
+            include_bytes!("../../../test-ca/rsa/client.key"),
+        )
+        .key;
+
+        let mut resolver = ResolvesClientCertUsingCaHints::new();
+        assert!(resolver
+            .add(CertifiedKey::new(vec![], Arc::clone(&key)))
+            .is_err());
+        assert!(resolver
+            .add(CertifiedKey::new(
+                vec![CertificateDer::from(b"not a certificate".to_vec())],
+                key
+            ))
+            .is_err());
+        assert!(!resolver.has_certs());
+    }
 }
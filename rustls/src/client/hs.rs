@@ -4,31 +4,35 @@ use crate::check::inappropriate_handshake_message;
 use crate::common_state::{CommonState, State};
 use crate::conn::ConnectionRandoms;
 use crate::crypto::ActiveKeyExchange;
-use crate::enums::{AlertDescription, CipherSuite, ContentType, HandshakeType, ProtocolVersion};
+use crate::enums::{
+    AlertDescription, CipherSuite, ContentType, HandshakeType, ProtocolVersion, SignatureScheme,
+};
 use crate::error::{Error, PeerIncompatible, PeerMisbehaved};
 use crate::hash_hs::HandshakeHashBuffer;
 #[cfg(feature = "logging")]
 use crate::log::{debug, trace};
 use crate::msgs::base::Payload;
+use crate::msgs::codec::Codec;
 use crate::msgs::enums::{Compression, ExtensionType};
 use crate::msgs::enums::{ECPointFormat, PSKKeyExchangeMode};
 use crate::msgs::handshake::ConvertProtocolNameList;
 use crate::msgs::handshake::{CertificateStatusRequest, ClientSessionTicket};
 use crate::msgs::handshake::{ClientExtension, HasServerExtensions};
 use crate::msgs::handshake::{ClientHelloPayload, HandshakeMessagePayload, HandshakePayload};
-use crate::msgs::handshake::{HelloRetryRequest, KeyShareEntry};
+use crate::msgs::handshake::{HelloRetryRequest, KeyShareEntry, UnknownExtension};
 use crate::msgs::handshake::{Random, SessionId};
 use crate::msgs::message::{Message, MessagePayload};
 use crate::msgs::persist;
 use crate::tls13::key_schedule::KeyScheduleEarly;
 use crate::SupportedCipherSuite;
 
+use super::grease::{self, Grease};
 #[cfg(feature = "tls12")]
 use super::tls12;
 use super::Tls12Resumption;
 use crate::client::client_conn::ClientConnectionData;
 use crate::client::common::ClientHelloDetails;
-use crate::client::{tls13, ClientConfig, ServerName};
+use crate::client::{tls13, ClientConfig, ClientHelloPadding, ServerName};
 
 use pki_types::UnixTime;
 
@@ -147,6 +151,10 @@ pub(super) fn start_handshake(
     };
 
     let random = Random::new(config.provider)?;
+    let grease = match config.enable_grease {
+        true => Some(Grease::new(config.provider)?),
+        false => None,
+    };
 
     Ok(emit_client_hello_for_retry(
         transcript_buffer,
@@ -164,6 +172,7 @@ pub(super) fn start_handshake(
             hello: ClientHelloDetails::new(),
             session_id,
             server_name,
+            grease,
         },
         cx,
     ))
@@ -192,6 +201,7 @@ struct ClientHelloInput {
     hello: ClientHelloDetails,
     session_id: SessionId,
     server_name: ServerName,
+    grease: Option<Grease>,
 }
 
 fn emit_client_hello_for_retry(
@@ -219,25 +229,38 @@ fn emit_client_hello_for_retry(
     // should be unreachable thanks to config builder
     assert!(!supported_versions.is_empty());
 
+    let grease = input.grease;
+    let mut named_groups: Vec<_> = config
+        .kx_groups
+        .iter()
+        .map(|skxg| skxg.name())
+        .collect();
+    let mut signature_schemes = config
+        .verifier
+        .supported_verify_schemes();
+    if let Some(grease) = &grease {
+        supported_versions.insert(0, grease.version());
+        named_groups.insert(0, grease.group());
+        signature_schemes.insert(0, grease.signature_scheme());
+    }
+
     let mut exts = vec![
         ClientExtension::SupportedVersions(supported_versions),
         ClientExtension::EcPointFormats(ECPointFormat::SUPPORTED.to_vec()),
-        ClientExtension::NamedGroups(
-            config
-                .kx_groups
-                .iter()
-                .map(|skxg| skxg.name())
-                .collect(),
-        ),
-        ClientExtension::SignatureAlgorithms(
-            config
-                .verifier
-                .supported_verify_schemes(),
-        ),
+        ClientExtension::NamedGroups(named_groups),
+        ClientExtension::SignatureAlgorithms(signature_schemes),
         ClientExtension::ExtendedMasterSecretRequest,
         ClientExtension::CertificateStatusRequest(CertificateStatusRequest::build_ocsp()),
     ];
 
+    if let Some(grease) = &grease {
+        exts.insert(0, grease.first_extension());
+    }
+
+    if config.ct_policy.is_some() {
+        exts.push(ClientExtension::SignedCertificateTimestampRequest);
+    }
+
     if let (Some(sni_name), true) = (input.server_name.for_sni(), config.enable_sni) {
         exts.push(ClientExtension::make_sni(sni_name));
     }
@@ -245,13 +268,36 @@ fn emit_client_hello_for_retry(
     if let Some(key_share) = &key_share {
         debug_assert!(support_tls13);
         let key_share = KeyShareEntry::new(key_share.group(), key_share.pub_key());
-        exts.push(ClientExtension::KeyShare(vec![key_share]));
+        // A ClientHello retried after a HelloRetryRequest must contain
+        // exactly the one key share the server asked for.
+        exts.push(ClientExtension::KeyShare(match (&grease, retryreq) {
+            (Some(grease), None) => vec![grease.key_share(), key_share],
+            _ => vec![key_share],
+        }));
     }
 
     if let Some(cookie) = retryreq.and_then(HelloRetryRequest::get_cookie) {
         exts.push(ClientExtension::Cookie(cookie.clone()));
     }
 
+    if support_tls13 && config.enable_delegated_credentials {
+        let mut schemes = config
+            .verifier
+            .supported_verify_schemes();
+        schemes.retain(SignatureScheme::supported_in_tls13);
+        exts.push(ClientExtension::DelegatedCredential(schemes));
+    }
+
+    if support_tls13
+        && !config
+            .certificate_authorities
+            .is_empty()
+    {
+        exts.push(ClientExtension::AuthorityNames(
+            config.certificate_authorities.clone(),
+        ));
+    }
+
     if support_tls13 {
         // We could support PSK_KE here too. Such connections don't
         // have forward secrecy, and are similar to TLS1.2 resumption.
@@ -260,13 +306,21 @@ fn emit_client_hello_for_retry(
     }
 
     if !config.alpn_protocols.is_empty() {
-        exts.push(ClientExtension::Protocols(Vec::from_slices(
+        let mut protocols = Vec::from_slices(
             &config
                 .alpn_protocols
                 .iter()
                 .map(|proto| &proto[..])
                 .collect::<Vec<_>>(),
-        )));
+        );
+        if let Some(grease) = &grease {
+            protocols.insert(0, grease.alpn_protocol());
+        }
+        exts.push(ClientExtension::Protocols(protocols));
+    }
+
+    if let Some(grease) = &grease {
+        exts.push(grease.last_extension());
     }
 
     // Extra extensions must be placed before the PSK extension
@@ -275,10 +329,12 @@ fn emit_client_hello_for_retry(
     // Do we have a SessionID or ticket cached for this host?
     let tls13_session = prepare_resumption(&input.resuming, &mut exts, suite, cx, config);
 
-    // Note what extensions we sent.
+    // Note what extensions we sent.  The server must not reply to
+    // GREASE extensions.
     input.hello.sent_extensions = exts
         .iter()
         .map(ClientExtension::get_type)
+        .filter(|typ| !grease::is_grease(typ.get_u16()))
         .collect();
 
     let mut cipher_suites: Vec<_> = config
@@ -286,6 +342,9 @@ fn emit_client_hello_for_retry(
         .iter()
         .map(|cs| cs.suite())
         .collect();
+    if let Some(grease) = &grease {
+        cipher_suites.insert(0, grease.cipher_suite());
+    }
     // We don't do renegotiation at all, in fact.
     cipher_suites.push(CipherSuite::TLS_EMPTY_RENEGOTIATION_INFO_SCSV);
 
@@ -301,6 +360,10 @@ fn emit_client_hello_for_retry(
         }),
     };
 
+    // This happens after noting the extensions we sent: the server must
+    // not reply to the padding extension either.
+    pad_client_hello(config.client_hello_padding, &mut chp);
+
     let early_key_schedule = if let Some(resuming) = tls13_session {
         let schedule = tls13::fill_in_psk_binder(&resuming, &transcript_buffer, &mut chp);
         Some((resuming.suite(), schedule))
@@ -364,6 +427,33 @@ fn emit_client_hello_for_retry(
     }
 }
 
+/// Add a padding extension (RFC 7685) to `chp`, if `padding` requires one.
+///
+/// This must be done before the PSK binder is filled in, as the binder
+/// covers the padding.
+fn pad_client_hello(padding: ClientHelloPadding, chp: &mut HandshakeMessagePayload) {
+    let padding_len = match padding.padding_len(chp.get_encoding().len()) {
+        Some(padding_len) => padding_len,
+        None => return,
+    };
+
+    if let HandshakePayload::ClientHello(client_hello) = &mut chp.payload {
+        let exts = &mut client_hello.extensions;
+        // The PSK extension must remain last.
+        let position = match exts.last() {
+            Some(ClientExtension::PresharedKey(_)) => exts.len() - 1,
+            _ => exts.len(),
+        };
+        exts.insert(
+            position,
+            ClientExtension::Unknown(UnknownExtension {
+                typ: ExtensionType::Padding,
+                payload: Payload::new(vec![0; padding_len]),
+            }),
+        );
+    }
+}
+
 /// Prepare resumption with the session state retrieved from storage.
 ///
 /// This function will push onto `exts` to
@@ -813,7 +903,9 @@ impl ExpectServerHelloOrHelloRetryRequest {
         // Early data is not allowed after HelloRetryrequest
         if cx.data.early_data.is_enabled() {
             cx.data.early_data.rejected();
+            cx.common.observe_early_data(false);
         }
+        cx.common.observe_hello_retry_request();
 
         let key_share = match req_group {
             Some(group) if group != offered_key_share.group() => {
@@ -0,0 +1,283 @@
+use crate::client::ServerName;
+use crate::crypto::hash::Hash;
+use crate::dns_name::DnsNameRef;
+use crate::error::{CertificateError, Error};
+use crate::msgs::enums::HashAlgorithm;
+use crate::msgs::handshake::DelegatedCredential;
+use crate::verify::{
+    DigitallySignedStruct, HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+    VerifiedChain,
+};
+use crate::x509;
+use crate::SignatureScheme;
+
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use pki_types::{CertificateDer, UnixTime};
+
+/// The SHA-256 hash of a DER-encoded `SubjectPublicKeyInfo`.
+///
+/// This is the same as the (base64-decoded) `pin-sha256` directive of
+/// HTTP Public Key Pinning, described in RFC7469.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpkiPin([u8; 32]);
+
+impl From<[u8; 32]> for SpkiPin {
+    fn from(hash: [u8; 32]) -> Self {
+        Self(hash)
+    }
+}
+
+impl AsRef<[u8]> for SpkiPin {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// A set of pins, one of which must match a certificate in the verified chain.
+#[derive(Clone, Debug, Default)]
+pub struct SpkiPinSet {
+    pins: Vec<SpkiPin>,
+    backup_pins: Vec<SpkiPin>,
+}
+
+impl SpkiPinSet {
+    /// Make a pin set from the pins of keys currently in use.
+    pub fn new(pins: impl IntoIterator<Item = SpkiPin>) -> Self {
+        Self {
+            pins: pins.into_iter().collect(),
+            backup_pins: Vec::new(),
+        }
+    }
+
+    /// Add pins for backup keys, which are not yet in use.
+    ///
+    /// Backup pins are accepted exactly like other pins.  Configuring at least
+    /// one means the server can move to a new key (or CA) without locking out
+    /// clients that have not been updated.
+    pub fn with_backup_pins(mut self, pins: impl IntoIterator<Item = SpkiPin>) -> Self {
+        self.backup_pins.extend(pins);
+        self
+    }
+
+    fn contains(&self, pin: &SpkiPin) -> bool {
+        self.pins.contains(pin) || self.backup_pins.contains(pin)
+    }
+}
+
+/// A [`ServerCertVerifier`] that adds public key pinning to another verifier.
+///
+/// The wrapped verifier, typically a [`WebPkiServerVerifier`], first verifies
+/// the server's certificate as usual.  Then, if there is a [`SpkiPinSet`] for
+/// the server, the handshake fails with [`CertificateError::PinMismatch`] unless
+/// the `SubjectPublicKeyInfo` of some certificate in the verified chain, or of
+/// its trust anchor, matches a pin.
+///
+/// Pins are checked against the chain the wrapped verifier reports in
+/// [`ServerCertVerified::verified_chain()`], not against the certificates the
+/// server sent; a server could send any certificate.  If the wrapped verifier
+/// does not report a chain, pinned servers are rejected.
+///
+/// [`WebPkiServerVerifier`]: crate::client::WebPkiServerVerifier
+pub struct PinningServerVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    pins: Option<SpkiPinSet>,
+    host_pins: Vec<(ServerName, SpkiPinSet)>,
+    sha256: &'static dyn Hash,
+}
+
+impl PinningServerVerifier {
+    /// Add pinning to `inner`, using the *ring* crypto provider.
+    ///
+    /// No servers are pinned until pins are configured.
+    #[cfg(feature = "ring")]
+    pub fn new(inner: Arc<dyn ServerCertVerifier>) -> Self {
+        Self::new_with_hash(inner, &crate::crypto::ring::hash::SHA256)
+    }
+
+    /// Add pinning to `inner`.
+    ///
+    /// `sha256` is used to hash `SubjectPublicKeyInfo`s; this panics if it
+    /// is not SHA-256.
+    ///
+    /// No servers are pinned until pins are configured.
+    pub fn new_with_hash(inner: Arc<dyn ServerCertVerifier>, sha256: &'static dyn Hash) -> Self {
+        assert_eq!(
+            (sha256.algorithm(), sha256.output_len()),
+            (HashAlgorithm::SHA256, 32),
+            "PinningServerVerifier requires a SHA-256 implementation"
+        );
+
+        Self {
+            inner,
+            pins: None,
+            host_pins: Vec::new(),
+            sha256,
+        }
+    }
+
+    /// Pin all servers without host-specific pins to `pins`.
+    ///
+    /// If this is called multiple times, the last call wins.
+    pub fn with_pins(mut self, pins: SpkiPinSet) -> Self {
+        self.pins = Some(pins);
+        self
+    }
+
+    /// Pin the server `server_name` to `pins`, instead of any pins given
+    /// to [`PinningServerVerifier::with_pins()`].
+    ///
+    /// Names are compared ignoring case and any trailing dot.  If this is
+    /// called multiple times for the same name, the last call wins.
+    pub fn with_host_pins(mut self, server_name: ServerName, pins: SpkiPinSet) -> Self {
+        let server_name = normalise(&server_name);
+        self.host_pins
+            .retain(|(name, _)| *name != server_name);
+        self.host_pins.push((server_name, pins));
+        self
+    }
+
+    /// Compute the pin for a DER-encoded `SubjectPublicKeyInfo`.
+    pub fn pin_for_spki(&self, spki: &[u8]) -> SpkiPin {
+        let mut pin = [0u8; 32];
+        pin.copy_from_slice(self.sha256.hash(spki).as_ref());
+        SpkiPin(pin)
+    }
+
+    /// Compute the pin for the `SubjectPublicKeyInfo` of a DER-encoded certificate.
+    pub fn pin_for_certificate(&self, cert: &CertificateDer<'_>) -> Result<SpkiPin, Error> {
+        x509::cert_spki(cert)
+            .map(|spki| self.pin_for_spki(spki))
+            .ok_or_else(|| CertificateError::BadEncoding.into())
+    }
+
+    fn pins_for(&self, server_name: &ServerName) -> Option<&SpkiPinSet> {
+        let server_name = normalise(server_name);
+        self.host_pins
+            .iter()
+            .find(|(name, _)| *name == server_name)
+            .map(|(_, pins)| pins)
+            .or(self.pins.as_ref())
+    }
+
+    fn check_pins(&self, pins: &SpkiPinSet, chain: &VerifiedChain) -> Result<(), Error> {
+        let certs = Some(chain.end_entity())
+            .into_iter()
+            .chain(chain.intermediates());
+        for cert in certs {
+            if pins.contains(&self.pin_for_certificate(cert)?) {
+                return Ok(());
+            }
+        }
+
+        // webpki's trust anchors omit the `SubjectPublicKeyInfo`'s outer SEQUENCE
+        let mut anchor_spki = chain
+            .trust_anchor()
+            .subject_public_key_info
+            .as_ref()
+            .to_vec();
+        x509::wrap_in_sequence(&mut anchor_spki);
+        match pins.contains(&self.pin_for_spki(&anchor_spki)) {
+            true => Ok(()),
+            false => Err(CertificateError::PinMismatch.into()),
+        }
+    }
+}
+
+impl ServerCertVerifier for PinningServerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        if let Some(pins) = self.pins_for(server_name) {
+            let chain = verified
+                .verified_chain()
+                .ok_or_else(|| {
+                    Error::General(
+                        "pinning requires a verifier which reports the verified chain".to_string(),
+                    )
+                })?;
+            self.check_pins(pins, chain)?;
+        }
+
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner
+            .verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner
+            .verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+
+    fn verify_delegated_credential(
+        &self,
+        message: &[u8],
+        end_entity: &CertificateDer<'_>,
+        credential: &DelegatedCredential,
+        dss: &DigitallySignedStruct,
+        now: UnixTime,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner
+            .verify_delegated_credential(message, end_entity, credential, dss, now)
+    }
+}
+
+/// Lowercase a DNS name and remove any trailing dot, as name matching does.
+fn normalise(server_name: &ServerName) -> ServerName {
+    match server_name {
+        ServerName::DnsName(name) => {
+            let name = name.as_ref();
+            let name = name.strip_suffix('.').unwrap_or(name);
+            // A valid name stays valid without its trailing dot.
+            ServerName::DnsName(
+                DnsNameRef::try_from(name)
+                    .unwrap()
+                    .to_lowercase_owned(),
+            )
+        }
+        ServerName::IpAddress(_) => server_name.clone(),
+    }
+}
+
+impl fmt::Debug for PinningServerVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PinningServerVerifier")
+            .field("inner", &self.inner)
+            .field("pins", &self.pins)
+            .field("host_pins", &self.host_pins)
+            .finish()
+    }
+}
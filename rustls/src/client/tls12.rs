@@ -11,11 +11,12 @@ use crate::msgs::base::{Payload, PayloadU8};
 use crate::msgs::ccs::ChangeCipherSpecPayload;
 use crate::msgs::codec::Codec;
 use crate::msgs::handshake::{
-    CertificatePayload, HandshakeMessagePayload, HandshakePayload, NewSessionTicketPayload,
+    CertificatePayload, HandshakeMessagePayload, HandshakePayload, NewSessionTicketPayload, Sct,
     ServerEcdhParams, SessionId,
 };
 use crate::msgs::message::{Message, MessagePayload};
 use crate::msgs::persist;
+use crate::observer::HandshakeKind;
 use crate::sign::Signer;
 use crate::suites::{PartiallyExtractedSecrets, SupportedCipherSuite};
 use crate::tls12::{self, ConnectionSecrets, Tls12CipherSuite};
@@ -102,10 +103,20 @@ mod server_hello {
                 debug!("Server may staple OCSP response");
             }
 
+            // Save any sent SCTs for verification against the certificate.
+            let server_cert_sct_list = server_hello
+                .get_sct_list()
+                .map(<[_]>::to_vec);
+            #[cfg(feature = "logging")]
+            if let Some(sct_list) = &server_cert_sct_list {
+                debug!("Server sent {} SCTs", sct_list.len());
+            }
+
             // See if we're successfully resuming.
             if let Some(resuming) = self.resuming_session {
                 if resuming.session_id == server_hello.session_id {
                     debug!("Server agreed to resume");
+                    cx.common.handshake_kind = HandshakeKind::Resumed;
 
                     // Is the server telling lies about the ciphersuite?
                     if resuming.suite() != suite {
@@ -175,6 +186,7 @@ mod server_hello {
                 suite,
                 may_send_cert_status,
                 must_issue_new_ticket,
+                server_cert_sct_list,
             }))
         }
     }
@@ -191,6 +203,7 @@ struct ExpectCertificate {
     pub(super) suite: &'static Tls12CipherSuite,
     may_send_cert_status: bool,
     must_issue_new_ticket: bool,
+    server_cert_sct_list: Option<Vec<Sct>>,
 }
 
 impl State<ClientConnectionData> for ExpectCertificate {
@@ -218,9 +231,11 @@ impl State<ClientConnectionData> for ExpectCertificate {
                 suite: self.suite,
                 server_cert_chain,
                 must_issue_new_ticket: self.must_issue_new_ticket,
+                server_cert_sct_list: self.server_cert_sct_list,
             }))
         } else {
-            let server_cert = ServerCertDetails::new(server_cert_chain, vec![]);
+            let server_cert =
+                ServerCertDetails::new(server_cert_chain, vec![], self.server_cert_sct_list);
 
             Ok(Box::new(ExpectServerKx {
                 config: self.config,
@@ -249,6 +264,7 @@ struct ExpectCertificateStatusOrServerKx {
     suite: &'static Tls12CipherSuite,
     server_cert_chain: CertificatePayload,
     must_issue_new_ticket: bool,
+    server_cert_sct_list: Option<Vec<Sct>>,
 }
 
 impl State<ClientConnectionData> for ExpectCertificateStatusOrServerKx {
@@ -270,7 +286,11 @@ impl State<ClientConnectionData> for ExpectCertificateStatusOrServerKx {
                 using_ems: self.using_ems,
                 transcript: self.transcript,
                 suite: self.suite,
-                server_cert: ServerCertDetails::new(self.server_cert_chain, vec![]),
+                server_cert: ServerCertDetails::new(
+                    self.server_cert_chain,
+                    vec![],
+                    self.server_cert_sct_list,
+                ),
                 must_issue_new_ticket: self.must_issue_new_ticket,
             })
             .handle(cx, m),
@@ -292,6 +312,7 @@ impl State<ClientConnectionData> for ExpectCertificateStatusOrServerKx {
                 suite: self.suite,
                 server_cert_chain: self.server_cert_chain,
                 must_issue_new_ticket: self.must_issue_new_ticket,
+                server_cert_sct_list: self.server_cert_sct_list,
             })
             .handle(cx, m),
             payload => Err(inappropriate_handshake_message(
@@ -317,6 +338,7 @@ struct ExpectCertificateStatus {
     suite: &'static Tls12CipherSuite,
     server_cert_chain: CertificatePayload,
     must_issue_new_ticket: bool,
+    server_cert_sct_list: Option<Vec<Sct>>,
 }
 
 impl State<ClientConnectionData> for ExpectCertificateStatus {
@@ -338,7 +360,11 @@ impl State<ClientConnectionData> for ExpectCertificateStatus {
             &server_cert_ocsp_response
         );
 
-        let server_cert = ServerCertDetails::new(self.server_cert_chain, server_cert_ocsp_response);
+        let server_cert = ServerCertDetails::new(
+            self.server_cert_chain,
+            server_cert_ocsp_response,
+            self.server_cert_sct_list,
+        );
 
         Ok(Box::new(ExpectServerKx {
             config: self.config,
@@ -706,6 +732,7 @@ impl State<ClientConnectionData> for ExpectServerDone {
             .cert_chain
             .split_first()
             .ok_or(Error::NoCertificatesPresented)?;
+        let now = UnixTime::now();
         let cert_verified = st
             .config
             .verifier
@@ -714,13 +741,29 @@ impl State<ClientConnectionData> for ExpectServerDone {
                 intermediates,
                 &st.server_name,
                 &st.server_cert.ocsp_response,
-                UnixTime::now(),
+                now,
             )
             .map_err(|err| {
                 cx.common
                     .send_cert_verify_error_alert(err)
             })?;
 
+        // 2.
+        if let Some(ct_policy) = &st.config.ct_policy {
+            ct_policy
+                .verify(
+                    end_entity,
+                    intermediates,
+                    st.server_cert.scts(),
+                    &st.server_cert.ocsp_response,
+                    now,
+                )
+                .map_err(|err| {
+                    cx.common
+                        .send_cert_verify_error_alert(err)
+                })?;
+        }
+
         // 3.
         // Build up the contents of the signed message.
         // It's ClientHello.random || ServerHello.random || ServerKeyExchange.params
@@ -751,6 +794,7 @@ impl State<ClientConnectionData> for ExpectServerDone {
                 })?
         };
         cx.common.peer_certificates = Some(st.server_cert.cert_chain);
+        cx.common.verified_chain = cert_verified.verified_chain().cloned();
 
         // 4.
         if let Some(client_auth) = &st.client_auth {
@@ -771,6 +815,7 @@ impl State<ClientConnectionData> for ExpectServerDone {
                 return Err(PeerMisbehaved::SelectedUnofferedKxGroup.into());
             }
         };
+        cx.common.kx_group = Some(named_group);
         let kx = skxg
             .start()
             .map_err(|_| Error::FailedToGetRandomBytes)?;
@@ -18,12 +18,13 @@ use crate::msgs::enums::ExtensionType;
 use crate::msgs::enums::KeyUpdateRequest;
 use crate::msgs::handshake::NewSessionTicketPayloadTls13;
 use crate::msgs::handshake::{CertificateEntry, CertificatePayloadTls13};
-use crate::msgs::handshake::{ClientExtension, ServerExtension};
+use crate::msgs::handshake::{ClientExtension, DelegatedCredential, ServerExtension};
 use crate::msgs::handshake::{HandshakeMessagePayload, HandshakePayload};
 use crate::msgs::handshake::{HasServerExtensions, ServerHelloPayload};
 use crate::msgs::handshake::{PresharedKeyIdentity, PresharedKeyOffer};
 use crate::msgs::message::{Message, MessagePayload};
 use crate::msgs::persist;
+use crate::observer::HandshakeKind;
 use crate::sign::{CertifiedKey, Signer};
 use crate::suites::PartiallyExtractedSecrets;
 use crate::tls13::construct_client_verify_message;
@@ -63,6 +64,7 @@ static DISALLOWED_TLS13_EXTS: &[ExtensionType] = &[
     ExtensionType::SessionTicket,
     ExtensionType::RenegotiationInfo,
     ExtensionType::ExtendedMasterSecret,
+    ExtensionType::SCT,
 ];
 
 pub(super) fn handle_server_hello(
@@ -98,6 +100,7 @@ pub(super) fn handle_server_hello(
             )
         });
     }
+    cx.common.kx_group = Some(their_key_share.group);
 
     let key_schedule_pre_handshake = if let (Some(selected_psk), Some(early_key_schedule)) =
         (server_hello.get_psk_index(), early_key_schedule)
@@ -136,6 +139,7 @@ pub(super) fn handle_server_hello(
             }
 
             debug!("Resuming using PSK");
+            cx.common.handshake_kind = HandshakeKind::Resumed;
             // The key schedule has been initialized and set in fill_in_psk_binder()
         } else {
             return Err(PeerMisbehaved::SelectedUnofferedPsk.into());
@@ -144,6 +148,9 @@ pub(super) fn handle_server_hello(
     } else {
         debug!("Not resuming");
         // Discard the early data key schedule.
+        if cx.data.early_data.is_enabled() {
+            cx.common.observe_early_data(false);
+        }
         cx.data.early_data.rejected();
         cx.common.early_traffic = false;
         resuming_session.take();
@@ -412,9 +419,11 @@ impl State<ClientConnectionData> for ExpectEncryptedExtensions {
             if was_early_traffic {
                 if exts.early_data_extension_offered() {
                     cx.data.early_data.accepted();
+                    cx.common.observe_early_data(true);
                 } else {
                     cx.data.early_data.rejected();
                     cx.common.early_traffic = false;
+                    cx.common.observe_early_data(false);
                 }
             }
 
@@ -625,8 +634,30 @@ impl State<ClientConnectionData> for ExpectCertificate {
             ));
         }
 
-        let server_cert =
-            ServerCertDetails::new(cert_chain.convert(), cert_chain.get_end_entity_ocsp());
+        let server_cert = ServerCertDetails::new(
+            cert_chain.convert(),
+            cert_chain.get_end_entity_ocsp(),
+            cert_chain
+                .get_end_entity_scts()
+                .map(<[_]>::to_vec),
+        );
+
+        if server_cert.scts.is_some() && self.config.ct_policy.is_none() {
+            return Err(cx.common.send_fatal_alert(
+                AlertDescription::UnsupportedExtension,
+                PeerMisbehaved::UnsolicitedSctList,
+            ));
+        }
+
+        let delegated_credential = cert_chain
+            .get_end_entity_delegated_credential()
+            .cloned();
+        if delegated_credential.is_some() && !self.config.enable_delegated_credentials {
+            return Err(cx.common.send_fatal_alert(
+                AlertDescription::UnsupportedExtension,
+                PeerMisbehaved::UnsolicitedDelegatedCredential,
+            ));
+        }
 
         Ok(Box::new(ExpectCertificateVerify {
             config: self.config,
@@ -636,6 +667,7 @@ impl State<ClientConnectionData> for ExpectCertificate {
             transcript: self.transcript,
             key_schedule: self.key_schedule,
             server_cert,
+            delegated_credential,
             client_auth: self.client_auth,
         }))
     }
@@ -650,6 +682,7 @@ struct ExpectCertificateVerify {
     transcript: HandshakeHash,
     key_schedule: KeyScheduleHandshake,
     server_cert: ServerCertDetails,
+    delegated_credential: Option<DelegatedCredential>,
     client_auth: Option<ClientAuthDetails>,
 }
 
@@ -669,6 +702,7 @@ impl State<ClientConnectionData> for ExpectCertificateVerify {
             .cert_chain
             .split_first()
             .ok_or(Error::NoCertificatesPresented)?;
+        let now = UnixTime::now();
         let cert_verified = self
             .config
             .verifier
@@ -677,29 +711,51 @@ impl State<ClientConnectionData> for ExpectCertificateVerify {
                 intermediates,
                 &self.server_name,
                 &self.server_cert.ocsp_response,
-                UnixTime::now(),
+                now,
             )
             .map_err(|err| {
                 cx.common
                     .send_cert_verify_error_alert(err)
             })?;
 
-        // 2. Verify their signature on the handshake.
+        // Also verify any SCTs provided with the certificate.
+        if let Some(ct_policy) = &self.config.ct_policy {
+            ct_policy
+                .verify(
+                    end_entity,
+                    intermediates,
+                    self.server_cert.scts(),
+                    &self.server_cert.ocsp_response,
+                    now,
+                )
+                .map_err(|err| {
+                    cx.common
+                        .send_cert_verify_error_alert(err)
+                })?;
+        }
+
+        // 2. Verify their signature on the handshake, which is made by the
+        // delegated credential's key if they sent one.
         let handshake_hash = self.transcript.get_current_hash();
-        let sig_verified = self
-            .config
-            .verifier
-            .verify_tls13_signature(
-                &construct_server_verify_message(&handshake_hash),
-                &self.server_cert.cert_chain[0],
+        let message = construct_server_verify_message(&handshake_hash);
+        let verifier = &self.config.verifier;
+        let sig_verified = match &self.delegated_credential {
+            Some(credential) => verifier.verify_delegated_credential(
+                &message,
+                end_entity,
+                credential,
                 cert_verify,
-            )
-            .map_err(|err| {
-                cx.common
-                    .send_cert_verify_error_alert(err)
-            })?;
+                now,
+            ),
+            None => verifier.verify_tls13_signature(&message, end_entity, cert_verify),
+        }
+        .map_err(|err| {
+            cx.common
+                .send_cert_verify_error_alert(err)
+        })?;
 
         cx.common.peer_certificates = Some(self.server_cert.cert_chain);
+        cx.common.verified_chain = cert_verified.verified_chain().cloned();
         self.transcript.add_message(&m);
 
         Ok(Box::new(ExpectFinished {
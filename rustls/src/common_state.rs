@@ -4,11 +4,12 @@ use crate::error::{Error, InvalidMessage, PeerMisbehaved};
 use crate::log::{debug, warn};
 use crate::msgs::alert::AlertMessagePayload;
 use crate::msgs::base::Payload;
-use crate::msgs::enums::{AlertLevel, KeyUpdateRequest};
+use crate::msgs::enums::{AlertLevel, KeyUpdateRequest, NamedGroup};
 use crate::msgs::fragmenter::MessageFragmenter;
 #[cfg(feature = "quic")]
 use crate::msgs::message::MessagePayload;
 use crate::msgs::message::{BorrowedPlainMessage, Message, OpaqueMessage, PlainMessage};
+use crate::observer::{HandshakeKind, HandshakeObserver, HandshakeSummary};
 #[cfg(feature = "quic")]
 use crate::quic;
 use crate::record_layer;
@@ -17,9 +18,12 @@ use crate::suites::SupportedCipherSuite;
 #[cfg(feature = "tls12")]
 use crate::tls12::ConnectionSecrets;
 use crate::vecbuf::ChunkVecBuffer;
+use crate::verify::{VerifiedChain, VerifiedChainUnavailable};
 
 use alloc::boxed::Box;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+use std::time::Instant;
 
 use pki_types::CertificateDer;
 
@@ -40,6 +44,7 @@ pub struct CommonState {
     pub(crate) has_seen_eof: bool,
     pub(crate) received_middlebox_ccs: u8,
     pub(crate) peer_certificates: Option<Vec<CertificateDer<'static>>>,
+    pub(crate) verified_chain: Option<VerifiedChain>,
     message_fragmenter: MessageFragmenter,
     pub(crate) received_plaintext: ChunkVecBuffer,
     sendable_plaintext: ChunkVecBuffer,
@@ -52,6 +57,15 @@ pub struct CommonState {
     #[cfg(feature = "quic")]
     pub(crate) quic: quic::Quic,
     pub(crate) enable_secret_extraction: bool,
+
+    observer: Option<Arc<dyn HandshakeObserver>>,
+    handshake_start: Option<Instant>,
+    pub(crate) handshake_kind: HandshakeKind,
+    pub(crate) kx_group: Option<NamedGroup>,
+    hello_retry_request: bool,
+
+    #[cfg(feature = "tracing")]
+    pub(crate) span: tracing::Span,
 }
 
 impl CommonState {
@@ -71,6 +85,7 @@ impl CommonState {
             has_seen_eof: false,
             received_middlebox_ccs: 0,
             peer_certificates: None,
+            verified_chain: None,
             message_fragmenter: MessageFragmenter::default(),
             received_plaintext: ChunkVecBuffer::new(Some(DEFAULT_RECEIVED_PLAINTEXT_LIMIT)),
             sendable_plaintext: ChunkVecBuffer::new(Some(DEFAULT_BUFFER_LIMIT)),
@@ -81,6 +96,15 @@ impl CommonState {
             #[cfg(feature = "quic")]
             quic: quic::Quic::default(),
             enable_secret_extraction: false,
+
+            observer: None,
+            handshake_start: None,
+            handshake_kind: HandshakeKind::Full,
+            kx_group: None,
+            hello_retry_request: false,
+
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::none(),
         }
     }
 
@@ -121,6 +145,38 @@ impl CommonState {
         self.peer_certificates.as_deref()
     }
 
+    /// Retrieves the certificate chain built when verifying the peer's
+    /// certificate, ending at the trust anchor that was used.
+    ///
+    /// Unlike [`CommonState::peer_certificates()`], this contains only the
+    /// certificates the verifier actually used, so it can be relied on to
+    /// identify the peer's real issuer.
+    ///
+    /// This is only available for full handshakes, and only if the verifier
+    /// reported the chain it built (as the verifiers in this crate do); see
+    /// [`ServerCertVerified::with_verified_chain()`] and
+    /// [`ClientCertVerified::with_verified_chain()`].  Otherwise, the error
+    /// says why there is no chain.
+    ///
+    /// For clients, this is the server's chain.  For servers, this is the
+    /// client's chain, if client authentication was completed.
+    ///
+    /// [`ServerCertVerified::with_verified_chain()`]: crate::client::danger::ServerCertVerified::with_verified_chain
+    /// [`ClientCertVerified::with_verified_chain()`]: crate::server::danger::ClientCertVerified::with_verified_chain
+    pub fn verified_chain(&self) -> Result<&VerifiedChain, VerifiedChainUnavailable> {
+        if let Some(chain) = &self.verified_chain {
+            return Ok(chain);
+        }
+
+        Err(match self.handshake_kind {
+            HandshakeKind::Resumed => VerifiedChainUnavailable::Resumed,
+            HandshakeKind::Full if self.peer_certificates.is_some() && !self.is_handshaking() => {
+                VerifiedChainUnavailable::NotReported
+            }
+            HandshakeKind::Full => VerifiedChainUnavailable::NotVerified,
+        })
+    }
+
     /// Retrieves the protocol agreed with the peer via ALPN.
     ///
     /// A return value of `None` after handshake completion
@@ -148,6 +204,12 @@ impl CommonState {
         matches!(self.negotiated_version, Some(ProtocolVersion::TLSv1_3))
     }
 
+    /// Whether this is a server answering an ACME TLS-ALPN-01 challenge.
+    pub(crate) fn is_acme_challenge(&self) -> bool {
+        self.side == Side::Server
+            && self.alpn_protocol.as_deref() == Some(crate::server::ACME_TLS_ALPN_PROTOCOL)
+    }
+
     pub(crate) fn process_main_protocol<Data>(
         &mut self,
         msg: Message,
@@ -167,9 +229,14 @@ impl CommonState {
             }
         }
 
+        #[cfg(feature = "tracing")]
+        let from = state.name();
+
         let mut cx = Context { common: self, data };
         match state.handle(&mut cx, msg) {
             Ok(next) => {
+                #[cfg(feature = "tracing")]
+                self.trace_transition(from, next.name());
                 state = next;
                 Ok(state)
             }
@@ -294,6 +361,12 @@ impl CommonState {
             return len;
         }
 
+        if self.is_acme_challenge() {
+            // RFC 8737 forbids application data on these connections, so
+            // discard it.
+            return data.len();
+        }
+
         debug_assert!(self.record_layer.is_encrypting());
 
         if data.is_empty() {
@@ -310,8 +383,89 @@ impl CommonState {
     }
 
     pub(crate) fn start_traffic(&mut self) {
+        let first = !self.may_receive_application_data;
         self.may_receive_application_data = true;
         self.start_outgoing_traffic();
+        if first {
+            self.observe_handshake_complete();
+        }
+    }
+
+    /// Report events in this connection's handshake to `observer`,
+    /// timing the handshake from now.
+    pub(crate) fn set_handshake_observer(&mut self, observer: &Arc<dyn HandshakeObserver>) {
+        self.observer = Some(Arc::clone(observer));
+        self.handshake_start = Some(Instant::now());
+    }
+
+    fn observe_handshake_complete(&self) {
+        let (observer, suite, version) = match (&self.observer, self.suite, self.negotiated_version)
+        {
+            (Some(observer), Some(suite), Some(version)) => (observer, suite, version),
+            _ => return,
+        };
+
+        observer.handshake_complete(&HandshakeSummary {
+            side: self.side,
+            kind: self.handshake_kind,
+            hello_retry_request: self.hello_retry_request,
+            protocol_version: version,
+            cipher_suite: suite.suite(),
+            kx_group: self.kx_group,
+            duration: self
+                .handshake_start
+                .map(|start| start.elapsed())
+                .unwrap_or_default(),
+        });
+    }
+
+    /// Report `err` as a handshake failure, if the handshake is still
+    /// in progress.
+    pub(crate) fn observe_handshake_failure(&self, err: &Error) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(error = %err, handshaking = self.is_handshaking(), "connection failed");
+
+        if let Some(observer) = &self.observer {
+            if self.is_handshaking() {
+                observer.handshake_failed(self.side, err);
+            }
+        }
+    }
+
+    /// Start a new span for this connection, which is a child of the
+    /// current span.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn start_span(&mut self) {
+        self.span = tracing::info_span!(
+            "tls",
+            side = ?self.side,
+            server_name = tracing::field::Empty,
+            protocol_version = tracing::field::Empty,
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    fn trace_transition(&self, from: &'static str, to: &'static str) {
+        if let Some(version) = self.negotiated_version {
+            self.span
+                .record("protocol_version", tracing::field::debug(version));
+        }
+        if from != to {
+            tracing::debug!(from, to, "state transition");
+        }
+    }
+
+    pub(crate) fn observe_hello_retry_request(&mut self) {
+        self.hello_retry_request = true;
+        if let Some(observer) = &self.observer {
+            observer.hello_retry_request(self.side);
+        }
+    }
+
+    pub(crate) fn observe_early_data(&self, accepted: bool) {
+        if let Some(observer) = &self.observer {
+            observer.early_data(self.side, accepted);
+        }
     }
 
     /// Sets a limit on the internal buffers used to buffer
@@ -437,6 +591,10 @@ impl CommonState {
     }
 
     pub(crate) fn process_alert(&mut self, alert: &AlertMessagePayload) -> Result<(), Error> {
+        if let Some(observer) = &self.observer {
+            observer.alert_received(self.side, alert.description);
+        }
+
         // Reject unknown AlertLevels.
         if let AlertLevel::Unknown(_) = alert.level {
             return Err(self.send_fatal_alert(
@@ -487,6 +645,9 @@ impl CommonState {
         let m = Message::build_alert(AlertLevel::Fatal, desc);
         self.send_msg(m, self.record_layer.is_encrypting());
         self.sent_fatal_alert = true;
+        if let Some(observer) = &self.observer {
+            observer.alert_sent(self.side, desc);
+        }
         err.into()
     }
 
@@ -503,6 +664,9 @@ impl CommonState {
     fn send_warning_alert_no_log(&mut self, desc: AlertDescription) {
         let m = Message::build_alert(AlertLevel::Warning, desc);
         self.send_msg(m, self.record_layer.is_encrypting());
+        if let Some(observer) = &self.observer {
+            observer.alert_sent(self.side, desc);
+        }
     }
 
     pub(crate) fn set_max_fragment_size(&mut self, new: Option<usize>) -> Result<(), Error> {
@@ -630,6 +794,12 @@ pub(crate) trait State<Data>: Send + Sync {
         message: Message,
     ) -> Result<Box<dyn State<Data>>, Error>;
 
+    /// The name of this state, for tracing.
+    #[cfg(feature = "tracing")]
+    fn name(&self) -> &'static str {
+        core::any::type_name::<Self>()
+    }
+
     fn export_keying_material(
         &self,
         _output: &mut [u8],
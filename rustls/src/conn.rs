@@ -623,10 +623,25 @@ impl<Data> ConnectionCore<Data> {
             }
         };
 
-        while let Some(msg) = self.deframe()? {
+        #[cfg(feature = "tracing")]
+        let _span = self.common_state.span.clone().entered();
+
+        loop {
+            let msg = match self.deframe() {
+                Ok(Some(msg)) => msg,
+                Ok(None) => break,
+                Err(e) => {
+                    self.common_state
+                        .observe_handshake_failure(&e);
+                    return Err(e);
+                }
+            };
+
             match self.process_msg(msg, state) {
                 Ok(new) => state = new,
                 Err(e) => {
+                    self.common_state
+                        .observe_handshake_failure(&e);
                     self.state = Err(e.clone());
                     return Err(e);
                 }
@@ -6,6 +6,7 @@ use crate::tls13::key_schedule::{hkdf_expand_label, hkdf_expand_label_aead_key};
 use crate::tls13::Tls13CipherSuite;
 
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 use ring::aead;
 
@@ -29,26 +30,51 @@ impl HeaderProtectionKey {
         packet_number: &mut [u8],
         masked: bool,
     ) -> Result<(), Error> {
-        // This implements "Header Protection Application" almost verbatim.
-        // <https://datatracker.ietf.org/doc/html/rfc9001#section-5.4.1>
+        let mask = self.new_mask(sample, packet_number)?;
+        Self::apply_mask(&mask, first, packet_number, masked);
+        Ok(())
+    }
+
+    fn xor_batch_in_place(
+        &self,
+        headers: &mut [quic::BatchHeader<'_>],
+        masked: bool,
+    ) -> Result<(), Error> {
+        // Compute every mask before touching any header, so a bad entry
+        // leaves the whole batch unchanged.
+        let masks = headers
+            .iter()
+            .map(|header| self.new_mask(header.sample, header.packet_number))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (header, mask) in headers.iter_mut().zip(masks.iter()) {
+            Self::apply_mask(mask, header.first, header.packet_number, masked);
+        }
+
+        Ok(())
+    }
 
+    fn new_mask(&self, sample: &[u8], packet_number: &[u8]) -> Result<[u8; 5], Error> {
         let mask = self
             .0
             .new_mask(sample)
             .map_err(|_| Error::General("sample of invalid length".into()))?;
 
-        // The `unwrap()` will not panic because `new_mask` returns a
-        // non-empty result.
-        let (first_mask, pn_mask) = mask.split_first().unwrap();
-
         // It is OK for the `mask` to be longer than `packet_number`,
         // but a valid `packet_number` will never be longer than `mask`.
-        if packet_number.len() > pn_mask.len() {
+        if packet_number.len() > mask.len() - 1 {
             return Err(Error::General("packet number too long".into()));
         }
 
-        // Infallible from this point on. Before this point, `first` and
-        // `packet_number` are unchanged.
+        Ok(mask)
+    }
+
+    fn apply_mask(mask: &[u8; 5], first: &mut u8, packet_number: &mut [u8], masked: bool) {
+        // This implements "Header Protection Application" almost verbatim.
+        // <https://datatracker.ietf.org/doc/html/rfc9001#section-5.4.1>
+
+        // The `unwrap()` will not panic because the mask is non-empty.
+        let (first_mask, pn_mask) = mask.split_first().unwrap();
 
         const LONG_HEADER_FORM: u8 = 0x80;
         let bits = match *first & LONG_HEADER_FORM == LONG_HEADER_FORM {
@@ -72,8 +98,6 @@ impl HeaderProtectionKey {
         {
             *dst ^= m;
         }
-
-        Ok(())
     }
 }
 
@@ -96,6 +120,22 @@ impl quic::HeaderProtectionKey for HeaderProtectionKey {
         self.xor_in_place(sample, first, packet_number, true)
     }
 
+    /// Adds QUIC Header Protection to a batch of packets.
+    ///
+    /// All the masks are computed before any header is changed, so on error
+    /// every entry of `headers` is left as it was.
+    fn encrypt_batch_in_place(&self, headers: &mut [quic::BatchHeader<'_>]) -> Result<(), Error> {
+        self.xor_batch_in_place(headers, false)
+    }
+
+    /// Removes QUIC Header Protection from a batch of packets.
+    ///
+    /// All the masks are computed before any header is changed, so on error
+    /// every entry of `headers` is left as it was.
+    fn decrypt_batch_in_place(&self, headers: &mut [quic::BatchHeader<'_>]) -> Result<(), Error> {
+        self.xor_batch_in_place(headers, true)
+    }
+
     #[inline]
     fn sample_len(&self) -> usize {
         self.0.algorithm().sample_len()
@@ -225,6 +265,19 @@ impl crate::quic::Algorithm for KeyBuilder {
             expander, version, self.1,
         ))
     }
+
+    fn fixed_packet_key(
+        &self,
+        suite: &'static Tls13CipherSuite,
+        key: &[u8],
+        iv: Iv,
+    ) -> Box<dyn quic::PacketKey> {
+        Box::new(PacketKey {
+            key: aead::LessSafeKey::new(aead::UnboundKey::new(self.0, key).unwrap()),
+            iv,
+            suite,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -422,4 +475,74 @@ mod tests {
         ];
         assert_eq!(server_packet[..], expected_server_packet[..]);
     }
+
+    fn test_retry_integrity_tag(version: Version, retry_packet: &[u8]) {
+        const ORIGINAL_DST_CID: &[u8] = &[0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+        let (packet, tag) = retry_packet.split_at(retry_packet.len() - 16);
+
+        let computed = version
+            .retry_integrity_tag(TLS13_AES_128_GCM_SHA256_INTERNAL, ORIGINAL_DST_CID, packet)
+            .unwrap();
+        assert_eq!(computed.as_ref(), tag);
+
+        version
+            .verify_retry_integrity_tag(
+                TLS13_AES_128_GCM_SHA256_INTERNAL,
+                ORIGINAL_DST_CID,
+                retry_packet,
+            )
+            .unwrap();
+
+        let mut corrupt = retry_packet.to_vec();
+        corrupt[1] ^= 1;
+        assert_eq!(
+            version.verify_retry_integrity_tag(
+                TLS13_AES_128_GCM_SHA256_INTERNAL,
+                ORIGINAL_DST_CID,
+                &corrupt,
+            ),
+            Err(Error::DecryptError)
+        );
+        assert_eq!(
+            version.verify_retry_integrity_tag(
+                TLS13_AES_128_GCM_SHA256_INTERNAL,
+                &ORIGINAL_DST_CID[1..],
+                retry_packet,
+            ),
+            Err(Error::DecryptError)
+        );
+    }
+
+    #[test]
+    fn test_retry_integrity_tag_v1() {
+        // https://www.rfc-editor.org/rfc/rfc9001.html#name-retry
+        test_retry_integrity_tag(
+            Version::V1,
+            &[
+                0xff, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0xf0, 0x67, 0xa5, 0x50, 0x2a, 0x42, 0x62,
+                0xb5, 0x74, 0x6f, 0x6b, 0x65, 0x6e, 0x04, 0xa2, 0x65, 0xba, 0x2e, 0xff, 0x4d, 0x82,
+                0x90, 0x58, 0xfb, 0x3f, 0x0f, 0x24, 0x96, 0xba,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_retry_integrity_tag_v2() {
+        // https://www.rfc-editor.org/rfc/rfc9369.html#name-retry
+        test_retry_integrity_tag(
+            Version::V2,
+            &[
+                0xcf, 0x6b, 0x33, 0x43, 0xcf, 0x00, 0x08, 0xf0, 0x67, 0xa5, 0x50, 0x2a, 0x42, 0x62,
+                0xb5, 0x74, 0x6f, 0x6b, 0x65, 0x6e, 0xc8, 0x64, 0x6c, 0xe8, 0xbf, 0xe3, 0x39, 0x52,
+                0xd9, 0x55, 0x54, 0x36, 0x65, 0xdc, 0xc7, 0xb6,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_retry_integrity_tag_requires_aes_128_gcm() {
+        assert!(Version::V1
+            .retry_integrity_tag(TLS13_CHACHA20_POLY1305_SHA256_INTERNAL, &[], &[0xff])
+            .is_err());
+    }
 }
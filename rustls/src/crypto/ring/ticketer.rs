@@ -1,6 +1,7 @@
 use crate::error::Error;
 use crate::rand::GetRandomFailed;
 use crate::server::ProducesTickets;
+use crate::ticketer::{TicketKeyRing, TicketKeys};
 
 use ring::aead;
 
@@ -22,20 +23,36 @@ impl Ticketer {
             make_ticket_generator,
         )?))
     }
+
+    /// Make a Ticketer that uses keys supplied by the application,
+    /// so that several servers can share them.  Each key's secret
+    /// must be 32 random bytes.
+    ///
+    /// `lifetime` is the lifetime of tickets in seconds.  Keys are
+    /// rotated by calling [`TicketKeyRing::set_keys()`] on the result.
+    ///
+    /// The encryption mechanism used is Chacha20Poly1305.
+    pub fn with_keys(lifetime: u32, keys: TicketKeys) -> Result<Arc<TicketKeyRing>, Error> {
+        Ok(Arc::new(TicketKeyRing::new(
+            lifetime,
+            make_keyed_ticketer,
+            keys,
+        )?))
+    }
 }
 
 fn make_ticket_generator() -> Result<Box<dyn ProducesTickets>, GetRandomFailed> {
     let mut key = [0u8; 32];
     super::RING.fill_random(&mut key)?;
 
-    let alg = &aead::CHACHA20_POLY1305;
-    let key = aead::UnboundKey::new(alg, &key).unwrap();
+    Ok(Box::new(AeadTicketer::new(&key).unwrap()))
+}
 
-    Ok(Box::new(AeadTicketer {
-        alg,
-        key: aead::LessSafeKey::new(key),
-        lifetime: 60 * 60 * 12,
-    }))
+fn make_keyed_ticketer(secret: &[u8]) -> Result<Box<dyn ProducesTickets>, Error> {
+    match AeadTicketer::new(secret) {
+        Ok(ticketer) => Ok(Box::new(ticketer)),
+        Err(_) => Err(Error::General("ticket key must be 32 bytes".into())),
+    }
 }
 
 /// This is a `ProducesTickets` implementation which uses
@@ -48,6 +65,19 @@ struct AeadTicketer {
     lifetime: u32,
 }
 
+impl AeadTicketer {
+    fn new(key: &[u8]) -> Result<Self, ring::error::Unspecified> {
+        let alg = &aead::CHACHA20_POLY1305;
+        let key = aead::UnboundKey::new(alg, key)?;
+
+        Ok(Self {
+            alg,
+            key: aead::LessSafeKey::new(key),
+            lifetime: 60 * 60 * 12,
+        })
+    }
+}
+
 impl ProducesTickets for AeadTicketer {
     fn enabled(&self) -> bool {
         true
@@ -177,4 +207,84 @@ mod tests {
         assert_eq!(t.decrypt(&cipher2).unwrap(), b"ticket 2");
         assert_eq!(t.decrypt(&cipher3).unwrap(), b"ticket 3");
     }
+
+    fn ticket_key(id: u8) -> crate::TicketKey {
+        crate::TicketKey {
+            id: [id; crate::TICKET_KEY_ID_LEN],
+            secret: vec![id; 32],
+        }
+    }
+
+    fn ticket_keys(previous: Option<u8>, current: u8, next: Option<u8>) -> TicketKeys {
+        TicketKeys {
+            current: ticket_key(current),
+            previous: previous.map(ticket_key),
+            next: next.map(ticket_key),
+        }
+    }
+
+    #[test]
+    fn ticketkeyring_shares_keys() {
+        let a = Ticketer::with_keys(3600, ticket_keys(None, 1, None)).unwrap();
+        let b = Ticketer::with_keys(3600, ticket_keys(None, 1, None)).unwrap();
+        assert!(a.enabled());
+        assert_eq!(a.lifetime(), 3600);
+
+        let cipher = a.encrypt(b"ticket").unwrap();
+        assert_eq!(
+            cipher[..crate::TICKET_KEY_ID_LEN],
+            [1; crate::TICKET_KEY_ID_LEN]
+        );
+        assert_eq!(b.decrypt(&cipher).unwrap(), b"ticket");
+    }
+
+    #[test]
+    fn ticketkeyring_rotation_test() {
+        let a = Ticketer::with_keys(3600, ticket_keys(None, 1, Some(2))).unwrap();
+        let b = Ticketer::with_keys(3600, ticket_keys(None, 1, Some(2))).unwrap();
+        let cipher1 = b.encrypt(b"ticket 1").unwrap();
+
+        // `a` rotates before `b`: each accepts the other's tickets
+        a.set_keys(ticket_keys(Some(1), 2, Some(3)))
+            .unwrap();
+        let cipher2 = a.encrypt(b"ticket 2").unwrap();
+        assert_eq!(a.decrypt(&cipher1).unwrap(), b"ticket 1");
+        assert_eq!(b.decrypt(&cipher2).unwrap(), b"ticket 2");
+
+        a.set_keys(ticket_keys(Some(2), 3, None))
+            .unwrap();
+        let cipher3 = a.encrypt(b"ticket 3").unwrap();
+        assert!(a.decrypt(&cipher1).is_none());
+        assert_eq!(a.decrypt(&cipher2).unwrap(), b"ticket 2");
+        assert_eq!(a.decrypt(&cipher3).unwrap(), b"ticket 3");
+        assert!(b.decrypt(&cipher3).is_none());
+    }
+
+    #[test]
+    fn ticketkeyring_rejects_bad_keys() {
+        let mut keys = ticket_keys(None, 1, None);
+        keys.current.secret.truncate(16);
+        assert!(Ticketer::with_keys(3600, keys).is_err());
+
+        assert!(Ticketer::with_keys(3600, ticket_keys(Some(1), 1, None)).is_err());
+        assert!(Ticketer::with_keys(3600, ticket_keys(None, 1, Some(1))).is_err());
+
+        // a failed rotation leaves the keys in use unchanged
+        let t = Ticketer::with_keys(3600, ticket_keys(None, 1, None)).unwrap();
+        let cipher = t.encrypt(b"ticket").unwrap();
+        assert!(t
+            .set_keys(ticket_keys(Some(2), 2, None))
+            .is_err());
+        assert_eq!(t.decrypt(&cipher).unwrap(), b"ticket");
+    }
+
+    #[test]
+    fn ticketkeyring_rejects_short_tickets() {
+        let t = Ticketer::with_keys(3600, ticket_keys(None, 1, None)).unwrap();
+        assert!(t.decrypt(&[]).is_none());
+        assert!(t.decrypt(&[1; 4]).is_none());
+        assert!(t
+            .decrypt(&[1; crate::TICKET_KEY_ID_LEN])
+            .is_none());
+    }
 }
@@ -1,6 +1,8 @@
 use crate::enums::{SignatureAlgorithm, SignatureScheme};
 use crate::error::Error;
 
+use crate::msgs::handshake::DelegatedCredential;
+
 use pki_types::CertificateDer;
 
 use alloc::boxed::Box;
@@ -41,6 +43,17 @@ pub struct CertifiedKey {
     /// An optional OCSP response from the certificate issuer,
     /// attesting to its continued validity.
     pub ocsp: Option<Vec<u8>>,
+
+    /// An optional collection of SCTs from CT logs, proving the
+    /// certificate is included on those logs.  This must be
+    /// a `SignedCertificateTimestampList` encoding; see RFC6962.
+    pub sct_list: Option<Vec<u8>>,
+
+    /// An optional delegated credential, and its private key.
+    ///
+    /// This is used in place of `key` to sign TLS1.3 handshakes with
+    /// clients that support delegated credentials.
+    pub delegated_credential: Option<DelegatedKey>,
 }
 
 impl CertifiedKey {
@@ -53,6 +66,8 @@ impl CertifiedKey {
             cert,
             key,
             ocsp: None,
+            sct_list: None,
+            delegated_credential: None,
         }
     }
 
@@ -63,3 +78,13 @@ impl CertifiedKey {
             .ok_or(Error::NoCertificatesPresented)
     }
 }
+
+/// A delegated credential (see RFC 9345), and the private key it delegates to.
+#[derive(Clone)]
+pub struct DelegatedKey {
+    /// The delegated credential, signed by the end-entity certificate's key.
+    pub credential: DelegatedCredential,
+
+    /// The private key matching the credential's public key.
+    pub key: Arc<dyn SigningKey>,
+}
@@ -127,6 +127,8 @@ pub enum InvalidMessage {
     MissingData(&'static str),
     /// A peer did not advertise its supported key exchange groups.
     MissingKeyExchange,
+    /// A peer sent an empty list of certificate authorities
+    NoCertificateAuthorities,
     /// A peer sent an empty list of signature schemes
     NoSignatureSchemes,
     /// Trailing data found for the named handshake payload value
@@ -164,6 +166,7 @@ impl From<InvalidMessage> for Error {
 /// Please file a bug against rustls if you see `Error::PeerMisbehaved` in
 /// the wild.
 pub enum PeerMisbehaved {
+    ApplicationDataOnAcmeChallenge,
     AttemptedDowngradeToTls12WhenTls13IsSupported,
     BadCertChainExtensions,
     DisallowedEncryptedExtension,
@@ -225,6 +228,7 @@ pub enum PeerMisbehaved {
     TooMuchEarlyDataReceived,
     UnexpectedCleartextExtension,
     UnsolicitedCertExtension,
+    UnsolicitedDelegatedCredential,
     UnsolicitedEncryptedExtension,
     UnsolicitedSctList,
     UnsolicitedServerHelloExtension,
@@ -321,6 +325,35 @@ pub enum CertificateError {
     /// reasons.
     ApplicationVerificationFailure,
 
+    /// The certificate is not accompanied by enough valid Signed Certificate
+    /// Timestamps to satisfy the configured Certificate Transparency policy.
+    CertificateTransparencyPolicyNotMet,
+
+    /// The server authenticated with a delegated credential which is
+    /// expired, valid for too long, not permitted by the certificate,
+    /// or which does not match the handshake signature.
+    InvalidDelegatedCredential,
+
+    /// The end-entity certificate does not contain exactly one valid
+    /// SPIFFE ID in its URI subject alternative names.
+    InvalidSpiffeId,
+
+    /// The end-entity certificate's SPIFFE ID is not allowed by the
+    /// verifier's configuration.
+    SpiffeIdNotAllowed,
+
+    /// No certificate in the verified chain has a public key matching
+    /// one of the configured pins.
+    PinMismatch,
+
+    /// A certificate revocation list in a [`CrlStore`] that could be used to
+    /// check the certificate chain is past its `nextUpdate` time, and the
+    /// store's [`ExpiredCrlPolicy`] is `Deny`.
+    ///
+    /// [`CrlStore`]: crate::CrlStore
+    /// [`ExpiredCrlPolicy`]: crate::ExpiredCrlPolicy
+    ExpiredRevocationList,
+
     /// Any other error.
     ///
     /// This can be used by custom verifiers to expose the underlying error
@@ -349,6 +382,12 @@ impl PartialEq<Self> for CertificateError {
             (NotValidForName, NotValidForName) => true,
             (InvalidPurpose, InvalidPurpose) => true,
             (ApplicationVerificationFailure, ApplicationVerificationFailure) => true,
+            (CertificateTransparencyPolicyNotMet, CertificateTransparencyPolicyNotMet) => true,
+            (InvalidDelegatedCredential, InvalidDelegatedCredential) => true,
+            (InvalidSpiffeId, InvalidSpiffeId) => true,
+            (SpiffeIdNotAllowed, SpiffeIdNotAllowed) => true,
+            (PinMismatch, PinMismatch) => true,
+            (ExpiredRevocationList, ExpiredRevocationList) => true,
             _ => false,
         }
     }
@@ -361,7 +400,12 @@ impl From<CertificateError> for AlertDescription {
     fn from(e: CertificateError) -> Self {
         use CertificateError::*;
         match e {
-            BadEncoding | UnhandledCriticalExtension | NotValidForName => Self::BadCertificate,
+            BadEncoding
+            | UnhandledCriticalExtension
+            | NotValidForName
+            | CertificateTransparencyPolicyNotMet
+            | InvalidSpiffeId
+            | PinMismatch => Self::BadCertificate,
             // RFC 5246/RFC 8446
             // certificate_expired
             //  A certificate has expired or **is not currently valid**.
@@ -369,10 +413,12 @@ impl From<CertificateError> for AlertDescription {
             Revoked => Self::CertificateRevoked,
             // OpenSSL, BoringSSL and AWS-LC all generate an Unknown CA alert for
             // the case where revocation status can not be determined, so we do the same here.
-            UnknownIssuer | UnknownRevocationStatus => Self::UnknownCA,
+            UnknownIssuer | UnknownRevocationStatus | ExpiredRevocationList => Self::UnknownCA,
             BadSignature => Self::DecryptError,
+            // RFC 9345 section 4.2
+            InvalidDelegatedCredential => Self::IllegalParameter,
             InvalidPurpose => Self::UnsupportedCertificate,
-            ApplicationVerificationFailure => Self::AccessDenied,
+            ApplicationVerificationFailure | SpiffeIdNotAllowed => Self::AccessDenied,
             // RFC 5246/RFC 8446
             // certificate_unknown
             //  Some other (unspecified) issue arose in processing the
@@ -554,6 +600,15 @@ mod tests {
             ApplicationVerificationFailure,
             ApplicationVerificationFailure
         );
+        assert_eq!(
+            CertificateTransparencyPolicyNotMet,
+            CertificateTransparencyPolicyNotMet
+        );
+        assert_eq!(InvalidDelegatedCredential, InvalidDelegatedCredential);
+        assert_eq!(InvalidSpiffeId, InvalidSpiffeId);
+        assert_eq!(SpiffeIdNotAllowed, SpiffeIdNotAllowed);
+        assert_eq!(PinMismatch, PinMismatch);
+        assert_eq!(ExpiredRevocationList, ExpiredRevocationList);
         let other = Other(alloc::sync::Arc::from(Box::from("")));
         assert_ne!(other, other);
         assert_ne!(BadEncoding, Expired);
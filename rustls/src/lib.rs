@@ -103,7 +103,8 @@
 //!
 //! First we load some root certificates.  These are used to authenticate the server.
 //! The recommended way is to depend on the `webpki_roots` crate which contains
-//! the Mozilla set of root certificates.
+//! the Mozilla set of root certificates.  Alternatively, with the `pem` crate
+//! feature, `RootCertStore::load_system()` loads the system's roots on Linux.
 //!
 //! ```rust,no_run
 //! # #[cfg(feature = "ring")] {
@@ -256,6 +257,20 @@
 //!   which is used for cryptography.
 //!   Without this feature, these items must be provided externally to the core
 //!   rustls crate.
+//!
+//! - `pem`: this makes the rustls crate depend on the `rustls-pemfile` crate.
+//!   Together with `ring`, it adds [`server::ResolvesServerCertFromFiles`],
+//!   which serves a certificate chain and private key from PEM files and
+//!   reloads them when they change.  It also adds
+//!   [`RootCertStore::load_system()`], which loads trust anchors from the
+//!   system's certificate store on Linux.
+//!
+//! - `tracing`: this makes the rustls crate depend on the `tracing` crate.
+//!   Each connection is wrapped in a `tls` span, a child of the span current
+//!   when it was created, which records the side, the server name and the
+//!   negotiated protocol version.  Within it rustls emits structured events
+//!   for handshake state transitions, record layer key changes and errors.
+//!   Like the `logging` output, these do not contain secret key data.
 
 // Require docs for public APIs, deny unsafe code, etc.
 #![forbid(unsafe_code, unused_must_use)]
@@ -344,6 +359,8 @@ mod limited_cache;
 mod rand;
 mod record_layer;
 mod stream;
+#[cfg(test)]
+mod test_util;
 #[cfg(feature = "tls12")]
 mod tls12;
 mod tls13;
@@ -359,6 +376,7 @@ mod builder;
 mod enums;
 mod key_log;
 mod key_log_file;
+mod observer;
 mod suites;
 mod ticketer;
 mod versions;
@@ -421,24 +439,36 @@ pub use crate::error::{
 pub use crate::key_log::{KeyLog, NoKeyLog};
 pub use crate::key_log_file::KeyLogFile;
 pub use crate::msgs::enums::NamedGroup;
-pub use crate::msgs::handshake::DistinguishedName;
+pub use crate::msgs::handshake::{DelegatedCredential, DistinguishedName};
+pub use crate::observer::{
+    HandshakeCounters, HandshakeKind, HandshakeObserver, HandshakeStats, HandshakeSummary,
+    NoHandshakeObserver,
+};
 pub use crate::stream::{Stream, StreamOwned};
 pub use crate::suites::{ConnectionTrafficSecrets, ExtractedSecrets, SupportedCipherSuite};
-pub use crate::ticketer::TicketSwitcher;
+pub use crate::ticketer::{
+    TicketKey, TicketKeyRing, TicketKeys, TicketSwitcher, TICKET_KEY_ID_LEN,
+};
 #[cfg(feature = "tls12")]
 pub use crate::tls12::Tls12CipherSuite;
 pub use crate::tls13::Tls13CipherSuite;
-pub use crate::verify::DigitallySignedStruct;
+pub use crate::verify::{DigitallySignedStruct, VerifiedChain, VerifiedChainUnavailable};
 pub use crate::versions::{SupportedProtocolVersion, ALL_VERSIONS, DEFAULT_VERSIONS};
-pub use crate::webpki::{RootCertStore, WebPkiSupportedAlgorithms};
+pub use crate::webpki::{CrlStore, ExpiredCrlPolicy, RootCertStore, WebPkiSupportedAlgorithms};
+#[cfg(feature = "pem")]
+pub use crate::webpki::{LoadedRoots, RootCertLocations};
 
 /// Items for use in a client.
 pub mod client {
     pub(super) mod builder;
     mod client_conn;
     mod common;
+    mod ct;
+    mod file_cache;
+    mod grease;
     pub(super) mod handy;
     mod hs;
+    mod pinning;
     #[cfg(feature = "tls12")]
     mod tls12;
     mod tls13;
@@ -446,10 +476,14 @@ pub mod client {
     pub use crate::dns_name::InvalidDnsNameError;
     pub use builder::WantsClientCert;
     pub use client_conn::{
-        ClientConfig, ClientConnection, ClientConnectionData, ClientSessionStore,
-        ResolvesClientCert, Resumption, ServerName, Tls12Resumption, WriteEarlyData,
+        ClientConfig, ClientConnection, ClientConnectionData, ClientHelloPadding,
+        ClientSessionStore, ResolvesClientCert, Resumption, ServerName, Tls12Resumption,
+        WriteEarlyData,
     };
-    pub use handy::ClientSessionMemoryCache;
+    pub use ct::{CertificateTransparencyPolicy, CtLog};
+    pub use file_cache::ClientSessionFileCache;
+    pub use handy::{ClientSessionMemoryCache, ResolvesClientCertUsingCaHints};
+    pub use pinning::{PinningServerVerifier, SpkiPin, SpkiPinSet};
 
     /// Dangerous configuration that should be audited and used with extreme care.
     pub mod danger {
@@ -462,6 +496,7 @@ pub mod client {
         verify_server_cert_signed_by_trust_anchor, verify_server_name, ServerCertVerifierBuilder,
         VerifierBuilderError, WebPkiServerVerifier,
     };
+    pub use crate::webpki::{SpiffeVerifier, SpiffeVerifierBuilder};
 
     pub use crate::msgs::persist::Tls12ClientSessionValue;
     pub use crate::msgs::persist::Tls13ClientSessionValue;
@@ -471,8 +506,11 @@ pub use client::{ClientConfig, ClientConnection, ServerName};
 
 /// Items for use in a server.
 pub mod server {
+    mod acme;
     pub(crate) mod builder;
     mod common;
+    #[cfg(all(feature = "pem", feature = "ring"))]
+    mod files;
     pub(crate) mod handy;
     mod hs;
     mod server_conn;
@@ -483,9 +521,13 @@ pub mod server {
     pub use crate::verify::NoClientAuth;
     pub use crate::webpki::WebPkiClientVerifier;
     pub use crate::webpki::{ClientCertVerifierBuilder, VerifierBuilderError};
+    pub use crate::webpki::{SpiffeVerifier, SpiffeVerifierBuilder};
+    pub use acme::{AcmeTlsAlpn01Resolver, ACME_TLS_ALPN_PROTOCOL};
     pub use builder::WantsServerCert;
+    #[cfg(all(feature = "pem", feature = "ring"))]
+    pub use files::ResolvesServerCertFromFiles;
     pub use handy::ResolvesServerCertUsingSni;
-    pub use handy::{NoServerSessionStorage, ServerSessionMemoryCache};
+    pub use handy::{NoServerSessionStorage, ServerSessionCacheStats, ServerSessionMemoryCache};
     pub use server_conn::StoresServerSessions;
     pub use server_conn::{
         Accepted, Acceptor, ReadEarlyData, ServerConfig, ServerConnection, ServerConnectionData,
@@ -535,7 +577,7 @@ pub mod sign {
     pub use crate::crypto::ring::sign::{
         any_ecdsa_type, any_eddsa_type, any_supported_type, RsaSigningKey,
     };
-    pub use crate::crypto::signer::{CertifiedKey, Signer, SigningKey};
+    pub use crate::crypto::signer::{CertifiedKey, DelegatedKey, Signer, SigningKey};
 }
 
 #[cfg(feature = "quic")]
@@ -1,11 +1,12 @@
 #![allow(non_camel_case_types)]
 
+use crate::crypto::signer::Signer;
 #[cfg(feature = "tls12")]
 use crate::crypto::ActiveKeyExchange;
 use crate::crypto::CryptoProvider;
 use crate::dns_name::{DnsName, DnsNameRef};
 use crate::enums::{CipherSuite, HandshakeType, ProtocolVersion, SignatureScheme};
-use crate::error::InvalidMessage;
+use crate::error::{CertificateError, Error, InvalidMessage};
 #[cfg(feature = "logging")]
 use crate::log::warn;
 use crate::msgs::base::{Payload, PayloadU16, PayloadU24, PayloadU8};
@@ -17,7 +18,7 @@ use crate::msgs::enums::{
 use crate::verify::DigitallySignedStruct;
 use crate::{rand, x509};
 
-use pki_types::CertificateDer;
+use pki_types::{CertificateDer, UnixTime};
 
 use alloc::borrow::ToOwned;
 #[cfg(feature = "logging")]
@@ -526,6 +527,13 @@ impl CertificateStatusRequest {
 
 // ---
 
+// --- RFC6962 signed certificate timestamps ---
+wrapped_payload!(pub struct Sct, PayloadU16,);
+
+impl TlsListElement for Sct {
+    const SIZE_LEN: ListLength = ListLength::U16;
+}
+
 impl TlsListElement for PSKKeyExchangeMode {
     const SIZE_LEN: ListLength = ListLength::U8;
 }
@@ -553,6 +561,9 @@ pub enum ClientExtension {
     Cookie(PayloadU16),
     ExtendedMasterSecretRequest,
     CertificateStatusRequest(CertificateStatusRequest),
+    SignedCertificateTimestampRequest,
+    AuthorityNames(Vec<DistinguishedName>),
+    DelegatedCredential(Vec<SignatureScheme>),
     TransportParameters(Vec<u8>),
     TransportParametersDraft(Vec<u8>),
     EarlyData,
@@ -575,6 +586,9 @@ impl ClientExtension {
             Self::Cookie(_) => ExtensionType::Cookie,
             Self::ExtendedMasterSecretRequest => ExtensionType::ExtendedMasterSecret,
             Self::CertificateStatusRequest(_) => ExtensionType::StatusRequest,
+            Self::SignedCertificateTimestampRequest => ExtensionType::SCT,
+            Self::AuthorityNames(_) => ExtensionType::CertificateAuthorities,
+            Self::DelegatedCredential(_) => ExtensionType::DelegatedCredential,
             Self::TransportParameters(_) => ExtensionType::TransportParameters,
             Self::TransportParametersDraft(_) => ExtensionType::TransportParametersDraft,
             Self::EarlyData => ExtensionType::EarlyData,
@@ -595,6 +609,7 @@ impl Codec for ClientExtension {
             Self::ServerName(ref r) => r.encode(nested.buf),
             Self::SessionTicket(ClientSessionTicket::Request)
             | Self::ExtendedMasterSecretRequest
+            | Self::SignedCertificateTimestampRequest
             | Self::EarlyData => {}
             Self::SessionTicket(ClientSessionTicket::Offer(ref r)) => r.encode(nested.buf),
             Self::Protocols(ref r) => r.encode(nested.buf),
@@ -604,6 +619,8 @@ impl Codec for ClientExtension {
             Self::PresharedKey(ref r) => r.encode(nested.buf),
             Self::Cookie(ref r) => r.encode(nested.buf),
             Self::CertificateStatusRequest(ref r) => r.encode(nested.buf),
+            Self::AuthorityNames(ref r) => r.encode(nested.buf),
+            Self::DelegatedCredential(ref r) => r.encode(nested.buf),
             Self::TransportParameters(ref r) | Self::TransportParametersDraft(ref r) => {
                 nested.buf.extend_from_slice(r);
             }
@@ -642,6 +659,15 @@ impl Codec for ClientExtension {
                 let csr = CertificateStatusRequest::read(&mut sub)?;
                 Self::CertificateStatusRequest(csr)
             }
+            ExtensionType::SCT if !sub.any_left() => Self::SignedCertificateTimestampRequest,
+            ExtensionType::CertificateAuthorities => {
+                let names = Vec::read(&mut sub)?;
+                if names.is_empty() {
+                    return Err(InvalidMessage::NoCertificateAuthorities);
+                }
+                Self::AuthorityNames(names)
+            }
+            ExtensionType::DelegatedCredential => Self::DelegatedCredential(Vec::read(&mut sub)?),
             ExtensionType::TransportParameters => Self::TransportParameters(sub.rest().to_vec()),
             ExtensionType::TransportParametersDraft => {
                 Self::TransportParametersDraft(sub.rest().to_vec())
@@ -699,6 +725,7 @@ pub enum ServerExtension {
     PresharedKey(u16),
     ExtendedMasterSecretAck,
     CertificateStatusAck,
+    SignedCertificateTimestamp(Vec<Sct>),
     SupportedVersions(ProtocolVersion),
     TransportParameters(Vec<u8>),
     TransportParametersDraft(Vec<u8>),
@@ -718,6 +745,7 @@ impl ServerExtension {
             Self::PresharedKey(_) => ExtensionType::PreSharedKey,
             Self::ExtendedMasterSecretAck => ExtensionType::ExtendedMasterSecret,
             Self::CertificateStatusAck => ExtensionType::StatusRequest,
+            Self::SignedCertificateTimestamp(_) => ExtensionType::SCT,
             Self::SupportedVersions(_) => ExtensionType::SupportedVersions,
             Self::TransportParameters(_) => ExtensionType::TransportParameters,
             Self::TransportParametersDraft(_) => ExtensionType::TransportParametersDraft,
@@ -743,6 +771,7 @@ impl Codec for ServerExtension {
             Self::Protocols(ref r) => r.encode(nested.buf),
             Self::KeyShare(ref r) => r.encode(nested.buf),
             Self::PresharedKey(r) => r.encode(nested.buf),
+            Self::SignedCertificateTimestamp(ref r) => r.encode(nested.buf),
             Self::SupportedVersions(ref r) => r.encode(nested.buf),
             Self::TransportParameters(ref r) | Self::TransportParametersDraft(ref r) => {
                 nested.buf.extend_from_slice(r);
@@ -766,6 +795,7 @@ impl Codec for ServerExtension {
             ExtensionType::KeyShare => Self::KeyShare(KeyShareEntry::read(&mut sub)?),
             ExtensionType::PreSharedKey => Self::PresharedKey(u16::read(&mut sub)?),
             ExtensionType::ExtendedMasterSecret => Self::ExtendedMasterSecretAck,
+            ExtensionType::SCT => Self::SignedCertificateTimestamp(Vec::read(&mut sub)?),
             ExtensionType::SupportedVersions => {
                 Self::SupportedVersions(ProtocolVersion::read(&mut sub)?)
             }
@@ -787,6 +817,14 @@ impl ServerExtension {
         Self::Protocols(Vec::from_slices(proto))
     }
 
+    /// Make a stapled SCT list extension from `sct_list`, an encoded
+    /// `SignedCertificateTimestampList`.  Returns `None` if it doesn't parse.
+    pub(crate) fn make_sct(sct_list: &[u8]) -> Option<Self> {
+        Vec::<Sct>::read_bytes(sct_list)
+            .ok()
+            .map(Self::SignedCertificateTimestamp)
+    }
+
     #[cfg(feature = "tls12")]
     pub(crate) fn make_empty_renegotiation_info() -> Self {
         let empty = Vec::new();
@@ -916,6 +954,22 @@ impl ClientHelloPayload {
         }
     }
 
+    pub(crate) fn get_authorities_extension(&self) -> Option<&[DistinguishedName]> {
+        let ext = self.find_extension(ExtensionType::CertificateAuthorities)?;
+        match *ext {
+            ClientExtension::AuthorityNames(ref an) => Some(an),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get_delegated_credential_extension(&self) -> Option<&[SignatureScheme]> {
+        let ext = self.find_extension(ExtensionType::DelegatedCredential)?;
+        match *ext {
+            ClientExtension::DelegatedCredential(ref schemes) => Some(schemes),
+            _ => None,
+        }
+    }
+
     #[cfg(feature = "quic")]
     pub(crate) fn get_quic_params_extension(&self) -> Option<Vec<u8>> {
         let ext = self
@@ -1266,9 +1320,133 @@ impl TlsListElement for CertificateDer<'_> {
 // That's annoying. It means the parsing is not
 // context-free any more.
 
+/// A delegated credential, as described in [RFC 9345].
+///
+/// This binds a short-lived public key to an end-entity certificate, with a
+/// signature by the certificate's key.  A TLS1.3 server can then sign its
+/// handshake with the delegated key, so the certificate's own private key
+/// need not be present where TLS is terminated.
+///
+/// [RFC 9345]: https://www.rfc-editor.org/rfc/rfc9345
+#[derive(Clone, Debug)]
+pub struct DelegatedCredential {
+    valid_time: u32,
+    dc_cert_verify_algorithm: SignatureScheme,
+    public_key: PayloadU24,
+    algorithm: SignatureScheme,
+    signature: PayloadU16,
+}
+
+impl DelegatedCredential {
+    /// Make a delegated credential for `public_key`, a DER-encoded
+    /// `SubjectPublicKeyInfo`, which expires at `expiry`.
+    ///
+    /// The handshake is to be signed by the delegated key using
+    /// `dc_cert_verify_algorithm`.  `signer` is the private key of the
+    /// end-entity certificate `end_entity`, with the scheme it will use to
+    /// sign the credential.
+    ///
+    /// Note that clients reject credentials that are valid for more than
+    /// seven days.
+    pub fn new(
+        end_entity: &CertificateDer<'_>,
+        signer: &dyn Signer,
+        dc_cert_verify_algorithm: SignatureScheme,
+        public_key: Vec<u8>,
+        expiry: UnixTime,
+    ) -> Result<Self, Error> {
+        let not_before = x509::cert_not_before(end_entity)
+            .ok_or(Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        let valid_time = expiry
+            .as_secs()
+            .checked_sub(not_before.as_secs())
+            .and_then(|valid_time| u32::try_from(valid_time).ok())
+            .ok_or_else(|| Error::General("delegated credential expiry out of range".into()))?;
+
+        let mut credential = Self {
+            valid_time,
+            dc_cert_verify_algorithm,
+            public_key: PayloadU24::new(public_key),
+            algorithm: signer.scheme(),
+            signature: PayloadU16::empty(),
+        };
+        credential.signature = PayloadU16::new(signer.sign(&credential.message(end_entity))?);
+        Ok(credential)
+    }
+
+    /// The time after which this credential is no longer valid, if it
+    /// is for the end-entity certificate `end_entity`.
+    ///
+    /// Returns `None` if `end_entity` cannot be parsed.
+    pub fn expiry(&self, end_entity: &CertificateDer<'_>) -> Option<UnixTime> {
+        let not_before = x509::cert_not_before(end_entity)?;
+        Some(UnixTime::since_unix_epoch(core::time::Duration::from_secs(
+            not_before.as_secs() + u64::from(self.valid_time),
+        )))
+    }
+
+    /// The scheme the delegated key signs the handshake with.
+    pub fn dc_cert_verify_algorithm(&self) -> SignatureScheme {
+        self.dc_cert_verify_algorithm
+    }
+
+    /// The delegated key, as a DER-encoded `SubjectPublicKeyInfo`.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key.0
+    }
+
+    /// The scheme used to sign this credential with the end-entity certificate's key.
+    pub fn algorithm(&self) -> SignatureScheme {
+        self.algorithm
+    }
+
+    /// The end-entity certificate key's signature over [`DelegatedCredential::message()`].
+    pub fn signature(&self) -> &[u8] {
+        &self.signature.0
+    }
+
+    /// The message signed by the key of the end-entity certificate `end_entity`
+    /// to produce [`DelegatedCredential::signature()`].
+    pub fn message(&self, end_entity: &CertificateDer<'_>) -> Vec<u8> {
+        let mut message = vec![0x20u8; 64];
+        message.extend_from_slice(b"TLS, server delegated credentials\x00");
+        message.extend_from_slice(end_entity.as_ref());
+        self.encode_credential(&mut message);
+        self.algorithm.encode(&mut message);
+        message
+    }
+
+    fn encode_credential(&self, bytes: &mut Vec<u8>) {
+        self.valid_time.encode(bytes);
+        self.dc_cert_verify_algorithm
+            .encode(bytes);
+        self.public_key.encode(bytes);
+    }
+}
+
+impl Codec for DelegatedCredential {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.encode_credential(bytes);
+        self.algorithm.encode(bytes);
+        self.signature.encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Result<Self, InvalidMessage> {
+        Ok(Self {
+            valid_time: u32::read(r)?,
+            dc_cert_verify_algorithm: SignatureScheme::read(r)?,
+            public_key: PayloadU24::read(r)?,
+            algorithm: SignatureScheme::read(r)?,
+            signature: PayloadU16::read(r)?,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum CertificateExtension {
     CertificateStatus(CertificateStatus),
+    SignedCertificateTimestamp(Vec<Sct>),
+    DelegatedCredential(DelegatedCredential),
     Unknown(UnknownExtension),
 }
 
@@ -1276,6 +1454,8 @@ impl CertificateExtension {
     pub(crate) fn get_type(&self) -> ExtensionType {
         match *self {
             Self::CertificateStatus(_) => ExtensionType::StatusRequest,
+            Self::SignedCertificateTimestamp(_) => ExtensionType::SCT,
+            Self::DelegatedCredential(_) => ExtensionType::DelegatedCredential,
             Self::Unknown(ref r) => r.typ,
         }
     }
@@ -1286,6 +1466,20 @@ impl CertificateExtension {
             _ => None,
         }
     }
+
+    pub(crate) fn get_sct_list(&self) -> Option<&[Sct]> {
+        match *self {
+            Self::SignedCertificateTimestamp(ref sct) => Some(sct),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get_delegated_credential(&self) -> Option<&DelegatedCredential> {
+        match *self {
+            Self::DelegatedCredential(ref dc) => Some(dc),
+            _ => None,
+        }
+    }
 }
 
 impl Codec for CertificateExtension {
@@ -1295,6 +1489,8 @@ impl Codec for CertificateExtension {
         let nested = LengthPrefixedBuffer::new(ListLength::U16, bytes);
         match *self {
             Self::CertificateStatus(ref r) => r.encode(nested.buf),
+            Self::SignedCertificateTimestamp(ref r) => r.encode(nested.buf),
+            Self::DelegatedCredential(ref r) => r.encode(nested.buf),
             Self::Unknown(ref r) => r.encode(nested.buf),
         }
     }
@@ -1309,6 +1505,10 @@ impl Codec for CertificateExtension {
                 let st = CertificateStatus::read(&mut sub)?;
                 Self::CertificateStatus(st)
             }
+            ExtensionType::SCT => Self::SignedCertificateTimestamp(Vec::read(&mut sub)?),
+            ExtensionType::DelegatedCredential => {
+                Self::DelegatedCredential(DelegatedCredential::read(&mut sub)?)
+            }
             _ => Self::Unknown(UnknownExtension::read(typ, &mut sub)),
         };
 
@@ -1365,9 +1565,14 @@ impl CertificateEntry {
     }
 
     pub(crate) fn has_unknown_extension(&self) -> bool {
-        self.exts
-            .iter()
-            .any(|ext| ext.get_type() != ExtensionType::StatusRequest)
+        self.exts.iter().any(|ext| {
+            !matches!(
+                ext.get_type(),
+                ExtensionType::StatusRequest
+                    | ExtensionType::SCT
+                    | ExtensionType::DelegatedCredential
+            )
+        })
     }
 
     pub(crate) fn get_ocsp_response(&self) -> Option<&Vec<u8>> {
@@ -1376,6 +1581,20 @@ impl CertificateEntry {
             .find(|ext| ext.get_type() == ExtensionType::StatusRequest)
             .and_then(CertificateExtension::get_cert_status)
     }
+
+    pub(crate) fn get_sct_list(&self) -> Option<&[Sct]> {
+        self.exts
+            .iter()
+            .find(|ext| ext.get_type() == ExtensionType::SCT)
+            .and_then(CertificateExtension::get_sct_list)
+    }
+
+    pub(crate) fn get_delegated_credential(&self) -> Option<&DelegatedCredential> {
+        self.exts
+            .iter()
+            .find(|ext| ext.get_type() == ExtensionType::DelegatedCredential)
+            .and_then(CertificateExtension::get_delegated_credential)
+    }
 }
 
 impl TlsListElement for CertificateEntry {
@@ -1448,6 +1667,18 @@ impl CertificatePayloadTls13 {
             .unwrap_or_default()
     }
 
+    pub(crate) fn get_end_entity_scts(&self) -> Option<&[Sct]> {
+        self.entries
+            .first()
+            .and_then(CertificateEntry::get_sct_list)
+    }
+
+    pub(crate) fn get_end_entity_delegated_credential(&self) -> Option<&DelegatedCredential> {
+        self.entries
+            .first()
+            .and_then(CertificateEntry::get_delegated_credential)
+    }
+
     pub(crate) fn convert(&self) -> CertificatePayload {
         let mut ret = Vec::new();
         for entry in &self.entries {
@@ -1659,6 +1890,14 @@ pub(crate) trait HasServerExtensions {
         }
     }
 
+    fn get_sct_list(&self) -> Option<&[Sct]> {
+        let ext = self.find_extension(ExtensionType::SCT)?;
+        match *ext {
+            ServerExtension::SignedCertificateTimestamp(ref sct) => Some(sct),
+            _ => None,
+        }
+    }
+
     fn early_data_extension_offered(&self) -> bool {
         self.find_extension(ExtensionType::EarlyData)
             .is_some()
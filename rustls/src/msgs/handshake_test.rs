@@ -1,5 +1,6 @@
 use crate::dns_name::DnsNameRef;
 use crate::enums::{CipherSuite, HandshakeType, ProtocolVersion, SignatureScheme};
+use crate::error::InvalidMessage;
 use crate::msgs::base::{Payload, PayloadU16, PayloadU24, PayloadU8};
 use crate::msgs::codec::{put_u16, Codec, Reader};
 use crate::msgs::enums::{
@@ -10,12 +11,13 @@ use crate::msgs::handshake::{
     CertReqExtension, CertificateEntry, CertificateExtension, CertificatePayloadTls13,
     CertificateRequestPayload, CertificateRequestPayloadTls13, CertificateStatus,
     CertificateStatusRequest, ClientExtension, ClientHelloPayload, ClientSessionTicket,
-    ConvertProtocolNameList, ConvertServerNameList, DistinguishedName, EcParameters,
-    EcdheServerKeyExchange, HandshakeMessagePayload, HandshakePayload, HasServerExtensions,
-    HelloRetryExtension, HelloRetryRequest, KeyShareEntry, NewSessionTicketExtension,
-    NewSessionTicketPayload, NewSessionTicketPayloadTls13, PresharedKeyBinder,
-    PresharedKeyIdentity, PresharedKeyOffer, ProtocolName, Random, ServerEcdhParams,
-    ServerExtension, ServerHelloPayload, ServerKeyExchangePayload, SessionId, UnknownExtension,
+    ConvertProtocolNameList, ConvertServerNameList, DelegatedCredential, DistinguishedName,
+    EcParameters, EcdheServerKeyExchange, HandshakeMessagePayload, HandshakePayload,
+    HasServerExtensions, HelloRetryExtension, HelloRetryRequest, KeyShareEntry,
+    NewSessionTicketExtension, NewSessionTicketPayload, NewSessionTicketPayloadTls13,
+    PresharedKeyBinder, PresharedKeyIdentity, PresharedKeyOffer, ProtocolName, Random, Sct,
+    ServerEcdhParams, ServerExtension, ServerHelloPayload, ServerKeyExchangePayload, SessionId,
+    UnknownExtension,
 };
 use crate::verify::DigitallySignedStruct;
 
@@ -258,6 +260,15 @@ fn rejects_truncated_sni() {
     assert!(ClientExtension::read(&mut Reader::init(&bytes)).is_err());
 }
 
+#[test]
+fn rejects_empty_certificate_authorities() {
+    let bytes = [0, 0x2f, 0, 2, 0, 0];
+    assert_eq!(
+        ClientExtension::read(&mut Reader::init(&bytes)).unwrap_err(),
+        InvalidMessage::NoCertificateAuthorities
+    );
+}
+
 #[test]
 fn can_roundtrip_psk_identity() {
     let bytes = [0, 0, 0x11, 0x22, 0x33, 0x44];
@@ -388,6 +399,9 @@ fn get_sample_clienthellopayload() -> ClientHelloPayload {
             ClientExtension::Cookie(PayloadU16(vec![1, 2, 3])),
             ClientExtension::ExtendedMasterSecretRequest,
             ClientExtension::CertificateStatusRequest(CertificateStatusRequest::build_ocsp()),
+            ClientExtension::SignedCertificateTimestampRequest,
+            ClientExtension::AuthorityNames(vec![DistinguishedName::from(vec![1, 2, 3])]),
+            ClientExtension::DelegatedCredential(vec![SignatureScheme::ECDSA_NISTP256_SHA256]),
             ClientExtension::TransportParameters(vec![1, 2, 3]),
             ClientExtension::Unknown(UnknownExtension {
                 typ: ExtensionType::Unknown(12345),
@@ -539,6 +553,22 @@ fn client_get_alpn_extension() {
     });
 }
 
+#[test]
+fn client_get_authorities_extension() {
+    test_client_extension_getter(ExtensionType::CertificateAuthorities, |chp| {
+        chp.get_authorities_extension()
+            .is_some()
+    });
+}
+
+#[test]
+fn client_get_delegated_credential_extension() {
+    test_client_extension_getter(ExtensionType::DelegatedCredential, |chp| {
+        chp.get_delegated_credential_extension()
+            .is_some()
+    });
+}
+
 #[cfg(feature = "quic")]
 #[test]
 fn client_get_quic_params_extension() {
@@ -689,6 +719,11 @@ fn test_server_extension_getter(typ: ExtensionType, getter: fn(&ServerHelloPaylo
     assert!(!getter(&shp));
 }
 
+#[test]
+fn server_get_sct_list() {
+    test_server_extension_getter(ExtensionType::SCT, |shp| shp.get_sct_list().is_some());
+}
+
 #[test]
 fn server_get_key_share() {
     test_server_extension_getter(ExtensionType::KeyShare, |shp| shp.get_key_share().is_some());
@@ -741,6 +776,47 @@ fn certentry_get_ocsp_response() {
     });
 }
 
+#[test]
+fn certentry_get_sct_list() {
+    test_cert_extension_getter(ExtensionType::SCT, |ce| ce.get_sct_list().is_some());
+}
+
+#[test]
+fn certentry_get_delegated_credential() {
+    test_cert_extension_getter(ExtensionType::DelegatedCredential, |ce| {
+        ce.get_delegated_credential().is_some()
+    });
+}
+
+fn get_sample_delegated_credential() -> DelegatedCredential {
+    DelegatedCredential::read_bytes(&[
+        0x00, 0x00, 0x0e, 0x10, // valid_time
+        0x04, 0x03, // dc_cert_verify_algorithm
+        0x00, 0x00, 0x03, 0x01, 0x02, 0x03, // ASN1_subjectPublicKeyInfo
+        0x08, 0x04, // algorithm
+        0x00, 0x03, 0x04, 0x05, 0x06, // signature
+    ])
+    .unwrap()
+}
+
+#[test]
+fn delegated_credential_encoding() {
+    let dc = get_sample_delegated_credential();
+    assert_eq!(
+        dc.dc_cert_verify_algorithm(),
+        SignatureScheme::ECDSA_NISTP256_SHA256
+    );
+    assert_eq!(dc.public_key(), &[1, 2, 3]);
+    assert_eq!(dc.algorithm(), SignatureScheme::RSA_PSS_SHA256);
+    assert_eq!(dc.signature(), &[4, 5, 6]);
+    assert_eq!(
+        DelegatedCredential::read_bytes(&dc.get_encoding())
+            .unwrap()
+            .get_encoding(),
+        dc.get_encoding()
+    );
+}
+
 fn get_sample_serverhellopayload() -> ServerHelloPayload {
     ServerHelloPayload {
         legacy_version: ProtocolVersion::TLSv1_2,
@@ -758,6 +834,7 @@ fn get_sample_serverhellopayload() -> ServerHelloPayload {
             ServerExtension::PresharedKey(3),
             ServerExtension::ExtendedMasterSecretAck,
             ServerExtension::CertificateStatusAck,
+            ServerExtension::SignedCertificateTimestamp(vec![Sct::from(vec![0])]),
             ServerExtension::SupportedVersions(ProtocolVersion::TLSv1_2),
             ServerExtension::TransportParameters(vec![1, 2, 3]),
             ServerExtension::Unknown(UnknownExtension {
@@ -804,6 +881,8 @@ fn get_sample_certificatepayloadtls13() -> CertificatePayloadTls13 {
                 CertificateExtension::CertificateStatus(CertificateStatus {
                     ocsp_response: PayloadU24(vec![1, 2, 3]),
                 }),
+                CertificateExtension::SignedCertificateTimestamp(vec![Sct::from(vec![0])]),
+                CertificateExtension::DelegatedCredential(get_sample_delegated_credential()),
                 CertificateExtension::Unknown(UnknownExtension {
                     typ: ExtensionType::Unknown(12345),
                     payload: Payload(vec![1, 2, 3]),
@@ -1,11 +1,12 @@
 use crate::dns_name::DnsName;
 use crate::enums::{CipherSuite, ProtocolVersion};
-use crate::error::InvalidMessage;
+use crate::error::{Error, InvalidMessage};
 use crate::msgs::base::{PayloadU16, PayloadU8};
 use crate::msgs::codec::{Codec, Reader};
 use crate::msgs::handshake::CertificatePayload;
 #[cfg(feature = "tls12")]
 use crate::msgs::handshake::SessionId;
+use crate::suites::SupportedCipherSuite;
 #[cfg(feature = "tls12")]
 use crate::tls12::Tls12CipherSuite;
 use crate::tls13::Tls13CipherSuite;
@@ -52,12 +53,8 @@ impl Retrieved<&Tls13ClientSessionValue> {
 
 impl<T: core::ops::Deref<Target = ClientSessionCommon>> Retrieved<T> {
     pub(crate) fn has_expired(&self) -> bool {
-        let common = &*self.value;
-        common.lifetime_secs != 0
-            && common
-                .epoch
-                .saturating_add(u64::from(common.lifetime_secs))
-                < self.retrieved_at.as_secs()
+        self.value
+            .has_expired(self.retrieved_at)
     }
 }
 
@@ -129,6 +126,59 @@ impl Tls13ClientSessionValue {
     pub fn quic_params(&self) -> Vec<u8> {
         self.quic_params.0.clone()
     }
+
+    /// Encode this value in a stable, versioned format, for storage
+    /// outside this process.
+    ///
+    /// The encoding includes the session's secret: store it accordingly.
+    pub fn get_encoding(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        CLIENT_SESSION_FORMAT_VERSION.encode(&mut bytes);
+        self.suite
+            .common
+            .suite
+            .encode(&mut bytes);
+        self.age_add.encode(&mut bytes);
+        self.max_early_data_size
+            .encode(&mut bytes);
+        self.common.encode(&mut bytes);
+        #[cfg(feature = "quic")]
+        self.quic_params.encode(&mut bytes);
+        #[cfg(not(feature = "quic"))]
+        PayloadU16::empty().encode(&mut bytes);
+        bytes
+    }
+
+    /// Decode a value encoded by [`Tls13ClientSessionValue::get_encoding()`].
+    ///
+    /// This fails if the value was encoded in a different format version,
+    /// or if its cipher suite is not one of `suites`.
+    pub fn read_bytes(bytes: &[u8], suites: &[SupportedCipherSuite]) -> Result<Self, Error> {
+        let r = &mut Reader::init(bytes);
+        read_format_version(r)?;
+        let suite = CipherSuite::read(r)?;
+        let suite = suites
+            .iter()
+            .find_map(|scs| match scs {
+                SupportedCipherSuite::Tls13(inner) if inner.common.suite == suite => Some(*inner),
+                _ => None,
+            })
+            .ok_or_else(|| Error::General("session has an unsupported cipher suite".into()))?;
+        let age_add = u32::read(r)?;
+        let max_early_data_size = u32::read(r)?;
+        let common = ClientSessionCommon::read(r)?;
+        let _quic_params = PayloadU16::read(r)?;
+        r.expect_empty("Tls13ClientSessionValue")?;
+
+        Ok(Self {
+            suite,
+            age_add,
+            max_early_data_size,
+            common,
+            #[cfg(feature = "quic")]
+            quic_params: _quic_params,
+        })
+    }
 }
 
 impl core::ops::Deref for Tls13ClientSessionValue {
@@ -190,6 +240,51 @@ impl Tls12ClientSessionValue {
         self.suite
     }
 
+    /// Encode this value in a stable, versioned format, for storage
+    /// outside this process.
+    ///
+    /// The encoding includes the session's master secret: store it accordingly.
+    pub fn get_encoding(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        CLIENT_SESSION_FORMAT_VERSION.encode(&mut bytes);
+        self.suite
+            .common
+            .suite
+            .encode(&mut bytes);
+        self.session_id.encode(&mut bytes);
+        u8::from(self.extended_ms).encode(&mut bytes);
+        self.common.encode(&mut bytes);
+        bytes
+    }
+
+    /// Decode a value encoded by [`Tls12ClientSessionValue::get_encoding()`].
+    ///
+    /// This fails if the value was encoded in a different format version,
+    /// or if its cipher suite is not one of `suites`.
+    pub fn read_bytes(bytes: &[u8], suites: &[SupportedCipherSuite]) -> Result<Self, Error> {
+        let r = &mut Reader::init(bytes);
+        read_format_version(r)?;
+        let suite = CipherSuite::read(r)?;
+        let suite = suites
+            .iter()
+            .find_map(|scs| match scs {
+                SupportedCipherSuite::Tls12(inner) if inner.common.suite == suite => Some(*inner),
+                _ => None,
+            })
+            .ok_or_else(|| Error::General("session has an unsupported cipher suite".into()))?;
+        let session_id = SessionId::read(r)?;
+        let extended_ms = u8::read(r)? == 1;
+        let common = ClientSessionCommon::read(r)?;
+        r.expect_empty("Tls12ClientSessionValue")?;
+
+        Ok(Self {
+            suite,
+            session_id,
+            extended_ms,
+            common,
+        })
+    }
+
     #[doc(hidden)]
     /// Test only: rewind epoch by `delta` seconds.
     pub fn rewind_epoch(&mut self, delta: u32) {
@@ -243,6 +338,47 @@ impl ClientSessionCommon {
     pub(crate) fn ticket(&self) -> &[u8] {
         self.ticket.0.as_ref()
     }
+
+    pub(crate) fn has_expired(&self, now: UnixTime) -> bool {
+        self.lifetime_secs != 0
+            && self
+                .epoch
+                .saturating_add(u64::from(self.lifetime_secs))
+                < now.as_secs()
+    }
+}
+
+impl Codec for ClientSessionCommon {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.ticket.encode(bytes);
+        self.secret.encode(bytes);
+        self.epoch.encode(bytes);
+        self.lifetime_secs.encode(bytes);
+        self.server_cert_chain.encode(bytes);
+    }
+
+    fn read(r: &mut Reader) -> Result<Self, InvalidMessage> {
+        Ok(Self {
+            ticket: PayloadU16::read(r)?,
+            secret: Zeroizing::new(PayloadU8::read(r)?),
+            epoch: u64::read(r)?,
+            lifetime_secs: cmp::min(u32::read(r)?, MAX_TICKET_LIFETIME),
+            server_cert_chain: CertificatePayload::read(r)?,
+        })
+    }
+}
+
+/// The version of the format written by `get_encoding()` on client
+/// session values.  Increment this whenever that format changes.
+const CLIENT_SESSION_FORMAT_VERSION: u8 = 1;
+
+fn read_format_version(r: &mut Reader) -> Result<(), Error> {
+    match u8::read(r)? {
+        CLIENT_SESSION_FORMAT_VERSION => Ok(()),
+        _ => Err(Error::General(
+            "session was encoded in an unsupported format version".into(),
+        )),
+    }
 }
 
 static MAX_TICKET_LIFETIME: u32 = 7 * 24 * 60 * 60;
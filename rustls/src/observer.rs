@@ -0,0 +1,256 @@
+use crate::common_state::Side;
+use crate::enums::{AlertDescription, CipherSuite, ProtocolVersion};
+use crate::error::Error;
+use crate::msgs::enums::NamedGroup;
+
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::sync::Mutex;
+
+/// This trait represents the ability to be told about events in TLS
+/// handshakes, for example to export metrics about them.
+///
+/// Attach one to `ClientConfig::handshake_observer` or
+/// `ServerConfig::handshake_observer`; the same observer may be shared
+/// between several configs.  Every method has a default implementation
+/// that does nothing.
+///
+/// Methods are called while processing or sending TLS messages, so
+/// should return quickly.
+///
+/// See [`HandshakeCounters`] for an implementation that counts events.
+pub trait HandshakeObserver: Send + Sync {
+    /// A handshake completed: application data may now flow in both
+    /// directions.
+    fn handshake_complete(&self, _summary: &HandshakeSummary) {}
+
+    /// A handshake failed with `error`.
+    ///
+    /// This is called once per connection, for the first error returned by
+    /// `process_new_packets()` before the handshake completes.
+    fn handshake_failed(&self, _side: Side, _error: &Error) {}
+
+    /// A HelloRetryRequest was sent (by a server) or received (by a client).
+    fn hello_retry_request(&self, _side: Side) {}
+
+    /// The server accepted or rejected the early data the client offered.
+    fn early_data(&self, _side: Side, _accepted: bool) {}
+
+    /// An alert was sent to the peer.
+    fn alert_sent(&self, _side: Side, _description: AlertDescription) {}
+
+    /// An alert was received from the peer.
+    fn alert_received(&self, _side: Side, _description: AlertDescription) {}
+}
+
+/// HandshakeObserver that does exactly nothing.
+#[derive(Debug)]
+pub struct NoHandshakeObserver;
+
+impl HandshakeObserver for NoHandshakeObserver {}
+
+/// Whether a handshake established new keys, or resumed an earlier session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandshakeKind {
+    /// A full handshake.
+    Full,
+    /// A resumption of an earlier session, using a TLS1.3 pre-shared key,
+    /// a TLS1.2 session id or a TLS1.2 ticket.
+    Resumed,
+}
+
+/// What was negotiated by a completed handshake, passed to
+/// [`HandshakeObserver::handshake_complete`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct HandshakeSummary {
+    /// Which side of the connection completed the handshake.
+    pub side: Side,
+    /// Whether the handshake was full or resumed.
+    pub kind: HandshakeKind,
+    /// Whether a HelloRetryRequest was needed.
+    pub hello_retry_request: bool,
+    /// The negotiated protocol version.
+    pub protocol_version: ProtocolVersion,
+    /// The negotiated cipher suite.
+    pub cipher_suite: CipherSuite,
+    /// The key exchange group used, or `None` if there was no key
+    /// exchange (in a resumed TLS1.2 handshake).
+    pub kx_group: Option<NamedGroup>,
+    /// The time from the start of the connection until the handshake
+    /// completed.
+    ///
+    /// For connections made from an `Acceptor`, this starts when
+    /// `Accepted::into_connection()` is called.
+    pub duration: Duration,
+}
+
+/// A [`HandshakeObserver`] that counts handshake events.
+///
+/// Read the counts with [`HandshakeCounters::stats()`].
+#[derive(Debug, Default)]
+pub struct HandshakeCounters {
+    stats: Mutex<HandshakeStats>,
+}
+
+impl HandshakeCounters {
+    /// Make a new HandshakeCounters, with all counts zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the current counts.
+    pub fn stats(&self) -> HandshakeStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+impl HandshakeObserver for HandshakeCounters {
+    fn handshake_complete(&self, summary: &HandshakeSummary) {
+        let mut stats = self.stats.lock().unwrap();
+        match summary.kind {
+            HandshakeKind::Full => stats.full_handshakes += 1,
+            HandshakeKind::Resumed => stats.resumed_handshakes += 1,
+        }
+        stats.total_handshake_duration += summary.duration;
+
+        let negotiated = (
+            summary.protocol_version,
+            summary.cipher_suite,
+            summary.kx_group,
+        );
+        match stats
+            .negotiated
+            .iter_mut()
+            .find(|(version, suite, group, _)| (*version, *suite, *group) == negotiated)
+        {
+            Some((_, _, _, count)) => *count += 1,
+            None => stats
+                .negotiated
+                .push((negotiated.0, negotiated.1, negotiated.2, 1)),
+        }
+    }
+
+    fn handshake_failed(&self, _side: Side, _error: &Error) {
+        self.stats
+            .lock()
+            .unwrap()
+            .failed_handshakes += 1;
+    }
+
+    fn hello_retry_request(&self, _side: Side) {
+        self.stats
+            .lock()
+            .unwrap()
+            .hello_retry_requests += 1;
+    }
+
+    fn early_data(&self, _side: Side, accepted: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        match accepted {
+            true => stats.early_data_accepted += 1,
+            false => stats.early_data_rejected += 1,
+        }
+    }
+
+    fn alert_sent(&self, _side: Side, _description: AlertDescription) {
+        self.stats.lock().unwrap().alerts_sent += 1;
+    }
+
+    fn alert_received(&self, _side: Side, _description: AlertDescription) {
+        self.stats
+            .lock()
+            .unwrap()
+            .alerts_received += 1;
+    }
+}
+
+/// Counts of handshake events, from [`HandshakeCounters::stats()`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HandshakeStats {
+    /// Completed full handshakes.
+    pub full_handshakes: u64,
+    /// Completed resumed handshakes.
+    pub resumed_handshakes: u64,
+    /// Handshakes that failed.
+    pub failed_handshakes: u64,
+    /// HelloRetryRequests sent or received.
+    pub hello_retry_requests: u64,
+    /// Offers of early data that were accepted.
+    pub early_data_accepted: u64,
+    /// Offers of early data that were rejected.
+    pub early_data_rejected: u64,
+    /// Alerts sent, including `close_notify`.
+    pub alerts_sent: u64,
+    /// Alerts received, including `close_notify`.
+    pub alerts_received: u64,
+    /// The sum of the durations of completed handshakes.
+    pub total_handshake_duration: Duration,
+    /// Completed handshakes, by protocol version, cipher suite and key
+    /// exchange group.
+    pub negotiated: Vec<(ProtocolVersion, CipherSuite, Option<NamedGroup>, u64)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(kind: HandshakeKind, kx_group: Option<NamedGroup>) -> HandshakeSummary {
+        HandshakeSummary {
+            side: Side::Client,
+            kind,
+            hello_retry_request: false,
+            protocol_version: ProtocolVersion::TLSv1_3,
+            cipher_suite: CipherSuite::TLS13_AES_128_GCM_SHA256,
+            kx_group,
+            duration: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn test_handshake_counters() {
+        let counters = HandshakeCounters::new();
+        assert_eq!(counters.stats(), HandshakeStats::default());
+
+        counters.handshake_complete(&summary(HandshakeKind::Full, Some(NamedGroup::X25519)));
+        counters.handshake_complete(&summary(HandshakeKind::Resumed, Some(NamedGroup::X25519)));
+        counters.handshake_complete(&summary(HandshakeKind::Resumed, None));
+        counters.handshake_failed(Side::Server, &Error::HandshakeNotComplete);
+        counters.hello_retry_request(Side::Server);
+        counters.early_data(Side::Client, true);
+        counters.early_data(Side::Client, false);
+        counters.early_data(Side::Client, false);
+        counters.alert_sent(Side::Client, AlertDescription::CloseNotify);
+        counters.alert_received(Side::Client, AlertDescription::CloseNotify);
+        counters.alert_received(Side::Client, AlertDescription::HandshakeFailure);
+
+        assert_eq!(
+            counters.stats(),
+            HandshakeStats {
+                full_handshakes: 1,
+                resumed_handshakes: 2,
+                failed_handshakes: 1,
+                hello_retry_requests: 1,
+                early_data_accepted: 1,
+                early_data_rejected: 2,
+                alerts_sent: 1,
+                alerts_received: 2,
+                total_handshake_duration: Duration::from_millis(15),
+                negotiated: vec![
+                    (
+                        ProtocolVersion::TLSv1_3,
+                        CipherSuite::TLS13_AES_128_GCM_SHA256,
+                        Some(NamedGroup::X25519),
+                        2
+                    ),
+                    (
+                        ProtocolVersion::TLSv1_3,
+                        CipherSuite::TLS13_AES_128_GCM_SHA256,
+                        None,
+                        1
+                    ),
+                ],
+            }
+        );
+    }
+}
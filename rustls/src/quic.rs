@@ -2,11 +2,13 @@
 use crate::client::{ClientConfig, ClientConnectionData, ServerName};
 use crate::common_state::{CommonState, Protocol, Side};
 use crate::conn::{ConnectionCore, SideData};
+use crate::crypto::cipher::Iv;
 use crate::crypto::tls13::{HkdfExpander, OkmBlock};
-use crate::enums::{AlertDescription, ProtocolVersion};
+use crate::crypto::CryptoProvider;
+use crate::enums::{AlertDescription, CipherSuite, ProtocolVersion};
 use crate::error::Error;
 use crate::msgs::handshake::{ClientExtension, ServerExtension};
-use crate::server::{ServerConfig, ServerConnectionData};
+use crate::server::{self, ServerConfig, ServerConnectionData};
 use crate::tls13::key_schedule::hkdf_expand_label_block;
 use crate::tls13::Tls13CipherSuite;
 
@@ -17,6 +19,8 @@ use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::{self, Debug};
 use core::ops::{Deref, DerefMut};
+use core::time::Duration;
+use pki_types::UnixTime;
 
 /// A QUIC client or server connection.
 #[derive(Debug)]
@@ -38,6 +42,26 @@ impl Connection {
         }
     }
 
+    /// The QUIC version in use.
+    ///
+    /// See [`ConnectionCommon::quic_version()`] for more details.
+    pub fn quic_version(&self) -> Version {
+        match self {
+            Self::Client(conn) => conn.quic_version(),
+            Self::Server(conn) => conn.quic_version(),
+        }
+    }
+
+    /// Switch to another QUIC version, by compatible version negotiation.
+    ///
+    /// See [`ConnectionCommon::set_quic_version()`] for more details.
+    pub fn set_quic_version(&mut self, version: Version) -> Result<(), Error> {
+        match self {
+            Self::Client(conn) => conn.set_quic_version(version),
+            Self::Server(conn) => conn.set_quic_version(version),
+        }
+    }
+
     /// Compute the keys for encrypting/decrypting 0-RTT packets, if available
     pub fn zero_rtt_keys(&self) -> Option<DirectionalKeys> {
         match self {
@@ -213,6 +237,15 @@ impl ServerConnection {
         quic_version: Version,
         params: Vec<u8>,
     ) -> Result<Self, Error> {
+        Self::check_config(&config)?;
+        let ext = Self::transport_parameters(quic_version, params);
+        let mut core = ConnectionCore::for_server(config, vec![ext])?;
+        core.common_state.protocol = Protocol::Quic;
+        core.common_state.quic.version = quic_version;
+        Ok(Self { inner: core.into() })
+    }
+
+    fn check_config(config: &ServerConfig) -> Result<(), Error> {
         if !config.supports_version(ProtocolVersion::TLSv1_3) {
             return Err(Error::General(
                 "TLS 1.3 support is required for QUIC".into(),
@@ -225,15 +258,14 @@ impl ServerConnection {
             ));
         }
 
-        let ext = match quic_version {
+        Ok(())
+    }
+
+    fn transport_parameters(quic_version: Version, params: Vec<u8>) -> ServerExtension {
+        match quic_version {
             Version::V1Draft => ServerExtension::TransportParametersDraft(params),
             Version::V1 | Version::V2 => ServerExtension::TransportParameters(params),
-        };
-
-        let mut core = ConnectionCore::for_server(config, vec![ext])?;
-        core.common_state.protocol = Protocol::Quic;
-        core.common_state.quic.version = quic_version;
-        Ok(Self { inner: core.into() })
+        }
     }
 
     /// Explicitly discard early data, notifying the client
@@ -292,6 +324,101 @@ impl From<ServerConnection> for Connection {
     }
 }
 
+/// Handle the start of a QUIC server connection before choosing a [`ServerConfig`].
+///
+/// This is the QUIC equivalent of [`server::Acceptor`]: feed it the
+/// handshake data from the CRYPTO frames of the client's Initial packets
+/// until it yields an [`Accepted`], then inspect the client's hello and
+/// transport parameters to choose a configuration for the connection.
+///
+/// [`server::Acceptor`]: crate::server::Acceptor
+pub struct Acceptor {
+    inner: server::Acceptor,
+    quic_version: Version,
+}
+
+impl Acceptor {
+    /// Make an acceptor for a connection using `quic_version`.
+    pub fn new(quic_version: Version) -> Self {
+        Self {
+            inner: server::Acceptor::for_quic(quic_version),
+            quic_version,
+        }
+    }
+
+    /// Consume unencrypted TLS handshake data from the client's Initial packets.
+    ///
+    /// Returns an error if this `Acceptor` has already yielded an [`Accepted`].
+    pub fn read_hs(&mut self, plaintext: &[u8]) -> Result<(), Error> {
+        self.inner.read_quic_hs(plaintext)
+    }
+
+    /// Check if a complete `ClientHello` message has been received.
+    ///
+    /// Returns `Ok(None)` if the complete `ClientHello` has not yet been received.
+    /// Supply more data with [`Acceptor::read_hs()`] and then call this function again.
+    ///
+    /// Returns `Ok(Some(accepted))` if the connection has been accepted. Call
+    /// `accepted.into_connection()` to continue. Do not call this function again.
+    ///
+    /// Returns `Err(err)` if an error occurred. Do not call this function again.
+    pub fn accept(&mut self) -> Result<Option<Accepted>, Error> {
+        Ok(self
+            .inner
+            .accept()?
+            .map(|inner| Accepted {
+                transport_parameters: inner.quic_transport_parameters(),
+                inner,
+                quic_version: self.quic_version,
+            }))
+    }
+}
+
+/// A `ClientHello` received through a QUIC [`Acceptor`].
+pub struct Accepted {
+    inner: server::Accepted,
+    transport_parameters: Option<Vec<u8>>,
+    quic_version: Version,
+}
+
+impl Accepted {
+    /// Get the [`ClientHello`] for this connection.
+    ///
+    /// [`ClientHello`]: crate::server::ClientHello
+    pub fn client_hello(&self) -> server::ClientHello<'_> {
+        self.inner.client_hello()
+    }
+
+    /// The TLS-encoded transport parameters sent by the client.
+    ///
+    /// These are missing if the client is not a QUIC client, in which case
+    /// [`Accepted::into_connection()`] fails.
+    pub fn transport_parameters(&self) -> Option<&[u8]> {
+        self.transport_parameters.as_deref()
+    }
+
+    /// Convert the [`Accepted`] into a [`ServerConnection`].
+    ///
+    /// This takes the [`ServerConfig`] to use for the connection, and the
+    /// TLS-encoded transport parameters to send, as for [`ServerConnection::new()`].
+    /// Returns an error if configuration-dependent validation of the received
+    /// `ClientHello` message fails.
+    pub fn into_connection(
+        self,
+        config: Arc<ServerConfig>,
+        params: Vec<u8>,
+    ) -> Result<ServerConnection, Error> {
+        ServerConnection::check_config(&config)?;
+        let ext = ServerConnection::transport_parameters(self.quic_version, params);
+        Ok(ServerConnection {
+            inner: self
+                .inner
+                .into_core(config, vec![ext])?
+                .into(),
+        })
+    }
+}
+
 /// A shared interface for QUIC connections.
 pub struct ConnectionCommon<Data> {
     core: ConnectionCore<Data>,
@@ -314,6 +441,31 @@ impl<Data: SideData> ConnectionCommon<Data> {
             .map(|v| v.as_ref())
     }
 
+    /// The QUIC version in use.
+    pub fn quic_version(&self) -> Version {
+        self.core.common_state.quic.version
+    }
+
+    /// Switch to another QUIC version, by compatible version negotiation
+    /// (RFC 9368).
+    ///
+    /// A server may switch versions once it has read the client's first
+    /// flight, and a client switches when it receives the server's first
+    /// Initial packet using the new version.  In both cases this must happen
+    /// before any handshake keys are returned from [`Self::write_hs()`]:
+    /// those keys, and all later keys, are derived with the new version's
+    /// labels.  Initial keys for the new version are obtained from
+    /// [`Keys::initial()`].
+    ///
+    /// Returns an error if it is too late to switch, or `version` is not
+    /// compatible with the current version.
+    pub fn set_quic_version(&mut self, version: Version) -> Result<(), Error> {
+        self.core
+            .common_state
+            .quic
+            .set_version(version)
+    }
+
     /// Compute the keys for encrypting/decrypting 0-RTT packets, if available
     pub fn zero_rtt_keys(&self) -> Option<DirectionalKeys> {
         Some(DirectionalKeys::new(
@@ -389,12 +541,42 @@ pub(crate) struct Quic {
     pub(crate) early_secret: Option<OkmBlock>,
     pub(crate) hs_secrets: Option<Secrets>,
     pub(crate) traffic_secrets: Option<Secrets>,
+    /// Whether keys derived from hs_secrets have been passed to the QUIC implementation
+    pub(crate) returned_handshake_keys: bool,
     /// Whether keys derived from traffic_secrets have been passed to the QUIC implementation
     pub(crate) returned_traffic_keys: bool,
     pub(crate) version: Version,
 }
 
 impl Quic {
+    pub(crate) fn set_version(&mut self, version: Version) -> Result<(), Error> {
+        if version == self.version {
+            return Ok(());
+        }
+
+        if self.version == Version::V1Draft || version == Version::V1Draft {
+            return Err(Error::General(
+                "QUIC draft versions are not compatible with other versions".into(),
+            ));
+        }
+
+        if self.returned_handshake_keys {
+            return Err(Error::General(
+                "cannot change QUIC version after handshake keys are in use".into(),
+            ));
+        }
+
+        self.version = version;
+        // Secrets derived but not yet returned take the new version's labels.
+        for secrets in [&mut self.hs_secrets, &mut self.traffic_secrets]
+            .into_iter()
+            .flatten()
+        {
+            secrets.version = version;
+        }
+        Ok(())
+    }
+
     pub(crate) fn write_hs(&mut self, buf: &mut Vec<u8>) -> Option<KeyChange> {
         while let Some((_, msg)) = self.hs_queue.pop_front() {
             buf.extend_from_slice(&msg);
@@ -407,6 +589,7 @@ impl Quic {
         }
 
         if let Some(secrets) = self.hs_secrets.take() {
+            self.returned_handshake_keys = true;
             return Some(KeyChange::Handshake {
                 keys: Keys::new(&secrets),
             });
@@ -554,6 +737,14 @@ pub(crate) trait Algorithm: Send + Sync {
         secret: &dyn HkdfExpander,
         version: Version,
     ) -> Box<dyn HeaderProtectionKey>;
+
+    /// Make a packet key from a fixed `key` and `iv`, rather than a secret.
+    fn fixed_packet_key(
+        &self,
+        suite: &'static Tls13CipherSuite,
+        key: &[u8],
+        iv: Iv,
+    ) -> Box<dyn PacketKey>;
 }
 
 /// A QUIC header protection key
@@ -613,10 +804,66 @@ pub trait HeaderProtectionKey {
         packet_number: &mut [u8],
     ) -> Result<(), Error>;
 
+    /// Adds QUIC Header Protection to a batch of packets.
+    ///
+    /// Each entry of `headers` is treated as in [`Self::encrypt_in_place()`].
+    /// The default implementation protects each header in turn; providers can
+    /// override it to compute all the masks in one pass.
+    ///
+    /// Stops at the first entry that fails; earlier entries will have had
+    /// header protection added.
+    fn encrypt_batch_in_place(&self, headers: &mut [BatchHeader<'_>]) -> Result<(), Error> {
+        for header in headers.iter_mut() {
+            self.encrypt_in_place(header.sample, header.first, header.packet_number)?;
+        }
+        Ok(())
+    }
+
+    /// Removes QUIC Header Protection from a batch of packets.
+    ///
+    /// Each entry of `headers` is treated as in [`Self::decrypt_in_place()`].
+    /// The default implementation unprotects each header in turn; providers can
+    /// override it to compute all the masks in one pass.
+    ///
+    /// Stops at the first entry that fails; earlier entries will have had
+    /// header protection removed.
+    fn decrypt_batch_in_place(&self, headers: &mut [BatchHeader<'_>]) -> Result<(), Error> {
+        for header in headers.iter_mut() {
+            self.decrypt_in_place(header.sample, header.first, header.packet_number)?;
+        }
+        Ok(())
+    }
+
     /// Expected sample length for the key's algorithm
     fn sample_len(&self) -> usize;
 }
 
+/// The parts of one packet's header used by [`HeaderProtectionKey`] batch operations.
+///
+/// The fields have the same meaning as the arguments of
+/// [`HeaderProtectionKey::encrypt_in_place()`].
+pub struct BatchHeader<'a> {
+    /// The sample of encrypted payload
+    pub sample: &'a [u8],
+    /// The first byte of the header
+    pub first: &'a mut u8,
+    /// The Packet Number field
+    pub packet_number: &'a mut [u8],
+}
+
+/// One packet for [`PacketKey`] batch operations.
+///
+/// The fields have the same meaning as the arguments of
+/// [`PacketKey::encrypt_in_place()`].
+pub struct BatchPacket<'a> {
+    /// The packet number, used to derive the nonce
+    pub packet_number: u64,
+    /// The packet header, used as the additional authenticated data
+    pub header: &'a [u8],
+    /// The packet payload, encrypted or decrypted in place
+    pub payload: &'a mut [u8],
+}
+
 /// Keys to encrypt or decrypt the payload of a packet
 pub trait PacketKey {
     /// Encrypt a QUIC packet
@@ -647,6 +894,40 @@ pub trait PacketKey {
         payload: &'a mut [u8],
     ) -> Result<&'a [u8], Error>;
 
+    /// Encrypt a batch of QUIC packets
+    ///
+    /// Each entry of `packets` is encrypted as in [`Self::encrypt_in_place()`], and the
+    /// authentication tags are returned in the same order. The default implementation
+    /// encrypts each packet in turn; providers can override it to make use of
+    /// multi-buffer AEAD implementations.
+    ///
+    /// Stops at the first packet that fails; earlier packets will have been encrypted.
+    fn encrypt_batch_in_place(&self, packets: &mut [BatchPacket<'_>]) -> Result<Vec<Tag>, Error> {
+        packets
+            .iter_mut()
+            .map(|packet| {
+                self.encrypt_in_place(packet.packet_number, packet.header, packet.payload)
+            })
+            .collect()
+    }
+
+    /// Decrypt a batch of QUIC packets
+    ///
+    /// Each entry of `packets` is decrypted as in [`Self::decrypt_in_place()`]. Packets are
+    /// authenticated independently, so one result is returned per packet, in the same order:
+    /// on success, the decrypted payload can be found in that packet's `payload`, up to the
+    /// returned length. The default implementation decrypts each packet in turn; providers
+    /// can override it to make use of multi-buffer AEAD implementations.
+    fn decrypt_batch_in_place(&self, packets: &mut [BatchPacket<'_>]) -> Vec<Result<usize, Error>> {
+        packets
+            .iter_mut()
+            .map(|packet| {
+                self.decrypt_in_place(packet.packet_number, packet.header, packet.payload)
+                    .map(|plaintext| plaintext.len())
+            })
+            .collect()
+    }
+
     /// Number of times the packet key can be used without sacrificing confidentiality
     ///
     /// See <https://www.rfc-editor.org/rfc/rfc9001.html#name-confidentiality-limit>.
@@ -737,6 +1018,137 @@ impl Keys {
     }
 }
 
+/// Seals and opens the address validation tokens sent in Retry packets.
+///
+/// A server sends a token in a Retry packet, which the client echoes in
+/// its next Initial packet.  Tokens sealed here are encrypted and
+/// authenticated with a key known only to this sealer, and bound to the
+/// client's address.  They carry the Original Destination Connection ID,
+/// which the server must then send in its transport parameters, and when
+/// they were issued, so that old tokens are refused.
+///
+/// Tokens are protected with the first TLS1.3 cipher suite of the
+/// `CryptoProvider`, with a fresh key derived for each token.
+pub struct RetryTokenSealer {
+    suite: &'static Tls13CipherSuite,
+    secret: Box<dyn HkdfExpander>,
+    provider: &'static dyn CryptoProvider,
+    lifetime: Duration,
+}
+
+impl RetryTokenSealer {
+    /// The length of the random nonce each token starts with.
+    const NONCE_LEN: usize = 16;
+
+    /// How far in the future a token's issue time may be, to allow for
+    /// clock differences between servers sharing a secret.
+    const MAX_CLOCK_SKEW_SECS: u64 = 10;
+
+    /// Make a sealer with a random key, whose tokens are valid for `lifetime`.
+    ///
+    /// Only this sealer can open its tokens.  Use
+    /// [`RetryTokenSealer::with_secret()`] to share tokens between servers.
+    pub fn new(provider: &'static dyn CryptoProvider, lifetime: Duration) -> Result<Self, Error> {
+        let mut secret = [0u8; 32];
+        provider.fill_random(&mut secret)?;
+        Self::with_secret(provider, &secret, lifetime)
+    }
+
+    /// Make a sealer keyed with `secret`, whose tokens are valid for `lifetime`.
+    ///
+    /// Sealers with the same `secret` open each other's tokens.  `secret`
+    /// should be at least 32 random bytes.
+    pub fn with_secret(
+        provider: &'static dyn CryptoProvider,
+        secret: &[u8],
+        lifetime: Duration,
+    ) -> Result<Self, Error> {
+        let suite = provider
+            .default_cipher_suites()
+            .iter()
+            .find_map(|suite| suite.tls13())
+            .ok_or_else(|| Error::General("no TLS1.3 cipher suites available".into()))?;
+
+        Ok(Self {
+            suite,
+            secret: suite
+                .hkdf_provider
+                .extract_from_secret(None, secret),
+            provider,
+            lifetime,
+        })
+    }
+
+    /// Seal a token for a client at `address`, which sent its first Initial
+    /// packet to `original_dst_cid`.
+    ///
+    /// `address` may be any encoding of the client's address, as long as
+    /// it is the same when the token is opened.
+    pub fn seal(
+        &self,
+        address: &[u8],
+        original_dst_cid: &[u8],
+        now: UnixTime,
+    ) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0u8; Self::NONCE_LEN];
+        self.provider.fill_random(&mut nonce)?;
+
+        let mut token = nonce.to_vec();
+        token.extend_from_slice(&now.as_secs().to_be_bytes());
+        token.extend_from_slice(original_dst_cid);
+
+        let tag = self
+            .packet_key(&nonce)
+            .encrypt_in_place(0, address, &mut token[Self::NONCE_LEN..])?;
+        token.extend_from_slice(tag.as_ref());
+        Ok(token)
+    }
+
+    /// Open a token sealed for a client at `address`.
+    ///
+    /// This returns the Original Destination Connection ID, or `None` if the
+    /// token was not sealed by this sealer (or one with the same secret),
+    /// was sealed for a different address, or has expired.  Tokens issued
+    /// more than a few seconds after `now` are also refused.
+    pub fn open(&self, address: &[u8], token: &[u8], now: UnixTime) -> Option<Vec<u8>> {
+        if token.len() < Self::NONCE_LEN {
+            return None;
+        }
+
+        let (nonce, sealed) = token.split_at(Self::NONCE_LEN);
+        let mut sealed = sealed.to_vec();
+        let plain = self
+            .packet_key(nonce)
+            .decrypt_in_place(0, address, &mut sealed)
+            .ok()?;
+
+        let (issued, original_dst_cid) = match plain.len() >= 8 {
+            true => plain.split_at(8),
+            false => return None,
+        };
+        let issued = u64::from_be_bytes(issued.try_into().unwrap());
+        let now = now.as_secs();
+        let valid = issued <= now.saturating_add(Self::MAX_CLOCK_SKEW_SECS)
+            && now.saturating_sub(issued) <= self.lifetime.as_secs();
+        match valid {
+            true => Some(original_dst_cid.to_vec()),
+            false => None,
+        }
+    }
+
+    fn packet_key(&self, nonce: &[u8]) -> Box<dyn PacketKey> {
+        let secret = hkdf_expand_label_block(self.secret.as_ref(), b"quic retry token", nonce);
+        self.suite.quic.packet_key(
+            self.suite,
+            self.suite
+                .hkdf_provider
+                .expander_for_okm(&secret)
+                .as_ref(),
+            Version::V1,
+        )
+    }
+}
+
 /// Key material for use in QUIC packet spaces
 ///
 /// QUIC uses 4 different sets of keys (and progressive key updates for long-running connections):
@@ -770,7 +1182,7 @@ pub enum KeyChange {
 ///
 /// Governs version-specific behavior in the TLS layer
 #[non_exhaustive]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Version {
     /// Draft versions 29, 30, 31 and 32
     V1Draft,
@@ -822,6 +1234,108 @@ impl Version {
         }
     }
 
+    /// Compute the Retry Integrity Tag of a Retry packet.
+    ///
+    /// `retry_packet` is the Retry packet up to the tag, and `original_dst_cid`
+    /// is the Destination Connection ID of the client's Initial packet.
+    /// `suite` must be `TLS13_AES_128_GCM_SHA256`, as for [`Keys::initial()`].
+    ///
+    /// See <https://www.rfc-editor.org/rfc/rfc9001.html#name-retry-packet-integrity>.
+    pub fn retry_integrity_tag(
+        self,
+        suite: &'static Tls13CipherSuite,
+        original_dst_cid: &[u8],
+        retry_packet: &[u8],
+    ) -> Result<Tag, Error> {
+        let pseudo_packet = Self::retry_pseudo_packet(original_dst_cid, retry_packet)?;
+        self.retry_integrity_key(suite)?
+            .encrypt_in_place(0, &pseudo_packet, &mut [])
+    }
+
+    /// Verify the Retry Integrity Tag of a Retry packet.
+    ///
+    /// `retry_packet` is the whole Retry packet, ending with the tag, and
+    /// `original_dst_cid` is the Destination Connection ID of the client's
+    /// Initial packet.  `suite` must be `TLS13_AES_128_GCM_SHA256`, as for
+    /// [`Keys::initial()`].
+    ///
+    /// Returns [`Error::DecryptError`] if the tag is incorrect.
+    pub fn verify_retry_integrity_tag(
+        self,
+        suite: &'static Tls13CipherSuite,
+        original_dst_cid: &[u8],
+        retry_packet: &[u8],
+    ) -> Result<(), Error> {
+        let tag_start = retry_packet
+            .len()
+            .checked_sub(TAG_LEN)
+            .ok_or(Error::DecryptError)?;
+        let (retry_packet, tag) = retry_packet.split_at(tag_start);
+        let pseudo_packet = Self::retry_pseudo_packet(original_dst_cid, retry_packet)?;
+        self.retry_integrity_key(suite)?
+            .decrypt_in_place(0, &pseudo_packet, &mut tag.to_vec())
+            .map(|_| ())
+    }
+
+    fn retry_integrity_key(
+        self,
+        suite: &'static Tls13CipherSuite,
+    ) -> Result<Box<dyn PacketKey>, Error> {
+        if suite.common.suite != CipherSuite::TLS13_AES_128_GCM_SHA256 {
+            return Err(Error::General(
+                "Retry integrity requires TLS13_AES_128_GCM_SHA256".into(),
+            ));
+        }
+
+        let (key, nonce) = match self {
+            // https://datatracker.ietf.org/doc/html/draft-ietf-quic-tls-32#section-5.8
+            Self::V1Draft => (
+                [
+                    0xcc, 0xce, 0x18, 0x7e, 0xd0, 0x9a, 0x09, 0xd0, 0x57, 0x28, 0x15, 0x5a, 0x6c,
+                    0xb9, 0x6b, 0xe1,
+                ],
+                [
+                    0xe5, 0x49, 0x30, 0xf9, 0x7f, 0x21, 0x36, 0xf0, 0x53, 0x0a, 0x8c, 0x1c,
+                ],
+            ),
+            // https://www.rfc-editor.org/rfc/rfc9001.html#name-retry-packet-integrity
+            Self::V1 => (
+                [
+                    0xbe, 0x0c, 0x69, 0x0b, 0x9f, 0x66, 0x57, 0x5a, 0x1d, 0x76, 0x6b, 0x54, 0xe3,
+                    0x68, 0xc8, 0x4e,
+                ],
+                [
+                    0x46, 0x15, 0x99, 0xd3, 0x5d, 0x63, 0x2b, 0xf2, 0x23, 0x98, 0x25, 0xbb,
+                ],
+            ),
+            // https://www.rfc-editor.org/rfc/rfc9369.html#name-retry-integrity-tag
+            Self::V2 => (
+                [
+                    0x8f, 0xb4, 0xb0, 0x1b, 0x56, 0xac, 0x48, 0xe2, 0x60, 0xfb, 0xcb, 0xce, 0xad,
+                    0x7c, 0xcc, 0x92,
+                ],
+                [
+                    0xd8, 0x69, 0x69, 0xbc, 0x2d, 0x7c, 0x6d, 0x99, 0x90, 0xef, 0xb0, 0x4a,
+                ],
+            ),
+        };
+
+        Ok(suite
+            .quic
+            .fixed_packet_key(suite, &key, Iv::from(nonce)))
+    }
+
+    fn retry_pseudo_packet(original_dst_cid: &[u8], retry_packet: &[u8]) -> Result<Vec<u8>, Error> {
+        let cid_len = u8::try_from(original_dst_cid.len())
+            .map_err(|_| Error::General("connection ID too long".into()))?;
+
+        let mut pseudo_packet = Vec::with_capacity(1 + original_dst_cid.len() + retry_packet.len());
+        pseudo_packet.push(cid_len);
+        pseudo_packet.extend_from_slice(original_dst_cid);
+        pseudo_packet.extend_from_slice(retry_packet);
+        Ok(pseudo_packet)
+    }
+
     fn key_update_label(&self) -> &'static [u8] {
         match self {
             Self::V1Draft | Self::V1 => b"quic ku",
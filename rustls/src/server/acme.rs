@@ -0,0 +1,252 @@
+use crate::dns_name::DnsNameRef;
+use crate::error::Error;
+use crate::server::{self, ClientHello};
+use crate::sign;
+use crate::x509::{self, Der, DER_OCTET_STRING_TAG, DER_SEQUENCE_TAG};
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use std::collections;
+use std::sync::RwLock;
+
+/// The ALPN protocol used to answer ACME TLS-ALPN-01 challenges (RFC 8737).
+pub const ACME_TLS_ALPN_PROTOCOL: &[u8] = b"acme-tls/1";
+
+/// Something which answers ACME TLS-ALPN-01 challenges (RFC 8737),
+/// and otherwise resolves certificates using another resolver.
+///
+/// When a client offers the [`ACME_TLS_ALPN_PROTOCOL`], the challenge
+/// certificate added for the client's SNI name is used.  If there is none,
+/// no certificate is resolved: the other resolver is never used for such
+/// clients.  For the protocol to be negotiated, `ServerConfig::alpn_protocols`
+/// must include [`ACME_TLS_ALPN_PROTOCOL`].
+///
+/// No application data is exchanged on connections that negotiate
+/// [`ACME_TLS_ALPN_PROTOCOL`]: the server sends a `close_notify` alert as
+/// soon as the handshake completes, fails the connection if it receives any
+/// application data, and discards any written to it.
+pub struct AcmeTlsAlpn01Resolver {
+    inner: Arc<dyn server::ResolvesServerCert>,
+    challenges: RwLock<collections::HashMap<String, Arc<sign::CertifiedKey>>>,
+}
+
+impl AcmeTlsAlpn01Resolver {
+    /// Create a new resolver with no challenges, which uses `inner`
+    /// for other clients.
+    pub fn new(inner: Arc<dyn server::ResolvesServerCert>) -> Self {
+        Self {
+            inner,
+            challenges: RwLock::new(collections::HashMap::new()),
+        }
+    }
+
+    /// Add a challenge certificate for the SNI `name`, replacing any
+    /// existing one.
+    ///
+    /// This function fails if `name` is not a valid DNS name, or if the
+    /// certificate does not have `name` as its only subject alternative
+    /// name, or if it does not have an `acmeIdentifier` extension.
+    pub fn add_challenge(&self, name: &str, ck: sign::CertifiedKey) -> Result<(), Error> {
+        let name = DnsNameRef::try_from(name)
+            .map_err(|_| Error::General("Bad DNS name".into()))?
+            .to_lowercase_owned();
+
+        let cert = ck.end_entity_cert()?;
+        if !is_challenge_cert_for(cert, name.as_ref()) {
+            return Err(Error::General(
+                "not a TLS-ALPN-01 challenge certificate for this name".into(),
+            ));
+        }
+
+        self.challenges
+            .write()
+            .unwrap()
+            .insert(name.as_ref().to_string(), Arc::new(ck));
+        Ok(())
+    }
+
+    /// Remove the challenge certificate for the SNI `name`.
+    ///
+    /// Returns `true` if there was one.
+    pub fn remove_challenge(&self, name: &str) -> bool {
+        self.challenges
+            .write()
+            .unwrap()
+            .remove(&name.to_ascii_lowercase())
+            .is_some()
+    }
+}
+
+impl server::ResolvesServerCert for AcmeTlsAlpn01Resolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<sign::CertifiedKey>> {
+        let is_challenge = client_hello
+            .alpn()
+            .map_or(false, |mut protocols| {
+                protocols.any(|protocol| protocol == ACME_TLS_ALPN_PROTOCOL)
+            });
+        if !is_challenge {
+            return self.inner.resolve(client_hello);
+        }
+
+        let name = client_hello.server_name()?;
+        self.challenges
+            .read()
+            .unwrap()
+            .get(name)
+            .map(Arc::clone)
+    }
+}
+
+/// Whether `cert` has `name` as its only subject alternative name, and
+/// has an `acmeIdentifier` extension holding a SHA-256 digest.
+fn is_challenge_cert_for(cert: &[u8], name: &str) -> bool {
+    let has_identifier = matches!(
+        x509::cert_extension(cert, OID_ACME_IDENTIFIER),
+        Some([DER_OCTET_STRING_TAG, 32, digest @ ..]) if digest.len() == 32
+    );
+
+    let mut names = match x509::cert_extension(cert, x509::OID_SUBJECT_ALT_NAME)
+        .and_then(|san| Der::expect(san, DER_SEQUENCE_TAG))
+    {
+        Some(names) => Der::items(names),
+        None => return false,
+    };
+    let only_name = match (names.next(), names.next()) {
+        (Some(Some(only)), None) => only,
+        _ => return false,
+    };
+
+    has_identifier
+        && only_name.tag == x509::DER_DNS_NAME_TAG
+        && only_name
+            .value
+            .eq_ignore_ascii_case(name.as_bytes())
+}
+
+/// id-pe-acmeIdentifier: 1.3.6.1.5.5.7.1.31
+const OID_ACME_IDENTIFIER: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x1f];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::ResolvesServerCert;
+    use crate::DistinguishedName;
+
+    use pki_types::CertificateDer;
+
+    struct AlwaysResolves(Arc<sign::CertifiedKey>);
+
+    impl ResolvesServerCert for AlwaysResolves {
+        fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<sign::CertifiedKey>> {
+            Some(Arc::clone(&self.0))
+        }
+    }
+
+    fn certified_key(chain: &[u8]) -> sign::CertifiedKey {
+        let chain = rustls_pemfile::certs(&mut &chain[..])
+            .map(|cert| cert.unwrap())
+            .collect();
+        sign::CertifiedKey::new(chain, Arc::new(NoKey))
+    }
+
+    #[derive(Debug)]
+    struct NoKey;
+
+    impl sign::SigningKey for NoKey {
+        fn choose_scheme(
+            &self,
+            _offered: &[crate::SignatureScheme],
+        ) -> Option<Box<dyn sign::Signer>> {
+            None
+        }
+
+        fn algorithm(&self) -> crate::SignatureAlgorithm {
+            crate::SignatureAlgorithm::ECDSA
+        }
+    }
+
+    fn resolve_end_entity(
+        resolver: &AcmeTlsAlpn01Resolver,
+        name: &str,
+        alpn: &[&[u8]],
+    ) -> Option<CertificateDer<'static>> {
+        let name = Some(
+            DnsNameRef::try_from(name)
+                .unwrap()
+                .to_owned(),
+        );
+        let alpn = alpn
+            .iter()
+            .map(|protocol| crate::msgs::handshake::ProtocolName::from(protocol.to_vec()))
+            .collect::<Vec<_>>();
+        resolver
+            .resolve(ClientHello::new(
+                &name,
+                &[],
+                Some(&alpn),
+                &[],
+                None::<&[DistinguishedName]>,
+            ))
+            .map(|ck| ck.cert[0].clone())
+    }
+
+    const ACME_CHAIN: &[u8] = include_bytes!("../../../test-ca/ecdsa/end.acme.cert");
+    const END_CHAIN: &[u8] = include_bytes!("../../../test-ca/ecdsa/end.fullchain");
+
+    #[test]
+    fn test_answers_challenges() {
+        let (acme, end) = (certified_key(ACME_CHAIN), certified_key(END_CHAIN));
+        let (acme_ee, end_ee) = (acme.cert[0].clone(), end.cert[0].clone());
+
+        let resolver = AcmeTlsAlpn01Resolver::new(Arc::new(AlwaysResolves(Arc::new(end))));
+        resolver
+            .add_challenge("TestServer.com", acme)
+            .unwrap();
+
+        assert_eq!(
+            resolve_end_entity(&resolver, "testserver.com", &[ACME_TLS_ALPN_PROTOCOL]),
+            Some(acme_ee)
+        );
+        assert_eq!(
+            resolve_end_entity(&resolver, "other.com", &[ACME_TLS_ALPN_PROTOCOL]),
+            None
+        );
+        assert_eq!(
+            resolve_end_entity(&resolver, "testserver.com", &[b"h2"]),
+            Some(end_ee.clone())
+        );
+        assert_eq!(
+            resolve_end_entity(&resolver, "testserver.com", &[]),
+            Some(end_ee)
+        );
+
+        assert!(resolver.remove_challenge("testserver.com"));
+        assert!(!resolver.remove_challenge("testserver.com"));
+        assert_eq!(
+            resolve_end_entity(&resolver, "testserver.com", &[ACME_TLS_ALPN_PROTOCOL]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_challenge_certs() {
+        let resolver = AcmeTlsAlpn01Resolver::new(Arc::new(AlwaysResolves(Arc::new(
+            certified_key(END_CHAIN),
+        ))));
+
+        // no acmeIdentifier, and more than one name
+        assert!(resolver
+            .add_challenge("testserver.com", certified_key(END_CHAIN))
+            .is_err());
+        // wrong name
+        assert!(resolver
+            .add_challenge("localhost", certified_key(ACME_CHAIN))
+            .is_err());
+        assert!(resolver
+            .add_challenge("not a name", certified_key(ACME_CHAIN))
+            .is_err());
+        assert!(resolver
+            .add_challenge("testserver.com", certified_key(b""))
+            .is_err());
+    }
+}
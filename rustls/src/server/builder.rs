@@ -7,7 +7,7 @@ use crate::server::{ResolvesServerCert, ServerConfig};
 use crate::suites::SupportedCipherSuite;
 use crate::verify::{ClientCertVerifier, NoClientAuth};
 use crate::versions;
-use crate::NoKeyLog;
+use crate::{NoHandshakeObserver, NoKeyLog};
 
 #[cfg(feature = "ring")]
 use pki_types::{CertificateDer, PrivateKeyDer};
@@ -77,6 +77,25 @@ impl ConfigBuilder<ServerConfig, WantsServerCert> {
         Ok(self.with_cert_resolver(Arc::new(resolver)))
     }
 
+    #[cfg(feature = "ring")]
+    /// Sets a single certificate chain, matching private key and OCSP
+    /// response.  This certificate and key is used for all
+    /// subsequent connections, irrespective of things like SNI hostname.
+    ///
+    /// `cert_chain` is a vector of DER-encoded certificates.
+    /// `key_der` is a DER-encoded RSA, ECDSA, or Ed25519 private key.
+    /// `ocsp` is a DER-encoded OCSP response.  Ignored if zero length.
+    ///
+    /// This function fails if `key_der` is invalid.
+    pub fn with_single_cert_with_ocsp(
+        self,
+        cert_chain: Vec<CertificateDer<'static>>,
+        key_der: PrivateKeyDer<'static>,
+        ocsp: Vec<u8>,
+    ) -> Result<ServerConfig, Error> {
+        self.with_single_cert_with_ocsp_and_sct(cert_chain, key_der, ocsp, Vec::new())
+    }
+
     #[cfg(feature = "ring")]
     /// Sets a single certificate chain, matching private key, OCSP
     /// response and SCTs.  This certificate and key is used for all
@@ -85,15 +104,19 @@ impl ConfigBuilder<ServerConfig, WantsServerCert> {
     /// `cert_chain` is a vector of DER-encoded certificates.
     /// `key_der` is a DER-encoded RSA, ECDSA, or Ed25519 private key.
     /// `ocsp` is a DER-encoded OCSP response.  Ignored if zero length.
+    /// `scts` is an `SignedCertificateTimestampList` encoding (see RFC6962)
+    /// and is only used if the client requests it.  Ignored if zero length.
     ///
     /// This function fails if `key_der` is invalid.
-    pub fn with_single_cert_with_ocsp(
+    pub fn with_single_cert_with_ocsp_and_sct(
         self,
         cert_chain: Vec<CertificateDer<'static>>,
         key_der: PrivateKeyDer<'static>,
         ocsp: Vec<u8>,
+        scts: Vec<u8>,
     ) -> Result<ServerConfig, Error> {
-        let resolver = handy::AlwaysResolvesChain::new_with_extras(cert_chain, &key_der, ocsp)?;
+        let resolver =
+            handy::AlwaysResolvesChain::new_with_extras(cert_chain, &key_der, ocsp, scts)?;
         Ok(self.with_cert_resolver(Arc::new(resolver)))
     }
 
@@ -112,6 +135,7 @@ impl ConfigBuilder<ServerConfig, WantsServerCert> {
             alpn_protocols: Vec::new(),
             versions: self.state.versions,
             key_log: Arc::new(NoKeyLog {}),
+            handshake_observer: Arc::new(NoHandshakeObserver),
             enable_secret_extraction: false,
             max_early_data_size: 0,
             send_half_rtt_data: false,
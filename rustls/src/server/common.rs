@@ -1,12 +1,18 @@
+use crate::msgs::handshake::DistinguishedName;
+use crate::server::ClientHello;
 use crate::sign;
+use crate::verify::ClientCertVerifier;
+
+use alloc::vec::Vec;
 
 use pki_types::CertificateDer;
 
-/// ActiveCertifiedKey wraps CertifiedKey and tracks OSCP and SCT state
+/// ActiveCertifiedKey wraps CertifiedKey and tracks OCSP and SCT state
 /// in a single handshake.
 pub(super) struct ActiveCertifiedKey<'a> {
     key: &'a sign::CertifiedKey,
     ocsp: Option<&'a [u8]>,
+    sct_list: Option<&'a [u8]>,
 }
 
 impl<'a> ActiveCertifiedKey<'a> {
@@ -14,6 +20,7 @@ impl<'a> ActiveCertifiedKey<'a> {
         ActiveCertifiedKey {
             key,
             ocsp: key.ocsp.as_deref(),
+            sct_list: key.sct_list.as_deref(),
         }
     }
 
@@ -33,4 +40,43 @@ impl<'a> ActiveCertifiedKey<'a> {
     pub(super) fn get_ocsp(&self) -> Option<&[u8]> {
         self.ocsp
     }
+
+    #[inline]
+    pub(super) fn get_sct_list(&self) -> Option<&[u8]> {
+        self.sct_list
+    }
+
+    #[inline]
+    pub(super) fn get_delegated_credential(&self) -> Option<&sign::DelegatedKey> {
+        self.key.delegated_credential.as_ref()
+    }
+}
+
+/// How the server's `ClientCertVerifier` wants to authenticate the client in
+/// a single handshake.
+///
+/// This is decided when the `ClientHello` is received, and used when the
+/// `CertificateRequest` is sent and the client's `Certificate` is received.
+pub(super) struct ClientAuthRequest {
+    pub(super) mandatory: bool,
+    pub(super) root_hint_subjects: Vec<DistinguishedName>,
+}
+
+impl ClientAuthRequest {
+    /// Returns `None` if `verifier` does not offer client authentication for `client_hello`.
+    pub(super) fn new(
+        verifier: &dyn ClientCertVerifier,
+        client_hello: &ClientHello<'_>,
+    ) -> Option<Self> {
+        if !verifier.offer_client_auth(client_hello) {
+            return None;
+        }
+
+        Some(Self {
+            mandatory: verifier.client_auth_mandatory(client_hello),
+            root_hint_subjects: verifier
+                .root_hint_subjects(client_hello)
+                .to_vec(),
+        })
+    }
 }
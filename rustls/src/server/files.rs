@@ -0,0 +1,337 @@
+use crate::crypto::ring;
+use crate::error::Error;
+#[cfg(feature = "logging")]
+use crate::log::{debug, warn};
+use crate::server::{self, ClientHello};
+use crate::sign;
+use crate::webpki::{verify_signature_with_spki, ParsedCertificate, SUPPORTED_SIG_ALGS};
+use crate::x509;
+
+use pki_types::{CertificateDer, PrivateKeyDer};
+
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{Instant, SystemTime};
+
+/// Something which resolves to a certificate chain and private key
+/// loaded from PEM files, and reloads them when they change.
+///
+/// The files are reloaded when [`ResolvesServerCertFromFiles::reload()`]
+/// or [`ResolvesServerCertFromFiles::reload_if_changed()`] are called, or
+/// during handshakes if a poll interval is set with
+/// [`ResolvesServerCertFromFiles::with_poll_interval()`].
+///
+/// A new certificate chain and key are only used if the chain parses,
+/// and the key matches its end-entity certificate.  Otherwise, the
+/// previous ones continue to be used.  This means the files can be
+/// replaced one at a time without interrupting service.
+pub struct ResolvesServerCertFromFiles {
+    chain_path: PathBuf,
+    key_path: PathBuf,
+    poll_interval: Option<Duration>,
+    last_poll: Mutex<Instant>,
+    loaded: RwLock<LoadedFiles>,
+}
+
+struct LoadedFiles {
+    certified_key: Arc<sign::CertifiedKey>,
+    modified: [Option<(SystemTime, u64)>; 2],
+}
+
+impl ResolvesServerCertFromFiles {
+    /// Load a certificate chain from the PEM file `chain_path`, which starts
+    /// with the end-entity certificate, and its private key from the PEM file
+    /// `key_path`.
+    ///
+    /// This function fails if either file cannot be read, or if their
+    /// contents are not a valid certificate chain and matching key.
+    pub fn new(
+        chain_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+    ) -> Result<Self, Error> {
+        let chain_path = chain_path.into();
+        let key_path = key_path.into();
+        let loaded = LoadedFiles::load(&chain_path, &key_path)?;
+
+        Ok(Self {
+            chain_path,
+            key_path,
+            poll_interval: None,
+            last_poll: Mutex::new(Instant::now()),
+            loaded: RwLock::new(loaded),
+        })
+    }
+
+    /// Check whether the files have changed during handshakes, at most
+    /// once every `interval`, and reload them if so.
+    ///
+    /// Failures to reload are logged, and otherwise ignored.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Load the files again, whether or not they have changed.
+    ///
+    /// On error, the previous certificate chain and key continue to be used.
+    pub fn reload(&self) -> Result<(), Error> {
+        let loaded = LoadedFiles::load(&self.chain_path, &self.key_path)?;
+        *self.loaded.write().unwrap() = loaded;
+        debug!("Reloaded {:?} and {:?}", self.chain_path, self.key_path);
+        Ok(())
+    }
+
+    /// Load the files again if either's modification time or length has
+    /// changed since they were last loaded.  Returns whether they were reloaded.
+    ///
+    /// On error, the previous certificate chain and key continue to be used.
+    pub fn reload_if_changed(&self) -> Result<bool, Error> {
+        let modified = modification_times(&self.chain_path, &self.key_path);
+        if self.loaded.read().unwrap().modified == modified {
+            return Ok(false);
+        }
+
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// The certificate chain and key currently in use.
+    pub fn certified_key(&self) -> Arc<sign::CertifiedKey> {
+        Arc::clone(
+            &self
+                .loaded
+                .read()
+                .unwrap()
+                .certified_key,
+        )
+    }
+
+    fn poll(&self, interval: Duration) {
+        {
+            // Skip polling if another handshake is already doing it.
+            let mut last_poll = match self.last_poll.try_lock() {
+                Ok(last_poll) => last_poll,
+                Err(_) => return,
+            };
+            if last_poll.elapsed() < interval {
+                return;
+            }
+            *last_poll = Instant::now();
+        }
+
+        if let Err(_err) = self.reload_if_changed() {
+            warn!(
+                "Failed to reload {:?} and {:?}: {}",
+                self.chain_path, self.key_path, _err
+            );
+        }
+    }
+}
+
+impl server::ResolvesServerCert for ResolvesServerCertFromFiles {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<sign::CertifiedKey>> {
+        if let Some(interval) = self.poll_interval {
+            self.poll(interval);
+        }
+
+        Some(self.certified_key())
+    }
+}
+
+impl LoadedFiles {
+    fn load(chain_path: &Path, key_path: &Path) -> Result<Self, Error> {
+        // Note the modification times first, so that a change made while
+        // loading is noticed next time.
+        let modified = modification_times(chain_path, key_path);
+
+        let chain = read_chain(chain_path)?;
+        let key = read_key(key_path)?;
+        let key = ring::sign::any_supported_type(&key)
+            .map_err(|_| Error::General(format!("invalid private key in {:?}", key_path)))?;
+
+        let certified_key = sign::CertifiedKey::new(chain, key);
+        check_key_matches(&certified_key)?;
+
+        Ok(Self {
+            certified_key: Arc::new(certified_key),
+            modified,
+        })
+    }
+}
+
+fn modification_times(chain_path: &Path, key_path: &Path) -> [Option<(SystemTime, u64)>; 2] {
+    let modified = |path: &Path| {
+        let metadata = fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    };
+    [modified(chain_path), modified(key_path)]
+}
+
+fn read_chain(path: &Path) -> Result<Vec<CertificateDer<'static>>, Error> {
+    let pem = read_file(path)?;
+    rustls_pemfile::certs(&mut io::BufReader::new(&pem[..]))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| Error::General(format!("cannot parse {:?}: {}", path, err)))
+}
+
+fn read_key(path: &Path) -> Result<PrivateKeyDer<'static>, Error> {
+    let pem = read_file(path)?;
+    for item in rustls_pemfile::read_all(&mut io::BufReader::new(&pem[..])) {
+        match item {
+            Ok(rustls_pemfile::Item::Pkcs1Key(key)) => return Ok(key.into()),
+            Ok(rustls_pemfile::Item::Pkcs8Key(key)) => return Ok(key.into()),
+            Ok(rustls_pemfile::Item::Sec1Key(key)) => return Ok(key.into()),
+            Ok(_) => {}
+            Err(err) => return Err(Error::General(format!("cannot parse {:?}: {}", path, err))),
+        }
+    }
+
+    Err(Error::General(format!("no private key in {:?}", path)))
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|err| Error::General(format!("cannot read {:?}: {}", path, err)))
+}
+
+/// Check `certified_key`'s end-entity certificate is valid, and has
+/// the public key corresponding to its private key.
+fn check_key_matches(certified_key: &sign::CertifiedKey) -> Result<(), Error> {
+    let end_entity = certified_key.end_entity_cert()?;
+    ParsedCertificate::try_from(end_entity)?;
+
+    let schemes = SUPPORTED_SIG_ALGS.supported_schemes();
+    let signer = certified_key
+        .key
+        .choose_scheme(&schemes)
+        .ok_or_else(|| Error::General("unsupported private key".into()))?;
+
+    let message = b"rustls certificate and private key consistency check";
+    let signature = signer.sign(message)?;
+    let spki = x509::cert_spki(end_entity).ok_or(crate::CertificateError::BadEncoding)?;
+    verify_signature_with_spki(
+        spki,
+        signer.scheme(),
+        message,
+        &signature,
+        &SUPPORTED_SIG_ALGS,
+    )
+    .map_err(|_| Error::General("private key does not match certificate".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::ResolvesServerCert;
+    use crate::test_util::TempDir;
+
+    const RSA_CHAIN: &[u8] = include_bytes!("../../../test-ca/rsa/end.fullchain");
+    const RSA_KEY: &[u8] = include_bytes!("../../../test-ca/rsa/end.key");
+    const RSA_PKCS1_KEY: &[u8] = include_bytes!("../../../test-ca/rsa/end.rsa");
+    const ECDSA_CHAIN: &[u8] = include_bytes!("../../../test-ca/ecdsa/end.fullchain");
+    const ECDSA_KEY: &[u8] = include_bytes!("../../../test-ca/ecdsa/end.key");
+
+    fn end_entity(pem: &[u8]) -> CertificateDer<'static> {
+        rustls_pemfile::certs(&mut &pem[..])
+            .next()
+            .unwrap()
+            .unwrap()
+    }
+
+    fn resolved_end_entity(resolver: &ResolvesServerCertFromFiles) -> CertificateDer<'static> {
+        resolver
+            .resolve(ClientHello::new(&None, &[], None, &[], None))
+            .unwrap()
+            .cert[0]
+            .clone()
+    }
+
+    #[test]
+    fn test_loads_chain_and_key() {
+        let dir = TempDir::new();
+        let chain = dir.write("chain.pem", RSA_CHAIN);
+
+        let key = dir.write("key.pem", RSA_KEY);
+        let resolver = ResolvesServerCertFromFiles::new(&chain, &key).unwrap();
+        assert_eq!(resolved_end_entity(&resolver), end_entity(RSA_CHAIN));
+        assert_eq!(resolver.certified_key().cert.len(), 3);
+
+        let key = dir.write("key.rsa", RSA_PKCS1_KEY);
+        assert!(ResolvesServerCertFromFiles::new(&chain, &key).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_bad_files() {
+        let dir = TempDir::new();
+        let chain = dir.write("chain.pem", RSA_CHAIN);
+        let key = dir.write("key.pem", RSA_KEY);
+        let wrong_key = dir.write("wrong-key.pem", ECDSA_KEY);
+        let empty = dir.write("empty.pem", b"");
+
+        assert!(ResolvesServerCertFromFiles::new(&chain, &wrong_key).is_err());
+        assert!(ResolvesServerCertFromFiles::new(&chain, &empty).is_err());
+        assert!(ResolvesServerCertFromFiles::new(&empty, &key).is_err());
+        assert!(ResolvesServerCertFromFiles::new(&chain, dir.path().join("missing.pem")).is_err());
+        assert_eq!(
+            ResolvesServerCertFromFiles::new(&chain, &wrong_key).err(),
+            Some(Error::General(
+                "private key does not match certificate".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_on_error() {
+        let dir = TempDir::new();
+        let chain = dir.write("chain.pem", RSA_CHAIN);
+        let key = dir.write("key.pem", RSA_KEY);
+        let resolver = ResolvesServerCertFromFiles::new(&chain, &key).unwrap();
+
+        // only the chain has been replaced so far
+        dir.write("chain.pem", ECDSA_CHAIN);
+        assert!(resolver.reload().is_err());
+        assert_eq!(resolved_end_entity(&resolver), end_entity(RSA_CHAIN));
+
+        dir.write("key.pem", ECDSA_KEY);
+        assert!(resolver.reload().is_ok());
+        assert_eq!(resolved_end_entity(&resolver), end_entity(ECDSA_CHAIN));
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let dir = TempDir::new();
+        let chain = dir.write("chain.pem", RSA_CHAIN);
+        let key = dir.write("key.pem", RSA_KEY);
+        let resolver = ResolvesServerCertFromFiles::new(&chain, &key).unwrap();
+        assert_eq!(resolver.reload_if_changed(), Ok(false));
+
+        dir.write("chain.pem", ECDSA_CHAIN);
+        dir.write("key.pem", ECDSA_KEY);
+        assert_eq!(resolver.reload_if_changed(), Ok(true));
+        assert_eq!(resolved_end_entity(&resolver), end_entity(ECDSA_CHAIN));
+        assert_eq!(resolver.reload_if_changed(), Ok(false));
+    }
+
+    #[test]
+    fn test_polls_for_changes() {
+        let dir = TempDir::new();
+        let chain = dir.write("chain.pem", RSA_CHAIN);
+        let key = dir.write("key.pem", RSA_KEY);
+        let resolver = ResolvesServerCertFromFiles::new(&chain, &key)
+            .unwrap()
+            .with_poll_interval(Duration::from_secs(0));
+
+        dir.write("chain.pem", ECDSA_CHAIN);
+        dir.write("key.pem", ECDSA_KEY);
+        assert_eq!(resolved_end_entity(&resolver), end_entity(ECDSA_CHAIN));
+
+        // a broken chain is ignored
+        dir.write("chain.pem", b"");
+        assert_eq!(resolved_end_entity(&resolver), end_entity(ECDSA_CHAIN));
+    }
+}
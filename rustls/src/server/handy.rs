@@ -1,22 +1,24 @@
 #[cfg(feature = "ring")]
 use crate::crypto::ring;
-use crate::dns_name::DnsNameRef;
-use crate::error::Error;
-use crate::limited_cache;
+use crate::dns_name::{DnsName, DnsNameRef};
+use crate::error::{CertificateError, Error};
 use crate::server;
 use crate::server::ClientHello;
 use crate::sign;
 use crate::webpki::{verify_server_name, ParsedCertificate};
+use crate::x509;
 use crate::ServerName;
 
+use pki_types::UnixTime;
 #[cfg(feature = "ring")]
 use pki_types::{CertificateDer, PrivateKeyDer};
 
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use std::collections;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
 
 /// Something which never stores sessions.
 pub struct NoServerSessionStorage {}
@@ -37,21 +39,29 @@ impl server::StoresServerSessions for NoServerSessionStorage {
 }
 
 /// An implementer of `StoresServerSessions` that stores everything
-/// in memory.  If enforces a limit on the number of stored sessions
-/// to bound memory usage.
+/// in memory.  It enforces a limit on the number of stored sessions
+/// to bound memory usage, evicting the least recently used session
+/// to make room for a new one.
+///
+/// Sessions are discarded once they expire.  [`ServerSessionMemoryCache::stats()`]
+/// reports what the cache has been doing.
 pub struct ServerSessionMemoryCache {
-    cache: Mutex<limited_cache::LimitedCache<Vec<u8>, Vec<u8>>>,
+    cache: Mutex<LruSessionCache>,
 }
 
 impl ServerSessionMemoryCache {
     /// Make a new ServerSessionMemoryCache.  `size` is the maximum
-    /// number of stored sessions, and may be rounded-up for
-    /// efficiency.
+    /// number of stored sessions.
     pub fn new(size: usize) -> Arc<Self> {
         Arc::new(Self {
-            cache: Mutex::new(limited_cache::LimitedCache::new(size)),
+            cache: Mutex::new(LruSessionCache::new(size)),
         })
     }
+
+    /// Counters of this cache's activity since it was made.
+    pub fn stats(&self) -> ServerSessionCacheStats {
+        self.cache.lock().unwrap().stats
+    }
 }
 
 impl server::StoresServerSessions for ServerSessionMemoryCache {
@@ -59,7 +69,15 @@ impl server::StoresServerSessions for ServerSessionMemoryCache {
         self.cache
             .lock()
             .unwrap()
-            .insert(key, value);
+            .insert(key, value, None);
+        true
+    }
+
+    fn put_with_expiry(&self, key: Vec<u8>, value: Vec<u8>, expiry: UnixTime) -> bool {
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(key, value, Some(expiry));
         true
     }
 
@@ -67,12 +85,14 @@ impl server::StoresServerSessions for ServerSessionMemoryCache {
         self.cache
             .lock()
             .unwrap()
-            .get(key)
-            .cloned()
+            .get(key, UnixTime::now())
     }
 
     fn take(&self, key: &[u8]) -> Option<Vec<u8>> {
-        self.cache.lock().unwrap().remove(key)
+        self.cache
+            .lock()
+            .unwrap()
+            .take(key, UnixTime::now())
     }
 
     fn can_cache(&self) -> bool {
@@ -80,6 +100,131 @@ impl server::StoresServerSessions for ServerSessionMemoryCache {
     }
 }
 
+/// Counters of the activity of a [`ServerSessionMemoryCache`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ServerSessionCacheStats {
+    /// Lookups that found a session.
+    pub hits: u64,
+    /// Lookups that did not find a session, including those that
+    /// found an expired one.
+    pub misses: u64,
+    /// Sessions discarded to make room for another.
+    pub evictions: u64,
+    /// Sessions discarded because they expired.
+    pub expirations: u64,
+}
+
+struct LruSessionCache {
+    entries: collections::HashMap<Vec<u8>, CacheEntry>,
+    /// The keys of `entries`, by when they were last used.
+    recency: collections::BTreeMap<u64, Vec<u8>>,
+    clock: u64,
+    limit: usize,
+    stats: ServerSessionCacheStats,
+}
+
+struct CacheEntry {
+    value: Vec<u8>,
+    expiry: Option<UnixTime>,
+    last_used: u64,
+}
+
+impl LruSessionCache {
+    fn new(limit: usize) -> Self {
+        Self {
+            entries: collections::HashMap::with_capacity(limit),
+            recency: collections::BTreeMap::new(),
+            clock: 0,
+            limit,
+            stats: ServerSessionCacheStats::default(),
+        }
+    }
+
+    fn insert(&mut self, key: Vec<u8>, value: Vec<u8>, expiry: Option<UnixTime>) {
+        if self.limit == 0 {
+            return;
+        }
+
+        let last_used = self.tick();
+        let entry = CacheEntry {
+            value,
+            expiry,
+            last_used,
+        };
+        match self.entries.insert(key.clone(), entry) {
+            Some(old) => {
+                self.recency.remove(&old.last_used);
+            }
+            None if self.entries.len() > self.limit => self.evict_oldest(),
+            None => {}
+        }
+        self.recency.insert(last_used, key);
+    }
+
+    fn get(&mut self, key: &[u8], now: UnixTime) -> Option<Vec<u8>> {
+        if !self.lookup(key, now) {
+            return None;
+        }
+
+        let last_used = self.tick();
+        let entry = self.entries.get_mut(key)?;
+        let key = self.recency.remove(&entry.last_used)?;
+        entry.last_used = last_used;
+        self.recency.insert(last_used, key);
+        Some(entry.value.clone())
+    }
+
+    fn take(&mut self, key: &[u8], now: UnixTime) -> Option<Vec<u8>> {
+        match self.lookup(key, now) {
+            true => self.remove(key),
+            false => None,
+        }
+    }
+
+    /// Whether `key` has a session that has not expired at `now`,
+    /// discarding it if it has.
+    fn lookup(&mut self, key: &[u8], now: UnixTime) -> bool {
+        let expired = match self.entries.get(key) {
+            Some(entry) => entry
+                .expiry
+                .map_or(false, |expiry| expiry.as_secs() <= now.as_secs()),
+            None => {
+                self.stats.misses += 1;
+                return false;
+            }
+        };
+
+        if expired {
+            self.remove(key);
+            self.stats.expirations += 1;
+            self.stats.misses += 1;
+            return false;
+        }
+
+        self.stats.hits += 1;
+        true
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_used);
+        Some(entry.value)
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self.recency.keys().next().copied();
+        if let Some(key) = oldest.and_then(|last_used| self.recency.remove(&last_used)) {
+            self.entries.remove(&key);
+            self.stats.evictions += 1;
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
 /// Something which never produces tickets.
 pub(super) struct NeverProducesTickets {}
 
@@ -123,6 +268,7 @@ impl AlwaysResolvesChain {
         chain: Vec<CertificateDer<'static>>,
         priv_key: &PrivateKeyDer<'_>,
         ocsp: Vec<u8>,
+        scts: Vec<u8>,
     ) -> Result<Self, Error> {
         let mut r = Self::new(chain, priv_key)?;
 
@@ -131,6 +277,9 @@ impl AlwaysResolvesChain {
             if !ocsp.is_empty() {
                 cert.ocsp = Some(ocsp);
             }
+            if !scts.is_empty() {
+                cert.sct_list = Some(scts);
+            }
         }
 
         Ok(r)
@@ -145,30 +294,50 @@ impl server::ResolvesServerCert for AlwaysResolvesChain {
 
 /// Something that resolves do different cert chains/keys based
 /// on client-supplied server name (via SNI).
+///
+/// Names can be wildcards like `*.example.com`, which match names
+/// with exactly one more label, like `www.example.com`.  A certificate
+/// for the exact name is preferred to one for a matching wildcard.
+///
+/// Each name can have one certificate for each kind of key, for example
+/// one with an RSA key and one with an ECDSA key.  Of these, the first added
+/// which can sign using one of the client's signature schemes is used.
+///
+/// A default certificate can be configured for clients that do not
+/// send SNI, or which ask for a name with no certificate.
+///
+/// Certificates can be added, replaced and removed while the resolver
+/// is in use.
 pub struct ResolvesServerCertUsingSni {
-    by_name: collections::HashMap<String, Arc<sign::CertifiedKey>>,
+    state: RwLock<SniResolverState>,
+}
+
+struct SniResolverState {
+    by_name: collections::HashMap<String, Vec<Arc<sign::CertifiedKey>>>,
+    default: Option<Arc<sign::CertifiedKey>>,
 }
 
 impl ResolvesServerCertUsingSni {
     /// Create a new and empty (i.e., knows no certificates) resolver.
     pub fn new() -> Self {
         Self {
-            by_name: collections::HashMap::new(),
+            state: RwLock::new(SniResolverState {
+                by_name: collections::HashMap::new(),
+                default: None,
+            }),
         }
     }
 
-    /// Add a new `sign::CertifiedKey` to be used for the given SNI `name`.
+    /// Add a new `sign::CertifiedKey` to be used for the given SNI `name`,
+    /// which may be a wildcard like `*.example.com`.
+    ///
+    /// This replaces any certificate for `name` with the same kind of key.
     ///
     /// This function fails if `name` is not a valid DNS name, or if
     /// it's not valid for the supplied certificate, or if the certificate
     /// chain is syntactically faulty.
-    pub fn add(&mut self, name: &str, ck: sign::CertifiedKey) -> Result<(), Error> {
-        let server_name = {
-            let checked_name = DnsNameRef::try_from(name)
-                .map_err(|_| Error::General("Bad DNS name".into()))
-                .map(|name| name.to_lowercase_owned())?;
-            ServerName::DnsName(checked_name)
-        };
+    pub fn add(&self, name: &str, ck: sign::CertifiedKey) -> Result<(), Error> {
+        let name = checked_name(name)?;
 
         // Check the certificate chain for validity:
         // - it should be non-empty list
@@ -179,29 +348,105 @@ impl ResolvesServerCertUsingSni {
         // These checks are not security-sensitive.  They are the
         // *server* attempting to detect accidental misconfiguration.
 
-        ck.end_entity_cert()
-            .and_then(ParsedCertificate::try_from)
-            .and_then(|cert| verify_server_name(&cert, &server_name))?;
+        let end_entity = ck.end_entity_cert()?;
+        let cert = ParsedCertificate::try_from(end_entity)?;
+        match name.starts_with("*.") {
+            // webpki does not match wildcards in the name being checked, so
+            // look for the wildcard itself among the certificate's names.
+            true => {
+                let dns_names =
+                    x509::cert_dns_names(end_entity).ok_or(CertificateError::BadEncoding)?;
+                if !dns_names
+                    .iter()
+                    .any(|dns_name| dns_name.eq_ignore_ascii_case(name.as_bytes()))
+                {
+                    return Err(CertificateError::NotValidForName.into());
+                }
+            }
+            false => verify_server_name(&cert, &ServerName::DnsName(checked_dns_name(&name)?))?,
+        }
 
-        if let ServerName::DnsName(name) = server_name {
-            self.by_name
-                .insert(name.as_ref().to_string(), Arc::new(ck));
+        let mut state = self.state.write().unwrap();
+        let keys = state.by_name.entry(name).or_default();
+        let algorithm = ck.key.algorithm();
+        match keys
+            .iter_mut()
+            .find(|existing| existing.key.algorithm() == algorithm)
+        {
+            Some(existing) => *existing = Arc::new(ck),
+            None => keys.push(Arc::new(ck)),
         }
         Ok(())
     }
+
+    /// Remove all certificates for the given SNI `name`, which may be
+    /// a wildcard like `*.example.com`.
+    ///
+    /// Returns `true` if there were any.
+    pub fn remove(&self, name: &str) -> bool {
+        let name = match checked_name(name) {
+            Ok(name) => name,
+            Err(_) => return false,
+        };
+
+        self.state
+            .write()
+            .unwrap()
+            .by_name
+            .remove(&name)
+            .is_some()
+    }
+
+    /// Set the certificate used for clients that do not send SNI, or
+    /// which ask for a name with no certificate.  `None` means such
+    /// clients get no certificate, which is the default.
+    pub fn set_default(&self, ck: Option<sign::CertifiedKey>) {
+        self.state.write().unwrap().default = ck.map(Arc::new);
+    }
 }
 
 impl server::ResolvesServerCert for ResolvesServerCertUsingSni {
     fn resolve(&self, client_hello: ClientHello) -> Option<Arc<sign::CertifiedKey>> {
-        if let Some(name) = client_hello.server_name() {
-            self.by_name.get(name).map(Arc::clone)
-        } else {
-            // This kind of resolver requires SNI
-            None
-        }
+        let state = self.state.read().unwrap();
+        let schemes = client_hello.signature_schemes();
+        let choose = |name: &str| {
+            state
+                .by_name
+                .get(name)?
+                .iter()
+                .find(|ck| ck.key.choose_scheme(schemes).is_some())
+                .map(Arc::clone)
+        };
+
+        client_hello
+            .server_name()
+            .and_then(|name| {
+                choose(name).or_else(|| {
+                    let (_, parent) = name.split_once('.')?;
+                    choose(&format!("*.{}", parent))
+                })
+            })
+            .or_else(|| state.default.as_ref().map(Arc::clone))
+    }
+}
+
+/// Validates and lowercases the SNI `name`, which may be a wildcard like
+/// `*.example.com`.
+fn checked_name(name: &str) -> Result<String, Error> {
+    match name.strip_prefix("*.") {
+        Some(parent) => Ok(format!("*.{}", checked_dns_name(parent)?.as_ref())),
+        None => Ok(checked_dns_name(name)?
+            .as_ref()
+            .to_string()),
     }
 }
 
+fn checked_dns_name(name: &str) -> Result<DnsName, Error> {
+    DnsNameRef::try_from(name)
+        .map_err(|_| Error::General("Bad DNS name".into()))
+        .map(|name| name.to_lowercase_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,6 +517,60 @@ mod tests {
         assert!(count < 5);
     }
 
+    #[test]
+    fn test_serversessionmemorycache_evicts_least_recently_used() {
+        let c = ServerSessionMemoryCache::new(2);
+        assert!(c.put(vec![0x01], vec![0x02]));
+        assert!(c.put(vec![0x03], vec![0x04]));
+        assert_eq!(c.get(&[0x01]), Some(vec![0x02]));
+        assert!(c.put(vec![0x05], vec![0x06]));
+
+        assert_eq!(c.get(&[0x01]), Some(vec![0x02]));
+        assert_eq!(c.get(&[0x03]), None);
+        assert_eq!(c.get(&[0x05]), Some(vec![0x06]));
+        assert_eq!(
+            c.stats(),
+            ServerSessionCacheStats {
+                hits: 3,
+                misses: 1,
+                evictions: 1,
+                expirations: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_serversessionmemorycache_discards_expired() {
+        let past = UnixTime::since_unix_epoch(core::time::Duration::from_secs(1));
+        let future = UnixTime::since_unix_epoch(core::time::Duration::from_secs(
+            UnixTime::now().as_secs() + 60,
+        ));
+
+        let c = ServerSessionMemoryCache::new(4);
+        assert!(c.put_with_expiry(vec![0x01], vec![0x02], past));
+        assert!(c.put_with_expiry(vec![0x03], vec![0x04], future));
+        assert_eq!(c.get(&[0x01]), None);
+        assert_eq!(c.take(&[0x01]), None);
+        assert_eq!(c.take(&[0x03]), Some(vec![0x04]));
+        assert_eq!(c.take(&[0x03]), None);
+        assert_eq!(
+            c.stats(),
+            ServerSessionCacheStats {
+                hits: 1,
+                misses: 3,
+                evictions: 0,
+                expirations: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_serversessionmemorycache_zero_size_stores_nothing() {
+        let c = ServerSessionMemoryCache::new(0);
+        assert!(c.put(vec![0x01], vec![0x02]));
+        assert_eq!(c.get(&[0x01]), None);
+    }
+
     #[test]
     fn test_neverproducestickets_does_nothing() {
         let npt = NeverProducesTickets {};
@@ -285,7 +584,7 @@ mod tests {
     fn test_resolvesservercertusingsni_requires_sni() {
         let rscsni = ResolvesServerCertUsingSni::new();
         assert!(rscsni
-            .resolve(ClientHello::new(&None, &[], None, &[]))
+            .resolve(ClientHello::new(&None, &[], None, &[], None))
             .is_none());
     }
 
@@ -296,7 +595,209 @@ mod tests {
             .unwrap()
             .to_owned();
         assert!(rscsni
-            .resolve(ClientHello::new(&Some(name), &[], None, &[]))
+            .resolve(ClientHello::new(&Some(name), &[], None, &[], None))
             .is_none());
     }
+
+    #[cfg(feature = "ring")]
+    fn certified_key(chain: &[u8], key: &[u8]) -> sign::CertifiedKey {
+        let chain = rustls_pemfile::certs(&mut &chain[..])
+            .map(|cert| cert.unwrap())
+            .collect();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut &key[..])
+            .next()
+            .unwrap()
+            .unwrap();
+        sign::CertifiedKey::new(
+            chain,
+            ring::sign::any_supported_type(&PrivateKeyDer::Pkcs8(key)).unwrap(),
+        )
+    }
+
+    #[cfg(feature = "ring")]
+    fn resolve_end_entity(
+        resolver: &ResolvesServerCertUsingSni,
+        name: Option<&str>,
+        schemes: &[crate::SignatureScheme],
+    ) -> Option<CertificateDer<'static>> {
+        let name = name.map(|name| {
+            DnsNameRef::try_from(name)
+                .unwrap()
+                .to_owned()
+        });
+        resolver
+            .resolve(ClientHello::new(&name, schemes, None, &[], None))
+            .map(|ck| ck.cert[0].clone())
+    }
+
+    #[cfg(feature = "ring")]
+    const ECDSA_SCHEMES: &[crate::SignatureScheme] = &[
+        crate::SignatureScheme::ECDSA_NISTP256_SHA256,
+        crate::SignatureScheme::ECDSA_NISTP384_SHA384,
+    ];
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_resolvesservercertusingsni_selects_by_signature_scheme() {
+        let rsa = certified_key(
+            include_bytes!("../../../test-ca/rsa/end.fullchain"),
+            include_bytes!("../../../test-ca/rsa/end.key"),
+        );
+        let ecdsa = certified_key(
+            include_bytes!("../../../test-ca/ecdsa/end.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/end.key"),
+        );
+        let (rsa_ee, ecdsa_ee) = (rsa.cert[0].clone(), ecdsa.cert[0].clone());
+
+        let rscsni = ResolvesServerCertUsingSni::new();
+        rscsni.add("localhost", rsa).unwrap();
+        rscsni.add("localhost", ecdsa).unwrap();
+
+        let resolve = |schemes| resolve_end_entity(&rscsni, Some("localhost"), schemes);
+        assert_eq!(resolve(ECDSA_SCHEMES), Some(ecdsa_ee));
+        assert_eq!(
+            resolve(&[crate::SignatureScheme::RSA_PSS_SHA256]),
+            Some(rsa_ee.clone())
+        );
+        assert_eq!(
+            resolve(&[
+                crate::SignatureScheme::RSA_PSS_SHA256,
+                crate::SignatureScheme::ECDSA_NISTP256_SHA256
+            ]),
+            Some(rsa_ee)
+        );
+        assert_eq!(resolve(&[crate::SignatureScheme::ED25519]), None);
+    }
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_resolvesservercertusingsni_prefers_exact_names_to_wildcards() {
+        let exact = certified_key(
+            include_bytes!("../../../test-ca/ecdsa/end.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/end.key"),
+        );
+        let wildcard = certified_key(
+            include_bytes!("../../../test-ca/ecdsa/end.wildcard.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/end.key"),
+        );
+        let (exact_ee, wildcard_ee) = (exact.cert[0].clone(), wildcard.cert[0].clone());
+
+        let rscsni = ResolvesServerCertUsingSni::new();
+        rscsni
+            .add("*.TestServer.com", wildcard)
+            .unwrap();
+        rscsni
+            .add("second.testserver.com", exact)
+            .unwrap();
+
+        let resolve = |name| resolve_end_entity(&rscsni, Some(name), ECDSA_SCHEMES);
+        assert_eq!(resolve("second.testserver.com"), Some(exact_ee));
+        assert_eq!(resolve("third.testserver.com"), Some(wildcard_ee));
+        assert_eq!(resolve("testserver.com"), None);
+        assert_eq!(resolve("a.third.testserver.com"), None);
+    }
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_resolvesservercertusingsni_uses_default() {
+        let ecdsa = certified_key(
+            include_bytes!("../../../test-ca/ecdsa/end.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/end.key"),
+        );
+        let ecdsa_ee = ecdsa.cert[0].clone();
+
+        let rscsni = ResolvesServerCertUsingSni::new();
+        rscsni.set_default(Some(ecdsa));
+        assert_eq!(
+            resolve_end_entity(&rscsni, None, ECDSA_SCHEMES),
+            Some(ecdsa_ee.clone())
+        );
+        assert_eq!(
+            resolve_end_entity(&rscsni, Some("hello.com"), ECDSA_SCHEMES),
+            Some(ecdsa_ee)
+        );
+
+        rscsni.set_default(None);
+        assert_eq!(resolve_end_entity(&rscsni, None, ECDSA_SCHEMES), None);
+    }
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_resolvesservercertusingsni_replaces_and_removes() {
+        let first = certified_key(
+            include_bytes!("../../../test-ca/ecdsa/end.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/end.key"),
+        );
+        let second = certified_key(
+            include_bytes!("../../../test-ca/ecdsa/end.dc.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/end.key"),
+        );
+        let second_ee = second.cert[0].clone();
+
+        let rscsni = ResolvesServerCertUsingSni::new();
+        rscsni.add("localhost", first).unwrap();
+        rscsni.add("localhost", second).unwrap();
+        assert_eq!(
+            resolve_end_entity(&rscsni, Some("localhost"), ECDSA_SCHEMES),
+            Some(second_ee)
+        );
+
+        assert!(rscsni.remove("LOCALHOST"));
+        assert_eq!(
+            resolve_end_entity(&rscsni, Some("localhost"), ECDSA_SCHEMES),
+            None
+        );
+        assert!(!rscsni.remove("localhost"));
+        assert!(!rscsni.remove("not a name"));
+    }
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_resolvesservercertusingsni_removes_wildcards() {
+        let wildcard = certified_key(
+            include_bytes!("../../../test-ca/ecdsa/end.wildcard.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/end.key"),
+        );
+
+        let rscsni = ResolvesServerCertUsingSni::new();
+        rscsni
+            .add("*.testserver.com", wildcard)
+            .unwrap();
+        assert!(rscsni.remove("*.TestServer.COM"));
+        assert_eq!(
+            resolve_end_entity(&rscsni, Some("third.testserver.com"), ECDSA_SCHEMES),
+            None
+        );
+    }
+
+    #[cfg(feature = "ring")]
+    #[test]
+    fn test_resolvesservercertusingsni_requires_wildcard_in_certificate() {
+        use crate::x509::test_util::{der, extension, with_extension};
+        use crate::x509::{DER_DNS_NAME_TAG, DER_SEQUENCE_TAG, OID_SUBJECT_ALT_NAME};
+
+        // a certificate for the name substituted for the wildcard, rather
+        // than for the wildcard itself
+        let mut ck = certified_key(
+            include_bytes!("../../../test-ca/ecdsa/end.fullchain"),
+            include_bytes!("../../../test-ca/ecdsa/end.key"),
+        );
+        let san = der(
+            DER_SEQUENCE_TAG,
+            &der(DER_DNS_NAME_TAG, b"wildcard.testserver.com"),
+        );
+        ck.cert[0] = CertificateDer::from(with_extension(
+            &ck.cert[0],
+            &extension(OID_SUBJECT_ALT_NAME, &san),
+        ));
+
+        let rscsni = ResolvesServerCertUsingSni::new();
+        assert_eq!(
+            rscsni.add("*.testserver.com", ck.clone()),
+            Err(Error::InvalidCertificate(CertificateError::NotValidForName))
+        );
+        rscsni
+            .add("wildcard.testserver.com", ck)
+            .unwrap();
+    }
 }
@@ -7,7 +7,7 @@ use crate::enums::{AlertDescription, HandshakeType, ProtocolVersion, SignatureSc
 use crate::error::{Error, PeerIncompatible, PeerMisbehaved};
 use crate::hash_hs::{HandshakeHash, HandshakeHashBuffer};
 #[cfg(feature = "logging")]
-use crate::log::{debug, trace};
+use crate::log::{debug, trace, warn};
 use crate::msgs::enums::{Compression, ExtensionType};
 #[cfg(feature = "tls12")]
 use crate::msgs::handshake::SessionId;
@@ -22,18 +22,31 @@ use crate::SupportedCipherSuite;
 use super::server_conn::ServerConnectionData;
 #[cfg(feature = "tls12")]
 use super::tls12;
-use crate::server::common::ActiveCertifiedKey;
+use crate::server::common::{ActiveCertifiedKey, ClientAuthRequest};
 use crate::server::tls13;
 
 use alloc::borrow::ToOwned;
 use alloc::boxed::Box;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::time::Duration;
+
+use pki_types::UnixTime;
 
 pub(super) type NextState = Box<dyn State<ServerConnectionData>>;
 pub(super) type NextStateOrError = Result<NextState, Error>;
 pub(super) type ServerContext<'a> = crate::common_state::Context<'a, ServerConnectionData>;
 
+/// How long, in seconds, sessions in `ServerConfig::session_storage` can be resumed for.
+pub(super) const STATEFUL_SESSION_LIFETIME: u32 = 24 * 60 * 60; // this is a bit of a punt
+
+/// When a session stored at `now` in `ServerConfig::session_storage` expires.
+pub(super) fn stateful_session_expiry(now: UnixTime) -> UnixTime {
+    UnixTime::since_unix_epoch(Duration::from_secs(
+        now.as_secs() + u64::from(STATEFUL_SESSION_LIFETIME),
+    ))
+}
+
 pub(super) fn can_resume(
     suite: SupportedCipherSuite,
     sni: &Option<DnsName>,
@@ -70,6 +83,7 @@ impl ExtensionProcessing {
         config: &ServerConfig,
         cx: &mut ServerContext<'_>,
         ocsp_response: &mut Option<&[u8]>,
+        sct_list: &mut Option<&[u8]>,
         hello: &ClientHelloPayload,
         resumedata: Option<&persist::ServerSessionValue>,
         extra_exts: Vec<ServerExtension>,
@@ -158,6 +172,27 @@ impl ExtensionProcessing {
             ocsp_response.take();
         }
 
+        // Likewise for stapled SCTs: only if the client asked for them, and
+        // not when resuming.
+        if !for_resume
+            && hello
+                .find_extension(ExtensionType::SCT)
+                .is_some()
+        {
+            match sct_list.map(ServerExtension::make_sct) {
+                Some(None) => {
+                    warn!("Not stapling invalid SCT list");
+                    sct_list.take();
+                }
+                // TLS1.3 sends the SCT list alongside the end-entity
+                // certificate instead.
+                Some(Some(ext)) if !cx.common.is_tls13() => self.exts.push(ext),
+                _ => {}
+            }
+        } else {
+            sct_list.take();
+        }
+
         self.exts.extend(extra_exts);
 
         Ok(())
@@ -219,16 +254,10 @@ pub(super) struct ExpectClientHello {
 
 impl ExpectClientHello {
     pub(super) fn new(config: Arc<ServerConfig>, extra_exts: Vec<ServerExtension>) -> Self {
-        let mut transcript_buffer = HandshakeHashBuffer::new();
-
-        if config.verifier.offer_client_auth() {
-            transcript_buffer.set_client_auth_enabled();
-        }
-
         Self {
             config,
             extra_exts,
-            transcript: HandshakeHashOrBuffer::Buffer(transcript_buffer),
+            transcript: HandshakeHashOrBuffer::Buffer(HandshakeHashBuffer::new()),
             #[cfg(feature = "tls12")]
             session_id: SessionId::empty(),
             #[cfg(feature = "tls12")]
@@ -312,26 +341,30 @@ impl ExpectClientHello {
         sig_schemes
             .retain(|scheme| suites::compatible_sigscheme_for_suites(*scheme, &client_suites));
 
-        // Choose a certificate.
-        let certkey = {
+        // Decide on client authentication, and choose a certificate.
+        let (client_auth, certkey) = {
             let client_hello = ClientHello::new(
                 &cx.data.sni,
                 &sig_schemes,
                 client_hello.get_alpn_extension(),
                 &client_hello.cipher_suites,
+                client_hello.get_authorities_extension(),
             );
 
+            let client_auth = ClientAuthRequest::new(&*self.config.verifier, &client_hello);
+
             let certkey = self
                 .config
                 .cert_resolver
                 .resolve(client_hello);
 
-            certkey.ok_or_else(|| {
+            let certkey = certkey.ok_or_else(|| {
                 cx.common.send_fatal_alert(
                     AlertDescription::AccessDenied,
                     Error::General("no server certificate chain resolved".to_owned()),
                 )
-            })?
+            })?;
+            (client_auth, certkey)
         };
         let certkey = ActiveCertifiedKey::from_certified_key(&certkey);
 
@@ -367,7 +400,12 @@ impl ExpectClientHello {
         // Start handshake hash.
         let starting_hash = suite.hash_provider();
         let transcript = match self.transcript {
-            HandshakeHashOrBuffer::Buffer(inner) => inner.start_hash(starting_hash),
+            HandshakeHashOrBuffer::Buffer(mut inner) => {
+                if client_auth.is_some() {
+                    inner.set_client_auth_enabled();
+                }
+                inner.start_hash(starting_hash)
+            }
             HandshakeHashOrBuffer::Hash(inner)
                 if inner.algorithm() == starting_hash.algorithm() =>
             {
@@ -393,6 +431,7 @@ impl ExpectClientHello {
                 done_retry: self.done_retry,
                 send_tickets: self.send_tickets,
                 extra_exts: self.extra_exts,
+                client_auth,
             }
             .handle_client_hello(cx, certkey, m, client_hello, sig_schemes),
             #[cfg(feature = "tls12")]
@@ -405,6 +444,7 @@ impl ExpectClientHello {
                 randoms,
                 send_ticket: self.send_tickets > 0,
                 extra_exts: self.extra_exts,
+                client_auth,
             }
             .handle_client_hello(
                 cx,
@@ -494,6 +534,10 @@ pub(super) fn process_client_hello<'a>(
         // Save the SNI into the session.
         // The SNI hostname is immutable once set.
         assert!(cx.data.sni.is_none());
+        #[cfg(feature = "tracing")]
+        cx.common
+            .span
+            .record("server_name", sni.as_ref());
         cx.data.sni = Some(sni.clone());
     } else if cx.data.sni != sni {
         return Err(PeerMisbehaved::ServerNameDifferedOnRetry.into());
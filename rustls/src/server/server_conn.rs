@@ -1,4 +1,6 @@
 use crate::builder::{ConfigBuilder, WantsCipherSuites};
+#[cfg(feature = "quic")]
+use crate::common_state::Protocol;
 use crate::common_state::{CommonState, Context, Side, State};
 use crate::conn::{ConnectionCommon, ConnectionCore};
 use crate::crypto::{CryptoProvider, SupportedKxGroup};
@@ -8,14 +10,21 @@ use crate::error::Error;
 #[cfg(feature = "logging")]
 use crate::log::trace;
 use crate::msgs::base::Payload;
-use crate::msgs::handshake::{ClientHelloPayload, ProtocolName, ServerExtension};
+use crate::msgs::handshake::{
+    ClientHelloPayload, DistinguishedName, ProtocolName, ServerExtension,
+};
 use crate::msgs::message::Message;
+use crate::observer::HandshakeObserver;
+#[cfg(feature = "quic")]
+use crate::quic;
 use crate::sign;
 use crate::suites::{ExtractedSecrets, SupportedCipherSuite};
 use crate::vecbuf::ChunkVecBuffer;
 use crate::verify;
 use crate::KeyLog;
 
+use pki_types::UnixTime;
+
 use super::hs;
 
 use alloc::boxed::Box;
@@ -50,6 +59,18 @@ pub trait StoresServerSessions: Send + Sync {
     /// if the value was stored.
     fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool;
 
+    /// Store session secrets encoded in `value` against `key`, as
+    /// for [`StoresServerSessions::put`].  The session cannot be
+    /// resumed after `expiry`, so it may be discarded then.
+    ///
+    /// This is what rustls calls to store sessions.  Stores that
+    /// support a time-to-live for their entries should implement it;
+    /// the default implementation ignores `expiry` and calls `put`.
+    fn put_with_expiry(&self, key: Vec<u8>, value: Vec<u8>, expiry: UnixTime) -> bool {
+        let _ = expiry;
+        self.put(key, value)
+    }
+
     /// Find a value with the given `key`.  Return it, or None
     /// if it doesn't exist.
     fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
@@ -120,6 +141,7 @@ pub struct ClientHello<'a> {
     signature_schemes: &'a [SignatureScheme],
     alpn: Option<&'a Vec<ProtocolName>>,
     cipher_suites: &'a [CipherSuite],
+    certificate_authorities: Option<&'a [DistinguishedName]>,
 }
 
 impl<'a> ClientHello<'a> {
@@ -129,17 +151,20 @@ impl<'a> ClientHello<'a> {
         signature_schemes: &'a [SignatureScheme],
         alpn: Option<&'a Vec<ProtocolName>>,
         cipher_suites: &'a [CipherSuite],
+        certificate_authorities: Option<&'a [DistinguishedName]>,
     ) -> Self {
         trace!("sni {:?}", server_name);
         trace!("sig schemes {:?}", signature_schemes);
         trace!("alpn protocols {:?}", alpn);
         trace!("cipher suites {:?}", cipher_suites);
+        trace!("certificate authorities {:?}", certificate_authorities);
 
         ClientHello {
             server_name,
             signature_schemes,
             alpn,
             cipher_suites,
+            certificate_authorities,
         }
     }
 
@@ -188,6 +213,18 @@ impl<'a> ClientHello<'a> {
     pub fn cipher_suites(&self) -> &[CipherSuite] {
         self.cipher_suites
     }
+
+    /// Get the trust anchor subjects the client advertised in its
+    /// `certificate_authorities` extension.
+    ///
+    /// Returns `None` if the client did not include this extension.
+    ///
+    /// Each entry is the DER-encoded X.500 distinguished name of a trust
+    /// anchor the client accepts.  A server holding several certificate
+    /// chains can use this to choose one the client is able to verify.
+    pub fn certificate_authorities(&self) -> Option<&'a [DistinguishedName]> {
+        self.certificate_authorities
+    }
 }
 
 /// Common configuration for a set of server sessions.
@@ -204,6 +241,7 @@ impl<'a> ClientHello<'a> {
 /// * [`ServerConfig::session_storage`]: the default stores 256 sessions in memory.
 /// * [`ServerConfig::alpn_protocols`]: the default is empty -- no ALPN protocol is negotiated.
 /// * [`ServerConfig::key_log`]: key material is not logged.
+/// * [`ServerConfig::handshake_observer`]: handshake events are not observed.
 /// * [`ServerConfig::send_tls13_tickets`]: 4 tickets are sent.
 ///
 /// [`RootCertStore`]: crate::RootCertStore
@@ -264,6 +302,10 @@ pub struct ServerConfig {
     /// does nothing.
     pub key_log: Arc<dyn KeyLog>,
 
+    /// What to tell about handshake events, for example to export
+    /// metrics.  The default does nothing.
+    pub handshake_observer: Arc<dyn HandshakeObserver>,
+
     /// Allows traffic secrets to be extracted after the handshake,
     /// e.g. for kTLS setup.
     pub enable_secret_extraction: bool,
@@ -331,6 +373,7 @@ impl Clone for ServerConfig {
             versions: self.versions,
             verifier: Arc::clone(&self.verifier),
             key_log: Arc::clone(&self.key_log),
+            handshake_observer: Arc::clone(&self.handshake_observer),
             enable_secret_extraction: self.enable_secret_extraction,
             max_early_data_size: self.max_early_data_size,
             send_half_rtt_data: self.send_half_rtt_data,
@@ -608,6 +651,31 @@ impl Default for Acceptor {
 }
 
 impl Acceptor {
+    /// Make an acceptor for a QUIC connection using `version`.
+    #[cfg(feature = "quic")]
+    pub(crate) fn for_quic(version: quic::Version) -> Self {
+        let mut acceptor = Self::default();
+        if let Some(conn) = &mut acceptor.inner {
+            conn.core.common_state.protocol = Protocol::Quic;
+            conn.core.common_state.quic.version = version;
+        }
+        acceptor
+    }
+
+    /// Take handshake data from the CRYPTO frames of the client's Initial packets.
+    #[cfg(feature = "quic")]
+    pub(crate) fn read_quic_hs(&mut self, plaintext: &[u8]) -> Result<(), Error> {
+        match &mut self.inner {
+            Some(conn) => conn
+                .core
+                .message_deframer
+                .push(ProtocolVersion::TLSv1_3, plaintext),
+            None => Err(Error::General(
+                "acceptor cannot read after successful acceptance".into(),
+            )),
+        }
+    }
+
     /// Read TLS content from `rd`.
     ///
     /// Returns an error if this `Acceptor` has already yielded an [`Accepted`]. For more details,
@@ -678,6 +746,7 @@ impl Accepted {
             &self.sig_schemes,
             payload.get_alpn_extension(),
             &payload.cipher_suites,
+            payload.get_authorities_extension(),
         )
     }
 
@@ -686,26 +755,57 @@ impl Accepted {
     /// Takes the state returned from [`Acceptor::accept()`] as well as the [`ServerConfig`] and
     /// [`sign::CertifiedKey`] that should be used for the session. Returns an error if
     /// configuration-dependent validation of the received `ClientHello` message fails.
-    pub fn into_connection(mut self, config: Arc<ServerConfig>) -> Result<ServerConnection, Error> {
+    pub fn into_connection(self, config: Arc<ServerConfig>) -> Result<ServerConnection, Error> {
+        Ok(ServerConnection {
+            inner: self
+                .into_core(config, Vec::new())?
+                .into(),
+        })
+    }
+
+    /// Continue the handshake with `config`, sending `extra_exts` in the
+    /// server's extensions.
+    pub(crate) fn into_core(
+        mut self,
+        config: Arc<ServerConfig>,
+        extra_exts: Vec<ServerExtension>,
+    ) -> Result<ConnectionCore<ServerConnectionData>, Error> {
         self.connection
             .set_max_fragment_size(config.max_fragment_size)?;
 
         self.connection.enable_secret_extraction = config.enable_secret_extraction;
+        self.connection
+            .set_handshake_observer(&config.handshake_observer);
+        #[cfg(feature = "tracing")]
+        let _span = {
+            self.connection.start_span();
+            self.connection.span.clone().entered()
+        };
 
-        let state = hs::ExpectClientHello::new(config, Vec::new());
+        let state = hs::ExpectClientHello::new(config, extra_exts);
         let mut cx = hs::ServerContext::from(&mut self.connection);
 
-        let new = state.with_certified_key(
-            self.sig_schemes,
-            Self::client_hello_payload(&self.message),
-            &self.message,
-            &mut cx,
-        )?;
+        let new = state
+            .with_certified_key(
+                self.sig_schemes,
+                Self::client_hello_payload(&self.message),
+                &self.message,
+                &mut cx,
+            )
+            .map_err(|err| {
+                cx.common
+                    .observe_handshake_failure(&err);
+                err
+            })?;
 
         self.connection.replace_state(new);
-        Ok(ServerConnection {
-            inner: self.connection,
-        })
+        Ok(self.connection.core)
+    }
+
+    /// The QUIC transport parameters sent by the client, if any.
+    #[cfg(feature = "quic")]
+    pub(crate) fn quic_transport_parameters(&self) -> Option<Vec<u8>> {
+        Self::client_hello_payload(&self.message).get_quic_params_extension()
     }
 
     fn client_hello_payload(message: &Message) -> &ClientHelloPayload {
@@ -796,6 +896,9 @@ impl ConnectionCore<ServerConnectionData> {
         let mut common = CommonState::new(Side::Server);
         common.set_max_fragment_size(config.max_fragment_size)?;
         common.enable_secret_extraction = config.enable_secret_extraction;
+        common.set_handshake_observer(&config.handshake_observer);
+        #[cfg(feature = "tracing")]
+        common.start_span();
         Ok(Self::new(
             Box::new(hs::ExpectClientHello::new(config, extra_exts)),
             ServerConnectionData::default(),
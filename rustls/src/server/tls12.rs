@@ -19,7 +19,7 @@ use crate::suites::PartiallyExtractedSecrets;
 use crate::tls12::{self, ConnectionSecrets, Tls12CipherSuite};
 use crate::verify;
 
-use super::common::ActiveCertifiedKey;
+use super::common::{ActiveCertifiedKey, ClientAuthRequest};
 use super::hs::{self, ServerContext};
 use super::server_conn::{ProducesTickets, ServerConfig, ServerConnectionData};
 
@@ -46,6 +46,7 @@ mod client_hello {
     use crate::msgs::handshake::{ClientExtension, SessionId};
     use crate::msgs::handshake::{ClientHelloPayload, ServerHelloPayload};
     use crate::msgs::handshake::{ServerExtension, ServerKeyExchangePayload};
+    use crate::observer::HandshakeKind;
     use crate::sign;
     use crate::verify::DigitallySignedStruct;
 
@@ -60,6 +61,7 @@ mod client_hello {
         pub(in crate::server) randoms: ConnectionRandoms,
         pub(in crate::server) send_ticket: bool,
         pub(in crate::server) extra_exts: Vec<ServerExtension>,
+        pub(in crate::server) client_auth: Option<ClientAuthRequest>,
     }
 
     impl CompleteClientHelloHandling {
@@ -200,6 +202,7 @@ mod client_hello {
             debug_assert_eq!(ecpoint, ECPointFormat::Uncompressed);
 
             let mut ocsp_response = server_key.get_ocsp();
+            let mut sct_list = server_key.get_sct_list();
 
             // If we're not offered a ticket or a potential session ID, allocate a session ID.
             if !self.config.session_storage.can_cache() {
@@ -216,6 +219,7 @@ mod client_hello {
                 self.suite,
                 self.using_ems,
                 &mut ocsp_response,
+                &mut sct_list,
                 client_hello,
                 None,
                 &self.randoms,
@@ -233,10 +237,13 @@ mod client_hello {
                 server_key.get_key(),
                 &self.randoms,
             )?;
-            let doing_client_auth = emit_certificate_req(&self.config, &mut self.transcript, cx)?;
+            let client_auth_mandatory = self
+                .client_auth
+                .take()
+                .map(|req| emit_certificate_req(&self.config, req, &mut self.transcript, cx));
             emit_server_hello_done(&mut self.transcript, cx.common);
 
-            if doing_client_auth {
+            if let Some(client_auth_mandatory) = client_auth_mandatory {
                 Ok(Box::new(ExpectCertificate {
                     config: self.config,
                     transcript: self.transcript,
@@ -245,6 +252,7 @@ mod client_hello {
                     suite: self.suite,
                     using_ems: self.using_ems,
                     server_kx,
+                    client_auth_mandatory,
                     send_ticket: self.send_ticket,
                 }))
             } else {
@@ -278,6 +286,7 @@ mod client_hello {
                 ));
             }
 
+            cx.common.handshake_kind = HandshakeKind::Resumed;
             self.session_id = *id;
             self.send_ticket = emit_server_hello(
                 &self.config,
@@ -287,6 +296,7 @@ mod client_hello {
                 self.suite,
                 self.using_ems,
                 &mut None,
+                &mut None,
                 client_hello,
                 Some(&resumedata),
                 &self.randoms,
@@ -342,13 +352,22 @@ mod client_hello {
         suite: &'static Tls12CipherSuite,
         using_ems: bool,
         ocsp_response: &mut Option<&[u8]>,
+        sct_list: &mut Option<&[u8]>,
         hello: &ClientHelloPayload,
         resumedata: Option<&persist::ServerSessionValue>,
         randoms: &ConnectionRandoms,
         extra_exts: Vec<ServerExtension>,
     ) -> Result<bool, Error> {
         let mut ep = hs::ExtensionProcessing::new();
-        ep.process_common(config, cx, ocsp_response, hello, resumedata, extra_exts)?;
+        ep.process_common(
+            config,
+            cx,
+            ocsp_response,
+            sct_list,
+            hello,
+            resumedata,
+            extra_exts,
+        )?;
         ep.process_tls12(config, hello, using_ems);
 
         let sh = Message {
@@ -416,6 +435,7 @@ mod client_hello {
             .start()
             .map_err(|_| Error::FailedToGetRandomBytes)?;
         let secdh = ServerEcdhParams::new(&*kx);
+        common.kx_group = Some(selected_group.name());
 
         let mut msg = Vec::new();
         msg.extend(randoms.client);
@@ -446,23 +466,16 @@ mod client_hello {
         Ok(kx)
     }
 
+    /// Returns whether the client must send a certificate.
     fn emit_certificate_req(
         config: &ServerConfig,
+        client_auth: ClientAuthRequest,
         transcript: &mut HandshakeHash,
         cx: &mut ServerContext<'_>,
-    ) -> Result<bool, Error> {
-        let client_auth = &config.verifier;
-
-        if !client_auth.offer_client_auth() {
-            return Ok(false);
-        }
-
-        let verify_schemes = client_auth.supported_verify_schemes();
-
-        let names = config
+    ) -> bool {
+        let verify_schemes = config
             .verifier
-            .root_hint_subjects()
-            .to_vec();
+            .supported_verify_schemes();
 
         let cr = CertificateRequestPayload {
             certtypes: vec![
@@ -470,7 +483,7 @@ mod client_hello {
                 ClientCertificateType::ECDSASign,
             ],
             sigschemes: verify_schemes,
-            canames: names,
+            canames: client_auth.root_hint_subjects,
         };
 
         let m = Message {
@@ -484,7 +497,7 @@ mod client_hello {
         trace!("Sending CertificateRequest {:?}", m);
         transcript.add_message(&m);
         cx.common.send_msg(m, false);
-        Ok(true)
+        client_auth.mandatory
     }
 
     fn emit_server_hello_done(transcript: &mut HandshakeHash, common: &mut CommonState) {
@@ -510,6 +523,7 @@ struct ExpectCertificate {
     suite: &'static Tls12CipherSuite,
     using_ems: bool,
     server_kx: Box<dyn ActiveKeyExchange>,
+    client_auth_mandatory: bool,
     send_ticket: bool,
 }
 
@@ -522,16 +536,10 @@ impl State<ServerConnectionData> for ExpectCertificate {
             HandshakePayload::Certificate
         )?;
 
-        // If we can't determine if the auth is mandatory, abort
-        let mandatory = self
-            .config
-            .verifier
-            .client_auth_mandatory();
-
         trace!("certs {:?}", cert_chain);
 
         let client_cert = match cert_chain.split_first() {
-            None if mandatory => {
+            None if self.client_auth_mandatory => {
                 return Err(cx.common.send_fatal_alert(
                     AlertDescription::CertificateRequired,
                     Error::NoCertificatesPresented,
@@ -543,7 +551,8 @@ impl State<ServerConnectionData> for ExpectCertificate {
                 None
             }
             Some((end_entity, intermediates)) => {
-                self.config
+                let cert_verified = self
+                    .config
                     .verifier
                     .verify_client_cert(end_entity, intermediates, UnixTime::now())
                     .map_err(|err| {
@@ -551,7 +560,7 @@ impl State<ServerConnectionData> for ExpectCertificate {
                             .send_cert_verify_error_alert(err)
                     })?;
 
-                Some(cert_chain)
+                Some((cert_chain, cert_verified))
             }
         };
 
@@ -578,7 +587,7 @@ struct ExpectClientKx {
     suite: &'static Tls12CipherSuite,
     using_ems: bool,
     server_kx: Box<dyn ActiveKeyExchange>,
-    client_cert: Option<Vec<CertificateDer<'static>>>,
+    client_cert: Option<(Vec<CertificateDer<'static>>, verify::ClientCertVerified)>,
     send_ticket: bool,
 }
 
@@ -614,7 +623,7 @@ impl State<ServerConnectionData> for ExpectClientKx {
         cx.common
             .start_encryption_tls12(&secrets, Side::Server);
 
-        if let Some(client_cert) = self.client_cert {
+        if let Some((client_cert, cert_verified)) = self.client_cert {
             Ok(Box::new(ExpectCertificateVerify {
                 config: self.config,
                 secrets,
@@ -622,6 +631,7 @@ impl State<ServerConnectionData> for ExpectClientKx {
                 session_id: self.session_id,
                 using_ems: self.using_ems,
                 client_cert,
+                cert_verified,
                 send_ticket: self.send_ticket,
             }))
         } else {
@@ -646,6 +656,7 @@ struct ExpectCertificateVerify {
     session_id: SessionId,
     using_ems: bool,
     client_cert: Vec<CertificateDer<'static>>,
+    cert_verified: verify::ClientCertVerified,
     send_ticket: bool,
 }
 
@@ -687,6 +698,7 @@ impl State<ServerConnectionData> for ExpectCertificateVerify {
 
         trace!("client CertificateVerify OK");
         cx.common.peer_certificates = Some(self.client_cert);
+        cx.common.verified_chain = self.cert_verified.into_verified_chain();
 
         self.transcript.add_message(&m);
         Ok(Box::new(ExpectCcs {
@@ -866,17 +878,17 @@ impl State<ServerConnectionData> for ExpectFinished {
 
         // Save connection, perhaps
         if !self.resuming && !self.session_id.is_empty() {
-            let value = get_server_connection_value_tls12(
-                &self.secrets,
-                self.using_ems,
-                cx,
-                UnixTime::now(),
-            );
+            let now = UnixTime::now();
+            let value = get_server_connection_value_tls12(&self.secrets, self.using_ems, cx, now);
 
             let worked = self
                 .config
                 .session_storage
-                .put(self.session_id.get_encoding(), value.get_encoding());
+                .put_with_expiry(
+                    self.session_id.get_encoding(),
+                    value.get_encoding(),
+                    hs::stateful_session_expiry(now),
+                );
             if worked {
                 debug!("Session saved");
             } else {
@@ -904,6 +916,11 @@ impl State<ServerConnectionData> for ExpectFinished {
         }
 
         cx.common.start_traffic();
+        if cx.common.is_acme_challenge() {
+            // The handshake alone answers the challenge (RFC 8737).
+            cx.common.send_close_notify();
+        }
+
         Ok(Box::new(ExpectTraffic {
             secrets: self.secrets,
             _fin_verified,
@@ -922,6 +939,12 @@ impl ExpectTraffic {}
 impl State<ServerConnectionData> for ExpectTraffic {
     fn handle(self: Box<Self>, cx: &mut ServerContext<'_>, m: Message) -> hs::NextStateOrError {
         match m.payload {
+            MessagePayload::ApplicationData(_) if cx.common.is_acme_challenge() => {
+                return Err(cx.common.send_fatal_alert(
+                    AlertDescription::UnexpectedMessage,
+                    PeerMisbehaved::ApplicationDataOnAcmeChallenge,
+                ));
+            }
             MessagePayload::ApplicationData(payload) => cx
                 .common
                 .take_received_plaintext(payload),
@@ -55,14 +55,17 @@ mod client_hello {
     use crate::msgs::handshake::CertificateRequestPayloadTls13;
     use crate::msgs::handshake::CertificateStatus;
     use crate::msgs::handshake::ClientHelloPayload;
+    use crate::msgs::handshake::DelegatedCredential;
     use crate::msgs::handshake::HelloRetryExtension;
     use crate::msgs::handshake::HelloRetryRequest;
     use crate::msgs::handshake::KeyShareEntry;
     use crate::msgs::handshake::Random;
+    use crate::msgs::handshake::Sct;
     use crate::msgs::handshake::ServerExtension;
     use crate::msgs::handshake::ServerHelloPayload;
     use crate::msgs::handshake::SessionId;
-    use crate::server::common::ActiveCertifiedKey;
+    use crate::observer::HandshakeKind;
+    use crate::server::common::{ActiveCertifiedKey, ClientAuthRequest};
     use crate::sign;
     use crate::tls13::key_schedule::{
         KeyScheduleEarly, KeyScheduleHandshake, KeySchedulePreHandshake,
@@ -86,6 +89,7 @@ mod client_hello {
         pub(in crate::server) done_retry: bool,
         pub(in crate::server) send_tickets: usize,
         pub(in crate::server) extra_exts: Vec<ServerExtension>,
+        pub(in crate::server) client_auth: Option<ClientAuthRequest>,
     }
 
     fn max_early_data_size(configured: u32) -> usize {
@@ -258,6 +262,7 @@ mod client_hello {
                         });
 
                         return if early_data_requested {
+                            cx.common.observe_early_data(false);
                             Ok(Box::new(ExpectAndSkipRejectedEarlyData {
                                 skip_data_left: skip_early_data,
                                 next,
@@ -353,6 +358,7 @@ mod client_hello {
             if let Some(ref resume) = resumedata {
                 cx.data.received_resumption_data = Some(resume.application_data.0.clone());
                 cx.common.peer_certificates = resume.client_cert_chain.clone();
+                cx.common.handshake_kind = HandshakeKind::Resumed;
             }
 
             let full_handshake = resumedata.is_none();
@@ -375,35 +381,62 @@ mod client_hello {
             }
 
             let mut ocsp_response = server_key.get_ocsp();
+            let mut sct_list = server_key.get_sct_list();
             let doing_early_data = emit_encrypted_extensions(
                 &mut self.transcript,
                 self.suite,
                 cx,
                 &mut ocsp_response,
+                &mut sct_list,
                 client_hello,
                 resumedata.as_ref(),
                 self.extra_exts,
                 &self.config,
             )?;
 
-            let doing_client_auth = if full_handshake {
-                let client_auth =
-                    emit_certificate_req_tls13(&mut self.transcript, cx, &self.config)?;
+            let client_auth_mandatory = if full_handshake {
+                let client_auth_mandatory = self.client_auth.take().map(|req| {
+                    emit_certificate_req_tls13(&mut self.transcript, cx, &self.config, req)
+                });
+
+                // Use a delegated credential if the client supports both the
+                // algorithm it was signed with, and the one it signs with.
+                let delegated = server_key
+                    .get_delegated_credential()
+                    .filter(|dk| {
+                        client_hello
+                            .get_delegated_credential_extension()
+                            .map_or(false, |dc_schemes| {
+                                dc_schemes.contains(&dk.credential.dc_cert_verify_algorithm())
+                                    && sigschemes_ext.contains(&dk.credential.algorithm())
+                            })
+                    });
+
                 emit_certificate_tls13(
                     &mut self.transcript,
                     cx.common,
                     server_key.get_cert(),
                     ocsp_response,
+                    sct_list,
+                    delegated.map(|dk| &dk.credential),
                 );
-                emit_certificate_verify_tls13(
-                    &mut self.transcript,
-                    cx.common,
-                    server_key.get_key(),
-                    &sigschemes_ext,
-                )?;
-                client_auth
+                match delegated {
+                    Some(dk) => emit_certificate_verify_tls13(
+                        &mut self.transcript,
+                        cx.common,
+                        &*dk.key,
+                        &[dk.credential.dc_cert_verify_algorithm()],
+                    )?,
+                    None => emit_certificate_verify_tls13(
+                        &mut self.transcript,
+                        cx.common,
+                        server_key.get_key(),
+                        &sigschemes_ext,
+                    )?,
+                }
+                client_auth_mandatory
             } else {
-                false
+                None
             };
 
             // If we're not doing early data, then the next messages we receive
@@ -420,11 +453,13 @@ mod client_hello {
                         cx.common,
                     );
                     cx.data.early_data.reject();
+                    cx.common.observe_early_data(false);
                 }
                 EarlyDataDecision::Accepted => {
                     cx.data
                         .early_data
                         .accept(self.config.max_early_data_size as usize);
+                    cx.common.observe_early_data(true);
                 }
             }
 
@@ -437,19 +472,20 @@ mod client_hello {
                 &self.config,
             );
 
-            if !doing_client_auth && self.config.send_half_rtt_data {
+            if client_auth_mandatory.is_none() && self.config.send_half_rtt_data {
                 // Application data can be sent immediately after Finished, in one
                 // flight.  However, if client auth is enabled, we don't want to send
                 // application data to an unauthenticated peer.
                 cx.common.start_outgoing_traffic();
             }
 
-            if doing_client_auth {
+            if let Some(client_auth_mandatory) = client_auth_mandatory {
                 Ok(Box::new(ExpectCertificate {
                     config: self.config,
                     transcript: self.transcript,
                     suite: self.suite,
                     key_schedule: key_schedule_traffic,
+                    client_auth_mandatory,
                     send_tickets: self.send_tickets,
                 }))
             } else if doing_early_data == EarlyDataDecision::Accepted && !cx.common.is_quic() {
@@ -496,6 +532,7 @@ mod client_hello {
             .map_err(|_| Error::FailedToGetRandomBytes)?;
 
         let kse = KeyShareEntry::new(share.group, kx.pub_key());
+        cx.common.kx_group = Some(share.group);
         extensions.push(ServerExtension::KeyShare(kse));
         extensions.push(ServerExtension::SupportedVersions(ProtocolVersion::TLSv1_3));
 
@@ -599,6 +636,7 @@ mod client_hello {
         transcript.rollup_for_hrr();
         transcript.add_message(&m);
         common.send_msg(m, false);
+        common.observe_hello_retry_request();
     }
 
     #[allow(unknown_lints)] // The lint allowed below is nightly only for now
@@ -668,13 +706,22 @@ mod client_hello {
         suite: &'static Tls13CipherSuite,
         cx: &mut ServerContext<'_>,
         ocsp_response: &mut Option<&[u8]>,
+        sct_list: &mut Option<&[u8]>,
         hello: &ClientHelloPayload,
         resumedata: Option<&persist::ServerSessionValue>,
         extra_exts: Vec<ServerExtension>,
         config: &ServerConfig,
     ) -> Result<EarlyDataDecision, Error> {
         let mut ep = hs::ExtensionProcessing::new();
-        ep.process_common(config, cx, ocsp_response, hello, resumedata, extra_exts)?;
+        ep.process_common(
+            config,
+            cx,
+            ocsp_response,
+            sct_list,
+            hello,
+            resumedata,
+            extra_exts,
+        )?;
 
         let early_data = decide_if_early_data_allowed(cx, hello, resumedata, suite, config);
         if early_data == EarlyDataDecision::Accepted {
@@ -695,15 +742,13 @@ mod client_hello {
         Ok(early_data)
     }
 
+    /// Returns whether the client must send a certificate.
     fn emit_certificate_req_tls13(
         transcript: &mut HandshakeHash,
         cx: &mut ServerContext<'_>,
         config: &ServerConfig,
-    ) -> Result<bool, Error> {
-        if !config.verifier.offer_client_auth() {
-            return Ok(false);
-        }
-
+        client_auth: ClientAuthRequest,
+    ) -> bool {
         let mut cr = CertificateRequestPayloadTls13 {
             context: PayloadU8::empty(),
             extensions: Vec::new(),
@@ -717,10 +762,7 @@ mod client_hello {
 
         cr.extensions
             .push(CertReqExtension::AuthorityNames(
-                config
-                    .verifier
-                    .root_hint_subjects()
-                    .to_vec(),
+                client_auth.root_hint_subjects,
             ));
 
         let m = Message {
@@ -734,7 +776,7 @@ mod client_hello {
         trace!("Sending CertificateRequest {:?}", m);
         transcript.add_message(&m);
         cx.common.send_msg(m, true);
-        Ok(true)
+        client_auth.mandatory
     }
 
     fn emit_certificate_tls13(
@@ -742,6 +784,8 @@ mod client_hello {
         common: &mut CommonState,
         cert_chain: &[CertificateDer<'static>],
         ocsp_response: Option<&[u8]>,
+        sct_list: Option<&[u8]>,
+        delegated_credential: Option<&DelegatedCredential>,
     ) {
         let mut cert_entries = vec![];
         for cert in cert_chain {
@@ -762,6 +806,19 @@ mod client_hello {
                     .exts
                     .push(CertificateExtension::CertificateStatus(cst));
             }
+
+            // Likewise, SCT
+            if let Some(scts) = sct_list.and_then(|scts| Vec::<Sct>::read_bytes(scts).ok()) {
+                end_entity_cert
+                    .exts
+                    .push(CertificateExtension::SignedCertificateTimestamp(scts));
+            }
+
+            if let Some(dc) = delegated_credential {
+                end_entity_cert
+                    .exts
+                    .push(CertificateExtension::DelegatedCredential(dc.clone()));
+            }
         }
 
         let cert_body = CertificatePayloadTls13::new(cert_entries);
@@ -876,6 +933,7 @@ struct ExpectCertificate {
     transcript: HandshakeHash,
     suite: &'static Tls13CipherSuite,
     key_schedule: KeyScheduleTrafficWithClientFinishedPending,
+    client_auth_mandatory: bool,
     send_tickets: usize,
 }
 
@@ -896,14 +954,9 @@ impl State<ServerConnectionData> for ExpectCertificate {
 
         let client_cert = certp.convert();
 
-        let mandatory = self
-            .config
-            .verifier
-            .client_auth_mandatory();
-
         let (end_entity, intermediates) = match client_cert.split_first() {
             None => {
-                if !mandatory {
+                if !self.client_auth_mandatory {
                     debug!("client auth requested but no certificate supplied");
                     self.transcript.abandon_client_auth();
                     return Ok(Box::new(ExpectFinished {
@@ -923,7 +976,8 @@ impl State<ServerConnectionData> for ExpectCertificate {
             Some(chain) => chain,
         };
 
-        self.config
+        let cert_verified = self
+            .config
             .verifier
             .verify_client_cert(end_entity, intermediates, UnixTime::now())
             .map_err(|err| {
@@ -937,6 +991,7 @@ impl State<ServerConnectionData> for ExpectCertificate {
             transcript: self.transcript,
             key_schedule: self.key_schedule,
             client_cert,
+            cert_verified,
             send_tickets: self.send_tickets,
         }))
     }
@@ -948,6 +1003,7 @@ struct ExpectCertificateVerify {
     suite: &'static Tls13CipherSuite,
     key_schedule: KeyScheduleTrafficWithClientFinishedPending,
     client_cert: Vec<CertificateDer<'static>>,
+    cert_verified: verify::ClientCertVerified,
     send_tickets: usize,
 }
 
@@ -977,6 +1033,7 @@ impl State<ServerConnectionData> for ExpectCertificateVerify {
 
         trace!("client CertificateVerify OK");
         cx.common.peer_certificates = Some(self.client_cert);
+        cx.common.verified_chain = self.cert_verified.into_verified_chain();
 
         self.transcript.add_message(&m);
         Ok(Box::new(ExpectFinished {
@@ -1091,16 +1148,10 @@ impl ExpectFinished {
     ) -> Result<(), Error> {
         let nonce = rand::random_vec(config.provider, 32)?;
         let age_add = rand::random_u32(config.provider)?;
-        let plain = get_server_session_value(
-            transcript,
-            suite,
-            key_schedule,
-            cx,
-            &nonce,
-            UnixTime::now(),
-            age_add,
-        )
-        .get_encoding();
+        let now = UnixTime::now();
+        let plain =
+            get_server_session_value(transcript, suite, key_schedule, cx, &nonce, now, age_add)
+                .get_encoding();
 
         let stateless = config.ticketer.enabled();
         let (ticket, lifetime) = if stateless {
@@ -1111,15 +1162,16 @@ impl ExpectFinished {
             (ticket, config.ticketer.lifetime())
         } else {
             let id = rand::random_vec(config.provider, 32)?;
-            let stored = config
-                .session_storage
-                .put(id.clone(), plain);
+            let stored = config.session_storage.put_with_expiry(
+                id.clone(),
+                plain,
+                hs::stateful_session_expiry(now),
+            );
             if !stored {
                 trace!("resumption not available; not issuing ticket");
                 return Ok(());
             }
-            let stateful_lifetime = 24 * 60 * 60; // this is a bit of a punt
-            (id, stateful_lifetime)
+            (id, hs::STATEFUL_SESSION_LIFETIME)
         };
 
         let mut payload = NewSessionTicketPayloadTls13::new(lifetime, age_add, nonce, ticket);
@@ -1200,6 +1252,11 @@ impl State<ServerConnectionData> for ExpectFinished {
             }
         }
 
+        if cx.common.is_acme_challenge() {
+            // The handshake alone answers the challenge (RFC 8737).
+            cx.common.send_close_notify();
+        }
+
         Ok(Box::new(ExpectTraffic {
             key_schedule: key_schedule_traffic,
             _fin_verified: fin,
@@ -1246,6 +1303,12 @@ impl ExpectTraffic {
 impl State<ServerConnectionData> for ExpectTraffic {
     fn handle(mut self: Box<Self>, cx: &mut ServerContext, m: Message) -> hs::NextStateOrError {
         match m.payload {
+            MessagePayload::ApplicationData(_) if cx.common.is_acme_challenge() => {
+                return Err(cx.common.send_fatal_alert(
+                    AlertDescription::UnexpectedMessage,
+                    PeerMisbehaved::ApplicationDataOnAcmeChallenge,
+                ));
+            }
             MessagePayload::ApplicationData(payload) => cx
                 .common
                 .take_received_plaintext(payload),
@@ -3,9 +3,11 @@ use crate::server::ProducesTickets;
 use crate::Error;
 
 use pki_types::UnixTime;
+use zeroize::Zeroize;
 
 use alloc::boxed::Box;
 use alloc::vec::Vec;
+use core::iter;
 use core::mem;
 use std::sync::{Mutex, MutexGuard};
 
@@ -162,3 +164,165 @@ impl ProducesTickets for TicketSwitcher {
             })
     }
 }
+
+/// The length of a [`TicketKey`] identifier.
+pub const TICKET_KEY_ID_LEN: usize = 16;
+
+/// Ticket key material supplied by the application, for use
+/// by a [`TicketKeyRing`].
+pub struct TicketKey {
+    /// Identifies this key.  It prefixes every ticket encrypted
+    /// with this key, so is not secret, but must be unique among the
+    /// keys in use.
+    pub id: [u8; TICKET_KEY_ID_LEN],
+
+    /// The secret key material.  What is required depends on the
+    /// ticket encryption in use.  It is zeroed when this is dropped.
+    pub secret: Vec<u8>,
+}
+
+impl Drop for TicketKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+/// The keys used by a [`TicketKeyRing`] at one time.
+pub struct TicketKeys {
+    /// Encrypts new tickets, and decrypts them.
+    pub current: TicketKey,
+
+    /// The key that was `current` before; it only decrypts tickets.
+    pub previous: Option<TicketKey>,
+
+    /// The key that will be `current` next; it only decrypts tickets.
+    ///
+    /// Distributing keys before they are used means servers that
+    /// have not yet rotated accept tickets from those that have.
+    pub next: Option<TicketKey>,
+}
+
+type MakeTicketer = fn(&[u8]) -> Result<Box<dyn ProducesTickets>, Error>;
+
+struct KeyedTicketer {
+    id: [u8; TICKET_KEY_ID_LEN],
+    ticketer: Box<dyn ProducesTickets>,
+}
+
+struct TicketKeyRingState {
+    current: KeyedTicketer,
+    previous: Option<KeyedTicketer>,
+    next: Option<KeyedTicketer>,
+}
+
+impl TicketKeyRingState {
+    fn find(&self, id: &[u8]) -> Option<&KeyedTicketer> {
+        iter::once(&self.current)
+            .chain(self.previous.as_ref())
+            .chain(self.next.as_ref())
+            .find(|keyed| keyed.id == id)
+    }
+}
+
+/// A ticketer that uses keys supplied by the application.
+///
+/// Unlike [`TicketSwitcher`], which generates its own keys, this allows
+/// a set of servers to share keys distributed from a central secret
+/// store, so that sessions established with one server can be resumed
+/// with any of them.  The application rotates keys by calling
+/// [`TicketKeyRing::set_keys()`].
+///
+/// Every ticket is prefixed with the identifier of the key that
+/// encrypted it, and is accepted for as long as that key remains one of
+/// the [`TicketKeys`] in use.
+pub struct TicketKeyRing {
+    make_ticketer: MakeTicketer,
+    lifetime: u32,
+    state: Mutex<TicketKeyRingState>,
+}
+
+impl TicketKeyRing {
+    /// Creates a new `TicketKeyRing` using `keys`.
+    ///
+    /// `lifetime` is in seconds, and is given to clients as the lifetime
+    /// of tickets.  It should be no longer than a key stays in use after
+    /// it stops being current.  `make_ticketer` produces a `ProducesTickets`
+    /// implementation that encrypts using a key's secret.
+    pub fn new(
+        lifetime: u32,
+        make_ticketer: MakeTicketer,
+        keys: TicketKeys,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            make_ticketer,
+            lifetime,
+            state: Mutex::new(Self::load(make_ticketer, keys)?),
+        })
+    }
+
+    /// Replace the keys in use with `keys`.
+    ///
+    /// Tickets encrypted with a key that is not in `keys` are no longer
+    /// accepted.  On error, the keys in use are unchanged.
+    pub fn set_keys(&self, keys: TicketKeys) -> Result<(), Error> {
+        let state = Self::load(self.make_ticketer, keys)?;
+        *self.state.lock().unwrap() = state;
+        Ok(())
+    }
+
+    fn load(make_ticketer: MakeTicketer, keys: TicketKeys) -> Result<TicketKeyRingState, Error> {
+        let ids = [
+            Some(keys.current.id),
+            keys.previous.as_ref().map(|key| key.id),
+            keys.next.as_ref().map(|key| key.id),
+        ];
+        for (i, id) in ids.iter().enumerate() {
+            if id.is_some() && ids[i + 1..].contains(id) {
+                return Err(Error::General("duplicate ticket key id".into()));
+            }
+        }
+
+        let keyed = |key: TicketKey| -> Result<KeyedTicketer, Error> {
+            Ok(KeyedTicketer {
+                id: key.id,
+                ticketer: make_ticketer(&key.secret)?,
+            })
+        };
+
+        Ok(TicketKeyRingState {
+            current: keyed(keys.current)?,
+            previous: keys.previous.map(keyed).transpose()?,
+            next: keys.next.map(keyed).transpose()?,
+        })
+    }
+}
+
+impl ProducesTickets for TicketKeyRing {
+    fn lifetime(&self) -> u32 {
+        self.lifetime
+    }
+
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn encrypt(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let state = self.state.lock().ok()?;
+        let current = &state.current;
+
+        let mut ticket = current.id.to_vec();
+        ticket.extend(current.ticketer.encrypt(message)?);
+        Some(ticket)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+        let id = ciphertext.get(..TICKET_KEY_ID_LEN)?;
+        let ciphertext = ciphertext.get(TICKET_KEY_ID_LEN..)?;
+
+        let state = self.state.lock().ok()?;
+        state
+            .find(id)?
+            .ticketer
+            .decrypt(ciphertext)
+    }
+}
@@ -1,14 +1,15 @@
 use alloc::vec::Vec;
 use core::fmt;
 
-use pki_types::{CertificateDer, UnixTime};
+use pki_types::{CertificateDer, TrustAnchor, UnixTime};
 
 use crate::client::ServerName;
 use crate::enums::SignatureScheme;
-use crate::error::{Error, InvalidMessage};
+use crate::error::{CertificateError, Error, InvalidMessage};
 use crate::msgs::base::PayloadU16;
 use crate::msgs::codec::{Codec, Reader};
-use crate::msgs::handshake::DistinguishedName;
+use crate::msgs::handshake::{DelegatedCredential, DistinguishedName};
+use crate::server::ClientHello;
 
 // Marker types.  These are used to bind the fact some verification
 // (certificate chain or handshake signature) has taken place into
@@ -40,27 +41,127 @@ impl FinishedMessageVerified {
     }
 }
 
-/// Zero-sized marker type representing verification of a server cert chain.
+/// Marker type representing verification of a server cert chain.
+///
+/// It optionally records the chain that was verified; see
+/// [`ServerCertVerified::with_verified_chain()`].
 #[allow(unreachable_pub)]
 #[derive(Debug)]
-pub struct ServerCertVerified(());
+pub struct ServerCertVerified(Option<VerifiedChain>);
 
 #[allow(unreachable_pub)]
 impl ServerCertVerified {
     /// Make a `ServerCertVerified`
     pub fn assertion() -> Self {
-        Self(())
+        Self(None)
+    }
+
+    /// Make a `ServerCertVerified`, recording the chain that was verified.
+    pub fn with_verified_chain(chain: VerifiedChain) -> Self {
+        Self(Some(chain))
+    }
+
+    /// The chain that was verified, if the verifier recorded one.
+    pub fn verified_chain(&self) -> Option<&VerifiedChain> {
+        self.0.as_ref()
+    }
+}
+
+/// A certificate chain built by a certificate verifier, from the
+/// end-entity certificate to a trust anchor.
+///
+/// Unlike the chain sent by the peer, this contains only the intermediate
+/// certificates that were actually used, in order, and identifies the trust
+/// anchor the chain ends at.
+#[allow(unreachable_pub)]
+#[derive(Clone, Debug)]
+pub struct VerifiedChain {
+    end_entity: CertificateDer<'static>,
+    intermediates: Vec<CertificateDer<'static>>,
+    trust_anchor: TrustAnchor<'static>,
+}
+
+#[allow(unreachable_pub)]
+impl VerifiedChain {
+    /// Make a `VerifiedChain`.
+    ///
+    /// `intermediates` must be in order, starting with the issuer of
+    /// `end_entity`, and the last must be issued by `trust_anchor`.
+    pub fn new(
+        end_entity: CertificateDer<'static>,
+        intermediates: Vec<CertificateDer<'static>>,
+        trust_anchor: TrustAnchor<'static>,
+    ) -> Self {
+        Self {
+            end_entity,
+            intermediates,
+            trust_anchor,
+        }
+    }
+
+    /// The end-entity certificate.
+    pub fn end_entity(&self) -> &CertificateDer<'static> {
+        &self.end_entity
+    }
+
+    /// The intermediate certificates, starting with the issuer of the
+    /// end-entity certificate.
+    pub fn intermediates(&self) -> &[CertificateDer<'static>] {
+        &self.intermediates
+    }
+
+    /// The trust anchor the chain ends at.
+    pub fn trust_anchor(&self) -> &TrustAnchor<'static> {
+        &self.trust_anchor
     }
 }
 
-/// Zero-sized marker type representing verification of a client cert chain.
+/// Why a connection has no [`VerifiedChain`]; see
+/// [`CommonState::verified_chain()`].
+///
+/// [`CommonState::verified_chain()`]: crate::CommonState::verified_chain
+#[allow(unreachable_pub)]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifiedChainUnavailable {
+    /// The peer's certificate has not been verified on this connection:
+    /// either the handshake has not got that far, or the peer did not
+    /// authenticate with a certificate.
+    NotVerified,
+    /// The connection resumed an earlier session, so the peer's certificate
+    /// was not verified again.  Verified chains are not stored in sessions,
+    /// so are only available from the connection that made the session.
+    Resumed,
+    /// The peer's certificate was verified, but the verifier did not report
+    /// the chain it built.
+    NotReported,
+}
+
+/// Marker type representing verification of a client cert chain.
+///
+/// It optionally records the chain that was verified; see
+/// [`ClientCertVerified::with_verified_chain()`].
 #[derive(Debug)]
-pub struct ClientCertVerified(());
+pub struct ClientCertVerified(Option<VerifiedChain>);
 
 impl ClientCertVerified {
     /// Make a `ClientCertVerified`
     pub fn assertion() -> Self {
-        Self(())
+        Self(None)
+    }
+
+    /// Make a `ClientCertVerified`, recording the chain that was verified.
+    pub fn with_verified_chain(chain: VerifiedChain) -> Self {
+        Self(Some(chain))
+    }
+
+    /// The chain that was verified, if the verifier recorded one.
+    pub fn verified_chain(&self) -> Option<&VerifiedChain> {
+        self.0.as_ref()
+    }
+
+    pub(crate) fn into_verified_chain(self) -> Option<VerifiedChain> {
+        self.0
     }
 }
 
@@ -137,6 +238,38 @@ pub trait ServerCertVerifier: Send + Sync {
     ///
     /// This should be in priority order, with the most preferred first.
     fn supported_verify_schemes(&self) -> Vec<SignatureScheme>;
+
+    /// Verify a TLS1.3 handshake signature made using a delegated credential.
+    ///
+    /// This is called instead of [`ServerCertVerifier::verify_tls13_signature`]
+    /// when the server sends a delegated credential, which it only does if
+    /// [`ClientConfig::enable_delegated_credentials`] is set.
+    ///
+    /// Implementations must check that, per [RFC 9345 section 4.2]:
+    ///
+    /// - `credential` has not expired at `now`, and expires no more than seven
+    ///   days after it,
+    /// - `end_entity` has the DelegationUsage extension,
+    /// - `credential` is signed by the key of `end_entity`, and
+    /// - `dss` is a signature over `message` by the credential's public key,
+    ///   using [`DelegatedCredential::dc_cert_verify_algorithm()`].
+    ///
+    /// `end_entity` has already been validated by [`ServerCertVerifier::verify_server_cert`].
+    ///
+    /// The default implementation rejects all delegated credentials.
+    ///
+    /// [`ClientConfig::enable_delegated_credentials`]: crate::ClientConfig::enable_delegated_credentials
+    /// [RFC 9345 section 4.2]: https://www.rfc-editor.org/rfc/rfc9345#section-4.2
+    fn verify_delegated_credential(
+        &self,
+        _message: &[u8],
+        _end_entity: &CertificateDer<'_>,
+        _credential: &DelegatedCredential,
+        _dss: &DigitallySignedStruct,
+        _now: UnixTime,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        Err(CertificateError::InvalidDelegatedCredential.into())
+    }
 }
 
 impl fmt::Debug for dyn ServerCertVerifier {
@@ -150,15 +283,21 @@ impl fmt::Debug for dyn ServerCertVerifier {
 pub trait ClientCertVerifier: Send + Sync {
     /// Returns `true` to enable the server to request a client certificate and
     /// `false` to skip requesting a client certificate. Defaults to `true`.
-    fn offer_client_auth(&self) -> bool {
+    ///
+    /// `client_hello` is the client's `ClientHello`, so the decision can depend
+    /// on, for example, its SNI or ALPN protocols.
+    fn offer_client_auth(&self, _client_hello: &ClientHello<'_>) -> bool {
         true
     }
 
     /// Return `true` to require a client certificate and `false` to make
     /// client authentication optional.
-    /// Defaults to `Some(self.offer_client_auth())`.
-    fn client_auth_mandatory(&self) -> bool {
-        self.offer_client_auth()
+    /// Defaults to `self.offer_client_auth(client_hello)`.
+    ///
+    /// This is only called if [`ClientCertVerifier::offer_client_auth`] returned
+    /// `true` for the same `client_hello`.
+    fn client_auth_mandatory(&self, client_hello: &ClientHello<'_>) -> bool {
+        self.offer_client_auth(client_hello)
     }
 
     /// Returns the [`DistinguishedName`] [subjects] that the server will hint to clients to
@@ -167,8 +306,8 @@ pub trait ClientCertVerifier: Send + Sync {
     /// These hint values help the client pick a client certificate it believes the server will
     /// accept. The hints must be DER-encoded X.500 distinguished names, per [RFC 5280 A.1]. They
     /// are sent in the [`certificate_authorities`] extension of a [`CertificateRequest`] message
-    /// when [ClientCertVerifier::offer_client_auth] is true. When an empty list is sent the client
-    /// should always provide a client certificate if it has one.
+    /// when [ClientCertVerifier::offer_client_auth] is true for `client_hello`. When an empty list
+    /// is sent the client should always provide a client certificate if it has one.
     ///
     /// Generally this list should contain the [`DistinguishedName`] of each root trust
     /// anchor in the root cert store that the server is configured to use for authenticating
@@ -190,7 +329,7 @@ pub trait ClientCertVerifier: Send + Sync {
     /// [RFC 5280 A.1]: https://www.rfc-editor.org/rfc/rfc5280#appendix-A.1
     /// [`CertificateRequest`]: https://datatracker.ietf.org/doc/html/rfc8446#section-4.3.2
     /// [`certificate_authorities`]: https://datatracker.ietf.org/doc/html/rfc8446#section-4.2.4
-    fn root_hint_subjects(&self) -> &[DistinguishedName];
+    fn root_hint_subjects(&self, client_hello: &ClientHello<'_>) -> &[DistinguishedName];
 
     /// Verify the end-entity certificate `end_entity` is valid, acceptable,
     /// and chains to at least one of the trust anchors trusted by
@@ -271,11 +410,11 @@ impl fmt::Debug for dyn ClientCertVerifier {
 pub struct NoClientAuth;
 
 impl ClientCertVerifier for NoClientAuth {
-    fn offer_client_auth(&self) -> bool {
+    fn offer_client_auth(&self, _client_hello: &ClientHello<'_>) -> bool {
         false
     }
 
-    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+    fn root_hint_subjects(&self, _client_hello: &ClientHello<'_>) -> &[DistinguishedName] {
         unimplemented!();
     }
 
@@ -351,7 +490,7 @@ impl Codec for DigitallySignedStruct {
 fn assertions_are_debug() {
     assert_eq!(
         format!("{:?}", ClientCertVerified::assertion()),
-        "ClientCertVerified(())"
+        "ClientCertVerified(None)"
     );
     assert_eq!(
         format!("{:?}", HandshakeSignatureValid::assertion()),
@@ -363,6 +502,6 @@ fn assertions_are_debug() {
     );
     assert_eq!(
         format!("{:?}", ServerCertVerified::assertion()),
-        "ServerCertVerified(())"
+        "ServerCertVerified(None)"
     );
 }
@@ -4,13 +4,18 @@ use alloc::vec::Vec;
 use pki_types::{CertificateDer, CertificateRevocationListDer, UnixTime};
 use webpki::{CertRevocationList, RevocationCheckDepth, UnknownStatusPolicy};
 
-use super::{pki_error, VerifierBuilderError};
+use super::VerifierBuilderError;
+use crate::server::ClientHello;
 use crate::verify::{
     ClientCertVerified, ClientCertVerifier, DigitallySignedStruct, HandshakeSignatureValid,
     NoClientAuth,
 };
-use crate::webpki::parse_crls;
-use crate::webpki::verify::{verify_signed_struct, verify_tls13, ParsedCertificate};
+use crate::webpki::crls::with_revocation_options;
+use crate::webpki::verify::{
+    verify_client_cert_signed_by_trust_anchor_impl, verify_signed_struct, verify_tls13,
+    ParsedCertificate,
+};
+use crate::webpki::{parse_crls, CrlStore};
 use crate::{DistinguishedName, Error, RootCertStore, SignatureScheme, WebPkiSupportedAlgorithms};
 
 /// A builder for configuring a `webpki` client certificate verifier.
@@ -21,6 +26,7 @@ pub struct ClientCertVerifierBuilder {
     roots: Arc<RootCertStore>,
     root_hint_subjects: Vec<DistinguishedName>,
     crls: Vec<CertificateRevocationListDer<'static>>,
+    crl_store: Option<Arc<CrlStore>>,
     revocation_check_depth: RevocationCheckDepth,
     unknown_revocation_policy: UnknownStatusPolicy,
     anon_policy: AnonymousClientPolicy,
@@ -33,6 +39,7 @@ impl ClientCertVerifierBuilder {
             root_hint_subjects: roots.subjects(),
             roots,
             crls: Vec::new(),
+            crl_store: None,
             anon_policy: AnonymousClientPolicy::Deny,
             revocation_check_depth: RevocationCheckDepth::Chain,
             unknown_revocation_policy: UnknownStatusPolicy::Deny,
@@ -92,6 +99,23 @@ impl ClientCertVerifierBuilder {
         self
     }
 
+    /// Verify the revocation state of presented client certificates against the CRLs in
+    /// `store` at the time of each verification, in addition to any provided with
+    /// [`with_crls`][Self::with_crls].
+    ///
+    /// The store can be shared between verifiers, and its CRLs replaced with
+    /// [`CrlStore::replace`] without rebuilding them.  While a store is configured,
+    /// revocation status is checked even if it is empty, and
+    /// [`only_check_end_entity_revocation`][Self::only_check_end_entity_revocation] and
+    /// [`allow_unknown_revocation_status`][Self::allow_unknown_revocation_status] apply
+    /// as they do for CRLs provided with [`with_crls`][Self::with_crls].
+    ///
+    /// If this is called multiple times, the last call wins.
+    pub fn with_crl_store(mut self, store: Arc<CrlStore>) -> Self {
+        self.crl_store = Some(store);
+        self
+    }
+
     /// Only check the end entity certificate revocation status when using CRLs.
     ///
     /// If CRLs are provided using [`with_crls`][Self::with_crls] only check the end entity
@@ -175,6 +199,7 @@ impl ClientCertVerifierBuilder {
             self.roots,
             self.root_hint_subjects,
             parse_crls(self.crls)?,
+            self.crl_store,
             self.revocation_check_depth,
             self.unknown_revocation_policy,
             self.anon_policy,
@@ -239,6 +264,7 @@ pub struct WebPkiClientVerifier {
     roots: Arc<RootCertStore>,
     root_hint_subjects: Vec<DistinguishedName>,
     crls: Vec<CertRevocationList<'static>>,
+    crl_store: Option<Arc<CrlStore>>,
     revocation_check_depth: RevocationCheckDepth,
     unknown_revocation_policy: UnknownStatusPolicy,
     anonymous_policy: AnonymousClientPolicy,
@@ -272,6 +298,8 @@ impl WebPkiClientVerifier {
     ///   certificate authority subjects to a client.
     /// * `crls` is a `Vec` of owned certificate revocation lists (CRLs) to use for
     ///   client certificate validation.
+    /// * `crl_store` is a [`CrlStore`] whose current CRLs are also used for client
+    ///   certificate validation.
     /// * `revocation_check_depth` controls which certificates have their revocation status checked
     ///   when `crls` are provided.
     /// * `unknown_revocation_policy` controls how certificates with an unknown revocation status
@@ -283,6 +311,7 @@ impl WebPkiClientVerifier {
         roots: Arc<RootCertStore>,
         root_hint_subjects: Vec<DistinguishedName>,
         crls: Vec<CertRevocationList<'static>>,
+        crl_store: Option<Arc<CrlStore>>,
         revocation_check_depth: RevocationCheckDepth,
         unknown_revocation_policy: UnknownStatusPolicy,
         anonymous_policy: AnonymousClientPolicy,
@@ -292,6 +321,7 @@ impl WebPkiClientVerifier {
             roots,
             root_hint_subjects,
             crls,
+            crl_store,
             revocation_check_depth,
             unknown_revocation_policy,
             anonymous_policy,
@@ -301,18 +331,18 @@ impl WebPkiClientVerifier {
 }
 
 impl ClientCertVerifier for WebPkiClientVerifier {
-    fn offer_client_auth(&self) -> bool {
+    fn offer_client_auth(&self, _client_hello: &ClientHello<'_>) -> bool {
         true
     }
 
-    fn client_auth_mandatory(&self) -> bool {
+    fn client_auth_mandatory(&self, _client_hello: &ClientHello<'_>) -> bool {
         match self.anonymous_policy {
             AnonymousClientPolicy::Allow => false,
             AnonymousClientPolicy::Deny => true,
         }
     }
 
-    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+    fn root_hint_subjects(&self, _client_hello: &ClientHello<'_>) -> &[DistinguishedName] {
         &self.root_hint_subjects
     }
 
@@ -324,34 +354,26 @@ impl ClientCertVerifier for WebPkiClientVerifier {
     ) -> Result<ClientCertVerified, Error> {
         let cert = ParsedCertificate::try_from(end_entity)?;
 
-        let crl_refs = self.crls.iter().collect::<Vec<_>>();
-
-        let revocation = if self.crls.is_empty() {
-            None
-        } else {
-            Some(
-                webpki::RevocationOptionsBuilder::new(&crl_refs)
-                    // Note: safe to unwrap here - new is only fallible if no CRLs are provided
-                    //       and we verify this above.
-                    .unwrap()
-                    .with_depth(self.revocation_check_depth)
-                    .with_status_policy(self.unknown_revocation_policy)
-                    .build(),
-            )
-        };
-
-        cert.0
-            .verify_for_usage(
-                self.supported_algs.all,
-                &self.roots.roots,
-                intermediates,
-                now,
-                webpki::KeyUsage::client_auth(),
-                revocation,
-                None,
-            )
-            .map_err(pki_error)
-            .map(|_| ClientCertVerified::assertion())
+        with_revocation_options(
+            &self.crls,
+            self.crl_store.as_deref(),
+            self.revocation_check_depth,
+            self.unknown_revocation_policy,
+            now,
+            end_entity,
+            intermediates,
+            |revocation| {
+                verify_client_cert_signed_by_trust_anchor_impl(
+                    &cert,
+                    &self.roots,
+                    intermediates,
+                    revocation,
+                    now,
+                    self.supported_algs.all,
+                )
+            },
+        )
+        .map(ClientCertVerified::with_verified_chain)
     }
 
     fn verify_tls12_signature(
@@ -564,6 +586,8 @@ mod tests {
             VerifierBuilderError::NoRootAnchors,
             VerifierBuilderError::InvalidCrl(crate::CertRevocationListError::ParseError),
             VerifierBuilderError::NoSupportedAlgorithms,
+            VerifierBuilderError::NoAllowedSpiffeIds,
+            VerifierBuilderError::InvalidSpiffeIdPattern("spiffe://".into()),
         ];
 
         for err in all {
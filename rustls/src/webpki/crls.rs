@@ -0,0 +1,498 @@
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+use pki_types::{CertificateDer, CertificateRevocationListDer, UnixTime};
+use webpki::{
+    CertRevocationList, OwnedCertRevocationList, RevocationCheckDepth, RevocationOptions,
+    UnknownStatusPolicy,
+};
+
+use super::crl_error;
+use crate::error::{CertRevocationListError, CertificateError, Error};
+use crate::x509::{self, Der, DER_SEQUENCE_TAG};
+
+/// How a [`CrlStore`] treats a certificate revocation list which is past
+/// its `nextUpdate` time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpiredCrlPolicy {
+    /// Fail verification with [`CertificateError::ExpiredRevocationList`]
+    /// until the CRL is replaced, if the CRL could be used to check a
+    /// certificate being verified.  Expired CRLs from other issuers are
+    /// ignored.  This is the default.
+    Deny,
+    /// Ignore the CRL, as if it were not in the store.  Certificates it
+    /// covers then have unknown revocation status.
+    Ignore,
+    /// Keep using the CRL.
+    Allow,
+}
+
+impl Default for ExpiredCrlPolicy {
+    fn default() -> Self {
+        Self::Deny
+    }
+}
+
+/// A set of certificate revocation lists (CRLs) which can be replaced
+/// while verifiers are using it.
+///
+/// Verifiers configured with [`ClientCertVerifierBuilder::with_crl_store()`]
+/// or [`ServerCertVerifierBuilder::with_crl_store()`] check revocation against
+/// the CRLs in the store at the time of each verification.  Newly published
+/// CRLs are put into use with [`CrlStore::replace()`], without rebuilding the
+/// verifiers or the configurations that use them.
+///
+/// A CRL's `nextUpdate` time is when its issuer will have published a newer
+/// one.  Once it has passed, the CRL is handled according to the store's
+/// [`ExpiredCrlPolicy`].  CRLs without a `nextUpdate` time never expire.
+///
+/// [`ClientCertVerifierBuilder::with_crl_store()`]: crate::server::ClientCertVerifierBuilder::with_crl_store
+/// [`ServerCertVerifierBuilder::with_crl_store()`]: crate::client::ServerCertVerifierBuilder::with_crl_store
+pub struct CrlStore {
+    crls: RwLock<Arc<Vec<StoredCrl>>>,
+    expired_policy: ExpiredCrlPolicy,
+}
+
+struct StoredCrl {
+    crl: CertRevocationList<'static>,
+    next_update: Option<UnixTime>,
+}
+
+impl CrlStore {
+    /// Make a store containing the DER-encoded CRLs `crls`.
+    pub fn new<'a>(
+        crls: impl IntoIterator<Item = CertificateRevocationListDer<'a>>,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            crls: RwLock::new(Arc::new(parse_stored_crls(crls)?)),
+            expired_policy: ExpiredCrlPolicy::default(),
+        })
+    }
+
+    /// Make a store containing the CRLs in the files `paths`.
+    ///
+    /// Each file may contain a single DER-encoded CRL or, with the `pem`
+    /// crate feature, any number of PEM-encoded CRLs.
+    pub fn from_files(paths: impl IntoIterator<Item = impl AsRef<Path>>) -> Result<Self, Error> {
+        Self::new(read_crl_files(paths)?)
+    }
+
+    /// Sets how CRLs past their `nextUpdate` time are treated.
+    pub fn with_expired_crl_policy(mut self, policy: ExpiredCrlPolicy) -> Self {
+        self.expired_policy = policy;
+        self
+    }
+
+    /// Replace the contents of the store with the DER-encoded CRLs `crls`.
+    ///
+    /// Verifications already in progress finish with the previous CRLs.  On
+    /// error, the previous CRLs continue to be used.
+    pub fn replace<'a>(
+        &self,
+        crls: impl IntoIterator<Item = CertificateRevocationListDer<'a>>,
+    ) -> Result<(), Error> {
+        let crls = Arc::new(parse_stored_crls(crls)?);
+        *self.crls.write().unwrap() = crls;
+        Ok(())
+    }
+
+    /// Replace the contents of the store with the CRLs in the files `paths`,
+    /// as for [`CrlStore::from_files()`].
+    ///
+    /// On error, the previous CRLs continue to be used.
+    pub fn replace_from_files(
+        &self,
+        paths: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Result<(), Error> {
+        self.replace(read_crl_files(paths)?)
+    }
+
+    /// The number of CRLs in the store.
+    pub fn len(&self) -> usize {
+        self.snapshot().len()
+    }
+
+    /// Whether the store contains no CRLs.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The earliest `nextUpdate` time of the CRLs in the store, which is
+    /// when they should next be replaced.
+    pub fn next_update(&self) -> Option<UnixTime> {
+        self.snapshot()
+            .iter()
+            .filter_map(|stored| stored.next_update)
+            .min_by_key(|next_update| next_update.as_secs())
+    }
+
+    fn snapshot(&self) -> Arc<Vec<StoredCrl>> {
+        Arc::clone(&self.crls.read().unwrap())
+    }
+}
+
+impl fmt::Debug for CrlStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CrlStore")
+            .field("len", &self.len())
+            .field("next_update", &self.next_update())
+            .field("expired_policy", &self.expired_policy)
+            .finish()
+    }
+}
+
+/// Call `verify` with revocation options for the CRLs `crls` and those
+/// usable at `now` in `store`, or with `None` if there are no CRLs.
+///
+/// Only an expired CRL from the issuer of `end_entity` or, when checking
+/// the whole chain, of one of `intermediates` can be denied: no other CRL
+/// could be used to check the certificates being verified.
+///
+/// A configured store with no usable CRLs means the revocation status of
+/// every certificate is unknown, which `unknown_status_policy` may deny.
+pub(crate) fn with_revocation_options<T>(
+    crls: &[CertRevocationList<'static>],
+    store: Option<&CrlStore>,
+    depth: RevocationCheckDepth,
+    unknown_status_policy: UnknownStatusPolicy,
+    now: UnixTime,
+    end_entity: &CertificateDer<'_>,
+    intermediates: &[CertificateDer<'_>],
+    verify: impl FnOnce(Option<RevocationOptions<'_>>) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let stored = store.map(|store| (store.snapshot(), store.expired_policy));
+
+    let mut crl_refs = crls.iter().collect::<Vec<_>>();
+    if let Some((stored, expired_policy)) = &stored {
+        for crl in stored.iter() {
+            let expired = crl
+                .next_update
+                .map_or(false, |next_update| next_update.as_secs() < now.as_secs());
+            match (expired, expired_policy) {
+                (false, _) | (true, ExpiredCrlPolicy::Allow) => crl_refs.push(&crl.crl),
+                (true, ExpiredCrlPolicy::Ignore) => {}
+                (true, ExpiredCrlPolicy::Deny) => {
+                    if path_issuers(end_entity, intermediates, depth)
+                        .any(|issuer| issuer == crl.crl.issuer())
+                    {
+                        return Err(CertificateError::ExpiredRevocationList.into());
+                    }
+                }
+            }
+        }
+    }
+
+    if crl_refs.is_empty() {
+        let verified = verify(None)?;
+        return match store.is_some() && unknown_status_policy == UnknownStatusPolicy::Deny {
+            true => Err(CertificateError::UnknownRevocationStatus.into()),
+            false => Ok(verified),
+        };
+    }
+
+    verify(Some(
+        webpki::RevocationOptionsBuilder::new(&crl_refs)
+            // Note: safe to unwrap here - new is only fallible if no CRLs are provided
+            //       and we verify this above.
+            .unwrap()
+            .with_depth(depth)
+            .with_status_policy(unknown_status_policy)
+            .build(),
+    ))
+}
+
+/// The issuer names of `end_entity` and, when checking the whole chain,
+/// of `intermediates`, without their outer tag and length.
+fn path_issuers<'a>(
+    end_entity: &'a CertificateDer<'_>,
+    intermediates: &'a [CertificateDer<'_>],
+    depth: RevocationCheckDepth,
+) -> impl Iterator<Item = &'a [u8]> {
+    let intermediates = match depth {
+        RevocationCheckDepth::Chain => intermediates,
+        RevocationCheckDepth::EndEntity => &[],
+    };
+
+    core::iter::once(end_entity)
+        .chain(intermediates)
+        .filter_map(|cert| Der::expect(x509::cert_issuer(cert)?, DER_SEQUENCE_TAG))
+}
+
+fn parse_stored_crls<'a>(
+    crls: impl IntoIterator<Item = CertificateRevocationListDer<'a>>,
+) -> Result<Vec<StoredCrl>, Error> {
+    crls.into_iter()
+        .map(|der| {
+            let crl = OwnedCertRevocationList::from_der(der.as_ref()).map_err(crl_error)?;
+            let next_update =
+                x509::crl_next_update(der.as_ref()).ok_or(CertRevocationListError::ParseError)?;
+            Ok(StoredCrl {
+                crl: crl.into(),
+                next_update,
+            })
+        })
+        .collect()
+}
+
+fn read_crl_files(
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Vec<CertificateRevocationListDer<'static>>, Error> {
+    let mut crls = Vec::new();
+    for path in paths {
+        crls.extend(read_crl_file(path.as_ref())?);
+    }
+    Ok(crls)
+}
+
+fn read_crl_file(path: &Path) -> Result<Vec<CertificateRevocationListDer<'static>>, Error> {
+    let contents =
+        fs::read(path).map_err(|err| Error::General(format!("cannot read {:?}: {}", path, err)))?;
+
+    let is_pem = contents
+        .iter()
+        .skip_while(|b| b.is_ascii_whitespace())
+        .take(10)
+        .eq(b"-----BEGIN");
+    if !is_pem {
+        return Ok(alloc::vec![contents.into()]);
+    }
+
+    #[cfg(feature = "pem")]
+    {
+        let crls = rustls_pemfile::crls(&mut &contents[..])
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::General(format!("cannot parse {:?}: {}", path, err)))?;
+        match crls.is_empty() {
+            true => Err(Error::General(format!("no CRLs in {:?}", path))),
+            false => Ok(crls),
+        }
+    }
+
+    #[cfg(not(feature = "pem"))]
+    Err(Error::General(format!(
+        "cannot parse {:?}: PEM files require the `pem` crate feature",
+        path
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    use core::time::Duration;
+
+    const CLIENT_CRL: &[u8] = include_bytes!("../../../test-ca/ecdsa/client.revoked.crl.pem");
+    const INTER_CRL: &[u8] = include_bytes!("../../../test-ca/ecdsa/inter.revoked.crl.pem");
+    const END_CRL: &[u8] = include_bytes!("../../../test-ca/rsa/end.revoked.crl.pem");
+
+    // Issued by the ECDSA intermediate, which is issued by the ECDSA CA.
+    const CLIENT_CERT: &[u8] = include_bytes!("../../../test-ca/ecdsa/client.cert");
+    const INTER_CERT: &[u8] = include_bytes!("../../../test-ca/ecdsa/inter.cert");
+
+    fn crl(pem: &[u8]) -> CertificateRevocationListDer<'static> {
+        rustls_pemfile::crls(&mut &pem[..])
+            .next()
+            .unwrap()
+            .unwrap()
+    }
+
+    fn cert(pem: &[u8]) -> CertificateDer<'static> {
+        rustls_pemfile::certs(&mut &pem[..])
+            .next()
+            .unwrap()
+            .unwrap()
+    }
+
+    /// The `nextUpdate` time of the CRL `pem`.
+    fn next_update(pem: &[u8]) -> u64 {
+        x509::crl_next_update(crl(pem).as_ref())
+            .unwrap()
+            .unwrap()
+            .as_secs()
+    }
+
+    fn at(secs: u64) -> UnixTime {
+        UnixTime::since_unix_epoch(Duration::from_secs(secs))
+    }
+
+    /// The number of CRLs in `store` used to verify the ECDSA client
+    /// certificate at `now`, checking revocation to `depth`.
+    fn usable_crls_at_depth(
+        store: &CrlStore,
+        now: UnixTime,
+        depth: RevocationCheckDepth,
+    ) -> Result<usize, Error> {
+        with_revocation_options(
+            &[],
+            Some(store),
+            depth,
+            UnknownStatusPolicy::Allow,
+            now,
+            &cert(CLIENT_CERT),
+            &[cert(INTER_CERT)],
+            |revocation| Ok(revocation.is_some()),
+        )
+        .map(|some| if some { store.len() } else { 0 })
+    }
+
+    fn usable_crls(store: &CrlStore, now: UnixTime) -> Result<usize, Error> {
+        usable_crls_at_depth(store, now, RevocationCheckDepth::Chain)
+    }
+
+    #[test]
+    fn store_reports_next_update() {
+        let store = CrlStore::new([crl(CLIENT_CRL), crl(END_CRL)]).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            store.next_update().map(|t| t.as_secs()),
+            Some(next_update(CLIENT_CRL).min(next_update(END_CRL)))
+        );
+
+        let store = CrlStore::new([]).unwrap();
+        assert!(store.is_empty());
+        assert!(store.next_update().is_none());
+    }
+
+    #[test]
+    fn expired_crls_are_denied_by_default() {
+        let expiry = next_update(CLIENT_CRL);
+        let store = CrlStore::new([crl(CLIENT_CRL)]).unwrap();
+        assert_eq!(usable_crls(&store, at(expiry)), Ok(1));
+        assert_eq!(
+            usable_crls(&store, at(expiry + 1)),
+            Err(CertificateError::ExpiredRevocationList.into())
+        );
+    }
+
+    #[test]
+    fn expired_crls_off_the_path_are_not_denied() {
+        // The RSA intermediate's CRL cannot be used for the ECDSA client
+        // certificate, so its expiry does not matter.
+        let store = CrlStore::new([crl(END_CRL)]).unwrap();
+        assert_eq!(usable_crls(&store, at(next_update(END_CRL) + 1)), Ok(0));
+
+        // The CA's CRL is only used when checking the whole chain.
+        let expiry = next_update(INTER_CRL);
+        let store = CrlStore::new([crl(INTER_CRL)]).unwrap();
+        assert_eq!(
+            usable_crls(&store, at(expiry + 1)),
+            Err(CertificateError::ExpiredRevocationList.into())
+        );
+        assert_eq!(
+            usable_crls_at_depth(&store, at(expiry + 1), RevocationCheckDepth::EndEntity),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn expired_crls_can_be_ignored_or_allowed() {
+        let store = CrlStore::new([crl(CLIENT_CRL)])
+            .unwrap()
+            .with_expired_crl_policy(ExpiredCrlPolicy::Ignore);
+        assert_eq!(usable_crls(&store, at(next_update(CLIENT_CRL) + 1)), Ok(0));
+
+        let store = CrlStore::new([crl(CLIENT_CRL)])
+            .unwrap()
+            .with_expired_crl_policy(ExpiredCrlPolicy::Allow);
+        assert_eq!(usable_crls(&store, at(next_update(CLIENT_CRL) + 1)), Ok(1));
+    }
+
+    #[test]
+    fn empty_store_denies_unknown_status() {
+        let store = CrlStore::new([]).unwrap();
+        let result = with_revocation_options(
+            &[],
+            Some(&store),
+            RevocationCheckDepth::Chain,
+            UnknownStatusPolicy::Deny,
+            UnixTime::now(),
+            &cert(CLIENT_CERT),
+            &[],
+            |revocation| Ok(revocation.is_some()),
+        );
+        assert!(matches!(
+            result,
+            Err(Error::InvalidCertificate(
+                CertificateError::UnknownRevocationStatus
+            ))
+        ));
+
+        let result = with_revocation_options(
+            &[],
+            None,
+            RevocationCheckDepth::Chain,
+            UnknownStatusPolicy::Deny,
+            UnixTime::now(),
+            &cert(CLIENT_CERT),
+            &[],
+            |revocation| Ok(revocation.is_some()),
+        );
+        assert_eq!(result, Ok(false));
+    }
+
+    #[test]
+    fn replace_keeps_previous_crls_on_error() {
+        let store = CrlStore::new([crl(CLIENT_CRL)]).unwrap();
+        store
+            .replace([crl(CLIENT_CRL), crl(END_CRL)])
+            .unwrap();
+        assert_eq!(store.len(), 2);
+
+        let err = store
+            .replace([
+                crl(END_CRL),
+                CertificateRevocationListDer::from(&b"bad"[..]),
+            ])
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidCertRevocationList(_)));
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn loads_der_files() {
+        let dir = TempDir::new();
+        let client = dir.write("client.crl", crl(CLIENT_CRL).as_ref());
+        let end = dir.write("end.crl", crl(END_CRL).as_ref());
+
+        let store = CrlStore::from_files([&client]).unwrap();
+        assert_eq!(store.len(), 1);
+
+        store
+            .replace_from_files([&client, &end])
+            .unwrap();
+        assert_eq!(store.len(), 2);
+
+        let err = store
+            .replace_from_files([dir.path().join("missing.crl")])
+            .unwrap_err();
+        assert!(matches!(err, Error::General(msg) if msg.starts_with("cannot read")));
+        assert_eq!(store.len(), 2);
+    }
+
+    #[cfg(feature = "pem")]
+    #[test]
+    fn loads_pem_files() {
+        let dir = TempDir::new();
+        let client = dir.write("client.crl", crl(CLIENT_CRL).as_ref());
+        let end = dir.write("end.crl.pem", END_CRL);
+        let no_crls = dir.write(
+            "empty.pem",
+            b"-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----\n",
+        );
+
+        let store = CrlStore::from_files([&client, &end]).unwrap();
+        assert_eq!(store.len(), 2);
+
+        let err = store
+            .replace_from_files([&no_crls])
+            .unwrap_err();
+        assert!(matches!(err, Error::General(msg) if msg.starts_with("no CRLs in")));
+        assert_eq!(store.len(), 2);
+    }
+}
@@ -1,3 +1,4 @@
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::fmt;
@@ -10,15 +11,26 @@ use crate::error::{CertRevocationListError, CertificateError, Error};
 
 mod anchors;
 mod client_verifier;
+mod crls;
 mod server_verifier;
+mod spiffe;
+#[cfg(feature = "pem")]
+mod system_roots;
 mod verify;
 
 pub use anchors::RootCertStore;
 
 pub use client_verifier::{ClientCertVerifierBuilder, WebPkiClientVerifier};
+pub use crls::{CrlStore, ExpiredCrlPolicy};
 pub use server_verifier::{ServerCertVerifierBuilder, WebPkiServerVerifier};
+pub use spiffe::{SpiffeVerifier, SpiffeVerifierBuilder};
+#[cfg(feature = "pem")]
+pub use system_roots::{LoadedRoots, RootCertLocations};
 
+pub(crate) use verify::verify_signature_with_spki;
 pub use verify::WebPkiSupportedAlgorithms;
+#[cfg(feature = "ring")]
+pub(crate) use verify::SUPPORTED_SIG_ALGS;
 
 // Conditionally exported from crate.
 #[allow(unreachable_pub)]
@@ -39,6 +51,10 @@ pub enum VerifierBuilderError {
     /// Call `with_signature_verification_algorithms` on the builder, or compile
     /// with the `ring` feature.
     NoSupportedAlgorithms,
+    /// No SPIFFE ID patterns were allowed.
+    NoAllowedSpiffeIds,
+    /// The contained SPIFFE ID pattern is not valid.
+    InvalidSpiffeIdPattern(String),
 }
 
 impl From<CertRevocationListError> for VerifierBuilderError {
@@ -55,6 +71,10 @@ impl fmt::Display for VerifierBuilderError {
             Self::NoSupportedAlgorithms => {
                 write!(f, "no signature verification algorithms were provided")
             }
+            Self::NoAllowedSpiffeIds => write!(f, "no SPIFFE ID patterns were allowed"),
+            Self::InvalidSpiffeIdPattern(pattern) => {
+                write!(f, "invalid SPIFFE ID pattern: {:?}", pattern)
+            }
         }
     }
 }
@@ -6,16 +6,18 @@ use alloc::vec::Vec;
 use pki_types::{CertificateDer, CertificateRevocationListDer, UnixTime};
 use webpki::{CertRevocationList, RevocationCheckDepth, UnknownStatusPolicy};
 
+use crate::msgs::handshake::DelegatedCredential;
 use crate::verify::{
     DigitallySignedStruct, HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
 };
+use crate::webpki::crls::with_revocation_options;
 #[cfg(feature = "ring")]
 use crate::webpki::verify::SUPPORTED_SIG_ALGS;
 use crate::webpki::verify::{
-    verify_server_cert_signed_by_trust_anchor_impl, verify_signed_struct, verify_tls13,
-    ParsedCertificate,
+    verify_delegated_credential, verify_server_cert_signed_by_trust_anchor_impl,
+    verify_signed_struct, verify_tls13, ParsedCertificate,
 };
-use crate::webpki::{parse_crls, verify_server_name, VerifierBuilderError};
+use crate::webpki::{parse_crls, verify_server_name, CrlStore, VerifierBuilderError};
 use crate::{Error, RootCertStore, ServerName, SignatureScheme, WebPkiSupportedAlgorithms};
 
 /// A builder for configuring a `webpki` server certificate verifier.
@@ -25,6 +27,7 @@ use crate::{Error, RootCertStore, ServerName, SignatureScheme, WebPkiSupportedAl
 pub struct ServerCertVerifierBuilder {
     roots: Arc<RootCertStore>,
     crls: Vec<CertificateRevocationListDer<'static>>,
+    crl_store: Option<Arc<CrlStore>>,
     revocation_check_depth: RevocationCheckDepth,
     unknown_revocation_policy: UnknownStatusPolicy,
     supported_algs: Option<WebPkiSupportedAlgorithms>,
@@ -35,6 +38,7 @@ impl ServerCertVerifierBuilder {
         Self {
             roots,
             crls: Vec::new(),
+            crl_store: None,
             revocation_check_depth: RevocationCheckDepth::Chain,
             unknown_revocation_policy: UnknownStatusPolicy::Deny,
             supported_algs: None,
@@ -52,6 +56,23 @@ impl ServerCertVerifierBuilder {
         self
     }
 
+    /// Verify the revocation state of presented server certificates against the CRLs in
+    /// `store` at the time of each verification, in addition to any provided with
+    /// [`with_crls`][Self::with_crls].
+    ///
+    /// The store can be shared between verifiers, and its CRLs replaced with
+    /// [`CrlStore::replace`] without rebuilding them.  While a store is configured,
+    /// revocation status is checked even if it is empty, and
+    /// [`only_check_end_entity_revocation`][Self::only_check_end_entity_revocation] and
+    /// [`allow_unknown_revocation_status`][Self::allow_unknown_revocation_status] apply
+    /// as they do for CRLs provided with [`with_crls`][Self::with_crls].
+    ///
+    /// If this is called multiple times, the last call wins.
+    pub fn with_crl_store(mut self, store: Arc<CrlStore>) -> Self {
+        self.crl_store = Some(store);
+        self
+    }
+
     /// Only check the end entity certificate revocation status when using CRLs.
     ///
     /// If CRLs are provided using [`with_crls`][Self::with_crls] only check the end entity
@@ -123,6 +144,7 @@ impl ServerCertVerifierBuilder {
         Ok(Arc::new(WebPkiServerVerifier::new(
             self.roots,
             parse_crls(self.crls)?,
+            self.crl_store,
             self.revocation_check_depth,
             self.unknown_revocation_policy,
             supported_algs,
@@ -135,6 +157,7 @@ impl ServerCertVerifierBuilder {
 pub struct WebPkiServerVerifier {
     roots: Arc<RootCertStore>,
     crls: Vec<CertRevocationList<'static>>,
+    crl_store: Option<Arc<CrlStore>>,
     revocation_check_depth: RevocationCheckDepth,
     unknown_revocation_policy: UnknownStatusPolicy,
     supported: WebPkiSupportedAlgorithms,
@@ -157,6 +180,7 @@ impl WebPkiServerVerifier {
         Self::new(
             roots,
             Vec::default(),
+            None,
             RevocationCheckDepth::Chain,
             UnknownStatusPolicy::Allow,
             SUPPORTED_SIG_ALGS,
@@ -168,6 +192,8 @@ impl WebPkiServerVerifier {
     /// * `roots` is the set of trust anchors to trust for issuing server certs.
     /// * `crls` are a vec of owned certificate revocation lists (CRLs) to use for
     ///   client certificate validation.
+    /// * `crl_store` is a [`CrlStore`] whose current CRLs are also used for
+    ///   certificate validation.
     /// * `revocation_check_depth` controls which certificates have their revocation status checked
     ///   when `crls` are provided.
     /// * `unknown_revocation_policy` controls how certificates with an unknown revocation status
@@ -177,6 +203,7 @@ impl WebPkiServerVerifier {
     pub(crate) fn new(
         roots: impl Into<Arc<RootCertStore>>,
         crls: Vec<CertRevocationList<'static>>,
+        crl_store: Option<Arc<CrlStore>>,
         revocation_check_depth: RevocationCheckDepth,
         unknown_revocation_policy: UnknownStatusPolicy,
         supported: WebPkiSupportedAlgorithms,
@@ -184,6 +211,7 @@ impl WebPkiServerVerifier {
         Self {
             roots: roots.into(),
             crls,
+            crl_store,
             revocation_check_depth,
             unknown_revocation_policy,
             supported,
@@ -241,33 +269,26 @@ impl ServerCertVerifier for WebPkiServerVerifier {
     ) -> Result<ServerCertVerified, Error> {
         let cert = ParsedCertificate::try_from(end_entity)?;
 
-        let crl_refs = self.crls.iter().collect::<Vec<_>>();
-
-        let revocation = if self.crls.is_empty() {
-            None
-        } else {
-            // Note: unwrap here is safe because RevocationOptionsBuilder only errors when given
-            //       empty CRLs.
-            Some(
-                webpki::RevocationOptionsBuilder::new(crl_refs.as_slice())
-                    // Note: safe to unwrap here - new is only fallible if no CRLs are provided
-                    //       and we verify this above.
-                    .unwrap()
-                    .with_depth(self.revocation_check_depth)
-                    .with_status_policy(self.unknown_revocation_policy)
-                    .build(),
-            )
-        };
-
         // Note: we use the crate-internal `_impl` fn here in order to provide revocation
         // checking information, if applicable.
-        verify_server_cert_signed_by_trust_anchor_impl(
-            &cert,
-            &self.roots,
-            intermediates,
-            revocation,
+        let chain = with_revocation_options(
+            &self.crls,
+            self.crl_store.as_deref(),
+            self.revocation_check_depth,
+            self.unknown_revocation_policy,
             now,
-            self.supported.all,
+            end_entity,
+            intermediates,
+            |revocation| {
+                verify_server_cert_signed_by_trust_anchor_impl(
+                    &cert,
+                    &self.roots,
+                    intermediates,
+                    revocation,
+                    now,
+                    self.supported.all,
+                )
+            },
         )?;
 
         if !ocsp_response.is_empty() {
@@ -275,7 +296,7 @@ impl ServerCertVerifier for WebPkiServerVerifier {
         }
 
         verify_server_name(&cert, server_name)?;
-        Ok(ServerCertVerified::assertion())
+        Ok(ServerCertVerified::with_verified_chain(chain))
     }
 
     fn verify_tls12_signature(
@@ -299,6 +320,17 @@ impl ServerCertVerifier for WebPkiServerVerifier {
     fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
         self.supported.supported_schemes()
     }
+
+    fn verify_delegated_credential(
+        &self,
+        message: &[u8],
+        end_entity: &CertificateDer<'_>,
+        credential: &DelegatedCredential,
+        dss: &DigitallySignedStruct,
+        now: UnixTime,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_delegated_credential(message, end_entity, credential, dss, now, &self.supported)
+    }
 }
 
 #[cfg(all(test, feature = "ring"))]
@@ -0,0 +1,560 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use pki_types::{CertificateDer, UnixTime};
+
+use super::VerifierBuilderError;
+use crate::server::ClientHello;
+use crate::verify::{
+    ClientCertVerified, ClientCertVerifier, DigitallySignedStruct, HandshakeSignatureValid,
+    ServerCertVerified, ServerCertVerifier,
+};
+use crate::webpki::verify::{
+    verify_client_cert_signed_by_trust_anchor_impl, verify_server_cert_signed_by_trust_anchor_impl,
+    verify_signed_struct, verify_tls13, ParsedCertificate,
+};
+use crate::x509;
+use crate::{
+    CertificateError, DistinguishedName, Error, RootCertStore, ServerName, SignatureScheme,
+    WebPkiSupportedAlgorithms,
+};
+
+/// A builder for configuring a [`SpiffeVerifier`].
+///
+/// For more information, see the [`SpiffeVerifier`] documentation.
+#[derive(Debug, Clone)]
+pub struct SpiffeVerifierBuilder {
+    bundles: BTreeMap<String, Arc<RootCertStore>>,
+    allowed: Vec<String>,
+    supported_algs: Option<WebPkiSupportedAlgorithms>,
+}
+
+impl SpiffeVerifierBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            bundles: BTreeMap::new(),
+            allowed: Vec::new(),
+            supported_algs: None,
+        }
+    }
+
+    /// Trust `roots` to issue SPIFFE IDs in the trust domain `trust_domain`,
+    /// for example `example.org`.
+    ///
+    /// Calling this again for the same trust domain replaces its bundle.
+    pub fn with_trust_bundle(mut self, trust_domain: &str, roots: Arc<RootCertStore>) -> Self {
+        self.bundles
+            .insert(trust_domain.to_string(), roots);
+        self
+    }
+
+    /// Allow peers whose SPIFFE ID matches `pattern`.
+    ///
+    /// A pattern is a SPIFFE ID, such as `spiffe://example.org/ns/prod/sa/web`,
+    /// where any path segment may be `*` to match any single segment, and the
+    /// last path segment may be `**` to match any number of remaining segments.
+    /// So `spiffe://example.org/ns/*/sa/web` allows the `web` service account in
+    /// every namespace, and `spiffe://example.org/**` allows every SPIFFE ID in
+    /// the `example.org` trust domain.
+    ///
+    /// Calling this multiple times adds to the allow-list.
+    pub fn allow_id(mut self, pattern: &str) -> Self {
+        self.allowed.push(pattern.to_string());
+        self
+    }
+
+    /// Sets which signature verification algorithms are enabled.
+    ///
+    /// If this is called multiple times, the last call wins.
+    pub fn with_signature_verification_algorithms(
+        mut self,
+        supported_algs: WebPkiSupportedAlgorithms,
+    ) -> Self {
+        self.supported_algs = Some(supported_algs);
+        self
+    }
+
+    /// Build a SPIFFE certificate verifier.
+    ///
+    /// If the `ring` crate feature is supplied, and `with_signature_verification_algorithms` was not
+    /// called on the builder, a default set of signature verification algorithms is used.
+    ///
+    /// The returned verifier can be used as both an `Arc<dyn ClientCertVerifier>` and an
+    /// `Arc<dyn ServerCertVerifier>`.
+    ///
+    /// # Errors
+    /// This function will return a `VerifierBuilderError` if:
+    /// 1. No trust bundles, or an empty trust bundle, have been provided.
+    /// 2. No SPIFFE ID patterns have been allowed.
+    /// 3. An allowed pattern is not a valid SPIFFE ID pattern.
+    /// 4. No signature verification algorithms were set and the `ring` feature is not enabled.
+    #[cfg_attr(not(feature = "ring"), allow(unused_mut))]
+    pub fn build(mut self) -> Result<Arc<SpiffeVerifier>, VerifierBuilderError> {
+        if self.bundles.is_empty()
+            || self
+                .bundles
+                .values()
+                .any(|roots| roots.is_empty())
+        {
+            return Err(VerifierBuilderError::NoRootAnchors);
+        }
+
+        if self.allowed.is_empty() {
+            return Err(VerifierBuilderError::NoAllowedSpiffeIds);
+        }
+
+        let allowed = self
+            .allowed
+            .into_iter()
+            .map(|pattern| match SpiffeIdPattern::parse(&pattern) {
+                Some(parsed) => Ok(parsed),
+                None => Err(VerifierBuilderError::InvalidSpiffeIdPattern(pattern)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        #[cfg(feature = "ring")]
+        if self.supported_algs.is_none() {
+            self.supported_algs = Some(super::verify::SUPPORTED_SIG_ALGS);
+        }
+
+        let supported_algs = self
+            .supported_algs
+            .ok_or(VerifierBuilderError::NoSupportedAlgorithms)?;
+
+        let root_hint_subjects = self
+            .bundles
+            .values()
+            .flat_map(|roots| roots.subjects())
+            .collect();
+
+        Ok(Arc::new(SpiffeVerifier {
+            bundles: self.bundles,
+            root_hint_subjects,
+            allowed,
+            supported_algs,
+        }))
+    }
+}
+
+/// A certificate verifier that authenticates peers by their SPIFFE ID[^1].
+///
+/// The peer's end-entity certificate must be an X509-SVID[^2]: it must
+/// have exactly one URI subject alternative name, which is a SPIFFE ID.
+/// The certificate chain is validated against the trust bundle for that
+/// ID's trust domain, and the ID must then match one of the allowed
+/// patterns.
+///
+/// Certificates without a valid SPIFFE ID are rejected with
+/// [`CertificateError::InvalidSpiffeId`], those whose trust domain has no
+/// bundle with [`CertificateError::UnknownIssuer`], and those whose ID is
+/// not allowed with [`CertificateError::SpiffeIdNotAllowed`].
+///
+/// It implements both [`ClientCertVerifier`] and [`ServerCertVerifier`].
+/// When verifying servers the `ServerName` is not checked, as the server is
+/// identified by its SPIFFE ID instead. No revocation checking is performed.
+///
+/// Example:
+///
+/// To require all clients present an X509-SVID for the `web` service account
+/// in any namespace of the `example.org` trust domain:
+/// ```no_run
+/// # use rustls::RootCertStore;
+/// # use rustls::server::SpiffeVerifier;
+/// # let bundle = RootCertStore::empty();
+/// let client_verifier = SpiffeVerifier::builder()
+///   .with_trust_bundle("example.org", bundle.into())
+///   .allow_id("spiffe://example.org/ns/*/sa/web")
+///   .build()
+///   .unwrap();
+/// ```
+///
+/// [^1]: <https://github.com/spiffe/spiffe/blob/main/standards/SPIFFE-ID.md>
+/// [^2]: <https://github.com/spiffe/spiffe/blob/main/standards/X509-SVID.md>
+pub struct SpiffeVerifier {
+    bundles: BTreeMap<String, Arc<RootCertStore>>,
+    root_hint_subjects: Vec<DistinguishedName>,
+    allowed: Vec<SpiffeIdPattern>,
+    supported_algs: WebPkiSupportedAlgorithms,
+}
+
+impl SpiffeVerifier {
+    /// Create a builder to configure a `SpiffeVerifier`.
+    ///
+    /// For more information, see the [`SpiffeVerifierBuilder`] documentation.
+    pub fn builder() -> SpiffeVerifierBuilder {
+        SpiffeVerifierBuilder::new()
+    }
+
+    /// Returns the SPIFFE ID of `end_entity`, and the trust bundle for its
+    /// trust domain.
+    fn spiffe_id<'a>(
+        &self,
+        end_entity: &'a CertificateDer<'_>,
+    ) -> Result<(SpiffeId<'a>, &RootCertStore), Error> {
+        let uris = x509::cert_uri_names(end_entity).ok_or(CertificateError::BadEncoding)?;
+        let id = match uris.as_slice() {
+            [uri] => core::str::from_utf8(uri)
+                .ok()
+                .and_then(SpiffeId::parse),
+            _ => None,
+        }
+        .ok_or(CertificateError::InvalidSpiffeId)?;
+
+        match self.bundles.get(id.trust_domain) {
+            Some(roots) => Ok((id, roots)),
+            None => Err(CertificateError::UnknownIssuer.into()),
+        }
+    }
+
+    fn check_allowed(&self, id: &SpiffeId<'_>) -> Result<(), Error> {
+        match self
+            .allowed
+            .iter()
+            .any(|pattern| pattern.matches(id))
+        {
+            true => Ok(()),
+            false => Err(CertificateError::SpiffeIdNotAllowed.into()),
+        }
+    }
+}
+
+impl ClientCertVerifier for SpiffeVerifier {
+    fn root_hint_subjects(&self, _client_hello: &ClientHello<'_>) -> &[DistinguishedName] {
+        &self.root_hint_subjects
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, Error> {
+        let (id, roots) = self.spiffe_id(end_entity)?;
+        let cert = ParsedCertificate::try_from(end_entity)?;
+
+        let chain = verify_client_cert_signed_by_trust_anchor_impl(
+            &cert,
+            roots,
+            intermediates,
+            None,
+            now,
+            self.supported_algs.all,
+        )?;
+
+        self.check_allowed(&id)?;
+        Ok(ClientCertVerified::with_verified_chain(chain))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_signed_struct(message, cert, dss, &self.supported_algs)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13(message, cert, dss, &self.supported_algs)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.supported_algs.supported_schemes()
+    }
+}
+
+impl ServerCertVerifier for SpiffeVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName,
+        _ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        let (id, roots) = self.spiffe_id(end_entity)?;
+        let cert = ParsedCertificate::try_from(end_entity)?;
+
+        let chain = verify_server_cert_signed_by_trust_anchor_impl(
+            &cert,
+            roots,
+            intermediates,
+            None,
+            now,
+            self.supported_algs.all,
+        )?;
+
+        self.check_allowed(&id)?;
+        Ok(ServerCertVerified::with_verified_chain(chain))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_signed_struct(message, cert, dss, &self.supported_algs)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13(message, cert, dss, &self.supported_algs)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.supported_algs.supported_schemes()
+    }
+}
+
+impl fmt::Debug for SpiffeVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpiffeVerifier")
+            .field("trust_domains", &self.bundles.keys().collect::<Vec<_>>())
+            .field("allowed", &self.allowed)
+            .finish()
+    }
+}
+
+/// A SPIFFE ID, borrowed from a certificate.
+///
+/// See <https://github.com/spiffe/spiffe/blob/main/standards/SPIFFE-ID.md>.
+struct SpiffeId<'a> {
+    trust_domain: &'a str,
+    path: Vec<&'a str>,
+}
+
+impl<'a> SpiffeId<'a> {
+    fn parse(id: &'a str) -> Option<Self> {
+        Self::parse_with(id, |_| false)
+    }
+
+    /// Parse `id`, also accepting path segments for which `extra` is true.
+    fn parse_with(id: &'a str, extra: impl Fn(&str) -> bool) -> Option<Self> {
+        if id.len() > MAX_SPIFFE_ID_LEN {
+            return None;
+        }
+
+        let rest = id.strip_prefix("spiffe://")?;
+        let (trust_domain, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+            None => (rest, None),
+        };
+
+        let trust_domain_valid = !trust_domain.is_empty()
+            && trust_domain
+                .bytes()
+                .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_'));
+        if !trust_domain_valid {
+            return None;
+        }
+
+        let path = match path {
+            Some(path) => path.split('/').collect(),
+            None => Vec::new(),
+        };
+
+        for segment in path.iter() {
+            let valid = extra(segment)
+                || (!segment.is_empty()
+                    && *segment != "."
+                    && *segment != ".."
+                    && segment.bytes().all(|b| {
+                        matches!(b, b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_')
+                    }));
+            if !valid {
+                return None;
+            }
+        }
+
+        Some(Self { trust_domain, path })
+    }
+}
+
+/// An allowed SPIFFE ID pattern; see [`SpiffeVerifierBuilder::allow_id()`].
+#[derive(Debug)]
+struct SpiffeIdPattern {
+    trust_domain: String,
+    path: Vec<String>,
+}
+
+impl SpiffeIdPattern {
+    fn parse(pattern: &str) -> Option<Self> {
+        let id = SpiffeId::parse_with(pattern, |segment| segment == "*" || segment == "**")?;
+
+        // `**` may only be the last segment
+        if let Some((_, init)) = id.path.split_last() {
+            if init.contains(&"**") {
+                return None;
+            }
+        }
+
+        Some(Self {
+            trust_domain: id.trust_domain.to_string(),
+            path: id
+                .path
+                .iter()
+                .map(|segment| segment.to_string())
+                .collect(),
+        })
+    }
+
+    fn matches(&self, id: &SpiffeId<'_>) -> bool {
+        if self.trust_domain != id.trust_domain {
+            return false;
+        }
+
+        let (prefix, any_suffix) = match self.path.split_last() {
+            Some((last, init)) if last == "**" => (init, true),
+            _ => (&self.path[..], false),
+        };
+
+        let lengths_ok = match any_suffix {
+            true => id.path.len() >= prefix.len(),
+            false => id.path.len() == prefix.len(),
+        };
+
+        lengths_ok
+            && prefix
+                .iter()
+                .zip(id.path.iter())
+                .all(|(pattern, segment)| pattern == "*" || pattern == segment)
+    }
+}
+
+const MAX_SPIFFE_ID_LEN: usize = 2048;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_errors() {
+        let roots = Arc::new(RootCertStore {
+            roots: vec![webpki::extract_trust_anchor(&CertificateDer::from(
+                &include_bytes!("../../../test-ca/ecdsa/ca.der")[..],
+            ))
+            .unwrap()
+            .to_owned()],
+        });
+
+        assert!(matches!(
+            SpiffeVerifier::builder()
+                .allow_id("spiffe://example.org/**")
+                .build(),
+            Err(VerifierBuilderError::NoRootAnchors)
+        ));
+        assert!(matches!(
+            SpiffeVerifier::builder()
+                .with_trust_bundle("example.org", Arc::clone(&roots))
+                .with_trust_bundle("example.com", Arc::new(RootCertStore::empty()))
+                .allow_id("spiffe://example.org/**")
+                .build(),
+            Err(VerifierBuilderError::NoRootAnchors)
+        ));
+        assert!(matches!(
+            SpiffeVerifier::builder()
+                .with_trust_bundle("example.org", Arc::clone(&roots))
+                .build(),
+            Err(VerifierBuilderError::NoAllowedSpiffeIds)
+        ));
+        assert!(matches!(
+            SpiffeVerifier::builder()
+                .with_trust_bundle("example.org", roots)
+                .allow_id("spiffe://example.org/**")
+                .allow_id("spiffe://example.org/**/web")
+                .build(),
+            Err(VerifierBuilderError::InvalidSpiffeIdPattern(pattern))
+                if pattern == "spiffe://example.org/**/web"
+        ));
+    }
+
+    #[test]
+    fn test_spiffe_id_parse() {
+        let id = SpiffeId::parse("spiffe://example.org/ns/default/sa/web").unwrap();
+        assert_eq!(id.trust_domain, "example.org");
+        assert_eq!(id.path, vec!["ns", "default", "sa", "web"]);
+
+        let id = SpiffeId::parse("spiffe://example.org").unwrap();
+        assert_eq!(id.trust_domain, "example.org");
+        assert!(id.path.is_empty());
+
+        for invalid in &[
+            "",
+            "spiffe://",
+            "spiffe:///path",
+            "https://example.org/path",
+            "SPIFFE://example.org/path",
+            "spiffe://Example.org/path",
+            "spiffe://example.org:443/path",
+            "spiffe://user@example.org/path",
+            "spiffe://example.org/",
+            "spiffe://example.org/path/",
+            "spiffe://example.org//path",
+            "spiffe://example.org/./path",
+            "spiffe://example.org/../path",
+            "spiffe://example.org/path?query",
+            "spiffe://example.org/path#fragment",
+            "spiffe://example.org/pa%20th",
+            "spiffe://example.org/*",
+        ] {
+            assert!(SpiffeId::parse(invalid).is_none(), "{}", invalid);
+        }
+
+        let long = format!("spiffe://example.org/{}", "a".repeat(MAX_SPIFFE_ID_LEN));
+        assert!(SpiffeId::parse(&long).is_none());
+    }
+
+    #[test]
+    fn test_spiffe_id_pattern() {
+        fn matches(pattern: &str, id: &str) -> bool {
+            SpiffeIdPattern::parse(pattern)
+                .unwrap()
+                .matches(&SpiffeId::parse(id).unwrap())
+        }
+
+        let web = "spiffe://example.org/ns/prod/sa/web";
+        assert!(matches(web, web));
+        assert!(!matches(web, "spiffe://example.org/ns/prod/sa/db"));
+        assert!(!matches(web, "spiffe://example.com/ns/prod/sa/web"));
+        assert!(!matches(web, "spiffe://example.org/ns/prod/sa/web/extra"));
+        assert!(!matches(web, "spiffe://example.org/ns/prod/sa"));
+
+        let any_ns = "spiffe://example.org/ns/*/sa/web";
+        assert!(matches(any_ns, web));
+        assert!(matches(any_ns, "spiffe://example.org/ns/dev/sa/web"));
+        assert!(!matches(any_ns, "spiffe://example.org/ns/dev/sa/db"));
+        assert!(!matches(any_ns, "spiffe://example.org/ns/a/b/sa/web"));
+
+        let any_in_ns = "spiffe://example.org/ns/prod/**";
+        assert!(matches(any_in_ns, web));
+        assert!(matches(any_in_ns, "spiffe://example.org/ns/prod"));
+        assert!(!matches(any_in_ns, "spiffe://example.org/ns/dev/sa/web"));
+
+        let any = "spiffe://example.org/**";
+        assert!(matches(any, web));
+        assert!(matches(any, "spiffe://example.org"));
+        assert!(!matches(any, "spiffe://example.com/ns/prod/sa/web"));
+
+        for invalid in &[
+            "spiffe://*/ns/prod",
+            "spiffe://example.org/**/sa/web",
+            "spiffe://example.org/ns/pr*d",
+            "example.org/ns/prod",
+        ] {
+            assert!(SpiffeIdPattern::parse(invalid).is_none(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use pki_types::CertificateDer;
+use webpki::extract_trust_anchor;
+
+use super::pki_error;
+#[cfg(feature = "logging")]
+use crate::log::debug;
+use crate::{Error, RootCertStore};
+
+/// Where to find trust anchors in PEM files, as used by OpenSSL.
+///
+/// See [`RootCertLocations::linux()`] for the locations OpenSSL uses on Linux,
+/// and [`RootCertStore::load_system()`] to load them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RootCertLocations {
+    /// A file containing any number of PEM-encoded certificates.
+    pub file: Option<PathBuf>,
+    /// Directories of PEM files, each named for the hash of its certificate's
+    /// subject as by `openssl rehash` (for example `5ad8a5d6.0`).  Other files
+    /// in these directories are ignored.
+    pub dirs: Vec<PathBuf>,
+}
+
+/// Trust anchors loaded from PEM files, and the problems found on the way.
+#[derive(Debug)]
+pub struct LoadedRoots {
+    /// The trust anchors which were loaded.
+    ///
+    /// A certificate found in more than one place is only added once.
+    pub roots: RootCertStore,
+    /// Each file which could not be read or parsed, or which contains a
+    /// certificate that cannot be used as a trust anchor, and why.
+    ///
+    /// Valid certificates in a file with errors are still loaded.
+    pub errors: Vec<(PathBuf, Error)>,
+}
+
+#[cfg(target_os = "linux")]
+const LINUX_CERT_FILES: &[&str] = &[
+    // Debian, Ubuntu, Arch, Gentoo
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora, RHEL
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/pki/ca-trust/extracted/pem/tls-ca-bundle.pem",
+    // openSUSE
+    "/etc/ssl/ca-bundle.pem",
+    // Alpine
+    "/etc/ssl/cert.pem",
+];
+
+#[cfg(target_os = "linux")]
+const LINUX_CERT_DIRS: &[&str] = &["/etc/ssl/certs", "/etc/pki/tls/certs"];
+
+impl RootCertLocations {
+    /// The locations OpenSSL uses on Linux.
+    ///
+    /// These are the file named by the `SSL_CERT_FILE` environment variable
+    /// and the directories listed (separated by `:`) in `SSL_CERT_DIR`.  If
+    /// either is not set, the first of the distributions' usual bundle files
+    /// (such as `/etc/ssl/certs/ca-certificates.crt`), or directories (such
+    /// as `/etc/ssl/certs`), which exists is used instead.
+    #[cfg(target_os = "linux")]
+    pub fn linux() -> Self {
+        Self::linux_from(
+            std::env::var_os("SSL_CERT_FILE"),
+            std::env::var_os("SSL_CERT_DIR"),
+        )
+    }
+
+    /// [`RootCertLocations::linux()`], given the values of `SSL_CERT_FILE`
+    /// and `SSL_CERT_DIR`.
+    #[cfg(target_os = "linux")]
+    fn linux_from(
+        cert_file: Option<std::ffi::OsString>,
+        cert_dir: Option<std::ffi::OsString>,
+    ) -> Self {
+        let file = match cert_file {
+            Some(file) => Some(PathBuf::from(file)),
+            None => first_existing(LINUX_CERT_FILES, Path::is_file),
+        };
+
+        let dirs = match cert_dir {
+            Some(dirs) => std::env::split_paths(&dirs)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .collect(),
+            None => first_existing(LINUX_CERT_DIRS, Path::is_dir)
+                .into_iter()
+                .collect(),
+        };
+
+        Self { file, dirs }
+    }
+
+    /// Whether there are no locations to load from.
+    pub fn is_empty(&self) -> bool {
+        self.file.is_none() && self.dirs.is_empty()
+    }
+
+    /// Load the certificates in these locations.
+    ///
+    /// This never fails outright: a file which cannot be read or parsed is
+    /// reported in [`LoadedRoots::errors`], and loading continues with the
+    /// next one.
+    pub fn load(&self) -> LoadedRoots {
+        let mut loader = Loader {
+            roots: RootCertStore::empty(),
+            errors: Vec::new(),
+            seen: BTreeSet::new(),
+        };
+
+        if let Some(file) = &self.file {
+            loader.load_file(file);
+        }
+
+        for dir in &self.dirs {
+            match hashed_cert_files(dir) {
+                Ok(files) => files
+                    .iter()
+                    .for_each(|file| loader.load_file(file)),
+                Err(err) => loader
+                    .errors
+                    .push((dir.clone(), read_error(err))),
+            }
+        }
+
+        debug!(
+            "loaded {} trust anchors, with errors in {} files",
+            loader.roots.len(),
+            loader.errors.len()
+        );
+
+        LoadedRoots {
+            roots: loader.roots,
+            errors: loader.errors,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl RootCertStore {
+    /// Load the trust anchors of the system's certificate store on Linux,
+    /// from [`RootCertLocations::linux()`].
+    ///
+    /// This fails if none of those locations exist.  Otherwise, check
+    /// [`LoadedRoots::errors`] for files which could not be loaded.
+    pub fn load_system() -> Result<LoadedRoots, Error> {
+        let locations = RootCertLocations::linux();
+        match locations.is_empty() {
+            true => Err(Error::General(
+                "no system certificate store found".to_string(),
+            )),
+            false => Ok(locations.load()),
+        }
+    }
+}
+
+struct Loader {
+    roots: RootCertStore,
+    errors: Vec<(PathBuf, Error)>,
+    seen: BTreeSet<Vec<u8>>,
+}
+
+impl Loader {
+    fn load_file(&mut self, path: &Path) {
+        let pem = match fs::read(path) {
+            Ok(pem) => pem,
+            Err(err) => {
+                self.errors
+                    .push((path.to_path_buf(), read_error(err)));
+                return;
+            }
+        };
+
+        let mut found = false;
+        for cert in rustls_pemfile::certs(&mut &pem[..]) {
+            found = true;
+            let cert = match cert {
+                Ok(cert) => cert,
+                Err(err) => {
+                    let err = Error::General(format!("cannot parse PEM: {}", err));
+                    self.errors
+                        .push((path.to_path_buf(), err));
+                    continue;
+                }
+            };
+
+            if let Err(err) = self.add(cert) {
+                self.errors
+                    .push((path.to_path_buf(), err));
+            }
+        }
+
+        if !found {
+            let err = Error::General("no PEM certificates found".to_string());
+            self.errors
+                .push((path.to_path_buf(), err));
+        }
+    }
+
+    fn add(&mut self, cert: CertificateDer<'_>) -> Result<(), Error> {
+        if self.seen.contains(cert.as_ref()) {
+            return Ok(());
+        }
+
+        let anchor = extract_trust_anchor(&cert).map_err(pki_error)?;
+        self.roots.roots.push(anchor.to_owned());
+        self.seen.insert(cert.to_vec());
+        Ok(())
+    }
+}
+
+/// The files in `dir` named as by `openssl rehash`: eight hex digits, a dot,
+/// and a decimal sequence number.  Revocation lists (`.r0`) are skipped.
+fn hashed_cert_files(dir: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_hashed = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split_once('.'))
+            .map_or(false, |(hash, seq)| {
+                hash.len() == 8
+                    && hash
+                        .bytes()
+                        .all(|b| b.is_ascii_hexdigit())
+                    && !seq.is_empty()
+                    && seq.bytes().all(|b| b.is_ascii_digit())
+            });
+        if is_hashed {
+            files.push(path);
+        }
+    }
+
+    // Directory order is arbitrary; make loading deterministic.
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(target_os = "linux")]
+fn first_existing(paths: &[&str], exists: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+    paths
+        .iter()
+        .map(Path::new)
+        .find(|path| exists(path))
+        .map(Path::to_path_buf)
+}
+
+fn read_error(err: io::Error) -> Error {
+    Error::General(format!("cannot read: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CertificateError;
+    use crate::test_util::TempDir;
+
+    use std::env;
+    use std::ffi::OsString;
+
+    const RSA_CA: &[u8] = include_bytes!("../../../test-ca/rsa/ca.cert");
+    const ECDSA_CA: &[u8] = include_bytes!("../../../test-ca/ecdsa/ca.cert");
+    const EDDSA_CA: &[u8] = include_bytes!("../../../test-ca/eddsa/ca.cert");
+
+    const BAD_CERT: &[u8] = b"-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n";
+
+    #[test]
+    fn loads_file_and_hashed_dirs() {
+        let dir = TempDir::new();
+        let bundle = dir.write("bundle.pem", &[RSA_CA, ECDSA_CA].concat());
+
+        let hashed = TempDir::new();
+        hashed.write("0a1b2c3d.0", EDDSA_CA);
+        // Certificates also in the bundle are only added once.
+        hashed.write("0a1b2c3d.1", RSA_CA);
+        // These are not named like certificates, so are ignored.
+        hashed.write("ca-certificates.crt", BAD_CERT);
+        hashed.write("0a1b2c3d.r0", BAD_CERT);
+        hashed.write("0a1b2c3.0", BAD_CERT);
+
+        let loaded = RootCertLocations {
+            file: Some(bundle),
+            dirs: vec![hashed.path().to_path_buf()],
+        }
+        .load();
+        assert_eq!(loaded.roots.len(), 3);
+        assert!(loaded.errors.is_empty());
+    }
+
+    #[test]
+    fn reports_errors_per_file() {
+        let dir = TempDir::new();
+        let mixed = dir.write("1234abcd.0", &[BAD_CERT, RSA_CA].concat());
+        let empty = dir.write("1234abcd.1", b"not a certificate\n");
+        let missing = dir.path().join("missing.pem");
+        let missing_dir = dir.path().join("missing");
+
+        let loaded = RootCertLocations {
+            file: Some(missing.clone()),
+            dirs: vec![dir.path().to_path_buf(), missing_dir.clone()],
+        }
+        .load();
+
+        // The valid certificate alongside the invalid one is still loaded.
+        assert_eq!(loaded.roots.len(), 1);
+
+        let paths = loaded
+            .errors
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec![missing, mixed, empty, missing_dir]);
+
+        assert!(
+            matches!(&loaded.errors[0].1, Error::General(msg) if msg.starts_with("cannot read"))
+        );
+        assert_eq!(
+            loaded.errors[1].1,
+            Error::InvalidCertificate(CertificateError::BadEncoding)
+        );
+        assert_eq!(
+            loaded.errors[2].1,
+            Error::General("no PEM certificates found".into())
+        );
+        assert!(
+            matches!(&loaded.errors[3].1, Error::General(msg) if msg.starts_with("cannot read"))
+        );
+    }
+
+    #[test]
+    fn continues_after_pem_errors() {
+        let dir = TempDir::new();
+        let bad_pem = b"-----BEGIN CERTIFICATE-----\n!!!!\n-----END CERTIFICATE-----\n";
+        let file = dir.write("bundle.pem", &[bad_pem, RSA_CA, bad_pem, ECDSA_CA].concat());
+
+        let loaded = RootCertLocations {
+            file: Some(file.clone()),
+            dirs: vec![],
+        }
+        .load();
+        assert_eq!(loaded.roots.len(), 2);
+        assert_eq!(loaded.errors.len(), 2);
+        for (path, err) in &loaded.errors {
+            assert_eq!(path, &file);
+            assert!(matches!(err, Error::General(msg) if msg.starts_with("cannot parse PEM")));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn linux_locations_honour_environment() {
+        let dir = TempDir::new();
+        let bundle = dir.write("bundle.pem", RSA_CA);
+        let first = TempDir::new();
+        first.write("00000000.0", ECDSA_CA);
+        let second = TempDir::new();
+        second.write("00000000.0", EDDSA_CA);
+
+        let locations = RootCertLocations::linux_from(
+            Some(bundle.clone().into_os_string()),
+            Some(env::join_paths([first.path(), second.path()]).unwrap()),
+        );
+        assert_eq!(
+            locations,
+            RootCertLocations {
+                file: Some(bundle),
+                dirs: vec![first.path().to_path_buf(), second.path().to_path_buf()],
+            }
+        );
+
+        let loaded = locations.load();
+        assert_eq!(loaded.roots.len(), 3);
+        assert!(loaded.errors.is_empty());
+
+        // Empty entries in SSL_CERT_DIR are ignored.
+        let locations = RootCertLocations::linux_from(
+            Some(OsString::from("/nonexistent.pem")),
+            Some(OsString::from(":")),
+        );
+        assert_eq!(
+            locations,
+            RootCertLocations {
+                file: Some(PathBuf::from("/nonexistent.pem")),
+                dirs: vec![],
+            }
+        );
+    }
+}
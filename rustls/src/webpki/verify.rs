@@ -10,8 +10,9 @@ use super::pki_error;
 use crate::client::ServerName;
 use crate::enums::SignatureScheme;
 use crate::error::{CertificateError, Error, PeerMisbehaved};
-
-use crate::verify::{DigitallySignedStruct, HandshakeSignatureValid};
+use crate::msgs::handshake::DelegatedCredential;
+use crate::verify::{DigitallySignedStruct, HandshakeSignatureValid, VerifiedChain};
+use crate::x509;
 
 /// Verify that the end-entity certificate `end_entity` is a valid server cert
 /// and chains to at least one of the trust anchors in the `roots` [RootCertStore].
@@ -40,6 +41,7 @@ pub fn verify_server_cert_signed_by_trust_anchor(
         now,
         supported_algs,
     )
+    .map(|_| ())
 }
 
 /// Verify that the `end_entity` has a name or alternative name matching the `server_name`
@@ -108,7 +110,7 @@ impl WebPkiSupportedAlgorithms {
     }
 
     /// Return the first item in `mapping` that matches `scheme`.
-    fn convert_scheme(
+    pub(crate) fn convert_scheme(
         &self,
         scheme: SignatureScheme,
     ) -> Result<&[&'static dyn SignatureVerificationAlgorithm], Error> {
@@ -257,6 +259,90 @@ pub(crate) fn verify_tls13(
         .map(|_| HandshakeSignatureValid::assertion())
 }
 
+/// Verify a TLS1.3 handshake signature `dss` over `msg` made using the
+/// delegated credential `credential`, as described in RFC 9345 section 4.2.
+///
+/// `end_entity` must already have been validated.
+pub(crate) fn verify_delegated_credential(
+    msg: &[u8],
+    end_entity: &CertificateDer<'_>,
+    credential: &DelegatedCredential,
+    dss: &DigitallySignedStruct,
+    now: UnixTime,
+    supported_schemes: &WebPkiSupportedAlgorithms,
+) -> Result<HandshakeSignatureValid, Error> {
+    // 1. The credential must be currently valid, and not for too long.
+    let expiry = credential
+        .expiry(end_entity)
+        .ok_or(CertificateError::BadEncoding)?;
+    let remaining = expiry
+        .as_secs()
+        .checked_sub(now.as_secs())
+        .ok_or(CertificateError::InvalidDelegatedCredential)?;
+    if remaining > MAX_DELEGATED_CREDENTIAL_VALIDITY_SECS {
+        return Err(CertificateError::InvalidDelegatedCredential.into());
+    }
+
+    // 2. The handshake must be signed using the algorithm the credential names.
+    if dss.scheme != credential.dc_cert_verify_algorithm() {
+        return Err(CertificateError::InvalidDelegatedCredential.into());
+    }
+
+    // 3. The certificate must permit delegation.
+    if x509::cert_extension(end_entity, OID_DELEGATION_USAGE) != Some(&[x509::DER_NULL_TAG, 0]) {
+        return Err(CertificateError::InvalidDelegatedCredential.into());
+    }
+
+    // 4. The credential must be signed by the certificate's key.
+    verify_tls13(
+        &credential.message(end_entity),
+        end_entity,
+        &DigitallySignedStruct::new(credential.algorithm(), credential.signature().to_vec()),
+        supported_schemes,
+    )?;
+
+    // 5. And the handshake by the credential's key.
+    if !dss.scheme.supported_in_tls13() {
+        return Err(PeerMisbehaved::SignedHandshakeWithUnadvertisedSigScheme.into());
+    }
+
+    verify_signature_with_spki(
+        credential.public_key(),
+        dss.scheme,
+        msg,
+        dss.signature(),
+        supported_schemes,
+    )
+    .map(|_| HandshakeSignatureValid::assertion())
+}
+
+/// Verify `signature` over `message` by the public key in the DER-encoded
+/// `SubjectPublicKeyInfo` `spki`, using the algorithm for `scheme` matching
+/// that key.
+pub(crate) fn verify_signature_with_spki(
+    spki: &[u8],
+    scheme: SignatureScheme,
+    message: &[u8],
+    signature: &[u8],
+    supported_schemes: &WebPkiSupportedAlgorithms,
+) -> Result<(), Error> {
+    let (alg_id, public_key) = x509::spki_parts(spki).ok_or(CertificateError::BadEncoding)?;
+    let alg = supported_schemes
+        .convert_scheme(scheme)?
+        .iter()
+        .find(|alg| alg.public_key_alg_id().as_ref() == alg_id)
+        .ok_or(CertificateError::BadSignature)?;
+
+    alg.verify_signature(public_key, message, signature)
+        .map_err(|_| CertificateError::BadSignature.into())
+}
+
+/// The longest a delegated credential may remain valid for: seven days.
+const MAX_DELEGATED_CREDENTIAL_VALIDITY_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// DelegationUsage: 1.3.6.1.4.1.44363.44
+const OID_DELEGATION_USAGE: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0xda, 0x4b, 0x2c];
+
 /// Verify that the end-entity certificate `end_entity` is a valid server cert
 /// and chains to at least one of the trust anchors in the `roots` [RootCertStore].
 ///
@@ -277,20 +363,68 @@ pub(crate) fn verify_server_cert_signed_by_trust_anchor_impl(
     revocation: Option<webpki::RevocationOptions>,
     now: UnixTime,
     supported_algs: &[&dyn SignatureVerificationAlgorithm],
-) -> Result<(), Error> {
-    let result = cert.0.verify_for_usage(
-        supported_algs,
-        &roots.roots,
+) -> Result<VerifiedChain, Error> {
+    verify_cert_chain(
+        cert,
+        roots,
         intermediates,
+        revocation,
         now,
+        supported_algs,
         webpki::KeyUsage::server_auth(),
+    )
+}
+
+/// Verify that `cert` is a valid client cert and chains to one of the trust
+/// anchors in `roots`, returning the chain that was built.
+pub(crate) fn verify_client_cert_signed_by_trust_anchor_impl(
+    cert: &ParsedCertificate,
+    roots: &RootCertStore,
+    intermediates: &[CertificateDer<'_>],
+    revocation: Option<webpki::RevocationOptions>,
+    now: UnixTime,
+    supported_algs: &[&dyn SignatureVerificationAlgorithm],
+) -> Result<VerifiedChain, Error> {
+    verify_cert_chain(
+        cert,
+        roots,
+        intermediates,
         revocation,
-        None,
-    );
-    match result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(pki_error(e)),
-    }
+        now,
+        supported_algs,
+        webpki::KeyUsage::client_auth(),
+    )
+}
+
+fn verify_cert_chain(
+    cert: &ParsedCertificate,
+    roots: &RootCertStore,
+    intermediates: &[CertificateDer<'_>],
+    revocation: Option<webpki::RevocationOptions>,
+    now: UnixTime,
+    supported_algs: &[&dyn SignatureVerificationAlgorithm],
+    usage: webpki::KeyUsage,
+) -> Result<VerifiedChain, Error> {
+    let path = cert
+        .0
+        .verify_for_usage(
+            supported_algs,
+            &roots.roots,
+            intermediates,
+            now,
+            usage,
+            revocation,
+            None,
+        )
+        .map_err(pki_error)?;
+
+    Ok(VerifiedChain::new(
+        CertificateDer::from(path.end_entity().der().to_vec()),
+        path.intermediate_certificates()
+            .map(|cert| CertificateDer::from(cert.der().to_vec()))
+            .collect(),
+        path.anchor().to_owned(),
+    ))
 }
 
 #[cfg(test)]
@@ -2,6 +2,8 @@
 
 use alloc::vec::Vec;
 
+use pki_types::UnixTime;
+
 pub(crate) fn wrap_in_asn1_len(bytes: &mut Vec<u8>) {
     let len = bytes.len();
 
@@ -25,7 +27,355 @@ pub(crate) fn wrap_in_sequence(bytes: &mut Vec<u8>) {
     bytes.insert(0, DER_SEQUENCE_TAG);
 }
 
-const DER_SEQUENCE_TAG: u8 = 0x30;
+/// Returns the contents of the certificate `cert`'s `TBSCertificate`.
+pub(crate) fn tbs_certificate(cert: &[u8]) -> Option<&[u8]> {
+    let mut cert = Der::expect(cert, DER_SEQUENCE_TAG)?;
+    let tbs = Der::read(&mut cert)?;
+    match tbs.tag == DER_SEQUENCE_TAG {
+        true => Some(tbs.value),
+        false => None,
+    }
+}
+
+/// Returns the `SubjectPublicKeyInfo` of the certificate `cert`, including its
+/// outer tag and length.
+pub(crate) fn cert_spki(cert: &[u8]) -> Option<&[u8]> {
+    tbs_field(cert, 5).map(|item| item.whole)
+}
+
+/// Returns the issuer `Name` of the certificate `cert`, including its outer
+/// tag and length.
+pub(crate) fn cert_issuer(cert: &[u8]) -> Option<&[u8]> {
+    tbs_field(cert, 2).map(|item| item.whole)
+}
+
+/// Returns the start of the validity period of the certificate `cert`.
+pub(crate) fn cert_not_before(cert: &[u8]) -> Option<UnixTime> {
+    let mut validity = Der::expect(tbs_field(cert, 3)?.whole, DER_SEQUENCE_TAG)?;
+    decode_time(Der::read(&mut validity)?)
+}
+
+/// Returns the `extnValue` contents of the certificate `cert`'s extension
+/// with the given `oid`, if it has one.
+pub(crate) fn cert_extension<'a>(cert: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+    let extensions = Der::items(tbs_certificate(cert)?)
+        .flatten()
+        .find(|item| item.tag == DER_EXTENSIONS_TAG)?;
+
+    Der::items(Der::expect(extensions.value, DER_SEQUENCE_TAG)?)
+        .flatten()
+        .find_map(|ext| extension_value(ext.value, oid))
+}
+
+/// Returns the `uniformResourceIdentifier` entries of the certificate `cert`'s
+/// subject alternative names, or `None` if its extensions are malformed.
+pub(crate) fn cert_uri_names(cert: &[u8]) -> Option<Vec<&[u8]>> {
+    cert_alt_names(cert, DER_URI_TAG)
+}
+
+/// Returns the `dNSName` entries of the certificate `cert`'s subject
+/// alternative names, or `None` if its extensions are malformed.
+pub(crate) fn cert_dns_names(cert: &[u8]) -> Option<Vec<&[u8]>> {
+    cert_alt_names(cert, DER_DNS_NAME_TAG)
+}
+
+/// Returns the subject alternative names of the certificate `cert` with
+/// the given `GeneralName` tag, or `None` if its extensions are malformed.
+fn cert_alt_names(cert: &[u8], tag: u8) -> Option<Vec<&[u8]>> {
+    let names = match cert_extension(cert, OID_SUBJECT_ALT_NAME) {
+        Some(names) => Der::expect(names, DER_SEQUENCE_TAG)?,
+        None => return Some(Vec::new()),
+    };
+
+    Der::items(names)
+        .filter_map(|name| match name {
+            Some(name) if name.tag != tag => None,
+            Some(name) => Some(Some(name.value)),
+            None => Some(None),
+        })
+        .collect()
+}
+
+/// Returns the `nextUpdate` time of the DER-encoded CRL `crl`, or `Some(None)`
+/// if it does not have one.  Returns `None` if `crl` is malformed.
+pub(crate) fn crl_next_update(crl: &[u8]) -> Option<Option<UnixTime>> {
+    let mut crl = Der::expect(crl, DER_SEQUENCE_TAG)?;
+    let tbs = Der::read(&mut crl)?;
+    if tbs.tag != DER_SEQUENCE_TAG {
+        return None;
+    }
+
+    // version is optional, then signature, issuer, thisUpdate and the
+    // optional nextUpdate.
+    let mut items = Der::items(tbs.value).peekable();
+    if let Some(Some(item)) = items.peek() {
+        if item.tag == DER_INTEGER_TAG {
+            items.next();
+        }
+    }
+
+    decode_time(items.nth(2)??)?;
+    match items.next() {
+        Some(Some(item)) if matches!(item.tag, DER_UTC_TIME_TAG | DER_GENERALIZED_TIME_TAG) => {
+            decode_time(item).map(Some)
+        }
+        Some(None) => None,
+        _ => Some(None),
+    }
+}
+
+/// Returns the `n`th field of the certificate `cert`'s `TBSCertificate`,
+/// not counting the optional version.
+fn tbs_field(cert: &[u8], n: usize) -> Option<Der<'_>> {
+    // version is optional, then serialNumber, signature, issuer, validity,
+    // subject and subjectPublicKeyInfo.
+    let mut items = Der::items(tbs_certificate(cert)?).peekable();
+    if let Some(Some(item)) = items.peek() {
+        if item.tag == DER_VERSION_TAG {
+            items.next();
+        }
+    }
+
+    items.nth(n)?
+}
+
+/// Splits a DER-encoded `SubjectPublicKeyInfo` into its `AlgorithmIdentifier`
+/// contents and `subjectPublicKey`.
+pub(crate) fn spki_parts(spki: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut r = Der::expect(spki, DER_SEQUENCE_TAG)?;
+    let alg_id = Der::read(&mut r)?;
+    let public_key = Der::read(&mut r)?;
+    if alg_id.tag != DER_SEQUENCE_TAG || public_key.tag != DER_BIT_STRING_TAG || !r.is_empty() {
+        return None;
+    }
+
+    match public_key.value.split_first() {
+        Some((0, public_key)) => Some((alg_id.value, public_key)),
+        _ => None,
+    }
+}
+
+/// If the `Extension` contents `ext` has the given `oid`, returns the
+/// contents of its `extnValue`.
+pub(crate) fn extension_value<'a>(mut ext: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+    let r = &mut ext;
+    let id = Der::read(r)?;
+    if id.tag != DER_OID_TAG || id.value != oid {
+        return None;
+    }
+
+    let mut value = Der::read(r)?;
+    if value.tag == DER_BOOLEAN_TAG {
+        value = Der::read(r)?;
+    }
+
+    match value.tag == DER_OCTET_STRING_TAG && r.is_empty() {
+        true => Some(value.value),
+        false => None,
+    }
+}
+
+/// Decodes a `Time`: either a `UTCTime` or a `GeneralizedTime`, in the
+/// restricted forms required by RFC5280 section 4.1.2.5.
+fn decode_time(time: Der<'_>) -> Option<UnixTime> {
+    let (year, rest) = match (time.tag, time.value.len()) {
+        (DER_UTC_TIME_TAG, 13) => {
+            let year = digits(&time.value[..2])?;
+            (
+                if year < 50 { 2000 + year } else { 1900 + year },
+                &time.value[2..],
+            )
+        }
+        (DER_GENERALIZED_TIME_TAG, 15) => (digits(&time.value[..4])?, &time.value[4..]),
+        _ => return None,
+    };
+
+    if rest[10] != b'Z' {
+        return None;
+    }
+
+    let (month, day) = (digits(&rest[0..2])?, digits(&rest[2..4])?);
+    let (hour, minute, second) = (
+        digits(&rest[4..6])?,
+        digits(&rest[6..8])?,
+        digits(&rest[8..10])?,
+    );
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    // Days since the epoch, from the proleptic Gregorian calendar date.
+    // See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+    let year = if month <= 2 { year - 1 } else { year };
+    let (era, year_of_era) = (year / 400, year % 400);
+    let month = (month + 9) % 12;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(UnixTime::since_unix_epoch(core::time::Duration::from_secs(
+        secs,
+    )))
+}
+
+fn digits(bytes: &[u8]) -> Option<u64> {
+    bytes
+        .iter()
+        .try_fold(0, |value, b| match b {
+            b'0'..=b'9' => Some(value * 10 + u64::from(b - b'0')),
+            _ => None,
+        })
+}
+
+/// A DER tag-length-value, borrowed from its encoding.
+#[derive(Clone, Copy)]
+pub(crate) struct Der<'a> {
+    pub(crate) tag: u8,
+    pub(crate) value: &'a [u8],
+    pub(crate) whole: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    /// Read one item from the front of `input`, with single-byte tag and
+    /// definite length.
+    pub(crate) fn read(input: &mut &'a [u8]) -> Option<Self> {
+        let bytes = *input;
+        let (tag, len_byte) = (*bytes.first()?, *bytes.get(1)?);
+        let (header_len, len) = match len_byte {
+            0..=0x7f => (2, len_byte as usize),
+            0x81..=0x83 => {
+                let n = (len_byte & 0x7f) as usize;
+                let len = bytes
+                    .get(2..2 + n)?
+                    .iter()
+                    .fold(0usize, |len, b| len << 8 | *b as usize);
+                (2 + n, len)
+            }
+            _ => return None,
+        };
+
+        let whole = bytes.get(..header_len + len)?;
+        *input = &bytes[whole.len()..];
+        Some(Self {
+            tag,
+            value: &whole[header_len..],
+            whole,
+        })
+    }
+
+    /// Decode all of `bytes` as an item with tag `tag`, returning its value.
+    pub(crate) fn expect(mut bytes: &'a [u8], tag: u8) -> Option<&'a [u8]> {
+        let item = Self::read(&mut bytes)?;
+        match item.tag == tag && bytes.is_empty() {
+            true => Some(item.value),
+            false => None,
+        }
+    }
+
+    /// Iterate over the items in `bytes`; a malformed item yields `None`
+    /// and ends the iteration.
+    pub(crate) fn items(mut bytes: &'a [u8]) -> impl Iterator<Item = Option<Self>> + 'a {
+        let mut failed = false;
+        core::iter::from_fn(move || {
+            if failed || bytes.is_empty() {
+                return None;
+            }
+
+            let item = Self::read(&mut bytes);
+            failed = item.is_none();
+            Some(item)
+        })
+    }
+}
+
+pub(crate) const DER_BOOLEAN_TAG: u8 = 0x01;
+const DER_INTEGER_TAG: u8 = 0x02;
+pub(crate) const DER_BIT_STRING_TAG: u8 = 0x03;
+pub(crate) const DER_OCTET_STRING_TAG: u8 = 0x04;
+pub(crate) const DER_NULL_TAG: u8 = 0x05;
+pub(crate) const DER_OID_TAG: u8 = 0x06;
+const DER_UTC_TIME_TAG: u8 = 0x17;
+const DER_GENERALIZED_TIME_TAG: u8 = 0x18;
+pub(crate) const DER_SEQUENCE_TAG: u8 = 0x30;
+const DER_VERSION_TAG: u8 = 0xa0;
+pub(crate) const DER_EXTENSIONS_TAG: u8 = 0xa3;
+/// `dNSName`, an `[2] IMPLICIT IA5String` `GeneralName`.
+pub(crate) const DER_DNS_NAME_TAG: u8 = 0x82;
+/// `uniformResourceIdentifier`, an `[6] IMPLICIT IA5String` `GeneralName`.
+const DER_URI_TAG: u8 = 0x86;
+
+/// id-ce-subjectAltName, 2.5.29.17
+pub(crate) const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Helpers for building test certificates and other DER structures.
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::*;
+
+    /// Encode `contents` with the given `tag`.
+    pub(crate) fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut bytes = contents.to_vec();
+        wrap_in_asn1_len(&mut bytes);
+        bytes.insert(0, tag);
+        bytes
+    }
+
+    /// Encode a non-critical `Extension` with the given `oid` and `extnValue` contents.
+    pub(crate) fn extension(oid: &[u8], value: &[u8]) -> Vec<u8> {
+        der(
+            DER_SEQUENCE_TAG,
+            &[der(DER_OID_TAG, oid), der(DER_OCTET_STRING_TAG, value)].concat(),
+        )
+    }
+
+    /// Returns `cert` with the encoded `Extension` `ext` appended to its
+    /// extensions, replacing any with the same OID.  The signature is left
+    /// as-is.
+    pub(crate) fn with_extension(cert: &[u8], ext: &[u8]) -> Vec<u8> {
+        let oid = |ext: &[u8]| {
+            Der::items(Der::expect(ext, DER_SEQUENCE_TAG).unwrap())
+                .next()
+                .unwrap()
+                .unwrap()
+                .value
+                .to_vec()
+        };
+
+        let mut cert_items =
+            Der::items(Der::expect(cert, DER_SEQUENCE_TAG).unwrap()).map(Option::unwrap);
+        let tbs = cert_items.next().unwrap();
+
+        let mut new_tbs = Vec::new();
+        for item in Der::items(tbs.value).map(Option::unwrap) {
+            if item.tag != DER_EXTENSIONS_TAG {
+                new_tbs.extend_from_slice(item.whole);
+                continue;
+            }
+
+            let mut exts = Vec::new();
+            for existing in Der::items(Der::expect(item.value, DER_SEQUENCE_TAG).unwrap())
+                .map(Option::unwrap)
+                .filter(|existing| oid(existing.whole) != oid(ext))
+            {
+                exts.extend_from_slice(existing.whole);
+            }
+            exts.extend_from_slice(ext);
+            new_tbs.extend(der(DER_EXTENSIONS_TAG, &der(DER_SEQUENCE_TAG, &exts)));
+        }
+
+        let mut new_cert = der(DER_SEQUENCE_TAG, &new_tbs);
+        for item in cert_items {
+            new_cert.extend_from_slice(item.whole);
+        }
+        der(DER_SEQUENCE_TAG, &new_cert)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -97,4 +447,143 @@ mod tests {
         );
         assert_eq!(val.len(), 0x1000000 + 6);
     }
+
+    #[test]
+    fn test_decode_time() {
+        let time = |tag, value: &[u8]| {
+            decode_time(Der {
+                tag,
+                value,
+                whole: &[],
+            })
+            .map(|time| time.as_secs())
+        };
+
+        assert_eq!(time(DER_UTC_TIME_TAG, b"700101000000Z"), Some(0));
+        assert_eq!(
+            time(DER_UTC_TIME_TAG, b"491231235959Z"),
+            Some(2_524_607_999)
+        );
+        assert_eq!(
+            time(DER_GENERALIZED_TIME_TAG, b"20491231235959Z"),
+            Some(2_524_607_999)
+        );
+        assert_eq!(time(DER_UTC_TIME_TAG, b"491231235959+"), None);
+        assert_eq!(time(DER_UTC_TIME_TAG, b"491331235959Z"), None);
+        assert_eq!(time(DER_UTC_TIME_TAG, b"20491231235959Z"), None);
+        assert_eq!(time(DER_OCTET_STRING_TAG, b"491231235959Z"), None);
+    }
+
+    #[test]
+    fn test_cert_not_before() {
+        let cert = load_cert(include_bytes!("../../test-ca/ecdsa/end.cert"));
+        let not_before = cert_not_before(&cert).unwrap();
+
+        // build-a-pki.sh issues end-entity certificates for 2000 days.
+        let mut validity =
+            Der::expect(tbs_field(&cert, 3).unwrap().whole, DER_SEQUENCE_TAG).unwrap();
+        Der::read(&mut validity).unwrap();
+        let not_after = decode_time(Der::read(&mut validity).unwrap()).unwrap();
+        assert_eq!(
+            not_after.as_secs() - not_before.as_secs(),
+            2000 * 24 * 60 * 60
+        );
+
+        assert!(cert_not_before(&[]).is_none());
+    }
+
+    #[test]
+    fn test_cert_issuer() {
+        let ca = load_cert(include_bytes!("../../test-ca/ecdsa/ca.cert"));
+        let inter = load_cert(include_bytes!("../../test-ca/ecdsa/inter.cert"));
+        let end = load_cert(include_bytes!("../../test-ca/ecdsa/end.cert"));
+
+        let issuer = cert_issuer(&inter).unwrap();
+        assert_eq!(issuer[0], DER_SEQUENCE_TAG);
+        assert_eq!(cert_issuer(&ca), Some(issuer));
+        assert_ne!(cert_issuer(&end), Some(issuer));
+        assert!(cert_issuer(&[]).is_none());
+    }
+
+    #[test]
+    fn test_cert_extension() {
+        // DelegationUsage: 1.3.6.1.4.1.44363.44
+        let oid = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0xda, 0x4b, 0x2c];
+
+        let cert = load_cert(include_bytes!("../../test-ca/ecdsa/end.dc.cert"));
+        assert_eq!(cert_extension(&cert, oid), Some(&[DER_NULL_TAG, 0][..]));
+
+        let cert = load_cert(include_bytes!("../../test-ca/ecdsa/end.cert"));
+        assert_eq!(cert_extension(&cert, oid), None);
+    }
+
+    #[test]
+    fn test_cert_uri_names() {
+        let cert = load_cert(include_bytes!("../../test-ca/ecdsa/client.spiffe.cert"));
+        assert_eq!(
+            cert_uri_names(&cert),
+            Some(vec![&b"spiffe://example.org/ns/default/sa/client"[..]])
+        );
+
+        // DNS and IP address names are ignored
+        let cert = load_cert(include_bytes!("../../test-ca/ecdsa/end.cert"));
+        assert_eq!(cert_uri_names(&cert), Some(vec![]));
+
+        // as are certificates without subject alternative names
+        let cert = load_cert(include_bytes!("../../test-ca/ecdsa/client.cert"));
+        assert_eq!(cert_uri_names(&cert), Some(vec![]));
+    }
+
+    #[test]
+    fn test_cert_dns_names() {
+        let cert = load_cert(include_bytes!("../../test-ca/ecdsa/end.cert"));
+        assert_eq!(
+            cert_dns_names(&cert),
+            Some(vec![
+                &b"testserver.com"[..],
+                b"second.testserver.com",
+                b"localhost"
+            ])
+        );
+
+        let cert = load_cert(include_bytes!("../../test-ca/ecdsa/end.wildcard.cert"));
+        assert_eq!(cert_dns_names(&cert), Some(vec![&b"*.testserver.com"[..]]));
+    }
+
+    #[test]
+    fn test_crl_next_update() {
+        let crl = rustls_pemfile::crls(
+            &mut &include_bytes!("../../test-ca/ecdsa/client.revoked.crl.pem")[..],
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+        // build-a-pki.sh issues CRLs with `-crldays 7`.  thisUpdate follows
+        // the version, signature and issuer.
+        let tbs = Der::items(Der::expect(&crl, DER_SEQUENCE_TAG).unwrap())
+            .next()
+            .unwrap()
+            .unwrap();
+        let this_update = decode_time(
+            Der::items(tbs.value)
+                .nth(3)
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            crl_next_update(&crl).map(|time| time.map(|time| time.as_secs())),
+            Some(Some(this_update.as_secs() + 7 * 24 * 60 * 60))
+        );
+        assert!(crl_next_update(&[]).is_none());
+        assert!(crl_next_update(&crl[..crl.len() - 1]).is_none());
+    }
+
+    fn load_cert(pem: &[u8]) -> Vec<u8> {
+        rustls_pemfile::certs(&mut &pem[..])
+            .next()
+            .unwrap()
+            .unwrap()
+            .to_vec()
+    }
 }
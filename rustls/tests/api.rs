@@ -9,10 +9,14 @@ use std::ops::{Deref, DerefMut};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 
-use pki_types::{CertificateDer, UnixTime};
+use pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
 use rustls::client::{
-    verify_server_cert_signed_by_trust_anchor, ResolvesClientCert, Resumption, WebPkiServerVerifier,
+    verify_server_cert_signed_by_trust_anchor, CertificateTransparencyPolicy, ClientHelloPadding,
+    CtLog, PinningServerVerifier, ResolvesClientCert, Resumption, SpkiPin, SpkiPinSet,
+    WebPkiServerVerifier,
 };
 use rustls::crypto::ring::ALL_CIPHER_SUITES;
 use rustls::internal::msgs::base::Payload;
@@ -20,15 +24,21 @@ use rustls::internal::msgs::codec::Codec;
 use rustls::internal::msgs::enums::AlertLevel;
 use rustls::internal::msgs::handshake::{ClientExtension, HandshakePayload};
 use rustls::internal::msgs::message::{Message, MessagePayload, PlainMessage};
-use rustls::server::{ClientHello, ParsedCertificate, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::server::{
+    ClientHello, ParsedCertificate, ResolvesServerCert, SpiffeVerifier, WebPkiClientVerifier,
+};
 use rustls::SupportedCipherSuite;
+use rustls::VerifiedChainUnavailable;
 use rustls::{
-    sign, AlertDescription, CertificateError, ConnectionCommon, ContentType, Error, KeyLog,
-    PeerIncompatible, PeerMisbehaved, SideData,
+    sign, AlertDescription, CertificateError, ConnectionCommon, ContentType, CrlStore, Error,
+    ExpiredCrlPolicy, HandshakeCounters, KeyLog, NamedGroup, PeerIncompatible, PeerMisbehaved,
+    SideData,
 };
 use rustls::{CipherSuite, ProtocolVersion, SignatureScheme};
 use rustls::{ClientConfig, ClientConnection};
-use rustls::{ConnectionTrafficSecrets, DistinguishedName};
+use rustls::{
+    ConnectionTrafficSecrets, DelegatedCredential, DigitallySignedStruct, DistinguishedName,
+};
 use rustls::{ServerConfig, ServerConnection};
 use rustls::{Stream, StreamOwned};
 
@@ -377,7 +387,7 @@ fn client_can_get_server_cert() {
             do_handshake(&mut client, &mut server);
 
             let certs = client.peer_certificates();
-            assert_eq!(certs, Some(kt.get_chain().as_slice()));
+            assert_eq!(certs, Some(kt.get_chain("end.fullchain").as_slice()));
         }
     }
 }
@@ -444,6 +454,92 @@ fn server_can_get_client_cert_after_resumption() {
     }
 }
 
+#[test]
+fn client_can_get_verified_server_chain() {
+    for kt in ALL_KEY_TYPES.iter() {
+        for version in rustls::ALL_VERSIONS {
+            let client_config = make_client_config_with_versions(*kt, &[version]);
+            let (mut client, mut server) =
+                make_pair_for_configs(client_config, make_server_config(*kt));
+            assert_eq!(
+                client.verified_chain().err(),
+                Some(VerifiedChainUnavailable::NotVerified)
+            );
+            do_handshake(&mut client, &mut server);
+
+            // The server sends its trust anchor too, but it is not part of the path.
+            let sent = kt.get_chain("end.fullchain");
+            let chain = client.verified_chain().unwrap();
+            assert_eq!(chain.end_entity(), &sent[0]);
+            assert_eq!(chain.intermediates(), &sent[1..2]);
+            assert_eq!(chain.trust_anchor(), &get_client_root_store(*kt).roots[0]);
+            assert_eq!(
+                server.verified_chain().err(),
+                Some(VerifiedChainUnavailable::NotVerified)
+            );
+        }
+    }
+}
+
+#[test]
+fn verified_server_chain_is_not_available_after_resumption() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = make_server_config(*kt);
+        for version in rustls::ALL_VERSIONS {
+            let client_config = make_client_config_with_versions(*kt, &[version]);
+            let (mut client, mut server) =
+                make_pair_for_configs(client_config.clone(), server_config.clone());
+            do_handshake(&mut client, &mut server);
+            assert!(client.verified_chain().is_ok());
+
+            let (mut client, mut server) =
+                make_pair_for_configs(client_config.clone(), server_config.clone());
+            do_handshake(&mut client, &mut server);
+            assert!(client.peer_certificates().is_some());
+            assert_eq!(
+                client.verified_chain().err(),
+                Some(VerifiedChainUnavailable::Resumed)
+            );
+        }
+    }
+}
+
+#[test]
+fn verified_server_chain_is_not_available_from_custom_verifier() {
+    let mut client_config = make_client_config(KeyType::Rsa);
+    client_config
+        .dangerous()
+        .set_certificate_verifier(Arc::new(AcceptAnyServerCert));
+    let (mut client, mut server) =
+        make_pair_for_configs(client_config, make_server_config(KeyType::Rsa));
+    do_handshake(&mut client, &mut server);
+    assert!(client.peer_certificates().is_some());
+    assert_eq!(
+        client.verified_chain().err(),
+        Some(VerifiedChainUnavailable::NotReported)
+    );
+}
+
+#[test]
+fn server_can_get_verified_client_chain() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = Arc::new(make_server_config_with_mandatory_client_auth(*kt));
+
+        for version in rustls::ALL_VERSIONS {
+            let client_config = make_client_config_with_versions_with_auth(*kt, &[version]);
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+            do_handshake(&mut client, &mut server);
+
+            let sent = kt.get_client_chain();
+            let chain = server.verified_chain().unwrap();
+            assert_eq!(chain.end_entity(), &sent[0]);
+            assert_eq!(chain.intermediates(), &sent[1..2]);
+            assert_eq!(chain.trust_anchor(), &get_client_root_store(*kt).roots[0]);
+        }
+    }
+}
+
 #[test]
 fn test_config_builders_debug() {
     let b = ServerConfig::builder();
@@ -493,7 +589,7 @@ fn server_allow_any_anonymous_or_authenticated_client() {
         let server_config = ServerConfig::builder()
             .with_safe_defaults()
             .with_client_cert_verifier(client_auth)
-            .with_single_cert(kt.get_chain(), kt.get_key())
+            .with_single_cert(kt.get_chain("end.fullchain"), kt.get_key())
             .unwrap();
         let server_config = Arc::new(server_config);
 
@@ -774,6 +870,9 @@ struct ServerCheckCertResolve {
     expected_sigalgs: Option<Vec<SignatureScheme>>,
     expected_alpn: Option<Vec<Vec<u8>>>,
     expected_cipher_suites: Option<Vec<CipherSuite>>,
+    /// `Some(None)` expects the client hello to have no
+    /// certificate_authorities extension.
+    expected_certificate_authorities: Option<Option<Vec<DistinguishedName>>>,
 }
 
 impl ResolvesServerCert for ServerCheckCertResolve {
@@ -824,6 +923,24 @@ impl ResolvesServerCert for ServerCheckCertResolve {
             );
         }
 
+        if let Some(expected_certificate_authorities) = &self.expected_certificate_authorities {
+            assert_eq!(
+                expected_certificate_authorities
+                    .as_ref()
+                    .map(|cas| cas
+                        .iter()
+                        .map(|dn| dn.as_ref())
+                        .collect::<Vec<_>>()),
+                client_hello
+                    .certificate_authorities()
+                    .map(|cas| cas
+                        .iter()
+                        .map(|dn| dn.as_ref())
+                        .collect::<Vec<_>>()),
+                "unexpected certificate authorities"
+            );
+        }
+
         None
     }
 }
@@ -870,6 +987,55 @@ fn server_cert_resolve_with_alpn() {
     }
 }
 
+#[test]
+fn server_cert_resolve_with_certificate_authorities() {
+    for kt in ALL_KEY_TYPES.iter() {
+        for (version, expected) in [
+            (
+                &rustls::version::TLS13,
+                Some(get_client_root_store(*kt).subjects()),
+            ),
+            #[cfg(feature = "tls12")]
+            (&rustls::version::TLS12, None),
+        ] {
+            let mut client_config = make_client_config_with_versions(*kt, &[version]);
+            client_config.certificate_authorities = get_client_root_store(*kt).subjects();
+
+            let mut server_config = make_server_config(*kt);
+            server_config.cert_resolver = Arc::new(ServerCheckCertResolve {
+                expected_certificate_authorities: Some(expected),
+                ..Default::default()
+            });
+
+            let mut client =
+                ClientConnection::new(Arc::new(client_config), server_name("sni-value")).unwrap();
+            let mut server = ServerConnection::new(Arc::new(server_config)).unwrap();
+
+            let err = do_handshake_until_error(&mut client, &mut server);
+            assert!(err.is_err());
+        }
+    }
+}
+
+#[test]
+fn client_does_not_send_empty_certificate_authorities() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let client_config = make_client_config(*kt);
+        let mut server_config = make_server_config(*kt);
+        server_config.cert_resolver = Arc::new(ServerCheckCertResolve {
+            expected_certificate_authorities: Some(None),
+            ..Default::default()
+        });
+
+        let mut client =
+            ClientConnection::new(Arc::new(client_config), server_name("sni-value")).unwrap();
+        let mut server = ServerConnection::new(Arc::new(server_config)).unwrap();
+
+        let err = do_handshake_until_error(&mut client, &mut server);
+        assert!(err.is_err());
+    }
+}
+
 #[test]
 fn client_trims_terminating_dot() {
     for kt in ALL_KEY_TYPES.iter() {
@@ -1087,6 +1253,56 @@ fn client_check_server_certificate_ee_revoked() {
     }
 }
 
+#[test]
+fn client_check_server_certificate_with_expired_crl_store() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = Arc::new(make_server_config(*kt));
+
+        // The test CRLs are past their next update time, which is an error by default.
+        let store = Arc::new(CrlStore::new([kt.end_entity_crl()]).unwrap());
+        let client_config = Arc::new(make_client_config_with_verifier(
+            &[&rustls::version::TLS13],
+            WebPkiServerVerifier::builder(get_client_root_store(*kt))
+                .with_crl_store(Arc::clone(&store))
+                .only_check_end_entity_revocation(),
+        ));
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                CertificateError::ExpiredRevocationList
+            )))
+        );
+
+        // The intermediate's CRL is not used when only the end-entity certificate's
+        // revocation status is checked, so its expiry does not matter.
+        store
+            .replace([kt.intermediate_crl()])
+            .unwrap();
+        let client_config = Arc::new(make_client_config_with_verifier(
+            &[&rustls::version::TLS13],
+            WebPkiServerVerifier::builder(get_client_root_store(*kt))
+                .with_crl_store(Arc::clone(&store))
+                .only_check_end_entity_revocation()
+                .allow_unknown_revocation_status(),
+        ));
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert!(do_handshake_until_error(&mut client, &mut server).is_ok());
+
+        // Once the expired CRL is removed, the server certificate's revocation status
+        // is unknown, which this verifier allows.
+        store.replace([]).unwrap();
+        let client_config = Arc::new(make_client_config_with_verifier(
+            &[&rustls::version::TLS13],
+            WebPkiServerVerifier::builder(get_client_root_store(*kt))
+                .with_crl_store(Arc::clone(&store))
+                .allow_unknown_revocation_status(),
+        ));
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert!(do_handshake_until_error(&mut client, &mut server).is_ok());
+    }
+}
+
 #[test]
 fn client_check_server_certificate_ee_unknown_revocation() {
     for kt in ALL_KEY_TYPES.iter() {
@@ -1185,13 +1401,180 @@ fn client_check_server_certificate_intermediate_revoked() {
     }
 }
 
+/// A CT log whose SCTs are produced by the test itself.
+struct TestCtLog {
+    key: ring::signature::EcdsaKeyPair,
+    log: CtLog,
+}
+
+impl TestCtLog {
+    fn new(operator: &str) -> Self {
+        use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+
+        // SubjectPublicKeyInfo for an uncompressed P-256 public key.
+        let mut spki = vec![
+            0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06,
+            0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+        ];
+        spki.extend_from_slice(key.public_key().as_ref());
+
+        Self {
+            key,
+            log: CtLog {
+                operator: operator.into(),
+                key: spki,
+            },
+        }
+    }
+
+    /// Produce a TLS-encoded SCT over the X.509 certificate `cert`.
+    fn sign(&self, cert: &CertificateDer<'_>) -> Vec<u8> {
+        let timestamp = (UnixTime::now().as_secs() - 60) * 1000;
+
+        let mut signed = vec![0, 0]; // v1, certificate_timestamp
+        signed.extend_from_slice(&timestamp.to_be_bytes());
+        signed.extend_from_slice(&[0, 0]); // x509_entry
+        signed.extend_from_slice(&(cert.len() as u32).to_be_bytes()[1..]);
+        signed.extend_from_slice(cert);
+        signed.extend_from_slice(&[0, 0]); // no extensions
+        let sig = self
+            .key
+            .sign(&ring::rand::SystemRandom::new(), &signed)
+            .unwrap();
+
+        let log_id = ring::digest::digest(&ring::digest::SHA256, &self.log.key);
+        let mut sct = vec![0]; // v1
+        sct.extend_from_slice(log_id.as_ref());
+        sct.extend_from_slice(&timestamp.to_be_bytes());
+        sct.extend_from_slice(&[0, 0]); // no extensions
+        sct.extend_from_slice(
+            &SignatureScheme::ECDSA_NISTP256_SHA256
+                .get_u16()
+                .to_be_bytes(),
+        );
+        sct.extend_from_slice(&(sig.as_ref().len() as u16).to_be_bytes());
+        sct.extend_from_slice(sig.as_ref());
+        sct
+    }
+}
+
+fn encode_sct_list(scts: &[Vec<u8>]) -> Vec<u8> {
+    let mut list = Vec::new();
+    for sct in scts {
+        list.extend_from_slice(&(sct.len() as u16).to_be_bytes());
+        list.extend_from_slice(sct);
+    }
+    let mut encoded = (list.len() as u16)
+        .to_be_bytes()
+        .to_vec();
+    encoded.extend(list);
+    encoded
+}
+
+fn make_server_config_with_scts(kt: KeyType, scts: &[Vec<u8>]) -> ServerConfig {
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert_with_ocsp_and_sct(
+            kt.get_chain("end.fullchain"),
+            kt.get_key(),
+            vec![],
+            encode_sct_list(scts),
+        )
+        .unwrap()
+}
+
+#[test]
+fn client_verifies_stapled_scts() {
+    let (log_a, log_b) = (TestCtLog::new("a"), TestCtLog::new("b"));
+    let policy = Arc::new(CertificateTransparencyPolicy::new(vec![
+        log_a.log.clone(),
+        log_b.log.clone(),
+    ]));
+
+    for kt in ALL_KEY_TYPES.iter() {
+        let ee = &kt.get_chain("end.fullchain")[0];
+        let server_config = Arc::new(make_server_config_with_scts(
+            *kt,
+            &[log_a.sign(ee), log_b.sign(ee)],
+        ));
+
+        for version in rustls::ALL_VERSIONS {
+            let mut client_config = make_client_config_with_versions(*kt, &[version]);
+            client_config.ct_policy = Some(Arc::clone(&policy));
+
+            let mut client =
+                ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+            let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+            do_handshake(&mut client, &mut server);
+        }
+    }
+}
+
+#[test]
+fn client_rejects_insufficient_scts() {
+    let (log_a, log_b) = (TestCtLog::new("a"), TestCtLog::new("a"));
+    let policy = Arc::new(CertificateTransparencyPolicy::new(vec![
+        log_a.log.clone(),
+        log_b.log.clone(),
+    ]));
+
+    for kt in ALL_KEY_TYPES.iter() {
+        let ee = &kt.get_chain("end.fullchain")[0];
+        // both logs are run by the same operator, and the default policy requires two
+        let server_config = Arc::new(make_server_config_with_scts(
+            *kt,
+            &[log_a.sign(ee), log_b.sign(ee)],
+        ));
+
+        for version in rustls::ALL_VERSIONS {
+            let mut client_config = make_client_config_with_versions(*kt, &[version]);
+            client_config.ct_policy = Some(Arc::clone(&policy));
+
+            let mut client =
+                ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+            let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+            assert_eq!(
+                do_handshake_until_error(&mut client, &mut server),
+                Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                    CertificateError::CertificateTransparencyPolicyNotMet
+                )))
+            );
+        }
+    }
+}
+
+#[test]
+fn server_only_staples_scts_when_requested() {
+    let log = TestCtLog::new("a");
+
+    for kt in ALL_KEY_TYPES.iter() {
+        let ee = &kt.get_chain("end.fullchain")[0];
+        let server_config = Arc::new(make_server_config_with_scts(*kt, &[log.sign(ee)]));
+
+        for version in rustls::ALL_VERSIONS {
+            // the client rejects unsolicited SCTs, so this fails if they are sent
+            let client_config = make_client_config_with_versions(*kt, &[version]);
+            let mut client =
+                ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+            let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+            do_handshake(&mut client, &mut server);
+        }
+    }
+}
+
 /// Simple smoke-test of the webpki verify_server_cert_signed_by_trust_anchor helper API.
 /// This public API is intended to be used by consumers implementing their own verifier and
 /// so isn't used by the other existing verifier tests.
 #[test]
 fn client_check_server_certificate_helper_api() {
     for kt in ALL_KEY_TYPES.iter() {
-        let chain = kt.get_chain();
+        let chain = kt.get_chain("end.fullchain");
         let correct_roots = get_client_root_store(*kt);
         let incorrect_roots = get_client_root_store(match kt {
             KeyType::Rsa => KeyType::Ecdsa,
@@ -1398,6 +1781,59 @@ fn client_auth_works() {
     }
 }
 
+#[test]
+fn client_auth_chooses_identity_by_ca_hints() {
+    let mut resolver = rustls::client::ResolvesClientCertUsingCaHints::new();
+    for kt in ALL_KEY_TYPES.iter() {
+        let key = sign::any_supported_type(&kt.get_client_key()).unwrap();
+        resolver
+            .add(sign::CertifiedKey::new(kt.get_client_chain(), key))
+            .unwrap();
+    }
+    let resolver = Arc::new(resolver);
+
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = Arc::new(make_server_config_with_mandatory_client_auth(*kt));
+
+        for version in rustls::ALL_VERSIONS {
+            let mut client_config = make_client_config_with_versions(*kt, &[version]);
+            client_config.client_auth_cert_resolver = resolver.clone();
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+            do_handshake(&mut client, &mut server);
+            assert_eq!(
+                server.peer_certificates().unwrap()[0],
+                kt.get_client_chain()[0]
+            );
+        }
+    }
+}
+
+#[test]
+fn client_auth_uses_default_identity_without_ca_hints() {
+    let kt = KeyType::Ecdsa;
+    let key = sign::any_supported_type(&kt.get_client_key()).unwrap();
+    let mut resolver = rustls::client::ResolvesClientCertUsingCaHints::new();
+    resolver.set_default(Some(sign::CertifiedKey::new(kt.get_client_chain(), key)));
+    let resolver = Arc::new(resolver);
+
+    let verifier =
+        WebPkiClientVerifier::builder(get_client_root_store(kt)).clear_root_hint_subjects();
+    let server_config = Arc::new(make_server_config_with_client_verifier(kt, verifier));
+
+    for version in rustls::ALL_VERSIONS {
+        let mut client_config = make_client_config_with_versions(kt, &[version]);
+        client_config.client_auth_cert_resolver = resolver.clone();
+        let (mut client, mut server) =
+            make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+        do_handshake(&mut client, &mut server);
+        assert_eq!(
+            server.peer_certificates().unwrap()[0],
+            kt.get_client_chain()[0]
+        );
+    }
+}
+
 #[test]
 fn client_mandatory_auth_client_revocation_works() {
     for kt in ALL_KEY_TYPES.iter() {
@@ -1470,6 +1906,58 @@ fn client_mandatory_auth_client_revocation_works() {
     }
 }
 
+#[test]
+fn client_mandatory_auth_crl_store_can_be_replaced() {
+    for kt in ALL_KEY_TYPES.iter() {
+        // The test CRLs are past their next update time, so allow them to be used.
+        let store = Arc::new(
+            CrlStore::new([kt.client_crl()])
+                .unwrap()
+                .with_expired_crl_policy(ExpiredCrlPolicy::Allow),
+        );
+        let verifier_builder = WebPkiClientVerifier::builder(get_client_root_store(*kt))
+            .with_crl_store(Arc::clone(&store))
+            .only_check_end_entity_revocation();
+        let server_config = Arc::new(make_server_config_with_client_verifier(
+            *kt,
+            verifier_builder,
+        ));
+        let client_config = Arc::new(make_client_config_with_auth(*kt));
+
+        // The store's CRL says the client certificate is revoked.
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                CertificateError::Revoked
+            )))
+        );
+
+        // Once it is replaced with one that doesn't cover the client certificate,
+        // its status is unknown.
+        store
+            .replace([kt.intermediate_crl()])
+            .unwrap();
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert!(matches!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                CertificateError::UnknownRevocationStatus
+            )))
+        ));
+
+        // An empty store can't vouch for the client certificate either.
+        store.replace([]).unwrap();
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert!(matches!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                CertificateError::UnknownRevocationStatus
+            )))
+        ));
+    }
+}
+
 #[test]
 fn client_mandatory_auth_intermediate_revocation_works() {
     for kt in ALL_KEY_TYPES.iter() {
@@ -1542,23 +2030,357 @@ fn client_optional_auth_client_revocation_works() {
     }
 }
 
-#[test]
-fn client_error_is_sticky() {
-    let (mut client, _) = make_pair(KeyType::Rsa);
-    client
-        .read_tls(&mut b"\x16\x03\x03\x00\x08\x0f\x00\x00\x04junk".as_ref())
+fn spiffe_server_config(kt: KeyType, trust_domain: &str, allowed: &str) -> Arc<ServerConfig> {
+    let verifier = SpiffeVerifier::builder()
+        .with_trust_bundle(trust_domain, get_client_root_store(kt))
+        .allow_id(allowed)
+        .build()
         .unwrap();
-    let mut err = client.process_new_packets();
-    assert!(err.is_err());
-    err = client.process_new_packets();
-    assert!(err.is_err());
+
+    Arc::new(
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(kt.get_chain("end.fullchain"), kt.get_key())
+            .unwrap(),
+    )
 }
 
-#[test]
-fn server_error_is_sticky() {
-    let (_, mut server) = make_pair(KeyType::Rsa);
-    server
-        .read_tls(&mut b"\x16\x03\x03\x00\x08\x0f\x00\x00\x04junk".as_ref())
+fn client_config_with_client_chain(
+    kt: KeyType,
+    chain: Vec<CertificateDer<'static>>,
+) -> Arc<ClientConfig> {
+    Arc::new(
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(get_client_root_store(kt))
+            .with_client_auth_cert(chain, kt.get_client_key())
+            .unwrap(),
+    )
+}
+
+#[test]
+fn spiffe_client_verifier() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let svid_client =
+            client_config_with_client_chain(*kt, kt.get_chain("client.spiffe.fullchain"));
+        let plain_client = client_config_with_client_chain(*kt, kt.get_client_chain());
+
+        // An allowed SPIFFE ID is accepted
+        let server_config =
+            spiffe_server_config(*kt, "example.org", "spiffe://example.org/ns/*/sa/client");
+        let (mut client, mut server) = make_pair_for_arc_configs(&svid_client, &server_config);
+        do_handshake_until_error(&mut client, &mut server).unwrap();
+
+        // A certificate without a SPIFFE ID is rejected
+        let (mut client, mut server) = make_pair_for_arc_configs(&plain_client, &server_config);
+        assert_eq!(
+            do_handshake_until_both_error(&mut client, &mut server),
+            Err(vec![
+                ErrorFromPeer::Server(Error::InvalidCertificate(CertificateError::InvalidSpiffeId)),
+                ErrorFromPeer::Client(Error::AlertReceived(AlertDescription::BadCertificate)),
+            ])
+        );
+
+        // A SPIFFE ID which is not allowed is rejected
+        let server_config =
+            spiffe_server_config(*kt, "example.org", "spiffe://example.org/ns/*/sa/web");
+        let (mut client, mut server) = make_pair_for_arc_configs(&svid_client, &server_config);
+        assert_eq!(
+            do_handshake_until_both_error(&mut client, &mut server),
+            Err(vec![
+                ErrorFromPeer::Server(Error::InvalidCertificate(
+                    CertificateError::SpiffeIdNotAllowed
+                )),
+                ErrorFromPeer::Client(Error::AlertReceived(AlertDescription::AccessDenied)),
+            ])
+        );
+
+        // As is one from a trust domain without a bundle
+        let server_config = spiffe_server_config(*kt, "example.com", "spiffe://example.com/**");
+        let (mut client, mut server) = make_pair_for_arc_configs(&svid_client, &server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+                CertificateError::UnknownIssuer
+            )))
+        );
+    }
+}
+
+#[test]
+fn spiffe_client_verifier_rejects_other_trust_bundle() {
+    // The client's SPIFFE ID is in `example.org`, but that trust domain's
+    // bundle does not contain the client's root.
+    let verifier = SpiffeVerifier::builder()
+        .with_trust_bundle("example.org", get_client_root_store(KeyType::Ecdsa))
+        .allow_id("spiffe://example.org/**")
+        .build()
+        .unwrap();
+    let server_config = Arc::new(
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                KeyType::Rsa.get_chain("end.fullchain"),
+                KeyType::Rsa.get_key(),
+            )
+            .unwrap(),
+    );
+    let client_config = client_config_with_client_chain(
+        KeyType::Rsa,
+        KeyType::Rsa.get_chain("client.spiffe.fullchain"),
+    );
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    assert_eq!(
+        do_handshake_until_error(&mut client, &mut server),
+        Err(ErrorFromPeer::Server(Error::InvalidCertificate(
+            CertificateError::UnknownIssuer
+        )))
+    );
+}
+
+#[test]
+fn spiffe_server_verifier() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let verifier = SpiffeVerifier::builder()
+            .with_trust_bundle("example.org", get_client_root_store(*kt))
+            .allow_id("spiffe://example.org/ns/default/sa/server")
+            .build()
+            .unwrap();
+        let client_config = Arc::new(
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .dangerous()
+                .with_custom_certificate_verifier(verifier)
+                .with_no_client_auth(),
+        );
+
+        // The server name is not checked
+        let server_config = Arc::new(
+            ServerConfig::builder()
+                .with_safe_defaults()
+                .with_no_client_auth()
+                .with_single_cert(kt.get_chain("end.spiffe.fullchain"), kt.get_key())
+                .unwrap(),
+        );
+        let mut client =
+            ClientConnection::new(client_config.clone(), server_name("unrelated.example")).unwrap();
+        let mut server = ServerConnection::new(server_config).unwrap();
+        do_handshake_until_error(&mut client, &mut server).unwrap();
+
+        // A server without a SPIFFE ID is rejected
+        let server_config = Arc::new(make_server_config(*kt));
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                CertificateError::InvalidSpiffeId
+            )))
+        );
+    }
+}
+
+fn pinning_verifier(kt: KeyType) -> PinningServerVerifier {
+    PinningServerVerifier::new(
+        WebPkiServerVerifier::builder(get_client_root_store(kt))
+            .build()
+            .unwrap(),
+    )
+}
+
+fn pinning_handshake(
+    kt: KeyType,
+    verifier: PinningServerVerifier,
+    name: &'static str,
+) -> Result<(), ErrorFromPeer> {
+    let client_config = Arc::new(
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth(),
+    );
+    let server_config = Arc::new(make_server_config(kt));
+    let mut client = ClientConnection::new(client_config, server_name(name)).unwrap();
+    let mut server = ServerConnection::new(server_config).unwrap();
+    do_handshake_until_error(&mut client, &mut server)
+}
+
+#[test]
+fn pinning_verifier_accepts_pin_anywhere_in_chain() {
+    for kt in ALL_KEY_TYPES.iter() {
+        // end-entity, intermediate, and trust anchor
+        for cert in kt.get_chain("end.fullchain") {
+            let verifier = pinning_verifier(*kt);
+            let pins = SpkiPinSet::new([verifier
+                .pin_for_certificate(&cert)
+                .unwrap()]);
+            let verifier = verifier.with_pins(pins);
+            assert_eq!(pinning_handshake(*kt, verifier, "localhost"), Ok(()));
+        }
+    }
+}
+
+#[test]
+fn pinning_verifier_rejects_mismatch() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let verifier = pinning_verifier(*kt).with_pins(SpkiPinSet::new([SpkiPin::from([0; 32])]));
+        assert_eq!(
+            pinning_handshake(*kt, verifier, "localhost"),
+            Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                CertificateError::PinMismatch
+            )))
+        );
+    }
+}
+
+#[test]
+fn pinning_verifier_ignores_certs_not_in_verified_chain() {
+    for kt in ALL_KEY_TYPES.iter() {
+        // The server sends an extra, pinned, certificate which is not part of its path.
+        let unrelated = kt.get_client_chain().remove(0);
+        let mut chain = kt.get_chain("end.fullchain");
+        chain.push(unrelated.clone());
+        let server_config = Arc::new(
+            ServerConfig::builder()
+                .with_safe_defaults()
+                .with_no_client_auth()
+                .with_single_cert(chain, kt.get_key())
+                .unwrap(),
+        );
+
+        let verifier = pinning_verifier(*kt);
+        let pin = verifier
+            .pin_for_certificate(&unrelated)
+            .unwrap();
+        let verifier = verifier.with_pins(SpkiPinSet::new([pin]));
+        let client_config = Arc::new(
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(verifier))
+                .with_no_client_auth(),
+        );
+
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                CertificateError::PinMismatch
+            )))
+        );
+    }
+}
+
+#[test]
+fn pinning_verifier_accepts_backup_pins() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let verifier = pinning_verifier(*kt);
+        let pin = verifier
+            .pin_for_certificate(&kt.get_chain("end.fullchain")[1])
+            .unwrap();
+        let pins = SpkiPinSet::new([SpkiPin::from([0; 32])]).with_backup_pins([pin]);
+        let verifier = verifier.with_pins(pins);
+        assert_eq!(pinning_handshake(*kt, verifier, "localhost"), Ok(()));
+    }
+}
+
+#[test]
+fn pinning_verifier_host_pins() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let make_verifier = || {
+            let verifier = pinning_verifier(*kt);
+            let pin = verifier
+                .pin_for_certificate(&kt.get_chain("end.fullchain")[1])
+                .unwrap();
+            verifier
+                .with_pins(SpkiPinSet::new([SpkiPin::from([0; 32])]))
+                .with_host_pins(server_name("testserver.com"), SpkiPinSet::new([pin]))
+        };
+
+        assert_eq!(
+            pinning_handshake(*kt, make_verifier(), "testserver.com"),
+            Ok(())
+        );
+        assert_eq!(
+            pinning_handshake(*kt, make_verifier(), "localhost"),
+            Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                CertificateError::PinMismatch
+            )))
+        );
+
+        // Only hosts with pins are pinned
+        let verifier = pinning_verifier(*kt).with_host_pins(
+            server_name("testserver.com"),
+            SpkiPinSet::new([SpkiPin::from([0; 32])]),
+        );
+        assert_eq!(pinning_handshake(*kt, verifier, "localhost"), Ok(()));
+    }
+}
+
+#[test]
+fn pinning_verifier_host_pins_ignore_case_and_trailing_dot() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let verifier = pinning_verifier(*kt);
+        let pin = verifier
+            .pin_for_certificate(&kt.get_chain("end.fullchain")[1])
+            .unwrap();
+        let verifier = verifier
+            .with_pins(SpkiPinSet::new([SpkiPin::from([0; 32])]))
+            .with_host_pins(server_name("testserver.com"), SpkiPinSet::new([pin]));
+        assert_eq!(pinning_handshake(*kt, verifier, "TestServer.COM"), Ok(()));
+
+        for (pinned, connect) in [
+            ("testserver.com", "TestServer.COM"),
+            ("TestServer.com.", "testserver.com"),
+        ] {
+            let verifier = pinning_verifier(*kt).with_host_pins(
+                server_name(pinned),
+                SpkiPinSet::new([SpkiPin::from([0; 32])]),
+            );
+            assert_eq!(
+                pinning_handshake(*kt, verifier, connect),
+                Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                    CertificateError::PinMismatch
+                )))
+            );
+        }
+    }
+}
+
+#[test]
+fn pinning_verifier_requires_verified_chain() {
+    let kt = KeyType::Rsa;
+    let verifier = PinningServerVerifier::new(Arc::new(AcceptAnyServerCert));
+    let pin = verifier
+        .pin_for_certificate(&kt.get_chain("end.fullchain")[0])
+        .unwrap();
+    let verifier = verifier.with_pins(SpkiPinSet::new([pin]));
+    assert!(matches!(
+        pinning_handshake(kt, verifier, "localhost"),
+        Err(ErrorFromPeer::Client(Error::General(_)))
+    ));
+}
+
+#[test]
+fn client_error_is_sticky() {
+    let (mut client, _) = make_pair(KeyType::Rsa);
+    client
+        .read_tls(&mut b"\x16\x03\x03\x00\x08\x0f\x00\x00\x04junk".as_ref())
+        .unwrap();
+    let mut err = client.process_new_packets();
+    assert!(err.is_err());
+    err = client.process_new_packets();
+    assert!(err.is_err());
+}
+
+#[test]
+fn server_error_is_sticky() {
+    let (_, mut server) = make_pair(KeyType::Rsa);
+    server
+        .read_tls(&mut b"\x16\x03\x03\x00\x08\x0f\x00\x00\x04junk".as_ref())
         .unwrap();
     let mut err = server.process_new_packets();
     assert!(err.is_err());
@@ -2523,13 +3345,13 @@ fn server_exposes_offered_sni_even_if_resolver_fails() {
 #[test]
 fn sni_resolver_works() {
     let kt = KeyType::Rsa;
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let resolver = rustls::server::ResolvesServerCertUsingSni::new();
     let signing_key = sign::RsaSigningKey::new(&kt.get_key()).unwrap();
     let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
     resolver
         .add(
             "localhost",
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone()),
+            sign::CertifiedKey::new(kt.get_chain("end.fullchain"), signing_key.clone()),
         )
         .unwrap();
 
@@ -2561,7 +3383,7 @@ fn sni_resolver_works() {
 #[test]
 fn sni_resolver_rejects_wrong_names() {
     let kt = KeyType::Rsa;
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let resolver = rustls::server::ResolvesServerCertUsingSni::new();
     let signing_key = sign::RsaSigningKey::new(&kt.get_key()).unwrap();
     let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
 
@@ -2569,21 +3391,21 @@ fn sni_resolver_rejects_wrong_names() {
         Ok(()),
         resolver.add(
             "localhost",
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone())
+            sign::CertifiedKey::new(kt.get_chain("end.fullchain"), signing_key.clone())
         )
     );
     assert_eq!(
         Err(Error::InvalidCertificate(CertificateError::NotValidForName)),
         resolver.add(
             "not-localhost",
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone())
+            sign::CertifiedKey::new(kt.get_chain("end.fullchain"), signing_key.clone())
         )
     );
     assert_eq!(
         Err(Error::General("Bad DNS name".into())),
         resolver.add(
             "not ascii 🦀",
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone())
+            sign::CertifiedKey::new(kt.get_chain("end.fullchain"), signing_key.clone())
         )
     );
 }
@@ -2591,7 +3413,7 @@ fn sni_resolver_rejects_wrong_names() {
 #[test]
 fn sni_resolver_lower_cases_configured_names() {
     let kt = KeyType::Rsa;
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let resolver = rustls::server::ResolvesServerCertUsingSni::new();
     let signing_key = sign::RsaSigningKey::new(&kt.get_key()).unwrap();
     let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
 
@@ -2599,7 +3421,7 @@ fn sni_resolver_lower_cases_configured_names() {
         Ok(()),
         resolver.add(
             "LOCALHOST",
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone())
+            sign::CertifiedKey::new(kt.get_chain("end.fullchain"), signing_key.clone())
         )
     );
 
@@ -2618,7 +3440,7 @@ fn sni_resolver_lower_cases_configured_names() {
 fn sni_resolver_lower_cases_queried_names() {
     // actually, the handshake parser does this, but the effect is the same.
     let kt = KeyType::Rsa;
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let resolver = rustls::server::ResolvesServerCertUsingSni::new();
     let signing_key = sign::RsaSigningKey::new(&kt.get_key()).unwrap();
     let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
 
@@ -2626,7 +3448,7 @@ fn sni_resolver_lower_cases_queried_names() {
         Ok(()),
         resolver.add(
             "localhost",
-            sign::CertifiedKey::new(kt.get_chain(), signing_key.clone())
+            sign::CertifiedKey::new(kt.get_chain("end.fullchain"), signing_key.clone())
         )
     );
 
@@ -2644,7 +3466,7 @@ fn sni_resolver_lower_cases_queried_names() {
 #[test]
 fn sni_resolver_rejects_bad_certs() {
     let kt = KeyType::Rsa;
-    let mut resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let resolver = rustls::server::ResolvesServerCertUsingSni::new();
     let signing_key = sign::RsaSigningKey::new(&kt.get_key()).unwrap();
     let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
 
@@ -2666,85 +3488,316 @@ fn sni_resolver_rejects_bad_certs() {
     );
 }
 
-fn do_exporter_test(client_config: ClientConfig, server_config: ServerConfig) {
-    let mut client_secret = [0u8; 64];
-    let mut server_secret = [0u8; 64];
-
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
-
-    assert_eq!(
-        Err(Error::HandshakeNotComplete),
-        client.export_keying_material(&mut client_secret, b"label", Some(b"context"))
-    );
-    assert_eq!(
-        Err(Error::HandshakeNotComplete),
-        server.export_keying_material(&mut server_secret, b"label", Some(b"context"))
-    );
-    do_handshake(&mut client, &mut server);
-
-    assert!(client
-        .export_keying_material(&mut client_secret, b"label", Some(b"context"))
-        .is_ok());
-    assert!(server
-        .export_keying_material(&mut server_secret, b"label", Some(b"context"))
-        .is_ok());
-    assert_eq!(client_secret.to_vec(), server_secret.to_vec());
-
-    assert!(client
-        .export_keying_material(&mut client_secret, b"label", None)
-        .is_ok());
-    assert_ne!(client_secret.to_vec(), server_secret.to_vec());
-    assert!(server
-        .export_keying_material(&mut server_secret, b"label", None)
-        .is_ok());
-    assert_eq!(client_secret.to_vec(), server_secret.to_vec());
-}
-
-#[cfg(feature = "tls12")]
 #[test]
-fn test_tls12_exporter() {
+fn sni_resolver_works_with_wildcards() {
     for kt in ALL_KEY_TYPES.iter() {
-        let client_config = make_client_config_with_versions(*kt, &[&rustls::version::TLS12]);
-        let server_config = make_server_config(*kt);
+        let resolver = rustls::server::ResolvesServerCertUsingSni::new();
+        let signing_key = sign::any_supported_type(&kt.get_key()).unwrap();
+        resolver
+            .add(
+                "*.testserver.com",
+                sign::CertifiedKey::new(kt.get_chain("end.wildcard.fullchain"), signing_key),
+            )
+            .unwrap();
 
-        do_exporter_test(client_config, server_config);
-    }
-}
+        let mut server_config = make_server_config(*kt);
+        server_config.cert_resolver = Arc::new(resolver);
+        let server_config = Arc::new(server_config);
+        let client_config = Arc::new(make_client_config(*kt));
 
-#[test]
-fn test_tls13_exporter() {
-    for kt in ALL_KEY_TYPES.iter() {
-        let client_config = make_client_config_with_versions(*kt, &[&rustls::version::TLS13]);
-        let server_config = make_server_config(*kt);
+        let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+        let mut client = ClientConnection::new(
+            Arc::clone(&client_config),
+            server_name("www.testserver.com"),
+        )
+        .unwrap();
+        assert_eq!(do_handshake_until_error(&mut client, &mut server), Ok(()));
 
-        do_exporter_test(client_config, server_config);
+        let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+        let mut client =
+            ClientConnection::new(Arc::clone(&client_config), server_name("testserver.com"))
+                .unwrap();
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Server(Error::General(
+                "no server certificate chain resolved".into()
+            )))
+        );
     }
 }
 
 #[test]
-fn test_tls13_exporter_maximum_output_length() {
-    let client_config =
-        make_client_config_with_versions(KeyType::Ecdsa, &[&rustls::version::TLS13]);
-    let server_config = make_server_config(KeyType::Ecdsa);
-
-    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
-    do_handshake(&mut client, &mut server);
+fn sni_resolver_rejects_wrong_wildcard_names() {
+    let kt = KeyType::Rsa;
+    let resolver = rustls::server::ResolvesServerCertUsingSni::new();
+    let signing_key = sign::RsaSigningKey::new(&kt.get_key()).unwrap();
+    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
 
     assert_eq!(
-        client.negotiated_cipher_suite(),
-        Some(find_suite(CipherSuite::TLS13_AES_256_GCM_SHA384))
-    );
-
-    let mut maximum_allowed_output_client = [0u8; 255 * 48];
-    let mut maximum_allowed_output_server = [0u8; 255 * 48];
-    client
-        .export_keying_material(
-            &mut maximum_allowed_output_client,
-            b"label",
-            Some(b"context"),
+        Err(Error::InvalidCertificate(CertificateError::NotValidForName)),
+        resolver.add(
+            "*.testserver.com",
+            sign::CertifiedKey::new(kt.get_chain("end.fullchain"), signing_key.clone())
         )
-        .unwrap();
-    server
+    );
+    assert_eq!(
+        Err(Error::InvalidCertificate(CertificateError::NotValidForName)),
+        resolver.add(
+            "*.example.com",
+            sign::CertifiedKey::new(kt.get_chain("end.wildcard.fullchain"), signing_key.clone())
+        )
+    );
+    assert_eq!(
+        Err(Error::General("Bad DNS name".into())),
+        resolver.add(
+            "*.*.testserver.com",
+            sign::CertifiedKey::new(kt.get_chain("end.wildcard.fullchain"), signing_key.clone())
+        )
+    );
+}
+
+#[test]
+fn sni_resolver_uses_default_without_sni() {
+    let kt = KeyType::Rsa;
+    let resolver = Arc::new(rustls::server::ResolvesServerCertUsingSni::new());
+    let signing_key = sign::RsaSigningKey::new(&kt.get_key()).unwrap();
+    let signing_key: Arc<dyn sign::SigningKey> = Arc::new(signing_key);
+    resolver.set_default(Some(sign::CertifiedKey::new(
+        kt.get_chain("end.fullchain"),
+        signing_key,
+    )));
+
+    let mut server_config = make_server_config(kt);
+    server_config.cert_resolver = resolver.clone();
+    let server_config = Arc::new(server_config);
+    let mut client_config = make_client_config(kt);
+    client_config.enable_sni = false;
+    let client_config = Arc::new(client_config);
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    assert_eq!(do_handshake_until_error(&mut client, &mut server), Ok(()));
+
+    // the resolver can be changed while in use
+    resolver.set_default(None);
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    assert_eq!(
+        do_handshake_until_error(&mut client, &mut server),
+        Err(ErrorFromPeer::Server(Error::General(
+            "no server certificate chain resolved".into()
+        )))
+    );
+}
+
+/// Accepts any server certificate, as an ACME client does when answering
+/// a TLS-ALPN-01 challenge with a self-signed certificate.
+#[derive(Debug)]
+struct AcceptAnyServerCert;
+
+impl ServerCertVerifier for AcceptAnyServerCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &rustls::ServerName,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        WebPkiServerVerifier::default_supported_verify_schemes()
+    }
+}
+
+#[test]
+fn acme_resolver_answers_challenges_without_application_data() {
+    let kt = KeyType::Rsa;
+    let signing_key: Arc<dyn sign::SigningKey> =
+        Arc::new(sign::RsaSigningKey::new(&kt.get_key()).unwrap());
+    let resolver =
+        rustls::server::AcmeTlsAlpn01Resolver::new(Arc::new(AlwaysResolvesCertifiedKey(Arc::new(
+            sign::CertifiedKey::new(kt.get_chain("end.fullchain"), Arc::clone(&signing_key)),
+        ))));
+    resolver
+        .add_challenge(
+            "testserver.com",
+            sign::CertifiedKey::new(kt.get_chain("end.acme.cert"), signing_key),
+        )
+        .unwrap();
+
+    let mut server_config = make_server_config(kt);
+    server_config.cert_resolver = Arc::new(resolver);
+    server_config.alpn_protocols = vec![
+        rustls::server::ACME_TLS_ALPN_PROTOCOL.to_vec(),
+        b"http/1.1".to_vec(),
+    ];
+    let server_config = Arc::new(server_config);
+
+    for version in rustls::ALL_VERSIONS {
+        let mut client_config = make_client_config_with_versions(kt, &[version]);
+        client_config.alpn_protocols = vec![rustls::server::ACME_TLS_ALPN_PROTOCOL.to_vec()];
+        client_config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(AcceptAnyServerCert));
+
+        let mut client =
+            ClientConnection::new(Arc::new(client_config), server_name("testserver.com")).unwrap();
+        let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+        assert_eq!(
+            server
+                .writer()
+                .write(b"before handshake")
+                .unwrap(),
+            16
+        );
+        do_handshake(&mut client, &mut server);
+        assert_eq!(
+            client.peer_certificates().unwrap()[0],
+            kt.get_chain("end.acme.cert")[0]
+        );
+
+        // the server discards data written to it, and closes the connection
+        // without sending any
+        assert_eq!(server.writer().write(b"after").unwrap(), 5);
+        server
+            .writer()
+            .write_all(b"more data")
+            .unwrap();
+        transfer(&mut server, &mut client);
+        let io_state = client.process_new_packets().unwrap();
+        assert!(io_state.peer_has_closed());
+        check_read_and_close(&mut client.reader(), b"");
+
+        // and rejects any data from the client
+        client
+            .writer()
+            .write_all(b"hello")
+            .unwrap();
+        transfer(&mut client, &mut server);
+        assert_eq!(
+            server.process_new_packets(),
+            Err(Error::PeerMisbehaved(
+                PeerMisbehaved::ApplicationDataOnAcmeChallenge
+            ))
+        );
+    }
+
+    // other clients use the inner resolver, and exchange data as usual
+    let client_config = make_client_config(kt);
+    let (mut client, mut server) =
+        make_pair_for_arc_configs(&Arc::new(client_config), &server_config);
+    do_handshake(&mut client, &mut server);
+    assert_eq!(
+        client.peer_certificates().unwrap()[0],
+        kt.get_chain("end.fullchain")[0]
+    );
+    server
+        .writer()
+        .write_all(b"hello")
+        .unwrap();
+    transfer(&mut server, &mut client);
+    client.process_new_packets().unwrap();
+    check_read(&mut client.reader(), b"hello");
+}
+
+fn do_exporter_test(client_config: ClientConfig, server_config: ServerConfig) {
+    let mut client_secret = [0u8; 64];
+    let mut server_secret = [0u8; 64];
+
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+
+    assert_eq!(
+        Err(Error::HandshakeNotComplete),
+        client.export_keying_material(&mut client_secret, b"label", Some(b"context"))
+    );
+    assert_eq!(
+        Err(Error::HandshakeNotComplete),
+        server.export_keying_material(&mut server_secret, b"label", Some(b"context"))
+    );
+    do_handshake(&mut client, &mut server);
+
+    assert!(client
+        .export_keying_material(&mut client_secret, b"label", Some(b"context"))
+        .is_ok());
+    assert!(server
+        .export_keying_material(&mut server_secret, b"label", Some(b"context"))
+        .is_ok());
+    assert_eq!(client_secret.to_vec(), server_secret.to_vec());
+
+    assert!(client
+        .export_keying_material(&mut client_secret, b"label", None)
+        .is_ok());
+    assert_ne!(client_secret.to_vec(), server_secret.to_vec());
+    assert!(server
+        .export_keying_material(&mut server_secret, b"label", None)
+        .is_ok());
+    assert_eq!(client_secret.to_vec(), server_secret.to_vec());
+}
+
+#[cfg(feature = "tls12")]
+#[test]
+fn test_tls12_exporter() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let client_config = make_client_config_with_versions(*kt, &[&rustls::version::TLS12]);
+        let server_config = make_server_config(*kt);
+
+        do_exporter_test(client_config, server_config);
+    }
+}
+
+#[test]
+fn test_tls13_exporter() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let client_config = make_client_config_with_versions(*kt, &[&rustls::version::TLS13]);
+        let server_config = make_server_config(*kt);
+
+        do_exporter_test(client_config, server_config);
+    }
+}
+
+#[test]
+fn test_tls13_exporter_maximum_output_length() {
+    let client_config =
+        make_client_config_with_versions(KeyType::Ecdsa, &[&rustls::version::TLS13]);
+    let server_config = make_server_config(KeyType::Ecdsa);
+
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    do_handshake(&mut client, &mut server);
+
+    assert_eq!(
+        client.negotiated_cipher_suite(),
+        Some(find_suite(CipherSuite::TLS13_AES_256_GCM_SHA384))
+    );
+
+    let mut maximum_allowed_output_client = [0u8; 255 * 48];
+    let mut maximum_allowed_output_server = [0u8; 255 * 48];
+    client
+        .export_keying_material(
+            &mut maximum_allowed_output_client,
+            b"label",
+            Some(b"context"),
+        )
+        .unwrap();
+    server
         .export_keying_material(
             &mut maximum_allowed_output_server,
             b"label",
@@ -3605,6 +4658,56 @@ fn tls13_stateless_resumption() {
     );
 }
 
+#[test]
+fn tls13_stateless_resumption_with_shared_ticket_keys() {
+    let kt = KeyType::Rsa;
+    let client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13]);
+    let client_config = Arc::new(client_config);
+
+    let keys = |ids: [u8; 2]| rustls::TicketKeys {
+        current: rustls::TicketKey {
+            id: [ids[0]; rustls::TICKET_KEY_ID_LEN],
+            secret: vec![ids[0]; 32],
+        },
+        previous: None,
+        next: Some(rustls::TicketKey {
+            id: [ids[1]; rustls::TICKET_KEY_ID_LEN],
+            secret: vec![ids[1]; 32],
+        }),
+    };
+    let server_config = |ticketer| {
+        let mut server_config = make_server_config(kt);
+        server_config.ticketer = ticketer;
+        Arc::new(server_config)
+    };
+
+    let ticketer_1 = rustls::crypto::ring::Ticketer::with_keys(3600, keys([1, 2])).unwrap();
+    let ticketer_2 = rustls::crypto::ring::Ticketer::with_keys(3600, keys([1, 2])).unwrap();
+    let server_config_1 = server_config(ticketer_1.clone());
+    let server_config_2 = server_config(ticketer_2);
+
+    // full handshake with one server
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config_1);
+    let (full_c2s, full_s2c) = do_handshake(&mut client, &mut server);
+
+    // resumed with the other
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config_2);
+    let (resume_c2s, resume_s2c) = do_handshake(&mut client, &mut server);
+    assert!(resume_c2s > full_c2s);
+    assert!(resume_s2c < full_s2c);
+
+    // the first server has rotated to the next key, which the other accepts
+    ticketer_1
+        .set_keys(keys([2, 3]))
+        .unwrap();
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config_1);
+    do_handshake(&mut client, &mut server);
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config_2);
+    let (resume_c2s, resume_s2c) = do_handshake(&mut client, &mut server);
+    assert!(resume_c2s > full_c2s);
+    assert!(resume_s2c < full_s2c);
+}
+
 #[test]
 fn early_data_not_available() {
     let (mut client, _) = make_pair(KeyType::Rsa);
@@ -3665,6 +4768,53 @@ fn early_data_is_available_on_resumption() {
     assert_eq!(&received_early_data[..], b"hello");
 }
 
+#[test]
+fn early_data_is_available_after_client_restart() {
+    let dir = std::env::temp_dir().join(format!("rustls-api-sessions-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sessions");
+
+    // each client config stands in for a new client process
+    let new_client_config = || {
+        let mut client_config = make_client_config(KeyType::Rsa);
+        client_config.enable_early_data = true;
+        client_config.resumption = Resumption::store(Arc::new(
+            rustls::client::ClientSessionFileCache::new(&path, 32, ALL_CIPHER_SUITES),
+        ));
+        Arc::new(client_config)
+    };
+    let (_, server_config) = early_data_configs();
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&new_client_config(), &server_config);
+    let (full_c2s, full_s2c) = do_handshake(&mut client, &mut server);
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&new_client_config(), &server_config);
+    assert_eq!(
+        client
+            .early_data()
+            .unwrap()
+            .write(b"hello")
+            .unwrap(),
+        5
+    );
+    let (resume_c2s, resume_s2c) = do_handshake(&mut client, &mut server);
+    assert!(resume_c2s > full_c2s);
+    assert!(resume_s2c < full_s2c);
+
+    let mut received_early_data = [0u8; 5];
+    assert_eq!(
+        server
+            .early_data()
+            .expect("early_data didn't happen")
+            .read(&mut received_early_data)
+            .expect("early_data failed unexpectedly"),
+        5
+    );
+    assert_eq!(&received_early_data[..], b"hello");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
 #[test]
 fn early_data_not_available_on_server_before_client_hello() {
     let mut server = ServerConnection::new(Arc::new(make_server_config(KeyType::Rsa))).unwrap();
@@ -3706,6 +4856,301 @@ fn early_data_can_be_rejected_by_server() {
     assert!(!client.is_early_data_accepted());
 }
 
+#[test]
+fn handshake_observer_counts_full_and_resumed_handshakes() {
+    for version in rustls::ALL_VERSIONS {
+        let client_counters = Arc::new(HandshakeCounters::new());
+        let mut client_config = make_client_config_with_versions(KeyType::Rsa, &[version]);
+        client_config.handshake_observer = client_counters.clone();
+
+        let server_counters = Arc::new(HandshakeCounters::new());
+        let mut server_config = make_server_config(KeyType::Rsa);
+        server_config.handshake_observer = server_counters.clone();
+
+        let (client_config, server_config) = (Arc::new(client_config), Arc::new(server_config));
+        let mut suite = None;
+        for _ in 0..2 {
+            let (mut client, mut server) =
+                make_pair_for_arc_configs(&client_config, &server_config);
+            do_handshake(&mut client, &mut server);
+            suite = client
+                .negotiated_cipher_suite()
+                .map(|suite| suite.suite());
+        }
+        let suite = suite.unwrap();
+
+        let negotiated = match version.version {
+            ProtocolVersion::TLSv1_3 => vec![(version.version, suite, Some(NamedGroup::X25519), 2)],
+            _ => vec![
+                (version.version, suite, Some(NamedGroup::X25519), 1),
+                (version.version, suite, None, 1),
+            ],
+        };
+
+        for stats in [client_counters.stats(), server_counters.stats()] {
+            assert_eq!(stats.full_handshakes, 1);
+            assert_eq!(stats.resumed_handshakes, 1);
+            assert_eq!(stats.failed_handshakes, 0);
+            assert_eq!(stats.hello_retry_requests, 0);
+            assert_eq!(stats.alerts_sent, 0);
+            assert_eq!(stats.alerts_received, 0);
+            assert_eq!(stats.negotiated, negotiated);
+        }
+    }
+}
+
+#[test]
+fn handshake_observer_counts_hello_retry_requests() {
+    let client_counters = Arc::new(HandshakeCounters::new());
+    let mut client_config = make_client_config_with_kx_groups(
+        KeyType::Rsa,
+        &[
+            rustls::crypto::ring::kx_group::SECP384R1,
+            rustls::crypto::ring::kx_group::X25519,
+        ],
+    );
+    client_config.handshake_observer = client_counters.clone();
+
+    let server_counters = Arc::new(HandshakeCounters::new());
+    let mut server_config =
+        make_server_config_with_kx_groups(KeyType::Rsa, &[rustls::crypto::ring::kx_group::X25519]);
+    server_config.handshake_observer = server_counters.clone();
+
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    do_handshake(&mut client, &mut server);
+
+    for stats in [client_counters.stats(), server_counters.stats()] {
+        assert_eq!(stats.full_handshakes, 1);
+        assert_eq!(stats.hello_retry_requests, 1);
+        assert_eq!(stats.negotiated.len(), 1);
+        assert_eq!(stats.negotiated[0].2, Some(NamedGroup::X25519));
+    }
+}
+
+#[test]
+fn handshake_observer_counts_early_data() {
+    let (client_config, server_config) = early_data_configs();
+    let client_counters = Arc::new(HandshakeCounters::new());
+    let mut client_config = ClientConfig::clone(&client_config);
+    client_config.handshake_observer = client_counters.clone();
+    let server_counters = Arc::new(HandshakeCounters::new());
+    let mut server_config = ServerConfig::clone(&server_config);
+    server_config.handshake_observer = server_counters.clone();
+    let (client_config, server_config) = (Arc::new(client_config), Arc::new(server_config));
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
+
+    for reject in [false, true] {
+        let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+        client
+            .early_data()
+            .unwrap()
+            .write_all(b"hello")
+            .unwrap();
+        if reject {
+            server.reject_early_data();
+        }
+        do_handshake(&mut client, &mut server);
+        assert_eq!(client.is_early_data_accepted(), !reject);
+    }
+
+    for stats in [client_counters.stats(), server_counters.stats()] {
+        assert_eq!(stats.resumed_handshakes, 2);
+        assert_eq!(stats.early_data_accepted, 1);
+        assert_eq!(stats.early_data_rejected, 1);
+    }
+}
+
+#[test]
+fn handshake_observer_counts_failed_handshakes_and_alerts() {
+    let client_counters = Arc::new(HandshakeCounters::new());
+    let mut client_config = make_client_config(KeyType::Ecdsa);
+    client_config.handshake_observer = client_counters.clone();
+
+    let server_counters = Arc::new(HandshakeCounters::new());
+    let mut server_config = make_server_config(KeyType::Rsa);
+    server_config.handshake_observer = server_counters.clone();
+
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    assert!(do_handshake_until_both_error(&mut client, &mut server).is_err());
+
+    let client_stats = client_counters.stats();
+    assert_eq!(client_stats.full_handshakes, 0);
+    assert_eq!(client_stats.failed_handshakes, 1);
+    assert_eq!(client_stats.alerts_sent, 1);
+    assert_eq!(client_stats.alerts_received, 0);
+
+    let server_stats = server_counters.stats();
+    assert_eq!(server_stats.full_handshakes, 0);
+    assert_eq!(server_stats.failed_handshakes, 1);
+    assert_eq!(server_stats.alerts_sent, 0);
+    assert_eq!(server_stats.alerts_received, 1);
+}
+
+#[cfg(feature = "tracing")]
+mod test_tracing {
+    use super::*;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// A subscriber which remembers every span and event.
+    #[derive(Default)]
+    struct Recorder {
+        state: Arc<Mutex<Recorded>>,
+    }
+
+    #[derive(Default)]
+    struct Recorded {
+        spans: Vec<RecordedSpan>,
+        entered: Vec<u64>,
+        events: Vec<(Option<u64>, String)>,
+    }
+
+    struct RecordedSpan {
+        name: &'static str,
+        parent: Option<u64>,
+        fields: Vec<(&'static str, String)>,
+    }
+
+    impl RecordedSpan {
+        fn field(&self, name: &str) -> Option<&str> {
+            self.fields
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    struct Fields<'a>(&'a mut Vec<(&'static str, String)>);
+
+    impl Visit for Fields<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0
+                .push((field.name(), value.to_string()));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .push((field.name(), format!("{:?}", value)));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let mut state = self.state.lock().unwrap();
+            let parent = match attrs.parent() {
+                Some(parent) => Some(parent.into_u64()),
+                None if attrs.is_contextual() => state.entered.last().copied(),
+                None => None,
+            };
+            let mut fields = Vec::new();
+            attrs.record(&mut Fields(&mut fields));
+            state.spans.push(RecordedSpan {
+                name: attrs.metadata().name(),
+                parent,
+                fields,
+            });
+            Id::from_u64(state.spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut state = self.state.lock().unwrap();
+            let span = &mut state.spans[span.into_u64() as usize - 1];
+            values.record(&mut Fields(&mut span.fields));
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut state = self.state.lock().unwrap();
+            let mut fields = Vec::new();
+            event.record(&mut Fields(&mut fields));
+            let message = fields
+                .into_iter()
+                .find(|(field, _)| *field == "message")
+                .map(|(_, message)| message)
+                .unwrap_or_default();
+            let span = event
+                .parent()
+                .map(Id::into_u64)
+                .or_else(|| state.entered.last().copied());
+            state.events.push((span, message));
+        }
+
+        fn enter(&self, span: &Id) {
+            self.state
+                .lock()
+                .unwrap()
+                .entered
+                .push(span.into_u64());
+        }
+
+        fn exit(&self, _span: &Id) {
+            self.state.lock().unwrap().entered.pop();
+        }
+    }
+
+    #[test]
+    fn connections_are_traced_as_child_spans() {
+        let recorder = Recorder::default();
+        let recorded = Arc::clone(&recorder.state);
+
+        tracing::subscriber::with_default(recorder, || {
+            let _request = tracing::info_span!("request").entered();
+
+            let (mut client, mut server) = make_pair(KeyType::Rsa);
+            do_handshake(&mut client, &mut server);
+
+            let (mut client, mut server) = make_pair_for_configs(
+                make_client_config(KeyType::Ecdsa),
+                make_server_config(KeyType::Rsa),
+            );
+            assert!(do_handshake_until_both_error(&mut client, &mut server).is_err());
+        });
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.spans[0].name, "request");
+        let tls_spans = recorded
+            .spans
+            .iter()
+            .zip(1u64..)
+            .filter(|(span, _)| span.name == "tls")
+            .collect::<Vec<_>>();
+        assert_eq!(tls_spans.len(), 4);
+        for (span, _) in &tls_spans {
+            assert_eq!(span.parent, Some(1));
+            assert_eq!(span.field("server_name"), Some("localhost"));
+        }
+
+        let (client_span, client_id) = tls_spans[0];
+        assert_eq!(client_span.field("side"), Some("Client"));
+        assert_eq!(client_span.field("protocol_version"), Some("TLSv1_3"));
+        let (server_span, server_id) = tls_spans[1];
+        assert_eq!(server_span.field("side"), Some("Server"));
+        assert_eq!(server_span.field("protocol_version"), Some("TLSv1_3"));
+
+        let has_event = |span: u64, message: &str| {
+            recorded
+                .events
+                .iter()
+                .any(|event| *event == (Some(span), message.to_string()))
+        };
+        for id in [client_id, server_id] {
+            assert!(has_event(id, "state transition"));
+            assert!(has_event(id, "new record layer key"));
+            assert!(!has_event(id, "connection failed"));
+        }
+        assert!(has_event(tls_spans[2].1, "connection failed"));
+        assert!(has_event(tls_spans[3].1, "connection failed"));
+    }
+}
+
 #[cfg(feature = "quic")]
 mod test_quic {
     use super::*;
@@ -3735,38 +5180,38 @@ mod test_quic {
         Ok(change)
     }
 
-    #[test]
-    fn test_quic_handshake() {
-        fn equal_packet_keys(x: &dyn quic::PacketKey, y: &dyn quic::PacketKey) -> bool {
-            // Check that these two sets of keys are equal.
-            let mut buf = [0; 32];
-            let (header, payload_tag) = buf.split_at_mut(8);
-            let (payload, tag_buf) = payload_tag.split_at_mut(8);
-            let tag = x
-                .encrypt_in_place(42, header, payload)
-                .unwrap();
-            tag_buf.copy_from_slice(tag.as_ref());
+    fn equal_packet_keys(x: &dyn quic::PacketKey, y: &dyn quic::PacketKey) -> bool {
+        // Check that these two sets of keys are equal.
+        let mut buf = [0; 32];
+        let (header, payload_tag) = buf.split_at_mut(8);
+        let (payload, tag_buf) = payload_tag.split_at_mut(8);
+        let tag = x
+            .encrypt_in_place(42, header, payload)
+            .unwrap();
+        tag_buf.copy_from_slice(tag.as_ref());
 
-            let result = y.decrypt_in_place(42, header, payload_tag);
-            match result {
-                Ok(payload) => payload == [0; 8],
-                Err(_) => false,
-            }
+        let result = y.decrypt_in_place(42, header, payload_tag);
+        match result {
+            Ok(payload) => payload == [0; 8],
+            Err(_) => false,
         }
+    }
 
-        fn compatible_keys(x: &quic::KeyChange, y: &quic::KeyChange) -> bool {
-            fn keys(kc: &quic::KeyChange) -> &quic::Keys {
-                match kc {
-                    quic::KeyChange::Handshake { keys } => keys,
-                    quic::KeyChange::OneRtt { keys, .. } => keys,
-                }
+    fn compatible_keys(x: &quic::KeyChange, y: &quic::KeyChange) -> bool {
+        fn keys(kc: &quic::KeyChange) -> &quic::Keys {
+            match kc {
+                quic::KeyChange::Handshake { keys } => keys,
+                quic::KeyChange::OneRtt { keys, .. } => keys,
             }
-
-            let (x, y) = (keys(x), keys(y));
-            equal_packet_keys(x.local.packet.as_ref(), y.remote.packet.as_ref())
-                && equal_packet_keys(x.remote.packet.as_ref(), y.local.packet.as_ref())
         }
 
+        let (x, y) = (keys(x), keys(y));
+        equal_packet_keys(x.local.packet.as_ref(), y.remote.packet.as_ref())
+            && equal_packet_keys(x.remote.packet.as_ref(), y.local.packet.as_ref())
+    }
+
+    #[test]
+    fn test_quic_handshake() {
         let kt = KeyType::Rsa;
         let mut client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13]);
         client_config.enable_early_data = true;
@@ -4343,43 +5788,483 @@ mod test_quic {
             0x18, 0xab, 0x08, 0x56, 0x97, 0x2e, 0x19, 0x4c, 0xd9, 0x34,
         ];
 
-        assert_eq!(&buf, PROTECTED);
+        assert_eq!(&buf, PROTECTED);
+
+        let (header, payload) = buf.split_at_mut(header_len);
+        let (first, rest) = header.split_at_mut(1);
+        let sample = &payload[..sample_len];
+
+        let server_keys = Keys::initial(
+            Version::V1,
+            TLS13_AES_128_GCM_SHA256
+                .tls13()
+                .unwrap(),
+            CONNECTION_ID,
+            Side::Server,
+        );
+        server_keys
+            .remote
+            .header
+            .decrypt_in_place(sample, &mut first[0], &mut rest[17..21])
+            .unwrap();
+        let payload = server_keys
+            .remote
+            .packet
+            .decrypt_in_place(PACKET_NUMBER, header, payload)
+            .unwrap();
+
+        assert_eq!(&payload[..PAYLOAD.len()], PAYLOAD);
+        assert_eq!(payload.len(), buf.len() - header_len - tag_len);
+    }
+
+    #[test]
+    fn test_quic_batch_packet_protection() {
+        use rustls::cipher_suite::TLS13_AES_128_GCM_SHA256;
+        use rustls::quic::{BatchHeader, BatchPacket, Keys, Version};
+        use rustls::Side;
+
+        const CONNECTION_ID: &[u8] = &[0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+        const HEADER_LEN: usize = 8;
+        const PN_OFFSET: usize = 4;
+        const PAYLOAD_LEN: usize = 64;
+        const TAG_LEN: usize = 16;
+
+        let suite = TLS13_AES_128_GCM_SHA256
+            .tls13()
+            .unwrap();
+        let client_keys = Keys::initial(Version::V1, suite, CONNECTION_ID, Side::Client);
+        let server_keys = Keys::initial(Version::V1, suite, CONNECTION_ID, Side::Server);
+        let sample_len = client_keys.local.header.sample_len();
+
+        let packet_numbers = [0u64, 1, 2, 1000];
+        let packets = packet_numbers
+            .iter()
+            .map(|&pn| {
+                let mut packet = vec![pn as u8; HEADER_LEN + PAYLOAD_LEN + TAG_LEN];
+                packet[PN_OFFSET..HEADER_LEN].copy_from_slice(&(pn as u32).to_be_bytes());
+                packet
+            })
+            .collect::<Vec<_>>();
+
+        // protect each packet in turn
+        let mut expected = packets.clone();
+        for (packet, &pn) in expected
+            .iter_mut()
+            .zip(packet_numbers.iter())
+        {
+            let (header, rest) = packet.split_at_mut(HEADER_LEN);
+            let (payload, tag) = rest.split_at_mut(PAYLOAD_LEN);
+            let t = client_keys
+                .local
+                .packet
+                .encrypt_in_place(pn, header, payload)
+                .unwrap();
+            tag.copy_from_slice(t.as_ref());
+            let (first, pn_bytes) = header.split_at_mut(1);
+            client_keys
+                .local
+                .header
+                .encrypt_in_place(
+                    &payload[..sample_len],
+                    &mut first[0],
+                    &mut pn_bytes[PN_OFFSET - 1..],
+                )
+                .unwrap();
+        }
+
+        // and in one batch
+        let mut batched = packets.clone();
+        let mut split = batched
+            .iter_mut()
+            .map(|packet| packet.split_at_mut(HEADER_LEN))
+            .collect::<Vec<_>>();
+        let tags = client_keys
+            .local
+            .packet
+            .encrypt_batch_in_place(
+                &mut split
+                    .iter_mut()
+                    .zip(packet_numbers.iter())
+                    .map(|((header, rest), &packet_number)| BatchPacket {
+                        packet_number,
+                        header,
+                        payload: &mut rest[..PAYLOAD_LEN],
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+        assert_eq!(tags.len(), packet_numbers.len());
+        for ((_, rest), tag) in split.iter_mut().zip(tags.iter()) {
+            rest[PAYLOAD_LEN..].copy_from_slice(tag.as_ref());
+        }
+        client_keys
+            .local
+            .header
+            .encrypt_batch_in_place(&mut batch_headers(&mut split, sample_len))
+            .unwrap();
+        assert_eq!(batched, expected);
+
+        // remove it again, with one corrupted packet
+        *batched[2].last_mut().unwrap() ^= 1;
+        let mut split = batched
+            .iter_mut()
+            .map(|packet| packet.split_at_mut(HEADER_LEN))
+            .collect::<Vec<_>>();
+        server_keys
+            .remote
+            .header
+            .decrypt_batch_in_place(&mut batch_headers(&mut split, sample_len))
+            .unwrap();
+        let results = server_keys
+            .remote
+            .packet
+            .decrypt_batch_in_place(
+                &mut split
+                    .iter_mut()
+                    .zip(packet_numbers.iter())
+                    .map(|((header, payload), &packet_number)| BatchPacket {
+                        packet_number,
+                        header,
+                        payload,
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        assert_eq!(
+            results,
+            vec![
+                Ok(PAYLOAD_LEN),
+                Ok(PAYLOAD_LEN),
+                Err(Error::DecryptError),
+                Ok(PAYLOAD_LEN)
+            ]
+        );
+        for (i, (packet, original)) in batched
+            .iter()
+            .zip(packets.iter())
+            .enumerate()
+        {
+            assert_eq!(packet[..HEADER_LEN], original[..HEADER_LEN]);
+            if i != 2 {
+                assert_eq!(
+                    packet[..HEADER_LEN + PAYLOAD_LEN],
+                    original[..HEADER_LEN + PAYLOAD_LEN]
+                );
+            }
+        }
+
+        fn batch_headers<'a>(
+            split: &'a mut [(&mut [u8], &mut [u8])],
+            sample_len: usize,
+        ) -> Vec<BatchHeader<'a>> {
+            split
+                .iter_mut()
+                .map(|(header, payload)| {
+                    let (first, rest) = header.split_at_mut(1);
+                    BatchHeader {
+                        sample: &payload[..sample_len],
+                        first: &mut first[0],
+                        packet_number: &mut rest[PN_OFFSET - 1..],
+                    }
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_quic_batch_header_protection_failure_changes_nothing() {
+        use rustls::cipher_suite::TLS13_AES_128_GCM_SHA256;
+        use rustls::quic::{BatchHeader, Keys, Version};
+        use rustls::Side;
+
+        let suite = TLS13_AES_128_GCM_SHA256
+            .tls13()
+            .unwrap();
+        let keys = Keys::initial(Version::V1, suite, &[1, 2, 3, 4], Side::Client);
+        let sample = vec![0xaa; keys.local.header.sample_len()];
+
+        let mut headers = [[0xc3u8, 0, 0, 0, 1]; 3];
+        let original = headers;
+        let mut batch = headers
+            .iter_mut()
+            .enumerate()
+            .map(|(i, header)| {
+                let (first, packet_number) = header.split_first_mut().unwrap();
+                BatchHeader {
+                    // the last entry's sample is too short
+                    sample: match i {
+                        2 => &sample[1..],
+                        _ => &sample,
+                    },
+                    first,
+                    packet_number,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        assert!(keys
+            .local
+            .header
+            .encrypt_batch_in_place(&mut batch)
+            .is_err());
+        assert!(keys
+            .local
+            .header
+            .decrypt_batch_in_place(&mut batch)
+            .is_err());
+        drop(batch);
+        assert_eq!(headers, original);
+    }
+
+    #[test]
+    fn test_quic_exporter() {
+        for &kt in ALL_KEY_TYPES.iter() {
+            let client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13]);
+            let server_config = make_server_config_with_versions(kt, &[&rustls::version::TLS13]);
+
+            do_exporter_test(client_config, server_config);
+        }
+    }
+
+    #[test]
+    fn test_quic_compatible_version_negotiation() {
+        let kt = KeyType::Rsa;
+        let client_config = Arc::new(make_client_config_with_versions(
+            kt,
+            &[&rustls::version::TLS13],
+        ));
+        let server_config = Arc::new(make_server_config_with_versions(
+            kt,
+            &[&rustls::version::TLS13],
+        ));
+
+        for client_follows in [true, false] {
+            let mut client = quic::ClientConnection::new(
+                Arc::clone(&client_config),
+                quic::Version::V1,
+                server_name("localhost"),
+                b"client params".to_vec(),
+            )
+            .unwrap();
+            let mut server = quic::ServerConnection::new(
+                Arc::clone(&server_config),
+                quic::Version::V1,
+                b"server params".to_vec(),
+            )
+            .unwrap();
+
+            // the client's first flight is sent in v1 Initial packets, and
+            // the server chooses to continue with v2
+            assert!(step(&mut client, &mut server)
+                .unwrap()
+                .is_none());
+            assert!(server
+                .set_quic_version(quic::Version::V1Draft)
+                .is_err());
+            server
+                .set_quic_version(quic::Version::V2)
+                .unwrap();
+            assert_eq!(server.quic_version(), quic::Version::V2);
+
+            // the client sees the server's first flight arrive in v2 Initial packets
+            if client_follows {
+                client
+                    .set_quic_version(quic::Version::V2)
+                    .unwrap();
+            }
+            let server_hs = step(&mut server, &mut client)
+                .unwrap()
+                .unwrap();
+            let client_hs = step(&mut client, &mut server)
+                .unwrap()
+                .unwrap();
+
+            // handshake keys use v2 labels, so a client which does not
+            // follow the switch cannot talk to the server
+            assert_eq!(compatible_keys(&server_hs, &client_hs), client_follows);
+            assert!(server
+                .set_quic_version(quic::Version::V1)
+                .is_err());
+            if !client_follows {
+                continue;
+            }
+
+            let server_1rtt = step(&mut server, &mut client)
+                .unwrap()
+                .unwrap();
+            let client_1rtt = step(&mut client, &mut server)
+                .unwrap()
+                .unwrap();
+            assert!(!client.is_handshaking());
+            assert!(!server.is_handshaking());
+            assert!(compatible_keys(&server_1rtt, &client_1rtt));
+
+            // as do key updates
+            let (mut server_next, mut client_next) = match (server_1rtt, client_1rtt) {
+                (
+                    quic::KeyChange::OneRtt {
+                        next: server_next, ..
+                    },
+                    quic::KeyChange::OneRtt {
+                        next: client_next, ..
+                    },
+                ) => (server_next, client_next),
+                _ => unreachable!(),
+            };
+            let server_keys = server_next.next_packet_keys();
+            let client_keys = client_next.next_packet_keys();
+            assert!(equal_packet_keys(
+                server_keys.local.as_ref(),
+                client_keys.remote.as_ref()
+            ));
+            assert!(equal_packet_keys(
+                client_keys.local.as_ref(),
+                server_keys.remote.as_ref()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_quic_acceptor() {
+        let kt = KeyType::Rsa;
+        let mut client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13]);
+        client_config.alpn_protocols = vec![b"h3".to_vec()];
+        let client_params = &b"client params"[..];
+        let server_params = &b"server params"[..];
+
+        let mut client = quic::ClientConnection::new(
+            Arc::new(client_config),
+            quic::Version::V2,
+            server_name("localhost"),
+            client_params.into(),
+        )
+        .unwrap();
+        let mut initial = Vec::new();
+        assert!(client.write_hs(&mut initial).is_none());
+
+        let mut acceptor = quic::Acceptor::new(quic::Version::V2);
+        let (first, second) = initial.split_at(initial.len() / 2);
+        acceptor.read_hs(first).unwrap();
+        assert!(acceptor.accept().unwrap().is_none());
+        acceptor.read_hs(second).unwrap();
+        let accepted = acceptor.accept().unwrap().unwrap();
+        assert!(acceptor.read_hs(&initial).is_err());
+
+        let hello = accepted.client_hello();
+        assert_eq!(hello.server_name(), Some("localhost"));
+        assert_eq!(
+            hello
+                .alpn()
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![&b"h3"[..]]
+        );
+        assert_eq!(accepted.transport_parameters(), Some(client_params));
 
-        let (header, payload) = buf.split_at_mut(header_len);
-        let (first, rest) = header.split_at_mut(1);
-        let sample = &payload[..sample_len];
+        let mut server_config = make_server_config_with_versions(kt, &[&rustls::version::TLS13]);
+        server_config.alpn_protocols = vec![b"h3".to_vec()];
+        let mut server = accepted
+            .into_connection(Arc::new(server_config), server_params.into())
+            .unwrap();
+        assert_eq!(server.quic_transport_parameters(), Some(client_params));
 
-        let server_keys = Keys::initial(
-            Version::V1,
-            TLS13_AES_128_GCM_SHA256
-                .tls13()
-                .unwrap(),
-            CONNECTION_ID,
-            Side::Server,
-        );
-        server_keys
-            .remote
-            .header
-            .decrypt_in_place(sample, &mut first[0], &mut rest[17..21])
+        step(&mut server, &mut client)
+            .unwrap()
             .unwrap();
-        let payload = server_keys
-            .remote
-            .packet
-            .decrypt_in_place(PACKET_NUMBER, header, payload)
+        step(&mut client, &mut server)
+            .unwrap()
+            .unwrap();
+        step(&mut server, &mut client)
+            .unwrap()
+            .unwrap();
+        step(&mut client, &mut server)
+            .unwrap()
             .unwrap();
+        assert!(!client.is_handshaking());
+        assert!(!server.is_handshaking());
+        assert_eq!(client.quic_transport_parameters(), Some(server_params));
+        assert_eq!(client.alpn_protocol(), Some(&b"h3"[..]));
+        assert_eq!(server.alpn_protocol(), Some(&b"h3"[..]));
+    }
 
-        assert_eq!(&payload[..PAYLOAD.len()], PAYLOAD);
-        assert_eq!(payload.len(), buf.len() - header_len - tag_len);
+    #[cfg(feature = "tls12")]
+    #[test]
+    fn test_quic_acceptor_rejects_unsuitable_config() {
+        let kt = KeyType::Rsa;
+        let mut client = quic::ClientConnection::new(
+            Arc::new(make_client_config_with_versions(
+                kt,
+                &[&rustls::version::TLS13],
+            )),
+            quic::Version::V1,
+            server_name("localhost"),
+            b"client params".to_vec(),
+        )
+        .unwrap();
+        let mut initial = Vec::new();
+        client.write_hs(&mut initial);
+
+        let mut acceptor = quic::Acceptor::new(quic::Version::V1);
+        acceptor.read_hs(&initial).unwrap();
+        let accepted = acceptor.accept().unwrap().unwrap();
+
+        let server_config = make_server_config_with_versions(kt, &[&rustls::version::TLS12]);
+        assert!(matches!(
+            accepted.into_connection(Arc::new(server_config), b"server params".to_vec()),
+            Err(Error::General(_))
+        ));
     }
 
     #[test]
-    fn test_quic_exporter() {
-        for &kt in ALL_KEY_TYPES.iter() {
-            let client_config = make_client_config_with_versions(kt, &[&rustls::version::TLS13]);
-            let server_config = make_server_config_with_versions(kt, &[&rustls::version::TLS13]);
+    fn test_retry_token_sealer() {
+        let provider = rustls::crypto::ring::RING;
+        let lifetime = Duration::from_secs(10);
+        let sealer = quic::RetryTokenSealer::new(provider, lifetime).unwrap();
+        let now = UnixTime::now();
+        let address = b"192.0.2.1:443";
+        let cid = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        let token = sealer.seal(address, &cid, now).unwrap();
+        assert_eq!(sealer.open(address, &token, now), Some(cid.to_vec()));
+        assert_ne!(sealer.seal(address, &cid, now).unwrap(), token);
+
+        // tokens are bound to the address, and expire
+        assert_eq!(sealer.open(b"192.0.2.2:443", &token, now), None);
+        let later = UnixTime::since_unix_epoch(Duration::from_secs(now.as_secs() + 10));
+        assert_eq!(sealer.open(address, &token, later), Some(cid.to_vec()));
+        let too_late = UnixTime::since_unix_epoch(Duration::from_secs(now.as_secs() + 11));
+        assert_eq!(sealer.open(address, &token, too_late), None);
+
+        // tokens from the future are refused, beyond a little clock skew
+        let earlier = UnixTime::since_unix_epoch(Duration::from_secs(now.as_secs() - 10));
+        assert_eq!(sealer.open(address, &token, earlier), Some(cid.to_vec()));
+        let too_early = UnixTime::since_unix_epoch(Duration::from_secs(now.as_secs() - 11));
+        assert_eq!(sealer.open(address, &token, too_early), None);
+        let future_token = sealer
+            .seal(
+                address,
+                &cid,
+                UnixTime::since_unix_epoch(Duration::from_secs(u64::MAX)),
+            )
+            .unwrap();
+        assert_eq!(sealer.open(address, &future_token, now), None);
 
-            do_exporter_test(client_config, server_config);
+        // and cannot be modified
+        for i in 0..token.len() {
+            let mut corrupt = token.clone();
+            corrupt[i] ^= 1;
+            assert_eq!(sealer.open(address, &corrupt, now), None);
         }
+        assert_eq!(sealer.open(address, &token[..10], now), None);
+
+        // only sealers with the same secret can open them
+        let other = quic::RetryTokenSealer::new(provider, lifetime).unwrap();
+        assert_eq!(other.open(address, &token, now), None);
+
+        let secret = [0x42; 32];
+        let first = quic::RetryTokenSealer::with_secret(provider, &secret, lifetime).unwrap();
+        let second = quic::RetryTokenSealer::with_secret(provider, &secret, lifetime).unwrap();
+        let token = first.seal(address, &cid, now).unwrap();
+        assert_eq!(second.open(address, &token, now), Some(cid.to_vec()));
     }
 } // mod test_quic
 
@@ -4965,6 +6850,264 @@ fn test_client_rejects_illegal_tls13_ccs() {
     );
 }
 
+/// Move the client's next flight to the server, returning its ClientHello.
+fn transfer_client_hello(
+    client: &mut ClientConnection,
+    server: &mut ServerConnection,
+) -> rustls::internal::msgs::handshake::ClientHelloPayload {
+    use rustls::internal::msgs::codec::Reader;
+    use rustls::internal::msgs::message::OpaqueMessage;
+
+    let mut buf = Vec::new();
+    client.write_tls(&mut buf).unwrap();
+    server.read_tls(&mut &buf[..]).unwrap();
+
+    let mut reader = Reader::init(&buf);
+    while reader.any_left() {
+        let message = OpaqueMessage::read(&mut reader).unwrap();
+        let message = Message::try_from(message.into_plain_message()).unwrap();
+        if let MessagePayload::Handshake { parsed, .. } = message.payload {
+            if let HandshakePayload::ClientHello(client_hello) = parsed.payload {
+                return client_hello;
+            }
+        }
+    }
+    panic!("no ClientHello sent");
+}
+
+/// The GREASE (RFC 8701) values in `client_hello`, and where they are.
+fn grease_in_client_hello(
+    client_hello: &rustls::internal::msgs::handshake::ClientHelloPayload,
+) -> Vec<(&'static str, u16)> {
+    fn is_grease(value: u16) -> bool {
+        let [high, low] = value.to_be_bytes();
+        high == low && low & 0x0f == 0x0a
+    }
+
+    let mut found = Vec::new();
+    let mut check = |place, value: u16| {
+        if is_grease(value) {
+            found.push((place, value));
+        }
+    };
+
+    for suite in &client_hello.cipher_suites {
+        check("cipher suite", suite.get_u16());
+    }
+    for ext in &client_hello.extensions {
+        let encoding = ext.get_encoding();
+        check("extension", u16::from_be_bytes([encoding[0], encoding[1]]));
+
+        match ext {
+            ClientExtension::NamedGroups(groups) => {
+                for group in groups {
+                    check("group", group.get_u16());
+                }
+            }
+            ClientExtension::SignatureAlgorithms(schemes) => {
+                for scheme in schemes {
+                    check("signature scheme", scheme.get_u16());
+                }
+            }
+            ClientExtension::SupportedVersions(versions) => {
+                for version in versions {
+                    check("version", version.get_u16());
+                }
+            }
+            ClientExtension::KeyShare(shares) => {
+                for share in shares {
+                    check("key share", share.group().get_u16());
+                }
+            }
+            ClientExtension::Protocols(protocols) => {
+                for protocol in protocols {
+                    if let [2, high, low] = protocol.get_encoding()[..] {
+                        check("alpn protocol", u16::from_be_bytes([high, low]));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    found
+}
+
+#[test]
+fn test_client_sends_grease_which_server_ignores() {
+    for version in rustls::ALL_VERSIONS {
+        let mut client_config = make_client_config_with_versions(KeyType::Rsa, &[version]);
+        client_config.alpn_protocols = vec![b"foo".to_vec(), b"bar".to_vec()];
+        let mut server_config = make_server_config(KeyType::Rsa);
+        server_config.alpn_protocols = vec![b"bar".to_vec()];
+
+        let (mut client, mut server) =
+            make_pair_for_configs(client_config.clone(), server_config.clone());
+        let client_hello = transfer_client_hello(&mut client, &mut server);
+        assert_eq!(grease_in_client_hello(&client_hello), vec![]);
+
+        client_config.enable_grease = true;
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        let client_hello = transfer_client_hello(&mut client, &mut server);
+        let grease = grease_in_client_hello(&client_hello);
+        let places = grease
+            .iter()
+            .map(|(place, _)| *place)
+            .collect::<Vec<_>>();
+        let mut expected = vec![
+            "cipher suite",
+            "extension",
+            "version",
+            "group",
+            "signature scheme",
+            "key share",
+            "alpn protocol",
+            "extension",
+        ];
+        if version.version == ProtocolVersion::TLSv1_2 {
+            expected.retain(|place| *place != "key share");
+        }
+        assert_eq!(places, expected);
+
+        do_handshake(&mut client, &mut server);
+        assert_eq!(client.protocol_version(), Some(version.version));
+        assert_eq!(server.protocol_version(), Some(version.version));
+        assert_eq!(client.alpn_protocol(), Some(&b"bar"[..]));
+        assert_eq!(server.alpn_protocol(), Some(&b"bar"[..]));
+    }
+}
+
+#[test]
+fn test_client_repeats_grease_after_helloretryrequest() {
+    let mut client_config = make_client_config_with_kx_groups(
+        KeyType::Rsa,
+        &[
+            rustls::crypto::ring::kx_group::SECP384R1,
+            rustls::crypto::ring::kx_group::X25519,
+        ],
+    );
+    client_config.enable_grease = true;
+    let server_config =
+        make_server_config_with_kx_groups(KeyType::Rsa, &[rustls::crypto::ring::kx_group::X25519]);
+
+    let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+    let first_hello = transfer_client_hello(&mut client, &mut server);
+    server.process_new_packets().unwrap();
+    transfer(&mut server, &mut client);
+    client.process_new_packets().unwrap();
+    let second_hello = transfer_client_hello(&mut client, &mut server);
+
+    let first_grease = grease_in_client_hello(&first_hello);
+    assert!(first_grease
+        .iter()
+        .any(|(place, _)| *place == "key share"));
+
+    // The retried hello keeps the same GREASE values, except that it must
+    // carry only the key share the server asked for.
+    let expected_grease = first_grease
+        .into_iter()
+        .filter(|(place, _)| *place != "key share")
+        .collect::<Vec<_>>();
+    assert!(!expected_grease.is_empty());
+    assert_eq!(grease_in_client_hello(&second_hello), expected_grease);
+    assert_eq!(
+        second_hello
+            .get_keyshare_extension()
+            .map(|shares| shares.len()),
+        Some(1)
+    );
+
+    do_handshake(&mut client, &mut server);
+    assert_eq!(
+        client
+            .negotiated_cipher_suite()
+            .map(|suite| suite.suite()),
+        server
+            .negotiated_cipher_suite()
+            .map(|suite| suite.suite())
+    );
+}
+
+/// The length of the ClientHello handshake message sent to `server_name`.
+fn client_hello_len(client_config: &Arc<ClientConfig>, server_name: &str) -> usize {
+    let mut client =
+        ClientConnection::new(Arc::clone(client_config), server_name.try_into().unwrap()).unwrap();
+    let mut server = ServerConnection::new(Arc::new(make_server_config(KeyType::Rsa))).unwrap();
+    let client_hello = transfer_client_hello(&mut client, &mut server);
+    // handshake type and length
+    4 + client_hello.get_encoding().len()
+}
+
+#[test]
+fn test_client_hello_padding_avoids_problematic_sizes() {
+    let mut client_config = make_client_config(KeyType::Rsa);
+    let unpadded_config = Arc::new(client_config.clone());
+    client_config.client_hello_padding = ClientHelloPadding::AvoidProblematicSizes;
+    let padded_config = Arc::new(client_config);
+
+    let mut problematic = 0;
+    for labels in 0..100 {
+        let server_name = format!("{}com", "a.".repeat(labels));
+        let unpadded_len = client_hello_len(&unpadded_config, &server_name);
+        let padded_len = client_hello_len(&padded_config, &server_name);
+
+        if (256..512).contains(&unpadded_len) {
+            problematic += 1;
+            assert!(padded_len >= 512);
+        } else {
+            assert_eq!(padded_len, unpadded_len);
+        }
+    }
+
+    // make sure we covered the interesting range
+    assert!(problematic > 0);
+}
+
+#[test]
+fn test_client_hello_padding_to_bucket() {
+    for bucket in [1, 100, 128, 1000] {
+        let mut client_config = make_client_config(KeyType::Rsa);
+        client_config.client_hello_padding = ClientHelloPadding::Bucket(bucket);
+        let client_config = Arc::new(client_config);
+
+        for labels in 0..50 {
+            let server_name = format!("{}com", "a.".repeat(labels));
+            let len = client_hello_len(&client_config, &server_name);
+            assert_eq!(len % usize::from(bucket), 0);
+            assert!(!(256..512).contains(&len));
+        }
+    }
+}
+
+#[test]
+fn test_client_hello_padding_precedes_psk_extension() {
+    let counters = Arc::new(HandshakeCounters::new());
+    let mut client_config =
+        make_client_config_with_versions(KeyType::Rsa, &[&rustls::version::TLS13]);
+    client_config.client_hello_padding = ClientHelloPadding::Bucket(256);
+    client_config.handshake_observer = counters.clone();
+    let client_config = Arc::new(client_config);
+    let server_config = Arc::new(make_server_config(KeyType::Rsa));
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    do_handshake(&mut client, &mut server);
+
+    let (mut client, mut server) = make_pair_for_arc_configs(&client_config, &server_config);
+    let client_hello = transfer_client_hello(&mut client, &mut server);
+    assert_eq!((4 + client_hello.get_encoding().len()) % 256, 0);
+    let types = client_hello
+        .extensions
+        .iter()
+        .map(|ext| u16::from_be_bytes([ext.get_encoding()[0], ext.get_encoding()[1]]))
+        .collect::<Vec<_>>();
+    // padding, then pre_shared_key
+    assert_eq!(types[types.len() - 2..], [0x0015, 0x0029]);
+
+    // the server accepts the binder, which covers the padding
+    do_handshake(&mut client, &mut server);
+    assert_eq!(counters.stats().resumed_handshakes, 1);
+}
+
 /// https://github.com/rustls/rustls/issues/797
 #[cfg(feature = "tls12")]
 #[test]
@@ -5230,7 +7373,7 @@ fn test_secret_extraction_enabled() {
             .with_protocol_versions(&[version])
             .unwrap()
             .with_no_client_auth()
-            .with_single_cert(kt.get_chain(), kt.get_key())
+            .with_single_cert(kt.get_chain("end.fullchain"), kt.get_key())
             .unwrap();
         // Opt into secret extraction from both sides
         server_config.enable_secret_extraction = true;
@@ -5294,7 +7437,7 @@ fn test_secret_extraction_disabled_or_too_early() {
             .with_safe_default_protocol_versions()
             .unwrap()
             .with_no_client_auth()
-            .with_single_cert(kt.get_chain(), kt.get_key())
+            .with_single_cert(kt.get_chain("end.fullchain"), kt.get_key())
             .unwrap();
         server_config.enable_secret_extraction = server_enable;
         let server_config = Arc::new(server_config);
@@ -5350,7 +7493,7 @@ fn test_received_plaintext_backpressure() {
             .with_safe_default_protocol_versions()
             .unwrap()
             .with_no_client_auth()
-            .with_single_cert(kt.get_chain(), kt.get_key())
+            .with_single_cert(kt.get_chain("end.fullchain"), kt.get_key())
             .unwrap(),
     );
 
@@ -5538,3 +7681,182 @@ fn test_client_construction_requires_64_bytes_of_random_material() {
     ClientConnection::new(Arc::new(client_config), server_name("localhost"))
         .expect("check how much random material ClientConnection::new consumes");
 }
+
+struct AlwaysResolvesCertifiedKey(Arc<sign::CertifiedKey>);
+
+impl ResolvesServerCert for AlwaysResolvesCertifiedKey {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<sign::CertifiedKey>> {
+        Some(Arc::clone(&self.0))
+    }
+}
+
+/// Make a certified key for `chain`, with a P-256 delegated credential
+/// valid until `expiry`.
+fn make_certified_key_with_delegated_credential(
+    kt: KeyType,
+    chain: Vec<CertificateDer<'static>>,
+    expiry: UnixTime,
+) -> Result<sign::CertifiedKey, Error> {
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    let rng = ring::rand::SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+    let public_key =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap()
+            .public_key()
+            .as_ref()
+            .to_vec();
+
+    // SubjectPublicKeyInfo for an uncompressed P-256 public key.
+    let mut spki = vec![
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+    ];
+    spki.extend(public_key);
+
+    let key = sign::any_supported_type(&kt.get_key()).unwrap();
+    let signer = key
+        .choose_scheme(&[
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::ED25519,
+        ])
+        .unwrap();
+    let credential = DelegatedCredential::new(
+        &chain[0],
+        &*signer,
+        SignatureScheme::ECDSA_NISTP256_SHA256,
+        spki,
+        expiry,
+    )?;
+
+    let mut certified_key = sign::CertifiedKey::new(chain, key);
+    certified_key.delegated_credential = Some(sign::DelegatedKey {
+        credential,
+        key: sign::any_ecdsa_type(&PrivateKeyDer::Pkcs8(pkcs8.as_ref().to_vec().into())).unwrap(),
+    });
+    Ok(certified_key)
+}
+
+fn make_server_config_with_delegated_credential(
+    kt: KeyType,
+    chain: Vec<CertificateDer<'static>>,
+    expiry: UnixTime,
+) -> ServerConfig {
+    let certified_key = make_certified_key_with_delegated_credential(kt, chain, expiry).unwrap();
+    let mut server_config = make_server_config(kt);
+    server_config.cert_resolver = Arc::new(AlwaysResolvesCertifiedKey(Arc::new(certified_key)));
+    server_config
+}
+
+fn in_days(days: u64) -> UnixTime {
+    UnixTime::since_unix_epoch(Duration::from_secs(
+        UnixTime::now().as_secs() + days * 24 * 60 * 60,
+    ))
+}
+
+#[test]
+fn client_accepts_delegated_credential() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = Arc::new(make_server_config_with_delegated_credential(
+            *kt,
+            kt.get_chain("end.dc.fullchain"),
+            in_days(1),
+        ));
+
+        for version in rustls::ALL_VERSIONS {
+            let mut client_config = make_client_config_with_versions(*kt, &[version]);
+            client_config.enable_delegated_credentials = true;
+
+            let mut client =
+                ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+            let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+            do_handshake(&mut client, &mut server);
+        }
+    }
+}
+
+#[test]
+fn server_only_sends_delegated_credential_when_requested() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = Arc::new(make_server_config_with_delegated_credential(
+            *kt,
+            kt.get_chain("end.dc.fullchain"),
+            in_days(1),
+        ));
+
+        // the client rejects unsolicited credentials, so this fails if one is sent
+        let client_config = make_client_config_with_versions(*kt, &[&rustls::version::TLS13]);
+        let mut client =
+            ClientConnection::new(Arc::new(client_config), server_name("localhost")).unwrap();
+        let mut server = ServerConnection::new(server_config).unwrap();
+        do_handshake(&mut client, &mut server);
+    }
+}
+
+#[test]
+fn client_rejects_delegated_credential_with_long_validity() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = make_server_config_with_delegated_credential(
+            *kt,
+            kt.get_chain("end.dc.fullchain"),
+            in_days(8),
+        );
+        let mut client_config = make_client_config_with_versions(*kt, &[&rustls::version::TLS13]);
+        client_config.enable_delegated_credentials = true;
+
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                CertificateError::InvalidDelegatedCredential
+            )))
+        );
+    }
+}
+
+#[test]
+fn client_rejects_expired_delegated_credential() {
+    let expired = UnixTime::since_unix_epoch(Duration::from_secs(UnixTime::now().as_secs() - 1));
+
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = make_server_config_with_delegated_credential(
+            *kt,
+            kt.get_chain("end.dc.fullchain"),
+            expired,
+        );
+        let mut client_config = make_client_config_with_versions(*kt, &[&rustls::version::TLS13]);
+        client_config.enable_delegated_credentials = true;
+
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                CertificateError::InvalidDelegatedCredential
+            )))
+        );
+    }
+}
+
+#[test]
+fn client_rejects_delegated_credential_without_delegation_usage() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let server_config = make_server_config_with_delegated_credential(
+            *kt,
+            kt.get_chain("end.fullchain"),
+            in_days(1),
+        );
+        let mut client_config = make_client_config_with_versions(*kt, &[&rustls::version::TLS13]);
+        client_config.enable_delegated_credentials = true;
+
+        let (mut client, mut server) = make_pair_for_configs(client_config, server_config);
+        assert_eq!(
+            do_handshake_until_error(&mut client, &mut server),
+            Err(ErrorFromPeer::Client(Error::InvalidCertificate(
+                CertificateError::InvalidDelegatedCredential
+            )))
+        );
+    }
+}
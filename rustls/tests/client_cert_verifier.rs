@@ -13,6 +13,7 @@ use rustls::client::danger::HandshakeSignatureValid;
 use rustls::client::WebPkiServerVerifier;
 use rustls::internal::msgs::handshake::DistinguishedName;
 use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::server::ClientHello;
 use rustls::{
     AlertDescription, ClientConnection, DigitallySignedStruct, Error, InvalidMessage, ServerConfig,
     ServerConnection, SignatureScheme,
@@ -44,7 +45,7 @@ fn server_config_with_verifier(
     ServerConfig::builder()
         .with_safe_defaults()
         .with_client_cert_verifier(Arc::new(client_cert_verifier))
-        .with_single_cert(kt.get_chain(), kt.get_key())
+        .with_single_cert(kt.get_chain("end.fullchain"), kt.get_key())
         .unwrap()
 }
 
@@ -139,11 +140,55 @@ fn client_verifier_fails_properly() {
     }
 }
 
+// Client auth policy can depend on the ClientHello
+#[test]
+fn client_verifier_per_server_name() {
+    for kt in ALL_KEY_TYPES.iter() {
+        let mut client_verifier = MockClientVerifier::new(ver_ok, *kt);
+        client_verifier.only_offer_to = Some("testserver.com");
+        let server_config = server_config_with_verifier(*kt, client_verifier);
+        let server_config = Arc::new(server_config);
+
+        for version in rustls::ALL_VERSIONS {
+            // Anonymous clients can connect to other names
+            let client_config = Arc::new(make_client_config_with_versions(*kt, &[version]));
+            let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+            let mut client =
+                ClientConnection::new(Arc::clone(&client_config), server_name("localhost"))
+                    .unwrap();
+            assert_eq!(do_handshake_until_error(&mut client, &mut server), Ok(()));
+            assert_eq!(server.peer_certificates(), None);
+
+            // ... but not to testserver.com
+            let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+            let mut client =
+                ClientConnection::new(client_config, server_name("testserver.com")).unwrap();
+            assert_eq!(
+                do_handshake_until_error(&mut client, &mut server),
+                Err(ErrorFromPeer::Server(Error::NoCertificatesPresented))
+            );
+
+            let client_config = make_client_config_with_versions_with_auth(*kt, &[version]);
+            let mut server = ServerConnection::new(Arc::clone(&server_config)).unwrap();
+            let mut client =
+                ClientConnection::new(Arc::new(client_config), server_name("testserver.com"))
+                    .unwrap();
+            assert_eq!(do_handshake_until_error(&mut client, &mut server), Ok(()));
+            assert_eq!(
+                server.peer_certificates(),
+                Some(kt.get_client_chain().as_slice())
+            );
+        }
+    }
+}
+
 pub struct MockClientVerifier {
     pub verified: fn() -> Result<ClientCertVerified, Error>,
     pub subjects: Vec<DistinguishedName>,
     pub mandatory: bool,
     pub offered_schemes: Option<Vec<SignatureScheme>>,
+    /// If set, client auth is only offered to clients sending this SNI.
+    pub only_offer_to: Option<&'static str>,
 }
 
 impl MockClientVerifier {
@@ -153,16 +198,24 @@ impl MockClientVerifier {
             subjects: get_client_root_store(kt).subjects(),
             mandatory: true,
             offered_schemes: None,
+            only_offer_to: None,
         }
     }
 }
 
 impl ClientCertVerifier for MockClientVerifier {
-    fn client_auth_mandatory(&self) -> bool {
+    fn offer_client_auth(&self, client_hello: &ClientHello<'_>) -> bool {
+        match self.only_offer_to {
+            Some(name) => client_hello.server_name() == Some(name),
+            None => true,
+        }
+    }
+
+    fn client_auth_mandatory(&self, _client_hello: &ClientHello<'_>) -> bool {
         self.mandatory
     }
 
-    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+    fn root_hint_subjects(&self, _client_hello: &ClientHello<'_>) -> &[DistinguishedName] {
         &self.subjects
     }
 
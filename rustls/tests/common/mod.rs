@@ -54,6 +54,11 @@ embed_files! {
     (ECDSA_INTERMEDIATE_CRL_PEM, "ecdsa", "inter.revoked.crl.pem");
     (ECDSA_END_CERT, "ecdsa", "end.cert");
     (ECDSA_END_CHAIN, "ecdsa", "end.chain");
+    (ECDSA_END_DC_FULLCHAIN, "ecdsa", "end.dc.fullchain");
+    (ECDSA_END_ACME_CERT, "ecdsa", "end.acme.cert");
+    (ECDSA_END_WILDCARD_FULLCHAIN, "ecdsa", "end.wildcard.fullchain");
+    (ECDSA_END_SPIFFE_FULLCHAIN, "ecdsa", "end.spiffe.fullchain");
+    (ECDSA_CLIENT_SPIFFE_FULLCHAIN, "ecdsa", "client.spiffe.fullchain");
     (ECDSA_END_FULLCHAIN, "ecdsa", "end.fullchain");
     (ECDSA_END_KEY, "ecdsa", "end.key");
     (ECDSA_END_REQ, "ecdsa", "end.req");
@@ -76,6 +81,11 @@ embed_files! {
     (EDDSA_INTERMEDIATE_CRL_PEM, "eddsa", "inter.revoked.crl.pem");
     (EDDSA_END_CERT, "eddsa", "end.cert");
     (EDDSA_END_CHAIN, "eddsa", "end.chain");
+    (EDDSA_END_DC_FULLCHAIN, "eddsa", "end.dc.fullchain");
+    (EDDSA_END_ACME_CERT, "eddsa", "end.acme.cert");
+    (EDDSA_END_WILDCARD_FULLCHAIN, "eddsa", "end.wildcard.fullchain");
+    (EDDSA_END_SPIFFE_FULLCHAIN, "eddsa", "end.spiffe.fullchain");
+    (EDDSA_CLIENT_SPIFFE_FULLCHAIN, "eddsa", "client.spiffe.fullchain");
     (EDDSA_END_FULLCHAIN, "eddsa", "end.fullchain");
     (EDDSA_END_KEY, "eddsa", "end.key");
     (EDDSA_END_REQ, "eddsa", "end.req");
@@ -97,6 +107,11 @@ embed_files! {
     (RSA_INTERMEDIATE_CRL_PEM, "rsa", "inter.revoked.crl.pem");
     (RSA_END_CERT, "rsa", "end.cert");
     (RSA_END_CHAIN, "rsa", "end.chain");
+    (RSA_END_DC_FULLCHAIN, "rsa", "end.dc.fullchain");
+    (RSA_END_ACME_CERT, "rsa", "end.acme.cert");
+    (RSA_END_WILDCARD_FULLCHAIN, "rsa", "end.wildcard.fullchain");
+    (RSA_END_SPIFFE_FULLCHAIN, "rsa", "end.spiffe.fullchain");
+    (RSA_CLIENT_SPIFFE_FULLCHAIN, "rsa", "client.spiffe.fullchain");
     (RSA_END_FULLCHAIN, "rsa", "end.fullchain");
     (RSA_END_KEY, "rsa", "end.key");
     (RSA_END_REQ, "rsa", "end.req");
@@ -207,8 +222,11 @@ impl KeyType {
         }
     }
 
-    pub fn get_chain(&self) -> Vec<CertificateDer<'static>> {
-        rustls_pemfile::certs(&mut io::BufReader::new(self.bytes_for("end.fullchain")))
+    /// The certificates in the PEM file `name`, such as `end.fullchain`.
+    ///
+    /// The end-entity certificates of the `end.*` files all have the same key.
+    pub fn get_chain(&self, name: &str) -> Vec<CertificateDer<'static>> {
+        rustls_pemfile::certs(&mut io::BufReader::new(self.bytes_for(name)))
             .map(|result| result.unwrap())
             .collect()
     }
@@ -223,9 +241,7 @@ impl KeyType {
     }
 
     pub fn get_client_chain(&self) -> Vec<CertificateDer<'static>> {
-        rustls_pemfile::certs(&mut io::BufReader::new(self.bytes_for("client.fullchain")))
-            .map(|result| result.unwrap())
-            .collect()
+        self.get_chain("client.fullchain")
     }
 
     pub fn end_entity_crl(&self) -> CertificateRevocationListDer<'static> {
@@ -240,7 +256,7 @@ impl KeyType {
         self.get_crl("inter")
     }
 
-    fn get_client_key(&self) -> PrivateKeyDer<'static> {
+    pub fn get_client_key(&self) -> PrivateKeyDer<'static> {
         PrivateKeyDer::Pkcs8(
             rustls_pemfile::pkcs8_private_keys(&mut io::BufReader::new(
                 self.bytes_for("client.key"),
@@ -266,7 +282,7 @@ pub fn finish_server_config(
     conf: rustls::ConfigBuilder<ServerConfig, rustls::WantsVerifier>,
 ) -> ServerConfig {
     conf.with_no_client_auth()
-        .with_single_cert(kt.get_chain(), kt.get_key())
+        .with_single_cert(kt.get_chain("end.fullchain"), kt.get_key())
         .unwrap()
 }
 
@@ -305,7 +321,7 @@ pub fn make_server_config_with_kx_groups(
 pub fn get_client_root_store(kt: KeyType) -> Arc<RootCertStore> {
     // The key type's chain file contains the DER encoding of the EE cert, the intermediate cert,
     // and the root trust anchor. We want only the trust anchor to build the root cert store.
-    let chain = kt.get_chain();
+    let chain = kt.get_chain("end.fullchain");
     let trust_anchor = chain.last().unwrap();
     RootCertStore {
         roots: vec![extract_trust_anchor(trust_anchor)
@@ -352,7 +368,7 @@ pub fn make_server_config_with_client_verifier(
     ServerConfig::builder()
         .with_safe_defaults()
         .with_client_cert_verifier(verifier_builder.build().unwrap())
-        .with_single_cert(kt.get_chain(), kt.get_key())
+        .with_single_cert(kt.get_chain("end.fullchain"), kt.get_key())
         .unwrap()
 }
 
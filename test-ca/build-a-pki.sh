@@ -194,6 +194,50 @@ for kt in rsa ecdsa eddsa ; do
             -set_serial 456 \
             -extensions v3_end -extfile openssl.cnf
 
+  # The same end-entity key, in a certificate that permits delegated credentials.
+  openssl x509 -req \
+            -in $kt/end.req \
+            -out $kt/end.dc.cert \
+            -CA $kt/inter.cert \
+            -CAkey $kt/inter.key \
+            -sha256 \
+            -days 2000 \
+            -set_serial 457 \
+            -extensions v3_end_dc -extfile openssl.cnf
+
+  # The same end-entity key, in a certificate for a wildcard name.
+  openssl x509 -req \
+            -in $kt/end.req \
+            -out $kt/end.wildcard.cert \
+            -CA $kt/inter.cert \
+            -CAkey $kt/inter.key \
+            -sha256 \
+            -days 2000 \
+            -set_serial 458 \
+            -extensions v3_end_wildcard -extfile openssl.cnf
+
+  # The same end-entity key, in a self-signed ACME TLS-ALPN-01 challenge
+  # certificate.
+  openssl x509 -req \
+            -in $kt/end.req \
+            -out $kt/end.acme.cert \
+            -signkey $kt/end.key \
+            -sha256 \
+            -days 2000 \
+            -set_serial 459 \
+            -extensions v3_end_acme -extfile openssl.cnf
+
+  # The same end-entity key, in a SPIFFE X509-SVID.
+  openssl x509 -req \
+            -in $kt/end.req \
+            -out $kt/end.spiffe.cert \
+            -CA $kt/inter.cert \
+            -CAkey $kt/inter.key \
+            -sha256 \
+            -days 2000 \
+            -set_serial 460 \
+            -extensions v3_end_spiffe -extfile openssl.cnf
+
   openssl x509 -req \
             -in $kt/client.req \
             -out $kt/client.cert \
@@ -204,6 +248,17 @@ for kt in rsa ecdsa eddsa ; do
             -set_serial 789 \
             -extensions v3_client -extfile openssl.cnf
 
+  # The same client key, in a SPIFFE X509-SVID.
+  openssl x509 -req \
+            -in $kt/client.req \
+            -out $kt/client.spiffe.cert \
+            -CA $kt/inter.cert \
+            -CAkey $kt/inter.key \
+            -sha256 \
+            -days 2000 \
+            -set_serial 790 \
+            -extensions v3_client_spiffe -extfile openssl.cnf
+
   # Generate a CRL revoking the client certificate
   gen_crl $kt inter client
   # Generate a CRL revoking the server certificate
@@ -213,9 +268,13 @@ for kt in rsa ecdsa eddsa ; do
 
   cat $kt/inter.cert $kt/ca.cert > $kt/end.chain
   cat $kt/end.cert $kt/inter.cert $kt/ca.cert > $kt/end.fullchain
+  cat $kt/end.dc.cert $kt/inter.cert $kt/ca.cert > $kt/end.dc.fullchain
+  cat $kt/end.wildcard.cert $kt/inter.cert $kt/ca.cert > $kt/end.wildcard.fullchain
+  cat $kt/end.spiffe.cert $kt/inter.cert $kt/ca.cert > $kt/end.spiffe.fullchain
 
   cat $kt/inter.cert $kt/ca.cert > $kt/client.chain
   cat $kt/client.cert $kt/inter.cert $kt/ca.cert > $kt/client.fullchain
+  cat $kt/client.spiffe.cert $kt/inter.cert $kt/ca.cert > $kt/client.spiffe.fullchain
 
   openssl asn1parse -in $kt/ca.cert -out $kt/ca.der > /dev/null
 done